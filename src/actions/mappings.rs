@@ -23,7 +23,8 @@ impl Plugin for InputMappingsPlugin {
                     dispatch_ui_actions,
                     dispatch_inter_actions,
                 )
-                    .run_if(in_state(GameState::Playing)),
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(not(crate::ui::text_field_focused)),
             );
     }
 }
@@ -25,6 +25,12 @@ pub enum UiAction {
     HotbarToggle,
     /// Toggle Inventory
     InventoryToggle,
+    /// Toggle research tree
+    ResearchTreeToggle,
+    /// Save the running level
+    QuickSave,
+    /// Change simulation speed, pausing again resumes
+    GameSpeed(crate::GameSpeed),
 }
 
 impl InputAction for UiAction {
@@ -39,6 +45,10 @@ impl InputAction for UiAction {
             Self::HUDToggle => "Toggle HUD".into(),
             Self::HotbarToggle => "Toggle hotbar".into(),
             Self::InventoryToggle => "Toggle inventory".into(),
+            Self::ResearchTreeToggle => "Toggle research tree".into(),
+            Self::QuickSave => "Quick save".into(),
+            Self::GameSpeed(crate::GameSpeed::Paused) => "Pause simulation".into(),
+            Self::GameSpeed(speed) => format!("Simulation speed {}", speed),
         }
     }
 
@@ -65,6 +75,10 @@ pub(crate) fn dispatch_ui_actions(
     mut game_state_channel: EventWriter<GameState>,
     mut enable_hud_channel: EventWriter<EnableHUD>,
     mut enable_inventory_channel: EventWriter<EnableInventory>,
+    mut enable_research_tree_channel: EventWriter<EnableResearchTree>,
+    mut save_level_channel: EventWriter<crate::SaveLevel>,
+    mut game_speed: ResMut<crate::GameSpeed>,
+    mut resumed_speed: Local<Option<crate::GameSpeed>>,
     mouse_scroll: MouseScrollEvent,
     mouse_button: MouseButtonResource,
     key_button: KeyButtonResource,
@@ -96,6 +110,40 @@ pub(crate) fn dispatch_ui_actions(
                     enable_inventory_channel.send(EnableInventory(true));
                 }
             }
+            UiAction::ResearchTreeToggle => {
+                if entry
+                    .just_pressed(UiAction::ResearchTreeToggle, ctrl_incoming)
+                    .is_some()
+                {
+                    enable_research_tree_channel.send(EnableResearchTree(true));
+                }
+            }
+            UiAction::QuickSave => {
+                if entry
+                    .just_pressed(UiAction::QuickSave, ctrl_incoming)
+                    .is_some()
+                {
+                    save_level_channel.send(crate::SaveLevel(crate::SaveLevel::QUICKSAVE.into()));
+                }
+            }
+            UiAction::GameSpeed(speed) => {
+                if entry
+                    .just_pressed(UiAction::GameSpeed(*speed), ctrl_incoming)
+                    .is_some()
+                {
+                    *game_speed = match (*game_speed, *speed) {
+                        // pausing twice resumes at the speed the game was paused from
+                        (crate::GameSpeed::Paused, crate::GameSpeed::Paused) => {
+                            resumed_speed.unwrap_or_default()
+                        }
+                        (_, speed) => speed,
+                    };
+
+                    if *game_speed != crate::GameSpeed::Paused {
+                        *resumed_speed = Some(*game_speed);
+                    }
+                }
+            }
             _ => {}
         });
 }
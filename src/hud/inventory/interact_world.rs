@@ -1,6 +1,7 @@
-use crate::{actions::*, *};
+use crate::{actions::*, ui::*, *};
 use bevy::prelude::*;
 use bevy_ecs_tilemap::prelude::*;
+use std::time::Duration;
 
 /// allows an inventory to interact with the world
 pub struct InventoryInteractWorldPlugin;
@@ -19,54 +20,181 @@ impl Plugin for InventoryInteractWorldPlugin {
 //#[derive(Component)]
 //pub struct InventoryBuildingSource;
 
+#[allow(clippy::too_many_arguments)]
 fn inventory_interact_world(
     mut cmd: Commands,
     mut inter_actions: EventReader<actions::InterAction>,
     cursor: Res<CursorWorldPosition>,
-    mut inventory: Query<(&mut Inventory, &InventoryActive)>,
+    inventory: Query<(&Inventory, &InventoryActive), With<player::Player>>,
     mut building_tilemap: TilemapQueryMut<(With<BuildingTilemap>, Without<FloorTilemap>)>,
+    floor_tilemap: Query<&TileStorage, (With<FloorTilemap>, Without<BuildingTilemap>)>,
+    belts: Query<(&TilePos, &MoverBelt)>,
+    mut infinite_ios: Query<&mut InfiniteIo>,
+    countertops: Query<(), With<Countertop>>,
+    placed: Query<(), With<Building>>,
+    buildings: Res<BuildingRegistry>,
+    mut notifications_channel: NotificationChannel,
 ) {
     inter_actions
         .read()
         .for_each(|inter_action| match *inter_action {
             InterAction::Construct => {
                 let (building_tilemap_entity, mut tile_storage, tile_pos) =
-                    building_tilemap.cursor_tile_position(&*cursor);
-                let (inventory, inventory_active) = inventory.single_mut();
-
-                inventory_active.0.map(|active| {
-                    inventory.0.get(active).map(|inventory_slot| {
-                        //
-                        if let &Some(ItemEntry { item, quantity }) = &inventory_slot {
-                            if let Some(tile_pos) = tile_pos {
-                                let texture_index = match item.0.as_str() {
-                                    "auto_arm" => Some(0),
-                                    "infinite_io" => Some(1),
-                                    "mover_belt" => Some(2),
-                                    _ => None,
-                                };
-                                texture_index.map(|index| {
-                                    let tile = cmd
-                                        .spawn(TileBundle {
-                                            position: tile_pos,
-                                            texture_index: TileTextureIndex(index),
-
-                                            tilemap_id: TilemapId(building_tilemap_entity),
-                                            ..default()
-                                        })
-                                        .id();
-
-                                    tile_storage.set(&tile_pos, tile);
-                                });
+                    building_tilemap.cursor_tile_position(&cursor);
+                let (inventory, inventory_active) = inventory.single();
+
+                let Some(item) = inventory_active
+                    .0
+                    .and_then(|active| inventory.0.get(active).cloned().flatten())
+                    .map(|entry| entry.item)
+                else {
+                    return;
+                };
+                let (Some(tile_pos), Some(definition)) = (tile_pos, buildings.building(&item))
+                else {
+                    return;
+                };
+
+                // continue the belt feeding into this tile
+                let feeding = belts
+                    .iter()
+                    .find(|(pos, belt)| belt.direction.step(**pos) == Some(tile_pos))
+                    .map(|(_, belt)| belt.direction)
+                    .filter(|_| definition.has(&BuildingComponent::MoverBelt));
+                let facing = definition.facing(feeding);
+
+                let floor = floor_tilemap.get_single().ok();
+                let surface = |pos: TilePos| {
+                    let tile = tile_storage.get(&pos);
+                    let countertop = tile.is_some_and(|tile| countertops.contains(tile));
+                    TileSurface {
+                        floor: floor.is_some_and(|floor| floor.get(&pos).is_some()),
+                        countertop,
+                        occupied: tile.is_some_and(|tile| !countertop || placed.contains(tile)),
+                    }
+                };
+
+                let cells =
+                    match definition.placement(tile_pos, facing, &tile_storage.size, surface) {
+                        Ok(cells) => cells,
+                        Err(err) => {
+                            debug!("unable to build {} at {:?}. {}", item.0, tile_pos, err);
+                            Notification {
+                                title: "Unable to build".into(),
+                                level: NotificationLevel::Warning,
+                                description: format!("{} {}", item.0, err),
                             }
+                            .queue(Some(Duration::from_secs(3)), &mut notifications_channel);
+                            return;
                         }
-                    })
-                });
+                    };
+
+                let building =
+                    definition.spawn(&mut cmd, building_tilemap_entity, tile_pos, facing);
+                for cell in cells {
+                    // countertops stay under the building
+                    if let Some(countertop) = tile_storage.get(&cell) {
+                        if cell == tile_pos {
+                            cmd.entity(building).insert(Countertop);
+                        }
+                        cmd.entity(countertop).despawn_recursive();
+                    }
+                    tile_storage.set(&cell, building);
+                }
             }
             InterAction::Deconstruct => {}
-            InterAction::Distribute => {}
+            // an infinite io supplies the item in hand, or sinks everything when the hand is empty
+            InterAction::Distribute => {
+                let (_, tile_storage, tile_pos) = building_tilemap.cursor_tile_position(&cursor);
+                let Some(mut infinite_io) = tile_pos
+                    .and_then(|tile_pos| tile_storage.get(&tile_pos))
+                    .and_then(|tile| infinite_ios.get_mut(tile).ok())
+                else {
+                    return;
+                };
+
+                let (inventory, inventory_active) = inventory.single();
+                let held = inventory_active
+                    .0
+                    .and_then(|active| inventory.0.get(active).cloned().flatten());
+
+                infinite_io.set_mode(match held {
+                    Some(entry) => InfiniteIoMode::Source(entry.item),
+                    None => InfiniteIoMode::Sink,
+                });
+            }
             InterAction::Pipette => {}
             InterAction::CopyConfiguration => {}
             InterAction::PasteConfiguration => {}
         });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespaced_items_place_their_buildings() {
+        let mut app = App::new();
+        app.add_event::<InterAction>()
+            .add_event::<NotificationEvent>()
+            .init_resource::<CursorWorldPosition>()
+            .add_systems(Update, inventory_interact_world);
+
+        let mut buildings = BuildingRegistry::default();
+        for (item, component) in [
+            ("base::mover_belt", BuildingComponent::MoverBelt),
+            ("base::auto_arm", BuildingComponent::AutoArm),
+            ("base::infinite_io", BuildingComponent::InfiniteIo),
+        ] {
+            buildings.define(BuildingDefinition {
+                components: vec![component],
+                ..ItemId::from(item).into()
+            });
+        }
+        app.insert_resource(buildings);
+
+        let size = TilemapSize { x: 8, y: 8 };
+        let tilemap = app
+            .world_mut()
+            .spawn((
+                BuildingTilemap,
+                size,
+                TilemapGridSize { x: 1., y: 1. },
+                TilemapType::Square,
+                TileStorage::empty(size),
+                Transform::default(),
+            ))
+            .id();
+        let player = app
+            .world_mut()
+            .spawn((player::Player, InventoryActive(Some(0))))
+            .id();
+
+        for (x, item) in ["base::mover_belt", "base::auto_arm", "base::infinite_io"]
+            .into_iter()
+            .enumerate()
+        {
+            let mut inventory = Inventory::with_capacity(1);
+            inventory.dump(vec![ItemEntry {
+                item: item.into(),
+                quantity: 1,
+            }]);
+            app.world_mut().entity_mut(player).insert(inventory);
+            app.world_mut().resource_mut::<CursorWorldPosition>().0 = Vec2::new(x as f32, 2.);
+            app.world_mut().send_event(InterAction::Construct);
+            app.update();
+        }
+
+        let placed = |x| {
+            app.world()
+                .get::<TileStorage>(tilemap)
+                .unwrap()
+                .get(&TilePos { x, y: 2 })
+                .unwrap()
+        };
+        assert!(app.world().get::<MoverBelt>(placed(0)).is_some());
+        assert!(app.world().get::<AutoArm>(placed(1)).is_some());
+        assert!(app.world().get::<InfiniteIo>(placed(2)).is_some());
+    }
+}
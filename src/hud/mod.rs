@@ -3,9 +3,11 @@ use bevy::prelude::*;
 
 mod hotbar;
 mod inventory;
+mod research_tree;
 
 pub use hotbar::*;
 pub use inventory::*;
+pub use research_tree::*;
 
 pub struct HUDPlugin;
 
@@ -20,7 +22,7 @@ impl Plugin for HUDPlugin {
             )
             .add_systems(OnEnter(EnableHUD::ENABLED), (spawn_hud_backdrop,));
 
-        app.add_plugins((HotbarPlugin, InventoryPlugin));
+        app.add_plugins((HotbarPlugin, InventoryPlugin, ResearchTreePlugin));
     }
 }
 
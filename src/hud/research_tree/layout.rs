@@ -0,0 +1,309 @@
+//! Layered layout of a graph of requirements, research is drawn left to right with every
+//! research to the right of what it requires. Knows nothing of the UI so it can be tested alone.
+
+use bevy::math::Vec2;
+use bevy::utils::HashMap;
+use std::collections::VecDeque;
+
+/// Sweeps of the barycenter ordering, each going forth then back through the layers
+const ORDERING_SWEEPS: usize = 4;
+
+/// Where each node of the graph lands, in the order the nodes were given
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct LayeredLayout {
+    pub nodes: Vec<LayoutNode>,
+    /// `(required, dependent)` pairs, unknown and repeated requirements left out
+    pub edges: Vec<(usize, usize)>,
+    /// count of nodes in each layer
+    pub layer_sizes: Vec<usize>,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LayoutNode {
+    /// longest chain of requirements leading to the node
+    pub layer: usize,
+    /// order of the node within its layer
+    pub slot: usize,
+    /// requires itself through a cycle, placed in a layer past every other
+    pub cyclic: bool,
+}
+
+impl LayeredLayout {
+    /// Lays out nodes given the indices each of them requires. Requirements pointing past the
+    /// nodes are ignored.
+    pub fn new(requirements: &[Vec<usize>]) -> Self {
+        let len = requirements.len();
+
+        let mut edges = vec![];
+        for (dependent, required) in requirements.iter().enumerate() {
+            for &required in required.iter() {
+                if required < len && !edges.contains(&(required, dependent)) {
+                    edges.push((required, dependent));
+                }
+            }
+        }
+
+        let mut nodes = vec![LayoutNode::default(); len];
+        let mut layered = vec![false; len];
+        let mut pending = vec![0; len];
+        for &(_, dependent) in edges.iter() {
+            pending[dependent] += 1;
+        }
+
+        // longest path layering, visiting nodes in topological order
+        let mut queue = (0..len)
+            .filter(|&node| pending[node] == 0)
+            .collect::<VecDeque<_>>();
+        while let Some(node) = queue.pop_front() {
+            layered[node] = true;
+            for &(required, dependent) in edges.iter() {
+                if required != node {
+                    continue;
+                }
+                nodes[dependent].layer = nodes[dependent].layer.max(nodes[node].layer + 1);
+                pending[dependent] -= 1;
+                if pending[dependent] == 0 {
+                    queue.push_back(dependent);
+                }
+            }
+        }
+
+        let acyclic_layers = nodes
+            .iter()
+            .zip(layered.iter())
+            .filter(|(_, layered)| **layered)
+            .map(|(node, _)| node.layer + 1)
+            .max()
+            .unwrap_or(0);
+        for (node, layered) in nodes.iter_mut().zip(layered) {
+            if !layered {
+                node.layer = acyclic_layers;
+                node.cyclic = true;
+            }
+        }
+
+        let layer_count = nodes.iter().map(|node| node.layer + 1).max().unwrap_or(0);
+        let mut layers = vec![vec![]; layer_count];
+        for (index, node) in nodes.iter().enumerate() {
+            layers[node.layer].push(index);
+        }
+
+        let mut layout = Self {
+            nodes,
+            edges,
+            layer_sizes: layers.iter().map(Vec::len).collect(),
+        };
+        layout.order(layers);
+        layout
+    }
+
+    /// Orders every layer by the mean slot of the neighbours in the layer before, then after,
+    /// keeping the ordering with the fewest crossings
+    fn order(&mut self, mut layers: Vec<Vec<usize>>) {
+        self.assign_slots(&layers);
+        let mut fewest = self.crossings();
+        let mut best = layers.clone();
+
+        for _ in 0..ORDERING_SWEEPS {
+            if fewest == 0 {
+                break;
+            }
+
+            for layer in 1..layers.len() {
+                self.sort_by_barycenter(&mut layers[layer], true);
+                self.assign_slots(&layers);
+            }
+            for layer in (0..layers.len().saturating_sub(1)).rev() {
+                self.sort_by_barycenter(&mut layers[layer], false);
+                self.assign_slots(&layers);
+            }
+
+            let crossings = self.crossings();
+            if crossings < fewest {
+                fewest = crossings;
+                best = layers.clone();
+            }
+        }
+
+        self.assign_slots(&best);
+    }
+
+    fn assign_slots(&mut self, layers: &[Vec<usize>]) {
+        for layer in layers.iter() {
+            for (slot, &node) in layer.iter().enumerate() {
+                self.nodes[node].slot = slot;
+            }
+        }
+    }
+
+    /// Nodes without neighbours on that side stay where they are
+    fn sort_by_barycenter(&self, layer: &mut [usize], towards_required: bool) {
+        let barycenters = layer
+            .iter()
+            .map(|&node| {
+                let neighbours = self
+                    .edges
+                    .iter()
+                    .filter_map(|&(required, dependent)| match towards_required {
+                        true if dependent == node => Some(required),
+                        false if required == node => Some(dependent),
+                        _ => None,
+                    })
+                    .map(|neighbour| self.row(neighbour))
+                    .collect::<Vec<_>>();
+
+                let barycenter = if neighbours.is_empty() {
+                    self.row(node)
+                } else {
+                    neighbours.iter().sum::<f32>() / neighbours.len() as f32
+                };
+                (node, barycenter)
+            })
+            .collect::<HashMap<_, _>>();
+
+        layer.sort_by(|a, b| barycenters[a].total_cmp(&barycenters[b]));
+    }
+
+    /// Slot of the node with its layer centered on the widest one
+    pub fn row(&self, node: usize) -> f32 {
+        let LayoutNode { layer, slot, .. } = self.nodes[node];
+        slot as f32 + (self.widest() - self.layer_sizes[layer]) as f32 / 2.
+    }
+
+    fn widest(&self) -> usize {
+        self.layer_sizes.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Top left corner of the node when each layer and row takes `spacing`
+    pub fn position(&self, node: usize, spacing: Vec2) -> Vec2 {
+        Vec2::new(self.nodes[node].layer as f32, self.row(node)) * spacing
+    }
+
+    /// Size taken by every node when each layer and row takes `spacing`
+    pub fn extent(&self, spacing: Vec2) -> Vec2 {
+        Vec2::new(self.layer_sizes.len() as f32, self.widest() as f32) * spacing
+    }
+
+    /// Pairs of edges crossing each other, counted by their rows as edges are drawn straight
+    pub fn crossings(&self) -> usize {
+        let mut crossings = 0;
+        for (index, &(a_from, a_to)) in self.edges.iter().enumerate() {
+            for &(b_from, b_to) in self.edges[index + 1..].iter() {
+                let spans = |from: usize, to: usize| (self.nodes[from].layer, self.nodes[to].layer);
+                if spans(a_from, a_to) != spans(b_from, b_to) {
+                    continue;
+                }
+
+                let from = self.row(a_from) - self.row(b_from);
+                let to = self.row(a_to) - self.row(b_to);
+                if from * to < 0. {
+                    crossings += 1;
+                }
+            }
+        }
+        crossings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layers(layout: &LayeredLayout) -> Vec<usize> {
+        layout.nodes.iter().map(|node| node.layer).collect()
+    }
+
+    #[test]
+    fn chains_take_a_layer_each() {
+        let layout = LayeredLayout::new(&[vec![], vec![0], vec![1], vec![2]]);
+
+        assert_eq!(layers(&layout), vec![0, 1, 2, 3]);
+        assert_eq!(layout.layer_sizes, vec![1, 1, 1, 1]);
+        assert_eq!(layout.edges, vec![(0, 1), (1, 2), (2, 3)]);
+    }
+
+    #[test]
+    fn nodes_follow_their_longest_requirement() {
+        // 3 requires both the root and the end of a chain
+        let layout = LayeredLayout::new(&[vec![], vec![0], vec![1], vec![0, 2]]);
+
+        assert_eq!(layers(&layout), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn diamonds_share_a_layer() {
+        let layout = LayeredLayout::new(&[vec![], vec![0], vec![0], vec![1, 2]]);
+
+        assert_eq!(layers(&layout), vec![0, 1, 1, 2]);
+        assert_eq!(layout.layer_sizes, vec![1, 2, 1]);
+        assert_eq!(layout.row(0), 0.5);
+        assert_eq!(layout.row(3), 0.5);
+        assert_eq!(layout.extent(Vec2::new(10., 4.)), Vec2::new(30., 8.));
+        assert_eq!(layout.position(2, Vec2::new(10., 4.)), Vec2::new(10., 4.));
+    }
+
+    #[test]
+    fn unknown_and_repeated_requirements_are_ignored() {
+        let layout = LayeredLayout::new(&[vec![7], vec![0, 0]]);
+
+        assert_eq!(layers(&layout), vec![0, 1]);
+        assert_eq!(layout.edges, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn cycles_are_placed_last() {
+        // 1 and 2 require each other, 3 requires the cycle
+        let layout = LayeredLayout::new(&[vec![], vec![0, 2], vec![1], vec![2], vec![0]]);
+
+        assert_eq!(layers(&layout), vec![0, 2, 2, 2, 1]);
+        assert_eq!(
+            layout
+                .nodes
+                .iter()
+                .map(|node| node.cyclic)
+                .collect::<Vec<_>>(),
+            vec![false, true, true, true, false]
+        );
+    }
+
+    #[test]
+    fn self_requirements_are_cyclic() {
+        let layout = LayeredLayout::new(&[vec![0]]);
+
+        assert!(layout.nodes[0].cyclic);
+        assert_eq!(layout.layer_sizes, vec![1]);
+    }
+
+    #[test]
+    fn ordering_untangles_crossings() {
+        // 2 requires 1 and 3 requires 0, declared in an order that crosses
+        let layout = LayeredLayout::new(&[vec![], vec![], vec![1], vec![0]]);
+
+        assert_eq!(layout.crossings(), 0);
+        assert_eq!(layout.nodes[0].slot, layout.nodes[3].slot);
+        assert_eq!(layout.nodes[1].slot, layout.nodes[2].slot);
+    }
+
+    #[test]
+    fn independent_roots_keep_declaration_order() {
+        let layout = LayeredLayout::new(&[vec![], vec![], vec![]]);
+
+        assert_eq!(
+            layout
+                .nodes
+                .iter()
+                .map(|node| node.slot)
+                .collect::<Vec<_>>(),
+            vec![0, 1, 2]
+        );
+    }
+
+    #[test]
+    fn empty_graphs_take_no_space() {
+        let layout = LayeredLayout::new(&[]);
+
+        assert!(layout.nodes.is_empty());
+        assert_eq!(layout.extent(Vec2::ONE), Vec2::ZERO);
+        assert_eq!(layout.crossings(), 0);
+    }
+}
@@ -0,0 +1,574 @@
+//! Window laying out the research of the level as a graph, research requiring other research
+//! sits to the right of it. Dragging pans, scrolling zooms and clicking available research
+//! starts it.
+
+use crate::hud::*;
+use crate::loading;
+use crate::ui::*;
+use crate::*;
+use bevy::input::mouse::{MouseScrollUnit, MouseWheel};
+use bevy::ui::RelativeCursorPosition;
+
+mod layout;
+
+pub use layout::*;
+
+pub struct ResearchTreePlugin;
+
+impl Plugin for ResearchTreePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_sub_state::<EnableResearchTree>()
+            .enable_state_scoped_entities::<EnableResearchTree>()
+            .add_state_scoped_event::<EnableResearchTree>(EnableHUD::ENABLED)
+            .init_resource::<ResearchTreeView>()
+            .init_resource::<ResearchTreeFilter>()
+            .add_systems(
+                Update,
+                handle_research_tree_enable.run_if(in_state(EnableHUD::ENABLED)),
+            )
+            .add_systems(
+                OnEnter(EnableResearchTree::ENABLED),
+                (spawn_research_tree,).after(spawn_hud_backdrop),
+            )
+            .add_systems(
+                Update,
+                (
+                    close_research_tree,
+                    filter_research_tree,
+                    start_research_from_tree,
+                    populate_research_tree,
+                    pan_and_zoom_research_tree,
+                    place_research_tree,
+                    show_research_states,
+                )
+                    .chain()
+                    .run_if(in_state(EnableResearchTree::ENABLED)),
+            );
+    }
+}
+
+/// Room taken by a research on the graph before zooming
+const NODE_SIZE: Vec2 = Vec2::new(UI_SCALE * 22., UI_SCALE * 7.);
+/// Room taken by each layer and each row of the graph before zooming
+const NODE_SPACING: Vec2 = Vec2::new(UI_SCALE * 30., UI_SCALE * 9.);
+const EDGE_WIDTH: f32 = 2.;
+const MIN_ZOOM: f32 = 0.25;
+const MAX_ZOOM: f32 = 3.;
+/// Zoom multiplier per line scrolled
+const ZOOM_STEP: f32 = 1.1;
+
+const LOCKED_COLOR: Color = Color::srgb(0.62, 0.62, 0.62);
+const AVAILABLE_COLOR: Color = Color::srgb(0.94, 0.8, 0.42);
+const IN_PROGRESS_COLOR: Color = Color::srgb(0.49, 0.7, 0.92);
+const COMPLETED_COLOR: Color = Color::srgb(0.53, 0.8, 0.51);
+const CYCLIC_BORDER_COLOR: Color = Color::srgb(0.85, 0.25, 0.25);
+const EDGE_COLOR: Color = Color::srgba(0.2, 0.2, 0.2, 0.6);
+
+#[derive(SubStates, Clone, Eq, PartialEq, Debug, Hash, Copy, Event)]
+#[source(EnableHUD = EnableHUD(true)) ]
+pub struct EnableResearchTree(pub bool);
+
+impl EnableResearchTree {
+    pub const ENABLED: Self = Self(true);
+    pub const DISABLED: Self = Self(false);
+}
+
+impl Default for EnableResearchTree {
+    fn default() -> Self {
+        Self::DISABLED
+    }
+}
+
+/// Pan and zoom of the graph, kept while the window is closed
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct ResearchTreeView {
+    /// where the top left corner of the graph sits within the viewport
+    pub offset: Vec2,
+    pub zoom: f32,
+}
+
+impl Default for ResearchTreeView {
+    fn default() -> Self {
+        Self {
+            offset: Vec2::splat(UI_SCALE * 2.),
+            zoom: 1.,
+        }
+    }
+}
+
+impl ResearchTreeView {
+    /// Zooms by `factor` keeping the point of the graph under `anchor` in place
+    pub fn zoom_at(&mut self, factor: f32, anchor: Vec2) {
+        let zoom = (self.zoom * factor).clamp(MIN_ZOOM, MAX_ZOOM);
+        self.offset = anchor - (anchor - self.offset) * (zoom / self.zoom);
+        self.zoom = zoom;
+    }
+
+    /// Rectangle of the viewport covered by a rectangle of the graph
+    fn project(&self, min: Vec2, size: Vec2) -> (Vec2, Vec2) {
+        (self.offset + min * self.zoom, size * self.zoom)
+    }
+}
+
+/// Only research declared in the namespace is shown, all of it when none
+#[derive(Resource, Debug, Clone, Default, PartialEq)]
+pub struct ResearchTreeFilter(pub Option<Namespace>);
+
+/// Clips the graph and pans it when dragged
+#[derive(Debug, Component)]
+#[require(DepressButton, RelativeCursorPosition)]
+pub struct ResearchTreeViewport;
+
+/// Holds every research and edge of the graph, rebuilt when the filter changes
+#[derive(Debug, Component)]
+pub struct ResearchTreeCanvas;
+
+/// A research on the graph at `position` before zooming
+#[derive(Debug, Component)]
+pub struct ResearchNodeUI {
+    pub id: ResearchId,
+    pub position: Vec2,
+    pub cyclic: bool,
+}
+
+/// Line of text under a research telling how far it is from completing
+#[derive(Debug, Component)]
+pub struct ResearchNodeProgress(pub ResearchId);
+
+/// Text shrinking and growing with the zoom
+#[derive(Debug, Component)]
+pub struct ResearchTreeText(pub f32);
+
+/// Straight piece of an edge between `from` and `to` before zooming, edges bend at right angles
+#[derive(Debug, Component)]
+pub struct ResearchEdgeUI {
+    pub from: Vec2,
+    pub to: Vec2,
+}
+
+#[derive(Debug, Component)]
+pub struct ResearchNamespaceFilter(pub Option<Namespace>);
+
+pub fn handle_research_tree_enable(
+    mut enable_research_tree_channel: EventReader<EnableResearchTree>,
+    enable_research_tree: Res<State<EnableResearchTree>>,
+    mut next_enable_research_tree: ResMut<NextState<EnableResearchTree>>,
+) {
+    enable_research_tree_channel.read().for_each(|_| {
+        info!("Received event to toggle research tree");
+        next_enable_research_tree.set(EnableResearchTree(!enable_research_tree.0));
+    });
+}
+
+pub fn spawn_research_tree(
+    mut cmd: Commands,
+    backdrop: HUDBackdropQuery,
+    namespaces: Option<Res<Namespaces>>,
+    ui: Res<loading::UiAssets>,
+    fonts: Res<loading::FontAssets>,
+) {
+    let filters = std::iter::once(None)
+        .chain(
+            namespaces
+                .iter()
+                .flat_map(|namespaces| namespaces.iter().map(Some)),
+        )
+        .collect::<Vec<_>>();
+
+    cmd.entity(backdrop.single()).with_children(|parent| {
+        spawn_window(
+            parent,
+            StateScoped(EnableResearchTree::ENABLED),
+            ChangeStates(EnableResearchTree::DISABLED),
+            &ui,
+            &fonts,
+            WindowMeta::new("research".into(), 400., 16. / 9.),
+            |parent| {
+                parent
+                    .spawn(Node {
+                        margin: UiRect::horizontal(Val::Px(UI_SCALE * 2.)),
+                        column_gap: Val::Px(UI_SCALE),
+                        height: Val::Px(UI_SCALE * 5.),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for namespace in filters.iter() {
+                            let text = namespace.map_or("all".to_string(), |ns| ns.to_string());
+                            spawn_button(
+                                ButtonType::Text {
+                                    text,
+                                    font_size: SMALL_MEDIUM_FONT,
+                                },
+                                ResearchNamespaceFilter(*namespace),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        }
+                    });
+
+                parent
+                    .spawn((
+                        ResearchTreeViewport,
+                        Node {
+                            flex_grow: 1.,
+                            margin: UiRect::all(Val::Px(UI_SCALE * 2.)),
+                            overflow: Overflow::clip(),
+                            ..default()
+                        },
+                        BackgroundColor(Color::srgba_u8(255, 255, 255, 150)),
+                        BorderRadius::all(Val::Px(2.)),
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            ResearchTreeCanvas,
+                            Node {
+                                position_type: PositionType::Absolute,
+                                left: Val::Px(0.),
+                                top: Val::Px(0.),
+                                ..default()
+                            },
+                        ));
+                    });
+            },
+        );
+    });
+}
+
+fn close_research_tree(
+    buttons: Query<
+        &DepressButton,
+        (
+            With<ChangeStates<EnableResearchTree>>,
+            Changed<DepressButton>,
+        ),
+    >,
+    mut enable_research_tree_channel: EventWriter<EnableResearchTree>,
+) {
+    if buttons.iter().any(DepressButton::invoked) {
+        enable_research_tree_channel.send(EnableResearchTree::DISABLED);
+    }
+}
+
+fn filter_research_tree(
+    buttons: Query<(&DepressButton, &ResearchNamespaceFilter), Changed<DepressButton>>,
+    mut filter: ResMut<ResearchTreeFilter>,
+) {
+    for (depress, ResearchNamespaceFilter(namespace)) in buttons.iter() {
+        if depress.invoked() {
+            filter.set_if_neq(ResearchTreeFilter(*namespace));
+        }
+    }
+}
+
+fn start_research_from_tree(
+    nodes: Query<(&DepressButton, &ResearchNodeUI), Changed<DepressButton>>,
+    level: Query<&ResearchProgress, With<Level>>,
+    mut start_research_channel: EventWriter<StartResearch>,
+) {
+    let Ok(progress) = level.get_single() else {
+        return;
+    };
+
+    for (depress, node) in nodes.iter() {
+        if depress.invoked() && progress.state(&node.id) == ResearchState::Available {
+            start_research_channel.send(StartResearch(node.id.clone()));
+        }
+    }
+}
+
+/// Research shown with the filter, along with the layout of what they require of each other
+pub fn research_layout<'a>(
+    research: &'a ResearchRegistry,
+    namespace: Option<Namespace>,
+) -> (Vec<&'a ResearchDeclared>, LayeredLayout) {
+    let shown = research
+        .iter()
+        .filter(|declared| {
+            namespace.map_or(true, |namespace| {
+                declared
+                    .id
+                    .0
+                    .parse::<NamespacedId>()
+                    .is_ok_and(|id| id.namespace == namespace)
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let requirements = shown
+        .iter()
+        .map(|declared| {
+            declared
+                .required_research
+                .iter()
+                .filter_map(|required| shown.iter().position(|shown| shown.id == *required))
+                .collect()
+        })
+        .collect::<Vec<Vec<usize>>>();
+
+    (shown, LayeredLayout::new(&requirements))
+}
+
+fn populate_research_tree(
+    mut cmd: Commands,
+    canvas: Query<(Entity, Ref<ResearchTreeCanvas>)>,
+    research: Option<Res<ResearchRegistry>>,
+    filter: Res<ResearchTreeFilter>,
+    fonts: Res<loading::FontAssets>,
+) {
+    let Ok((canvas, added)) = canvas.get_single() else {
+        return;
+    };
+    let Some(research) = research else {
+        return;
+    };
+    if !added.is_added() && !filter.is_changed() && !research.is_changed() {
+        return;
+    }
+
+    let (shown, layout) = research_layout(&research, filter.0);
+
+    cmd.entity(canvas)
+        .despawn_descendants()
+        .with_children(|parent| {
+            // edges first so research is drawn over them
+            for &(required, dependent) in layout.edges.iter() {
+                let from = layout.position(required, NODE_SPACING) + NODE_SIZE * Vec2::new(1., 0.5);
+                let to = layout.position(dependent, NODE_SPACING) + NODE_SIZE * Vec2::new(0., 0.5);
+                let bend = to.x - (NODE_SPACING.x - NODE_SIZE.x) / 2.;
+
+                for (from, to) in [
+                    (from, Vec2::new(bend, from.y)),
+                    (Vec2::new(bend, from.y), Vec2::new(bend, to.y)),
+                    (Vec2::new(bend, to.y), to),
+                ] {
+                    parent.spawn((
+                        ResearchEdgeUI { from, to },
+                        Node {
+                            position_type: PositionType::Absolute,
+                            ..default()
+                        },
+                        BackgroundColor(EDGE_COLOR),
+                    ));
+                }
+            }
+
+            for (index, declared) in shown.iter().enumerate() {
+                let cyclic = layout.nodes[index].cyclic;
+
+                parent
+                    .spawn((
+                        ResearchNodeUI {
+                            id: declared.id.clone(),
+                            position: layout.position(index, NODE_SPACING),
+                            cyclic,
+                        },
+                        DepressButton::default(),
+                        Node {
+                            position_type: PositionType::Absolute,
+                            flex_direction: FlexDirection::Column,
+                            padding: UiRect::all(Val::Px(UI_SCALE)),
+                            border: UiRect::all(Val::Px(EDGE_WIDTH)),
+                            overflow: Overflow::clip(),
+                            ..default()
+                        },
+                        BackgroundColor(LOCKED_COLOR),
+                        BorderColor(if cyclic {
+                            CYCLIC_BORDER_COLOR
+                        } else {
+                            EDGE_COLOR
+                        }),
+                        BorderRadius::all(Val::Px(4.)),
+                    ))
+                    .with_children(|parent| {
+                        body_text(&declared.display_name, parent, &fonts)
+                            .insert(ResearchTreeText(SMALL_MEDIUM_FONT));
+                        body_text("", parent, &fonts).insert((
+                            ResearchTreeText(SMALL_FONT * 1.5),
+                            ResearchNodeProgress(declared.id.clone()),
+                        ));
+                    });
+            }
+        });
+}
+
+fn pan_and_zoom_research_tree(
+    mut cursor_position: EventReader<CursorMoved>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    viewport: Query<
+        (&DepressButton, &RelativeCursorPosition, &ComputedNode),
+        With<ResearchTreeViewport>,
+    >,
+    mut view: ResMut<ResearchTreeView>,
+) {
+    let Ok((depress, cursor, computed)) = viewport.get_single() else {
+        return;
+    };
+
+    let dragged = cursor_position
+        .read()
+        .filter_map(|c| c.delta)
+        .fold(Vec2::ZERO, |acc, delta| acc + delta);
+    if depress.held() && dragged != Vec2::ZERO {
+        view.offset += dragged;
+    }
+
+    let scrolled = mouse_wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / 20.,
+        })
+        .sum::<f32>();
+    if let (true, Some(normalized)) = (cursor.mouse_over() && scrolled != 0., cursor.normalized) {
+        let anchor = normalized * computed.size() * computed.inverse_scale_factor();
+        view.zoom_at(ZOOM_STEP.powf(scrolled), anchor);
+    }
+}
+
+fn place_research_tree(
+    view: Res<ResearchTreeView>,
+    added: Query<(), Added<ResearchNodeUI>>,
+    mut nodes: Query<(&ResearchNodeUI, &mut Node), Without<ResearchEdgeUI>>,
+    mut edges: Query<(&ResearchEdgeUI, &mut Node), Without<ResearchNodeUI>>,
+    mut texts: Query<(&ResearchTreeText, &mut TextFont)>,
+) {
+    if !view.is_changed() && added.is_empty() {
+        return;
+    }
+
+    for (research, mut node) in nodes.iter_mut() {
+        let (position, size) = view.project(research.position, NODE_SIZE);
+        node.left = Val::Px(position.x);
+        node.top = Val::Px(position.y);
+        node.width = Val::Px(size.x);
+        node.height = Val::Px(size.y);
+        node.padding = UiRect::all(Val::Px(UI_SCALE * view.zoom));
+    }
+
+    for (edge, mut node) in edges.iter_mut() {
+        let thickness = Vec2::splat(EDGE_WIDTH / view.zoom.min(1.));
+        let min = edge.from.min(edge.to) - thickness / 2.;
+        let (position, size) = view.project(min, (edge.to - edge.from).abs() + thickness);
+        node.left = Val::Px(position.x);
+        node.top = Val::Px(position.y);
+        node.width = Val::Px(size.x);
+        node.height = Val::Px(size.y);
+    }
+
+    for (ResearchTreeText(font_size), mut font) in texts.iter_mut() {
+        font.font_size = font_size * view.zoom;
+    }
+}
+
+/// Progress of research in progress, percent done and the first condition holding it back
+fn progress_summary(progress: Option<&ConditionProgress>) -> String {
+    let Some(progress) = progress else {
+        return "started".into();
+    };
+
+    let blocking = progress
+        .blocking()
+        .into_iter()
+        .find_map(|blocking| blocking.measure.as_ref());
+    match blocking {
+        Some(measure) => format!("{:.0}% {}", progress.fraction * 100., measure),
+        None => format!("{:.0}%", progress.fraction * 100.),
+    }
+}
+
+fn show_research_states(
+    level: Query<&ResearchProgress, With<Level>>,
+    mut nodes: Query<(&ResearchNodeUI, &mut BackgroundColor)>,
+    mut texts: Query<(&ResearchNodeProgress, &mut Text)>,
+) {
+    let Ok(progress) = level.get_single() else {
+        return;
+    };
+
+    for (node, mut background) in nodes.iter_mut() {
+        let color = match progress.state(&node.id) {
+            ResearchState::Locked => LOCKED_COLOR,
+            ResearchState::Available => AVAILABLE_COLOR,
+            ResearchState::InProgress => IN_PROGRESS_COLOR,
+            ResearchState::Completed => COMPLETED_COLOR,
+        };
+        background.set_if_neq(BackgroundColor(color));
+    }
+
+    for (ResearchNodeProgress(id), mut text) in texts.iter_mut() {
+        let summary = match progress.state(id) {
+            ResearchState::Locked => "locked".into(),
+            ResearchState::Available => "click to start".into(),
+            ResearchState::InProgress => progress_summary(progress.unlock_progress(id)),
+            ResearchState::Completed => "completed".into(),
+        };
+        if text.0 != summary {
+            text.0 = summary;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn declared(id: &str, required: &[&str]) -> ResearchDeclared {
+        ResearchDeclared {
+            id: ResearchId(id.into()),
+            display_name: id.into(),
+            flavor_text: String::new(),
+            unlock_condition: ConditionFlag::SatisfyAll(vec![]),
+            required_research: required
+                .iter()
+                .map(|required| ResearchId(required.to_string()))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn filtered_research_drops_edges_leaving_the_namespace() {
+        let mut merged = Merged::default();
+        for declared in [
+            declared("base::belts", &[]),
+            declared("tyconic::ovens", &["base::belts"]),
+            declared("tyconic::pizzeria", &["tyconic::ovens"]),
+        ] {
+            merged.apply("test", Declaration::Declare(declared));
+        }
+        let research = ResearchRegistry::from(merged);
+
+        let (shown, layout) = research_layout(&research, None);
+        assert_eq!(shown.len(), 3);
+        assert_eq!(layout.edges, vec![(0, 1), (1, 2)]);
+
+        let tyconic = Namespace::new("tyconic").unwrap();
+        let (shown, layout) = research_layout(&research, Some(tyconic));
+        assert_eq!(
+            shown
+                .iter()
+                .map(|declared| declared.id.0.as_str())
+                .collect::<Vec<_>>(),
+            vec!["tyconic::ovens", "tyconic::pizzeria"]
+        );
+        assert_eq!(layout.edges, vec![(0, 1)]);
+        assert_eq!(layout.nodes[0].layer, 0);
+    }
+
+    #[test]
+    fn zooming_keeps_the_anchor_in_place() {
+        let mut view = ResearchTreeView {
+            offset: Vec2::new(10., 20.),
+            zoom: 1.,
+        };
+        let anchor = Vec2::new(110., 70.);
+        let under_anchor = (anchor - view.offset) / view.zoom;
+
+        view.zoom_at(2., anchor);
+        assert_eq!(view.zoom, 2.);
+        assert_eq!(view.offset + under_anchor * view.zoom, anchor);
+
+        view.zoom_at(100., anchor);
+        assert_eq!(view.zoom, MAX_ZOOM);
+    }
+}
@@ -0,0 +1,117 @@
+//! Periodically pauses the simulation to snapshot the level into [`AutosaveSettings::AUTOSAVE`]
+
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct AutosavePlugin;
+
+impl Plugin for AutosavePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AutosaveSettings>()
+            .init_resource::<AutosaveSettings>()
+            .add_systems(OnEnter(GameState::Playing), reset_autosave_timer)
+            .add_systems(
+                Update,
+                countdown_autosave
+                    .run_if(in_state(InGameState::Normal).and(resource_exists::<AutosaveTimer>)),
+            )
+            .add_systems(
+                OnEnter(InGameState::Paused),
+                autosave.run_if(resource_exists::<AutosavePending>),
+            );
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+pub struct AutosaveSettings {
+    pub enabled: bool,
+    pub interval: Duration,
+    /// previous generations of a save kept next to it
+    pub backups: usize,
+}
+
+impl AutosaveSettings {
+    pub const AUTOSAVE: &'static str = "autosave";
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval: Duration::from_secs(5 * 60),
+            backups: 3,
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct AutosaveTimer(pub Timer);
+
+/// Marks the current pause as requested by the autosave so it can resume afterwards
+#[derive(Resource, Debug)]
+pub struct AutosavePending;
+
+fn reset_autosave_timer(mut cmd: Commands, settings: Res<AutosaveSettings>) {
+    cmd.insert_resource(AutosaveTimer(Timer::new(
+        settings.interval,
+        TimerMode::Repeating,
+    )));
+}
+
+fn countdown_autosave(
+    mut cmd: Commands,
+    time: Res<Time>,
+    settings: Res<AutosaveSettings>,
+    mut timer: ResMut<AutosaveTimer>,
+    mut next_in_game_state: ResMut<NextState<InGameState>>,
+) {
+    if settings.is_changed() && timer.0.duration() != settings.interval {
+        timer.0.set_duration(settings.interval);
+        timer.0.reset();
+    }
+
+    if !settings.enabled {
+        return;
+    }
+
+    if timer.0.tick(time.delta()).just_finished() {
+        cmd.insert_resource(AutosavePending);
+        next_in_game_state.set(InGameState::Paused);
+    }
+}
+
+fn autosave(
+    mut cmd: Commands,
+    snapshot: LevelSnapshot,
+    settings: Res<AutosaveSettings>,
+    type_registry: Res<AppTypeRegistry>,
+    mut next_in_game_state: ResMut<NextState<InGameState>>,
+    mut notifications_channel: NotificationChannel,
+) {
+    cmd.remove_resource::<AutosavePending>();
+    // the simulation is only held for as long as the snapshot takes
+    next_in_game_state.set(InGameState::Normal);
+
+    let Some(save) = snapshot.snapshot() else {
+        warn!("no level to autosave");
+        return;
+    };
+
+    let written = save_path(AutosaveSettings::AUTOSAVE).and_then(|path| {
+        write_save(&path, &save, &type_registry.read(), settings.backups).map(|()| path)
+    });
+    match written {
+        Ok(path) => info!("autosaved level at {}", path.to_string_lossy()),
+        Err(err) => {
+            error!("failed to autosave level. {}", err);
+            Notification {
+                title: "Autosave failed".into(),
+                level: NotificationLevel::Error,
+                description: err.to_string(),
+            }
+            .queue(None, &mut notifications_channel);
+        }
+    }
+}
@@ -88,6 +88,10 @@ pub struct BuildingTilemap;
 pub type BuildingTilemapQuery<'a, 'b, 'c> =
     TilemapQuery<'a, 'b, 'c, (With<BuildingTilemap>, Without<FloorTilemap>)>;
 
+// marker component for countertop tiles of the building tilemap, kept by buildings standing on one
+#[derive(Component)]
+pub struct Countertop;
+
 // marker component for the floor tilemap
 #[derive(Component)]
 pub struct FloorTilemap;
@@ -124,15 +128,18 @@ fn render_countertops(
     for x in 18..20 {
         for y in 0..20 {
             let tile = cmd
-                .spawn(TileBundle {
-                    position: TilePos { x, y },
-                    texture_index: TileTextureIndex(match (x % 2, y % 2) {
-                        (0, _) => 1,
-                        _ => 0,
-                    }),
-                    tilemap_id: TilemapId(tilemap_entity),
-                    ..default()
-                })
+                .spawn((
+                    TileBundle {
+                        position: TilePos { x, y },
+                        texture_index: TileTextureIndex(match (x % 2, y % 2) {
+                            (0, _) => 1,
+                            _ => 0,
+                        }),
+                        tilemap_id: TilemapId(tilemap_entity),
+                        ..default()
+                    },
+                    Countertop,
+                ))
                 .id();
 
             floor_storage.set(&TilePos { x, y }, tile);
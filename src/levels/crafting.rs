@@ -0,0 +1,393 @@
+//! Machines crafting a selected [`Recipe`] out of their own [`Inventory`], one simulation
+//! tick at a time
+
+use crate::*;
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct CraftingPlugin;
+
+impl Plugin for CraftingPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CraftingMachine>()
+            .add_event::<ItemFlow>()
+            .add_systems(Simulation, run_crafting_machines);
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum CraftingState {
+    /// no recipe selected or the recipe isn't declared
+    #[default]
+    Idle,
+    /// waiting for ingredients
+    Starved,
+    /// done crafting, waiting for room in the inventory
+    OutputBlocked,
+    Working,
+}
+
+/// Pulls the ingredients of its recipe from its [`Inventory`] and after the duration of the
+/// recipe pushes the output back into it
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+#[require(Inventory)]
+pub struct CraftingMachine {
+    pub recipe: Option<RecipeId>,
+    pub state: CraftingState,
+    /// simulated time spent on the current craft
+    pub progress: Duration,
+}
+
+impl CraftingMachine {
+    pub fn new(recipe: RecipeId) -> Self {
+        Self {
+            recipe: Some(recipe),
+            ..default()
+        }
+    }
+
+    /// Switching recipes drops the current craft, its ingredients are lost
+    pub fn select(&mut self, recipe: Option<RecipeId>) {
+        if self.recipe != recipe {
+            *self = Self {
+                recipe,
+                ..default()
+            };
+        }
+    }
+
+    /// Items the machine gives up, only the output of its recipe so the ingredients brought in
+    /// stay for crafting. Nothing leaves a machine without a declared recipe
+    pub fn output(&self, recipes: &RecipeRegistry) -> Vec<ItemId> {
+        self.recipe
+            .as_ref()
+            .and_then(|id| recipes.recipe(id).ok())
+            .map(|declared| {
+                declared
+                    .recipe
+                    .output
+                    .iter()
+                    .map(|entry| entry.item.clone())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Fraction of the current craft done, between 0 and 1
+    pub fn fraction(&self, recipe: &Recipe) -> f32 {
+        match self.state {
+            CraftingState::Working => {
+                self.progress.as_secs_f32() / recipe_duration(recipe).as_secs_f32()
+            }
+            CraftingState::OutputBlocked => 1.,
+            _ => 0.,
+        }
+    }
+
+    /// Runs the machine for `delta` of simulated time, possibly crafting several times
+    pub fn advance(
+        &mut self,
+        delta: Duration,
+        recipe: Option<&Recipe>,
+        inventory: &mut Inventory,
+        stack_size: impl Fn(&ItemId) -> usize,
+    ) -> CraftingTurnover {
+        let mut turnover = CraftingTurnover::default();
+        let Some(recipe) = recipe else {
+            self.state = CraftingState::Idle;
+            self.progress = Duration::ZERO;
+            return turnover;
+        };
+        let duration = recipe_duration(recipe);
+        let mut budget = delta;
+
+        loop {
+            match self.state {
+                CraftingState::Working => {
+                    let remaining = duration.saturating_sub(self.progress);
+                    if budget < remaining {
+                        self.progress += budget;
+                        return turnover;
+                    }
+
+                    budget -= remaining;
+                    self.progress = duration;
+                    self.state = CraftingState::OutputBlocked;
+                }
+                CraftingState::OutputBlocked => {
+                    if !inventory.fits(&recipe.output, &stack_size) {
+                        return turnover;
+                    }
+
+                    for entry in recipe.output.iter() {
+                        inventory.insert(entry.clone(), stack_size(&entry.item));
+                    }
+                    turnover.finished += 1;
+                    self.progress = Duration::ZERO;
+                    self.state = CraftingState::Idle;
+                }
+                CraftingState::Idle | CraftingState::Starved => {
+                    if !inventory.contains(&recipe.ingredients) {
+                        self.state = CraftingState::Starved;
+                        return turnover;
+                    }
+                    // the next craft has to start within this step
+                    if budget.is_zero() {
+                        self.state = CraftingState::Idle;
+                        return turnover;
+                    }
+
+                    for entry in recipe.ingredients.iter() {
+                        inventory.remove(entry);
+                    }
+                    turnover.started += 1;
+                    self.state = CraftingState::Working;
+                }
+            }
+        }
+    }
+}
+
+/// Crafts a machine went through within a step
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CraftingTurnover {
+    /// ingredients were consumed
+    pub started: usize,
+    /// output was produced
+    pub finished: usize,
+}
+
+impl CraftingTurnover {
+    /// Items consumed and produced by the crafts
+    pub fn flows(self, recipe: &Recipe) -> impl Iterator<Item = ItemFlow> + '_ {
+        let consumed = recipe
+            .ingredients
+            .iter()
+            .map(move |entry| (entry, self.started, ItemFlowKind::Consumed));
+        let produced = recipe
+            .output
+            .iter()
+            .map(move |entry| (entry, self.finished, ItemFlowKind::Produced));
+
+        consumed
+            .chain(produced)
+            .filter(|(_, times, _)| *times > 0)
+            .map(|(entry, times, kind)| ItemFlow {
+                item: entry.item.clone(),
+                quantity: entry.quantity * times,
+                kind,
+            })
+    }
+}
+
+/// Recipes without duration still take a millisecond so a machine can't craft endlessly
+fn recipe_duration(recipe: &Recipe) -> Duration {
+    Duration::from_millis(recipe.duration.max(1) as u64)
+}
+
+/// Stack size declared for an item, the default one for undeclared items
+pub fn stack_size_of(items: &ItemRegistry) -> impl Fn(&ItemId) -> usize + '_ {
+    |item| {
+        items
+            .item(item)
+            .map(|declared| declared.stack_size)
+            .unwrap_or(StackSize::default().0)
+    }
+}
+
+pub fn run_crafting_machines(
+    time: Res<SimulationTime>,
+    recipes: Res<RecipeRegistry>,
+    items: Res<ItemRegistry>,
+    research: Query<&ResearchProgress>,
+    mut machines: Query<(&mut CraftingMachine, &mut Inventory)>,
+    mut flows: EventWriter<ItemFlow>,
+) {
+    let research = research.get_single().ok();
+
+    for (mut machine, mut inventory) in machines.iter_mut() {
+        // recipes locked behind research idle the machine like undeclared ones
+        let recipe = machine
+            .recipe
+            .as_ref()
+            .and_then(|id| recipes.recipe(id).ok())
+            .map(|declared| &declared.recipe)
+            .filter(|recipe| {
+                research.map_or(recipe.research_required.is_empty(), |research| {
+                    research.allows(recipe)
+                })
+            });
+
+        let turnover = machine.advance(time.delta, recipe, &mut inventory, stack_size_of(&items));
+        if let Some(recipe) = recipe {
+            flows.send_batch(turnover.flows(recipe));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    fn entry(item: &'static str, quantity: usize) -> ItemEntry {
+        ItemEntry {
+            item: item.into(),
+            quantity,
+        }
+    }
+
+    fn recipes() -> RecipeRegistry {
+        let mut recipes = Merged::<RecipeDeclared>::default();
+        recipes.apply(
+            "tyconic",
+            Declaration::Declare(RecipeDeclared {
+                id: RecipeId("tyconic::folk_pizza".into()),
+                recipe: Recipe {
+                    ingredients: vec![
+                        entry("tyconic::cheese_wheel", 1),
+                        entry("tyconic::bread_loaf", 2),
+                    ],
+                    output: vec![entry("tyconic::pizza_slice", 4)],
+                    research_required: vec![],
+                    duration: 1000,
+                },
+            }),
+        );
+        recipes.into()
+    }
+
+    /// Headless app stepping 100ms of simulated time per update
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .insert_resource(recipes())
+            .init_resource::<ItemRegistry>()
+            .add_plugins((SimulationPlugin, CraftingPlugin));
+        app
+    }
+
+    fn machine(app: &mut App, inventory: Inventory) -> Entity {
+        app.world_mut()
+            .spawn((
+                CraftingMachine::new(RecipeId("tyconic::folk_pizza".into())),
+                inventory,
+            ))
+            .id()
+    }
+
+    fn update(app: &mut App, times: usize) {
+        for _ in 0..times {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn crafts_after_duration() {
+        let mut app = app();
+        let mut inventory = Inventory::with_capacity(4);
+        inventory.dump(vec![
+            entry("tyconic::cheese_wheel", 2),
+            entry("tyconic::bread_loaf", 3),
+        ]);
+        let machine = machine(&mut app, inventory);
+
+        // the first update only starts the clock
+        update(&mut app, 2);
+        let crafting = app.world().get::<CraftingMachine>(machine).unwrap();
+        assert_eq!(crafting.state, CraftingState::Working);
+        let inventory = app.world().get::<Inventory>(machine).unwrap();
+        assert_eq!(inventory.count(&"tyconic::cheese_wheel".into()), 1);
+        assert_eq!(inventory.count(&"tyconic::bread_loaf".into()), 1);
+
+        update(&mut app, 10);
+        let inventory = app.world().get::<Inventory>(machine).unwrap();
+        assert_eq!(inventory.count(&"tyconic::pizza_slice".into()), 4);
+
+        // a single bread loaf is left
+        let crafting = app.world().get::<CraftingMachine>(machine).unwrap();
+        assert_eq!(crafting.state, CraftingState::Starved);
+    }
+
+    #[test]
+    fn paused_machines_dont_progress() {
+        let mut app = app();
+        let mut inventory = Inventory::with_capacity(4);
+        inventory.dump(vec![
+            entry("tyconic::cheese_wheel", 1),
+            entry("tyconic::bread_loaf", 2),
+        ]);
+        let machine = machine(&mut app, inventory);
+
+        update(&mut app, 3);
+        let progress = app
+            .world()
+            .get::<CraftingMachine>(machine)
+            .unwrap()
+            .progress;
+
+        app.world_mut()
+            .resource_mut::<NextState<InGameState>>()
+            .set(InGameState::Paused);
+        update(&mut app, 30);
+
+        let crafting = app.world().get::<CraftingMachine>(machine).unwrap();
+        assert_eq!(crafting.state, CraftingState::Working);
+        assert_eq!(crafting.progress, progress);
+    }
+
+    #[test]
+    fn output_blocked_until_room() {
+        let mut app = app();
+        let mut inventory = Inventory::with_capacity(3);
+        inventory.dump(vec![
+            entry("tyconic::cheese_wheel", 1),
+            entry("tyconic::bread_loaf", 2),
+            entry("tyconic::pork_slab", 10),
+        ]);
+        let machine = machine(&mut app, inventory);
+
+        // fill the slots the ingredients were taken from
+        update(&mut app, 2);
+        let leftover = app
+            .world_mut()
+            .get_mut::<Inventory>(machine)
+            .unwrap()
+            .insert(entry("tyconic::pork_slab", 20), 10);
+        assert_eq!(leftover, None);
+
+        update(&mut app, 15);
+        let crafting = app.world().get::<CraftingMachine>(machine).unwrap();
+        assert_eq!(crafting.state, CraftingState::OutputBlocked);
+
+        app.world_mut()
+            .get_mut::<Inventory>(machine)
+            .unwrap()
+            .remove(&entry("tyconic::pork_slab", 10));
+        update(&mut app, 1);
+
+        let inventory = app.world().get::<Inventory>(machine).unwrap();
+        assert_eq!(inventory.count(&"tyconic::pizza_slice".into()), 4);
+        let crafting = app.world().get::<CraftingMachine>(machine).unwrap();
+        assert_eq!(crafting.state, CraftingState::Starved);
+    }
+
+    #[test]
+    fn undeclared_recipe_idles() {
+        let mut app = app();
+        let machine = app
+            .world_mut()
+            .spawn(CraftingMachine::new(RecipeId("tyconic::missing".into())))
+            .id();
+
+        update(&mut app, 2);
+        let crafting = app.world().get::<CraftingMachine>(machine).unwrap();
+        assert_eq!(crafting.state, CraftingState::Idle);
+    }
+}
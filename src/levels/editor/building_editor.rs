@@ -1 +1,1166 @@
 //! windowed editor for placeable entities on the map
+
+use super::{
+    edit_mod, editor_button, editor_entry, issues_summary, labeled_row, load_editor, mod_picker,
+    profile_mods, replace, save_edits, spawn_editor_window, EditorState, ModEditor, RebuildEditor,
+};
+use crate::hud::*;
+use crate::loading::*;
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub struct BuildingEditorPlugin;
+
+#[derive(Debug, Component)]
+pub struct BuildingEditor(pub crate::Meta);
+
+impl Plugin for BuildingEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(EnableHUD::ENABLED),
+            spawn_building_editor_window
+                .after(spawn_hud_backdrop)
+                .run_if(in_state(crate::DeveloperMode(true))),
+        )
+        .add_systems(
+            Update,
+            (
+                load_editor::<BuildingEditor>,
+                handle_building_editor_buttons,
+                edit_building_fields,
+                rebuild_building_editor,
+                show_building_editor_state,
+                preview_building_sprite,
+            )
+                .chain()
+                .run_if(any_with_component::<BuildingEditor>),
+        );
+    }
+}
+
+/// Kinds of sprite source offered by the editor, clicking the kind moves to the next
+pub const SPRITE_SOURCES: [&str; 4] = ["infer", "path", "infer item", "infer with states"];
+
+pub fn sprite_source_kind(source: &BuildingAssetSource) -> usize {
+    match source {
+        BuildingAssetSource::Infer => 0,
+        BuildingAssetSource::Path(_) => 1,
+        BuildingAssetSource::InferItem => 2,
+        BuildingAssetSource::InferWithStates(_) => 3,
+    }
+}
+
+pub fn sprite_source_of_kind(kind: usize) -> BuildingAssetSource {
+    match kind % SPRITE_SOURCES.len() {
+        0 => BuildingAssetSource::Infer,
+        1 => BuildingAssetSource::Path(PathBuf::new()),
+        2 => BuildingAssetSource::InferItem,
+        _ => BuildingAssetSource::InferWithStates(vec![BACK_FACING.into(), BROKEN.into()]),
+    }
+}
+
+/// Offsets of the footprint grid shown by the editor, around the tile the building is placed on
+pub const FOOTPRINT_REACH: i32 = 2;
+
+/// Fields of a building edited as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildingField {
+    Item,
+    /// path of [`BuildingAssetSource::Path`] or comma separated states of
+    /// [`BuildingAssetSource::InferWithStates`]
+    Sprite,
+    /// slots of an inventory or recipe of a crafter, at this index of the components
+    Component(usize),
+}
+
+impl BuildingField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Item => "item",
+            Self::Sprite => "sprite",
+            Self::Component(_) => "",
+        }
+    }
+}
+
+/// What is wrong with the buildings of a mod
+#[derive(Debug, Clone, PartialEq)]
+pub enum BuildingIssue {
+    InvalidId {
+        id: String,
+        error: NamespaceError,
+    },
+    DuplicateBuilding(ItemId),
+    DuplicateComponent {
+        building: ItemId,
+        component: &'static str,
+    },
+    EmptyInventory(ItemId),
+    /// the building is placed by an item nobody declares
+    UnknownItem(ItemId),
+    UnknownRecipe {
+        building: ItemId,
+        recipe: RecipeId,
+    },
+    MissingSprite {
+        building: ItemId,
+        path: PathBuf,
+    },
+}
+
+impl BuildingIssue {
+    /// Whether the buildings can't be saved, items, recipes and sprites may be made afterwards
+    pub fn blocking(&self) -> bool {
+        !matches!(
+            self,
+            Self::UnknownItem(_) | Self::UnknownRecipe { .. } | Self::MissingSprite { .. }
+        )
+    }
+}
+
+impl fmt::Display for BuildingIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidId { id, error } => write!(f, "building `{}` is invalid. {}", id, error),
+            Self::DuplicateBuilding(id) => write!(f, "building `{}` is defined twice", id.0),
+            Self::DuplicateComponent {
+                building,
+                component,
+            } => write!(f, "building `{}` has {} twice", building.0, component),
+            Self::EmptyInventory(id) => write!(f, "building `{}` has 0 inventory slots", id.0),
+            Self::UnknownItem(id) => write!(f, "no item `{}` places the building", id.0),
+            Self::UnknownRecipe { building, recipe } => {
+                write!(f, "building `{}` crafts unknown `{}`", building.0, recipe.0)
+            }
+            Self::MissingSprite { building, path } => write!(
+                f,
+                "sprite of `{}` is missing at {}",
+                building.0,
+                path.to_string_lossy()
+            ),
+        }
+    }
+}
+
+/// Buildings of one mod
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct BuildingEditorState {
+    pub mod_path: PathBuf,
+    pub namespace: Namespace,
+    /// every entry of `derivations/buildings.ron`
+    pub buildings: Vec<BuildingDefinition>,
+    /// index within the buildings of the building being edited
+    pub selected: Option<usize>,
+    pub unsaved: bool,
+}
+
+impl BuildingEditorState {
+    pub fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<Self, String> {
+        let buildings = read_building_definitions(&mod_path, type_registry)?;
+
+        Ok(Self {
+            mod_path,
+            namespace,
+            selected: (!buildings.is_empty()).then_some(0),
+            buildings,
+            unsaved: false,
+        })
+    }
+
+    pub fn selected(&self) -> Option<&BuildingDefinition> {
+        self.buildings.get(self.selected?)
+    }
+
+    fn selected_mut(&mut self) -> Option<&mut BuildingDefinition> {
+        self.buildings.get_mut(self.selected?)
+    }
+
+    /// Sets a field of the selected building, returns whether it changed
+    pub fn set_field(&mut self, field: BuildingField, text: &str) -> Result<bool, String> {
+        let Some(building) = self.selected_mut() else {
+            return Ok(false);
+        };
+
+        let changed = match field {
+            BuildingField::Item => replace(&mut building.item.0, text.trim().to_string()),
+            BuildingField::Sprite => match &mut building.texture {
+                BuildingAssetSource::Path(path) => replace(path, PathBuf::from(text.trim())),
+                BuildingAssetSource::InferWithStates(states) => replace(
+                    states,
+                    text.split(',')
+                        .map(str::trim)
+                        .filter(|state| !state.is_empty())
+                        .map(str::to_string)
+                        .collect(),
+                ),
+                _ => false,
+            },
+            BuildingField::Component(index) => match building.components.get_mut(index) {
+                Some(BuildingComponent::Inventory(slots)) => {
+                    let value = text
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("`{}` isn't a number of slots", text))?;
+                    replace(slots, value)
+                }
+                Some(BuildingComponent::Crafter(recipe)) => {
+                    let value = Some(text.trim())
+                        .filter(|id| !id.is_empty())
+                        .map(|id| RecipeId(id.to_string()));
+                    replace(recipe, value)
+                }
+                _ => false,
+            },
+        };
+
+        self.unsaved |= changed;
+        Ok(changed)
+    }
+
+    /// Covers or uncovers a tile of the footprint, the tile placed on stays covered
+    pub fn toggle_footprint(&mut self, offset: IVec2) {
+        let Some(building) = self.selected_mut() else {
+            return;
+        };
+        if offset == IVec2::ZERO {
+            return;
+        }
+
+        match building.footprint.iter().position(|cell| *cell == offset) {
+            Some(index) => {
+                building.footprint.remove(index);
+            }
+            None => building.footprint.push(offset),
+        }
+        self.unsaved = true;
+    }
+
+    /// Allows or forbids facing `direction`, a building always faces at least one way
+    pub fn toggle_rotation(&mut self, direction: BeltDirection) {
+        let Some(building) = self.selected_mut() else {
+            return;
+        };
+
+        match building
+            .rotations
+            .iter()
+            .position(|allowed| *allowed == direction)
+        {
+            Some(_) if building.rotations.len() == 1 => return,
+            Some(index) => {
+                building.rotations.remove(index);
+            }
+            None => {
+                building.rotations.push(direction);
+                // kept in compass order so the first one stays north when allowed
+                building
+                    .rotations
+                    .sort_by_key(|allowed| BeltDirection::ALL.iter().position(|d| d == allowed));
+            }
+        }
+        self.unsaved = true;
+    }
+
+    pub fn cycle_sprite_source(&mut self) {
+        let Some(building) = self.selected_mut() else {
+            return;
+        };
+
+        building.texture = sprite_source_of_kind(sprite_source_kind(&building.texture) + 1);
+        self.unsaved = true;
+    }
+
+    pub fn cycle_placement(&mut self) {
+        let Some(building) = self.selected_mut() else {
+            return;
+        };
+
+        let index = PlacementRule::ALL
+            .iter()
+            .position(|rule| *rule == building.placement)
+            .unwrap_or_default();
+        building.placement = PlacementRule::ALL[(index + 1) % PlacementRule::ALL.len()];
+        self.unsaved = true;
+    }
+
+    /// Adds a component of the kind unless the building already has one
+    pub fn add_component(&mut self, component: BuildingComponent) -> bool {
+        let Some(building) = self.selected_mut() else {
+            return false;
+        };
+        if building.has(&component) {
+            return false;
+        }
+
+        building.components.push(component);
+        self.unsaved = true;
+        true
+    }
+
+    pub fn remove_component(&mut self, index: usize) {
+        let Some(building) = self.selected_mut() else {
+            return;
+        };
+        if index < building.components.len() {
+            building.components.remove(index);
+            self.unsaved = true;
+        }
+    }
+
+    /// Defines a building for the first item of the mod without one, or one with a new id,
+    /// and selects it
+    pub fn add_building(&mut self, items: &ItemRegistry) -> usize {
+        let prefix = format!("{}{}", self.namespace, NAMESPACE_SEPARATOR);
+        let taken = self
+            .buildings
+            .iter()
+            .map(|building| namespaced(self.namespace, &building.item.0))
+            .collect::<HashSet<_>>();
+
+        let mut unbuilt = items
+            .in_namespace(self.namespace)
+            .map(|declared| declared.id.0.clone())
+            .filter(|id| !taken.contains(id))
+            .collect::<Vec<_>>();
+        unbuilt.sort();
+
+        let id = match unbuilt.first() {
+            Some(id) => id.trim_start_matches(&prefix).to_string(),
+            None => (1..)
+                .map(|n| match n {
+                    1 => "new_building".to_string(),
+                    n => format!("new_building_{}", n),
+                })
+                .find(|id| !taken.contains(&namespaced(self.namespace, id)))
+                .unwrap(),
+        };
+
+        self.buildings.push(ItemId(id).into());
+        self.selected = Some(self.buildings.len() - 1);
+        self.unsaved = true;
+        self.buildings.len() - 1
+    }
+
+    pub fn remove_building(&mut self, index: usize) {
+        if index >= self.buildings.len() {
+            return;
+        }
+
+        self.buildings.remove(index);
+        self.selected = (!self.buildings.is_empty()).then_some(0);
+        self.unsaved = true;
+    }
+
+    pub fn issues(&self, items: &ItemRegistry, recipes: &RecipeRegistry) -> Vec<BuildingIssue> {
+        let mut issues = vec![];
+
+        let mut seen = HashSet::new();
+        for building in self.buildings.iter() {
+            let id = &building.item;
+            if let Err(error) = NamespacedId::parse_in(self.namespace, &id.0) {
+                issues.push(BuildingIssue::InvalidId {
+                    id: id.0.clone(),
+                    error,
+                });
+            }
+            if !seen.insert(namespaced(self.namespace, &id.0)) {
+                issues.push(BuildingIssue::DuplicateBuilding(id.clone()));
+            }
+
+            for (index, component) in building.components.iter().enumerate() {
+                if building.components[..index]
+                    .iter()
+                    .any(|other| other.same_kind(component))
+                {
+                    issues.push(BuildingIssue::DuplicateComponent {
+                        building: id.clone(),
+                        component: component.label(),
+                    });
+                }
+                if *component == BuildingComponent::Inventory(0) {
+                    issues.push(BuildingIssue::EmptyInventory(id.clone()));
+                }
+            }
+        }
+
+        for building in self.buildings.iter() {
+            let id = &building.item;
+            if items
+                .item(&ItemId(namespaced(self.namespace, &id.0)))
+                .is_err()
+            {
+                issues.push(BuildingIssue::UnknownItem(id.clone()));
+            }
+
+            for component in building.components.iter() {
+                if let BuildingComponent::Crafter(Some(recipe)) = component {
+                    if recipes
+                        .recipe(&RecipeId(namespaced(self.namespace, &recipe.0)))
+                        .is_err()
+                    {
+                        issues.push(BuildingIssue::UnknownRecipe {
+                            building: id.clone(),
+                            recipe: recipe.clone(),
+                        });
+                    }
+                }
+            }
+
+            // sprites at a path are looked up by the asset server, inferred ones are in the mod
+            if !matches!(building.texture, BuildingAssetSource::Path(_)) {
+                issues.extend(
+                    resolve_building_sprites(&self.mod_path, &id.0, &building.texture)
+                        .into_iter()
+                        .filter(|(_, path)| !path.exists())
+                        .map(|(_, path)| BuildingIssue::MissingSprite {
+                            building: id.clone(),
+                            path,
+                        }),
+                );
+            }
+        }
+
+        issues
+    }
+
+    pub fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        write_building_definitions(
+            &self.mod_path,
+            &BuildingDefinitions(self.buildings.clone()),
+            type_registry,
+        )?;
+
+        self.unsaved = false;
+        Ok(())
+    }
+}
+
+impl EditorState for BuildingEditorState {
+    const KIND: &'static str = "buildings";
+
+    fn unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        BuildingEditorState::save(self, type_registry)
+    }
+
+    fn path(&self) -> &Path {
+        &self.mod_path
+    }
+
+    fn summary(&self) -> String {
+        format!("{} buildings of {}", self.buildings.len(), self.namespace)
+    }
+}
+
+impl ModEditor for BuildingEditor {
+    type State = BuildingEditorState;
+
+    fn edited(&mut self) -> &mut Meta {
+        &mut self.0
+    }
+
+    fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<BuildingEditorState, String> {
+        BuildingEditorState::read(mod_path, namespace, type_registry)
+    }
+}
+
+/// Content of the editor window, respawned whenever what is edited changes shape
+#[derive(Debug, Component)]
+pub struct BuildingEditorBody;
+
+#[derive(Debug, Component, Clone, PartialEq)]
+pub enum BuildingEditorButton {
+    /// edit the buildings of the mod at this index of the profile
+    EditMod(usize),
+    Select(usize),
+    NewBuilding,
+    RemoveBuilding,
+    Footprint(IVec2),
+    Rotation(BeltDirection),
+    /// moves the sprite source of the selected building to the next kind
+    SpriteSource,
+    Placement,
+    AddComponent(BuildingComponent),
+    RemoveComponent(usize),
+    Save,
+}
+
+#[derive(Debug, Component)]
+pub struct BuildingFieldInput(pub BuildingField);
+
+/// Item of the building at this index of the buildings
+#[derive(Debug, Component)]
+pub struct BuildingEntryLabel(pub usize);
+
+#[derive(Debug, Component)]
+pub struct BuildingEditorIssues;
+
+/// Sprite of the selected building
+#[derive(Debug, Component)]
+pub struct BuildingSpritePreview;
+
+pub fn spawn_building_editor_window(
+    mut cmd: Commands,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+    hud_backdrop: HUDBackdropQuery,
+) {
+    let pack = crate::levels::pack::base_mod();
+
+    spawn_editor_window(
+        &mut cmd,
+        hud_backdrop.single(),
+        BuildingEditor(pack.meta.clone()),
+        BuildingEditorBody,
+        "building editor",
+        &ui,
+        &fonts,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_building_editor_buttons(
+    mut cmd: Commands,
+    buttons: Query<(&DepressButton, &BuildingEditorButton), Changed<DepressButton>>,
+    mut editor: Query<(Entity, &mut BuildingEditor, &mut BuildingEditorState)>,
+    profile: Query<&ModProfile, With<Level>>,
+    items: Res<ItemRegistry>,
+    recipes: Res<RecipeRegistry>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok((entity, mut editor, mut state)) = editor.get_single_mut() else {
+        return;
+    };
+
+    let pressed = buttons
+        .iter()
+        .filter(|(depress, _)| depress.invoked())
+        .map(|(_, button)| button.clone())
+        .collect::<Vec<_>>();
+
+    for button in pressed {
+        let reshaped = match button {
+            BuildingEditorButton::EditMod(index) => {
+                edit_mod(
+                    &mut cmd,
+                    entity,
+                    &mut editor.0,
+                    &*state,
+                    &profile,
+                    index,
+                    &mut notifications_channel,
+                );
+                false
+            }
+            BuildingEditorButton::Select(index) => {
+                state.selected = Some(index);
+                true
+            }
+            BuildingEditorButton::NewBuilding => {
+                state.add_building(&items);
+                true
+            }
+            BuildingEditorButton::RemoveBuilding => {
+                if let Some(selected) = state.selected {
+                    state.remove_building(selected);
+                }
+                true
+            }
+            BuildingEditorButton::Footprint(offset) => {
+                state.toggle_footprint(offset);
+                true
+            }
+            BuildingEditorButton::Rotation(direction) => {
+                state.toggle_rotation(direction);
+                true
+            }
+            BuildingEditorButton::SpriteSource => {
+                state.cycle_sprite_source();
+                true
+            }
+            BuildingEditorButton::Placement => {
+                state.cycle_placement();
+                true
+            }
+            BuildingEditorButton::AddComponent(component) => state.add_component(component),
+            BuildingEditorButton::RemoveComponent(index) => {
+                state.remove_component(index);
+                true
+            }
+            BuildingEditorButton::Save => {
+                save_buildings(
+                    &mut state,
+                    &items,
+                    &recipes,
+                    &type_registry.read(),
+                    &mut notifications_channel,
+                );
+                false
+            }
+        };
+
+        if reshaped {
+            cmd.entity(entity).insert(RebuildEditor);
+        }
+    }
+}
+
+/// Buildings are only written once nothing blocking is wrong with them
+fn save_buildings(
+    state: &mut BuildingEditorState,
+    items: &ItemRegistry,
+    recipes: &RecipeRegistry,
+    type_registry: &TypeRegistry,
+    notifications_channel: &mut NotificationChannel,
+) {
+    let issues = state
+        .issues(items, recipes)
+        .into_iter()
+        .filter(BuildingIssue::blocking)
+        .collect::<Vec<_>>();
+    save_edits(state, &issues, type_registry, notifications_channel);
+}
+
+fn edit_building_fields(
+    fields: Query<(&TextField, &BuildingFieldInput), Changed<TextField>>,
+    mut editor: Query<&mut BuildingEditorState>,
+) {
+    let Ok(mut state) = editor.get_single_mut() else {
+        return;
+    };
+
+    for (field, BuildingFieldInput(building_field)) in fields.iter() {
+        // half typed numbers are kept in the field until they parse
+        let _ = state.set_field(*building_field, &field.value);
+    }
+}
+
+fn rebuild_building_editor(
+    mut cmd: Commands,
+    editor: Query<(Entity, &BuildingEditor, &BuildingEditorState), With<RebuildEditor>>,
+    body: Query<Entity, With<BuildingEditorBody>>,
+    profile: Query<&ModProfile, With<Level>>,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+) {
+    let (Ok((entity, editor, state)), Ok(body)) = (editor.get_single(), body.get_single()) else {
+        return;
+    };
+    cmd.entity(entity).remove::<RebuildEditor>();
+
+    let mods = profile_mods(&profile);
+
+    cmd.entity(body)
+        .despawn_descendants()
+        .with_children(|parent| {
+            // buildings of the mod
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(35.),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    mod_picker(
+                        &editor.0,
+                        &mods,
+                        BuildingEditorButton::EditMod,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+
+                    for (index, building) in state.buildings.iter().enumerate() {
+                        editor_entry(
+                            &building.item.0,
+                            state.selected == Some(index),
+                            BuildingEditorButton::Select(index),
+                            BuildingEntryLabel(index),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    }
+
+                    editor_button(
+                        "new building",
+                        BuildingEditorButton::NewBuilding,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+                    editor_button("save", BuildingEditorButton::Save, parent, &fonts, &ui);
+                    body_text("", parent, &fonts).insert(BuildingEditorIssues);
+                });
+
+            // the selected building
+            parent
+                .spawn((
+                    Node {
+                        flex_grow: 1.,
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    let Some(building) = state.selected() else {
+                        section_text("no building selected", parent, &fonts);
+                        return;
+                    };
+
+                    parent.spawn((
+                        BuildingSpritePreview,
+                        ImageNode::default(),
+                        Node {
+                            width: Val::Px(UI_SCALE * 8.),
+                            height: Val::Px(UI_SCALE * 8.),
+                            flex_shrink: 0.,
+                            ..default()
+                        },
+                    ));
+
+                    labeled_row(BuildingField::Item.label(), parent, &fonts).with_children(
+                        |parent| {
+                            spawn_text_field(
+                                &building.item.0,
+                                BuildingFieldInput(BuildingField::Item),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        },
+                    );
+
+                    labeled_row(BuildingField::Sprite.label(), parent, &fonts).with_children(
+                        |parent| {
+                            editor_button(
+                                SPRITE_SOURCES[sprite_source_kind(&building.texture)],
+                                BuildingEditorButton::SpriteSource,
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+
+                            let value = match &building.texture {
+                                BuildingAssetSource::Path(path) => {
+                                    path.to_string_lossy().to_string()
+                                }
+                                BuildingAssetSource::InferWithStates(states) => states.join(", "),
+                                _ => return,
+                            };
+                            spawn_text_field(
+                                &value,
+                                BuildingFieldInput(BuildingField::Sprite),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        },
+                    );
+
+                    labeled_row("placement", parent, &fonts).with_children(|parent| {
+                        editor_button(
+                            building.placement.label(),
+                            BuildingEditorButton::Placement,
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    });
+
+                    labeled_row("rotations", parent, &fonts).with_children(|parent| {
+                        for direction in BeltDirection::ALL {
+                            editor_entry(
+                                &format!("{:?}", direction).to_lowercase(),
+                                building.rotations.contains(&direction),
+                                BuildingEditorButton::Rotation(direction),
+                                (),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        }
+                    });
+                    separator(parent);
+
+                    // north is up, the tile placed on sits in the middle
+                    section_text("footprint", parent, &fonts);
+                    for y in (-FOOTPRINT_REACH..=FOOTPRINT_REACH).rev() {
+                        parent
+                            .spawn(Node {
+                                column_gap: Val::Px(UI_SCALE),
+                                flex_shrink: 0.,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                for x in -FOOTPRINT_REACH..=FOOTPRINT_REACH {
+                                    let offset = IVec2::new(x, y);
+                                    editor_entry(
+                                        if offset == IVec2::ZERO { "o" } else { " " },
+                                        offset == IVec2::ZERO
+                                            || building.footprint.contains(&offset),
+                                        BuildingEditorButton::Footprint(offset),
+                                        (),
+                                        parent,
+                                        &fonts,
+                                        &ui,
+                                    );
+                                }
+                            });
+                    }
+                    separator(parent);
+
+                    section_text("components", parent, &fonts);
+                    for (index, component) in building.components.iter().enumerate() {
+                        labeled_row(component.label(), parent, &fonts).with_children(|parent| {
+                            let value = match component {
+                                BuildingComponent::Inventory(slots) => Some(slots.to_string()),
+                                BuildingComponent::Crafter(recipe) => {
+                                    Some(recipe.as_ref().map_or(String::new(), |id| id.0.clone()))
+                                }
+                                _ => None,
+                            };
+                            if let Some(value) = value {
+                                spawn_text_field(
+                                    &value,
+                                    BuildingFieldInput(BuildingField::Component(index)),
+                                    parent,
+                                    &fonts,
+                                    &ui,
+                                );
+                            }
+                            editor_button(
+                                "remove",
+                                BuildingEditorButton::RemoveComponent(index),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        });
+                    }
+                    parent
+                        .spawn(Node {
+                            column_gap: Val::Px(UI_SCALE),
+                            flex_wrap: FlexWrap::Wrap,
+                            flex_shrink: 0.,
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            for component in BuildingComponent::kinds()
+                                .into_iter()
+                                .filter(|component| !building.has(component))
+                            {
+                                editor_button(
+                                    &format!("+ {}", component.label()),
+                                    BuildingEditorButton::AddComponent(component),
+                                    parent,
+                                    &fonts,
+                                    &ui,
+                                );
+                            }
+                        });
+                    separator(parent);
+
+                    editor_button(
+                        "remove building",
+                        BuildingEditorButton::RemoveBuilding,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+                });
+        });
+}
+
+/// Keeps building names and issues up to date while typing
+fn show_building_editor_state(
+    editor: Query<&BuildingEditorState>,
+    mut labels: Query<(&BuildingEntryLabel, &mut Text), Without<BuildingEditorIssues>>,
+    mut issues_text: Query<&mut Text, With<BuildingEditorIssues>>,
+    fields: Query<(), Changed<TextField>>,
+    items: Res<ItemRegistry>,
+    recipes: Res<RecipeRegistry>,
+) {
+    let Ok(state) = editor.get_single() else {
+        return;
+    };
+    if fields.is_empty() && issues_text.iter().all(|text| !text.0.is_empty()) {
+        return;
+    }
+
+    for (BuildingEntryLabel(index), mut text) in labels.iter_mut() {
+        if let Some(building) = state.buildings.get(*index) {
+            if text.0 != building.item.0 {
+                text.0 = building.item.0.clone();
+            }
+        }
+    }
+
+    let issues = state.issues(&items, &recipes);
+    let summary = issues_summary(&issues, state.unsaved);
+
+    for mut text in issues_text.iter_mut() {
+        if text.0 != summary {
+            text.0 = summary.clone();
+        }
+    }
+}
+
+/// Sprite of a building of the mod at `mod_path` loaded from where it would be read, buildings
+/// drawn as their item show the texture of the item
+pub(crate) fn building_sprite(
+    mod_path: &Path,
+    namespace: Namespace,
+    building: &BuildingDefinition,
+    textures: Option<&ItemTextureMap>,
+    asset_server: &AssetServer,
+) -> Handle<Image> {
+    let id = &building.item.0;
+    match resolve_building_sprites(mod_path, id, &building.texture).first() {
+        Some((_, path)) => asset_server.load(path.clone()),
+        None => textures
+            .and_then(|textures| textures.0.get(&namespaced(namespace, id)))
+            .cloned()
+            .unwrap_or_default(),
+    }
+}
+
+fn preview_building_sprite(
+    editor: Query<&BuildingEditorState>,
+    mut previews: Query<&mut ImageNode, With<BuildingSpritePreview>>,
+    textures: Query<&ItemTextureMap, With<Level>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(state) = editor.get_single() else {
+        return;
+    };
+    let Some(building) = state.selected() else {
+        return;
+    };
+
+    let image = building_sprite(
+        &state.mod_path,
+        state.namespace,
+        building,
+        textures.get_single().ok(),
+        &asset_server,
+    );
+
+    for mut preview in previews.iter_mut() {
+        if preview.image != image {
+            preview.image = image.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::*;
+    use bevy_ecs_tilemap::prelude::TilePos;
+
+    fn editor(buildings: &[&'static str]) -> BuildingEditorState {
+        BuildingEditorState {
+            mod_path: PathBuf::new(),
+            namespace: Namespace::new("tyconic").unwrap(),
+            buildings: buildings
+                .iter()
+                .map(|id| BuildingDefinition {
+                    texture: BuildingAssetSource::Path(format!("{}.png", id).into()),
+                    ..ItemId::from(*id).into()
+                })
+                .collect(),
+            selected: Some(0),
+            unsaved: false,
+        }
+    }
+
+    #[test]
+    fn footprint_and_rotations_keep_the_building_placeable() {
+        let mut state = editor(&["oven"]);
+
+        state.toggle_footprint(IVec2::ZERO);
+        assert_eq!(state.selected().unwrap().footprint, vec![IVec2::ZERO]);
+        state.toggle_footprint(IVec2::new(1, 0));
+        state.toggle_footprint(IVec2::new(0, -1));
+        state.toggle_footprint(IVec2::new(1, 0));
+        assert_eq!(
+            state.selected().unwrap().footprint,
+            vec![IVec2::ZERO, IVec2::new(0, -1)]
+        );
+
+        state.toggle_rotation(BeltDirection::North);
+        assert_eq!(
+            state.selected().unwrap().rotations,
+            vec![BeltDirection::North]
+        );
+        state.toggle_rotation(BeltDirection::West);
+        state.toggle_rotation(BeltDirection::East);
+        state.toggle_rotation(BeltDirection::North);
+        assert_eq!(
+            state.selected().unwrap().rotations,
+            vec![BeltDirection::East, BeltDirection::West]
+        );
+        assert!(state.unsaved);
+    }
+
+    #[test]
+    fn building_fields_are_parsed() {
+        let mut state = editor(&["oven"]);
+
+        assert!(state.add_component(BuildingComponent::Inventory(4)));
+        assert!(!state.add_component(BuildingComponent::Inventory(8)));
+        assert!(state.add_component(BuildingComponent::Crafter(None)));
+
+        assert!(state.set_field(BuildingField::Component(0), "6").unwrap());
+        assert!(state.set_field(BuildingField::Component(0), "six").is_err());
+        assert!(state
+            .set_field(BuildingField::Component(1), "folk_pizza")
+            .unwrap());
+        assert_eq!(
+            state.selected().unwrap().components,
+            vec![
+                BuildingComponent::Inventory(6),
+                BuildingComponent::Crafter(Some(RecipeId("folk_pizza".into())))
+            ]
+        );
+
+        assert!(state.set_field(BuildingField::Sprite, "oven.png").is_ok());
+        state.cycle_sprite_source();
+        state.cycle_sprite_source();
+        assert!(state
+            .set_field(BuildingField::Sprite, "back-facing, , lit")
+            .unwrap());
+        assert_eq!(
+            state.selected().unwrap().states(),
+            &["back-facing".to_string(), "lit".to_string()]
+        );
+
+        state.cycle_placement();
+        assert_eq!(
+            state.selected().unwrap().placement,
+            PlacementRule::FloorOnly
+        );
+    }
+
+    #[test]
+    fn new_buildings_go_to_items_without_one() {
+        let items = item_registry(&["tyconic::oven", "tyconic::counter", "base::auto_arm"]);
+
+        let mut state = editor(&["oven"]);
+        state.add_building(&items);
+        assert_eq!(state.selected().unwrap().item.0, "counter");
+        state.add_building(&items);
+        assert_eq!(state.selected().unwrap().item.0, "new_building");
+
+        state.remove_building(1);
+        assert_eq!(state.buildings.len(), 2);
+        assert_eq!(state.selected, Some(0));
+    }
+
+    #[test]
+    fn issues_name_typos() {
+        let items = item_registry(&["tyconic::oven"]);
+        let recipes = RecipeRegistry::default();
+
+        let mut state = editor(&["oven", "tyconic::oven", "oven "]);
+        state.add_component(BuildingComponent::Inventory(0));
+        state.buildings[0]
+            .components
+            .push(BuildingComponent::Inventory(2));
+        state.buildings[1]
+            .components
+            .push(BuildingComponent::Crafter(Some(RecipeId(
+                "folk_pizza".into(),
+            ))));
+
+        let issues = state.issues(&items, &recipes);
+        assert_eq!(
+            issues,
+            vec![
+                BuildingIssue::EmptyInventory(ItemId::from("oven")),
+                BuildingIssue::DuplicateComponent {
+                    building: ItemId::from("oven"),
+                    component: "inventory",
+                },
+                BuildingIssue::DuplicateBuilding(ItemId::from("tyconic::oven")),
+                BuildingIssue::InvalidId {
+                    id: "oven ".into(),
+                    error: NamespaceError::InvalidId("oven ".into()),
+                },
+                BuildingIssue::UnknownRecipe {
+                    building: ItemId::from("tyconic::oven"),
+                    recipe: RecipeId("folk_pizza".into()),
+                },
+                BuildingIssue::UnknownItem(ItemId::from("oven ")),
+            ]
+        );
+        assert_eq!(issues.iter().filter(|issue| !issue.blocking()).count(), 2);
+    }
+
+    #[test]
+    fn saved_buildings_are_read_back() {
+        let mut type_registry = TypeRegistry::default();
+        type_registry.register::<BuildingDefinitions>();
+
+        let mod_path = std::env::temp_dir()
+            .join(format!("tyconia_building_editor_{}", std::process::id()))
+            .join("tyconic");
+        let namespace = Namespace::new("tyconic").unwrap();
+
+        let mut state =
+            BuildingEditorState::read(mod_path.clone(), namespace, &type_registry).unwrap();
+        assert!(state.buildings.is_empty());
+
+        state.add_building(&ItemRegistry::default());
+        state.set_field(BuildingField::Item, "oven").unwrap();
+        state.toggle_footprint(IVec2::X);
+        state.toggle_rotation(BeltDirection::South);
+        state.cycle_placement();
+        state.add_component(BuildingComponent::Crafter(None));
+        state.save(&type_registry).unwrap();
+        assert!(!state.unsaved);
+
+        let read = BuildingEditorState::read(mod_path.clone(), namespace, &type_registry).unwrap();
+        assert_eq!(read.buildings, state.buildings);
+
+        let mut oven = read_building_definitions(&mod_path, &type_registry)
+            .unwrap()
+            .remove(0);
+        oven.namespace(namespace);
+        assert_eq!(oven.item.0, "tyconic::oven");
+        assert_eq!(
+            oven.cells(TilePos { x: 3, y: 3 }, BeltDirection::South),
+            Some(vec![TilePos { x: 3, y: 3 }, TilePos { x: 2, y: 3 }])
+        );
+
+        let _ = std::fs::remove_dir_all(mod_path.parent().unwrap());
+    }
+}
@@ -1 +1,921 @@
 //! windowed editor for entities that process input and output
+
+use super::{
+    building_sprite, edit_mod, editor_button, editor_entry, issues_summary, labeled_row,
+    load_editor, mod_picker, profile_mods, replace, save_edits, spawn_editor_window, EditorState,
+    ModEditor, RebuildEditor,
+};
+use crate::hud::*;
+use crate::loading::*;
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+pub struct IoEditorPlugin;
+
+#[derive(Debug, Component)]
+pub struct IoEditor(pub crate::Meta);
+
+impl Plugin for IoEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            OnEnter(EnableHUD::ENABLED),
+            spawn_io_editor_window
+                .after(spawn_hud_backdrop)
+                .run_if(in_state(crate::DeveloperMode(true))),
+        )
+        .add_systems(
+            Update,
+            (
+                load_editor::<IoEditor>,
+                handle_io_editor_buttons,
+                edit_io_fields,
+                rebuild_io_editor,
+                show_io_editor_state,
+                preview_io_building_sprite,
+            )
+                .chain()
+                .run_if(any_with_component::<IoEditor>),
+        );
+    }
+}
+
+/// Width of a tile in the drawing of a building, tiles are half as high as they are wide like
+/// those of the map
+pub const IO_TILE_WIDTH: f32 = UI_SCALE * 12.;
+
+/// Where a point of a footprint facing north lands in the drawing of a building, relative to the
+/// center of the tile the building is placed on. Tiles are laid out like
+/// [`IsoCoordSystem::Diamond`] tilemaps lay them out, y grows downwards like in the UI
+pub fn iso_offset(point: Vec2, tile_width: f32) -> Vec2 {
+    Vec2::new(
+        (point.x + point.y) * tile_width / 2.,
+        (point.x - point.y) * tile_width / 4.,
+    )
+}
+
+/// Fields of the selected port edited as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IoField {
+    /// comma separated items passing the port
+    Filter,
+    Priority,
+}
+
+impl IoField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Filter => "filter",
+            Self::Priority => "priority",
+        }
+    }
+}
+
+/// What is wrong with the ports of a mod
+#[derive(Debug, Clone, PartialEq)]
+pub enum IoIssue {
+    PortOffFootprint {
+        building: ItemId,
+        tile: IVec2,
+    },
+    /// the edge leads to another tile of the same building
+    InnerEdge {
+        building: ItemId,
+        tile: IVec2,
+        edge: BeltDirection,
+    },
+    DuplicatePort {
+        building: ItemId,
+        tile: IVec2,
+        edge: BeltDirection,
+    },
+    UnknownFilterItem {
+        building: ItemId,
+        item: ItemId,
+    },
+    /// ports of a building holding no items pass nothing
+    NoInventory(ItemId),
+}
+
+impl IoIssue {
+    /// Whether the ports can't be saved, filtered items and inventories may be added afterwards
+    pub fn blocking(&self) -> bool {
+        !matches!(self, Self::UnknownFilterItem { .. } | Self::NoInventory(_))
+    }
+}
+
+impl fmt::Display for IoIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PortOffFootprint { building, tile } => write!(
+                f,
+                "`{}` has a port at {},{} off its footprint",
+                building.0, tile.x, tile.y
+            ),
+            Self::InnerEdge {
+                building,
+                tile,
+                edge,
+            } => write!(
+                f,
+                "`{}` has a port at {},{} {:?} facing itself",
+                building.0, tile.x, tile.y, edge
+            ),
+            Self::DuplicatePort {
+                building,
+                tile,
+                edge,
+            } => write!(
+                f,
+                "`{}` has two ports at {},{} {:?}",
+                building.0, tile.x, tile.y, edge
+            ),
+            Self::UnknownFilterItem { building, item } => {
+                write!(
+                    f,
+                    "a port of `{}` lets unknown `{}` pass",
+                    building.0, item.0
+                )
+            }
+            Self::NoInventory(building) => {
+                write!(f, "`{}` has ports but holds no items", building.0)
+            }
+        }
+    }
+}
+
+/// Ports of the buildings of one mod
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct IoEditorState {
+    pub mod_path: PathBuf,
+    pub namespace: Namespace,
+    /// every entry of `derivations/buildings.ron`, written back whole
+    pub buildings: Vec<BuildingDefinition>,
+    /// index within the buildings of the building being edited
+    pub selected: Option<usize>,
+    /// index within the ports of the selected building
+    pub selected_port: Option<usize>,
+    pub unsaved: bool,
+}
+
+impl IoEditorState {
+    pub fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<Self, String> {
+        let buildings = read_building_definitions(&mod_path, type_registry)?;
+
+        Ok(Self {
+            mod_path,
+            namespace,
+            selected: (!buildings.is_empty()).then_some(0),
+            buildings,
+            selected_port: None,
+            unsaved: false,
+        })
+    }
+
+    pub fn selected(&self) -> Option<&BuildingDefinition> {
+        self.buildings.get(self.selected?)
+    }
+
+    fn selected_mut(&mut self) -> Option<&mut BuildingDefinition> {
+        self.buildings.get_mut(self.selected?)
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = Some(index);
+        self.selected_port = None;
+    }
+
+    pub fn selected_port(&self) -> Option<&IoPort> {
+        self.selected()?.ports.get(self.selected_port?)
+    }
+
+    fn selected_port_mut(&mut self) -> Option<&mut IoPort> {
+        let index = self.selected_port?;
+        self.selected_mut()?.ports.get_mut(index)
+    }
+
+    /// Selects the port on an edge of a tile of the selected building, adding an input there
+    /// when there is none
+    pub fn click_edge(&mut self, tile: IVec2, edge: BeltDirection) {
+        let Some(building) = self.selected_mut() else {
+            return;
+        };
+
+        let found = building
+            .ports
+            .iter()
+            .position(|port| port.tile == tile && port.edge == edge);
+        let index = found.unwrap_or_else(|| {
+            building
+                .ports
+                .push(IoPort::new(PortKind::Input, tile, edge));
+            building.ports.len() - 1
+        });
+
+        self.unsaved |= found.is_none();
+        self.selected_port = Some(index);
+    }
+
+    /// Turns an input port into an output port and back
+    pub fn toggle_kind(&mut self) {
+        let Some(port) = self.selected_port_mut() else {
+            return;
+        };
+
+        port.kind = port.kind.other();
+        self.unsaved = true;
+    }
+
+    pub fn remove_port(&mut self) {
+        let Some(index) = self.selected_port.take() else {
+            return;
+        };
+        let Some(building) = self.selected_mut() else {
+            return;
+        };
+
+        if index < building.ports.len() {
+            building.ports.remove(index);
+            self.unsaved = true;
+        }
+    }
+
+    /// Sets a field of the selected port, returns whether it changed
+    pub fn set_field(&mut self, field: IoField, text: &str) -> Result<bool, String> {
+        let Some(port) = self.selected_port_mut() else {
+            return Ok(false);
+        };
+
+        let changed = match field {
+            IoField::Filter => replace(
+                &mut port.filter,
+                text.split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(|id| ItemId(id.to_string()))
+                    .collect(),
+            ),
+            IoField::Priority => {
+                let value = text
+                    .trim()
+                    .parse::<i32>()
+                    .map_err(|_| format!("`{}` isn't a priority", text))?;
+                replace(&mut port.priority, value)
+            }
+        };
+
+        self.unsaved |= changed;
+        Ok(changed)
+    }
+
+    pub fn issues(&self, items: &ItemRegistry) -> Vec<IoIssue> {
+        let mut issues = vec![];
+
+        for building in self.buildings.iter() {
+            let id = &building.item;
+            for (index, port) in building.ports.iter().enumerate() {
+                if !building.footprint.contains(&port.tile) && port.tile != IVec2::ZERO {
+                    issues.push(IoIssue::PortOffFootprint {
+                        building: id.clone(),
+                        tile: port.tile,
+                    });
+                }
+                let beyond = port.tile + port.edge.offset();
+                if beyond == IVec2::ZERO || building.footprint.contains(&beyond) {
+                    issues.push(IoIssue::InnerEdge {
+                        building: id.clone(),
+                        tile: port.tile,
+                        edge: port.edge,
+                    });
+                }
+                if building.ports[..index]
+                    .iter()
+                    .any(|other| other.tile == port.tile && other.edge == port.edge)
+                {
+                    issues.push(IoIssue::DuplicatePort {
+                        building: id.clone(),
+                        tile: port.tile,
+                        edge: port.edge,
+                    });
+                }
+            }
+        }
+
+        for building in self.buildings.iter() {
+            let id = &building.item;
+            for port in building.ports.iter() {
+                issues.extend(
+                    port.filter
+                        .iter()
+                        .filter(|item| {
+                            items
+                                .item(&ItemId(namespaced(self.namespace, &item.0)))
+                                .is_err()
+                        })
+                        .map(|item| IoIssue::UnknownFilterItem {
+                            building: id.clone(),
+                            item: item.clone(),
+                        }),
+                );
+            }
+
+            let holds_items = building.components.iter().any(|component| {
+                matches!(
+                    component,
+                    BuildingComponent::Inventory(_)
+                        | BuildingComponent::Crafter(_)
+                        | BuildingComponent::InfiniteIo
+                )
+            });
+            if !building.ports.is_empty() && !holds_items {
+                issues.push(IoIssue::NoInventory(id.clone()));
+            }
+        }
+
+        issues
+    }
+
+    pub fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        write_building_definitions(
+            &self.mod_path,
+            &BuildingDefinitions(self.buildings.clone()),
+            type_registry,
+        )?;
+
+        self.unsaved = false;
+        Ok(())
+    }
+}
+
+impl EditorState for IoEditorState {
+    const KIND: &'static str = "ports";
+
+    fn unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        IoEditorState::save(self, type_registry)
+    }
+
+    fn path(&self) -> &Path {
+        &self.mod_path
+    }
+
+    fn summary(&self) -> String {
+        format!(
+            "{} ports of {}",
+            self.buildings
+                .iter()
+                .map(|building| building.ports.len())
+                .sum::<usize>(),
+            self.namespace
+        )
+    }
+}
+
+impl ModEditor for IoEditor {
+    type State = IoEditorState;
+
+    fn edited(&mut self) -> &mut Meta {
+        &mut self.0
+    }
+
+    fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<IoEditorState, String> {
+        IoEditorState::read(mod_path, namespace, type_registry)
+    }
+}
+
+/// Content of the editor window, respawned whenever what is edited changes shape
+#[derive(Debug, Component)]
+pub struct IoEditorBody;
+
+#[derive(Debug, Component, Clone, PartialEq)]
+pub enum IoEditorButton {
+    /// edit the ports of the mod at this index of the profile
+    EditMod(usize),
+    Select(usize),
+    /// an edge of a tile of the footprint
+    Edge {
+        tile: IVec2,
+        edge: BeltDirection,
+    },
+    PortKind,
+    RemovePort,
+    Save,
+}
+
+#[derive(Debug, Component)]
+pub struct IoFieldInput(pub IoField);
+
+#[derive(Debug, Component)]
+pub struct IoEditorIssues;
+
+/// Sprite of the selected building under its ports
+#[derive(Debug, Component)]
+pub struct IoSpritePreview;
+
+pub fn spawn_io_editor_window(
+    mut cmd: Commands,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+    hud_backdrop: HUDBackdropQuery,
+) {
+    let pack = crate::levels::pack::base_mod();
+
+    spawn_editor_window(
+        &mut cmd,
+        hud_backdrop.single(),
+        IoEditor(pack.meta.clone()),
+        IoEditorBody,
+        "io editor",
+        &ui,
+        &fonts,
+    );
+}
+
+fn handle_io_editor_buttons(
+    mut cmd: Commands,
+    buttons: Query<(&DepressButton, &IoEditorButton), Changed<DepressButton>>,
+    mut editor: Query<(Entity, &mut IoEditor, &mut IoEditorState)>,
+    profile: Query<&ModProfile, With<Level>>,
+    items: Res<ItemRegistry>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok((entity, mut editor, mut state)) = editor.get_single_mut() else {
+        return;
+    };
+
+    let pressed = buttons
+        .iter()
+        .filter(|(depress, _)| depress.invoked())
+        .map(|(_, button)| button.clone())
+        .collect::<Vec<_>>();
+
+    for button in pressed {
+        let reshaped = match button {
+            IoEditorButton::EditMod(index) => {
+                edit_mod(
+                    &mut cmd,
+                    entity,
+                    &mut editor.0,
+                    &*state,
+                    &profile,
+                    index,
+                    &mut notifications_channel,
+                );
+                false
+            }
+            IoEditorButton::Select(index) => {
+                state.select(index);
+                true
+            }
+            IoEditorButton::Edge { tile, edge } => {
+                state.click_edge(tile, edge);
+                true
+            }
+            IoEditorButton::PortKind => {
+                state.toggle_kind();
+                true
+            }
+            IoEditorButton::RemovePort => {
+                state.remove_port();
+                true
+            }
+            IoEditorButton::Save => {
+                save_ports(
+                    &mut state,
+                    &items,
+                    &type_registry.read(),
+                    &mut notifications_channel,
+                );
+                false
+            }
+        };
+
+        if reshaped {
+            cmd.entity(entity).insert(RebuildEditor);
+        }
+    }
+}
+
+/// Ports are only written once nothing blocking is wrong with them
+fn save_ports(
+    state: &mut IoEditorState,
+    items: &ItemRegistry,
+    type_registry: &TypeRegistry,
+    notifications_channel: &mut NotificationChannel,
+) {
+    let issues = state
+        .issues(items)
+        .into_iter()
+        .filter(IoIssue::blocking)
+        .collect::<Vec<_>>();
+    save_edits(state, &issues, type_registry, notifications_channel);
+}
+
+fn edit_io_fields(
+    fields: Query<(&TextField, &IoFieldInput), Changed<TextField>>,
+    mut editor: Query<&mut IoEditorState>,
+) {
+    let Ok(mut state) = editor.get_single_mut() else {
+        return;
+    };
+
+    for (field, IoFieldInput(io_field)) in fields.iter() {
+        // half typed numbers are kept in the field until they parse
+        let _ = state.set_field(*io_field, &field.value);
+    }
+}
+
+/// Draws the footprint of a building facing north with a button on every outer edge of its
+/// tiles, ports show their kind
+fn spawn_port_drawing(
+    building: &BuildingDefinition,
+    selected_port: Option<&IoPort>,
+    parent: &mut ChildBuilder,
+    fonts: &Res<FontAssets>,
+    ui: &Res<UiAssets>,
+) {
+    let mut tiles = building.footprint.clone();
+    if !tiles.contains(&IVec2::ZERO) {
+        tiles.push(IVec2::ZERO);
+    }
+
+    // corners of every tile bound the drawing
+    let corners = tiles.iter().flat_map(|tile| {
+        [(-0.5, -0.5), (-0.5, 0.5), (0.5, -0.5), (0.5, 0.5)]
+            .map(|(x, y)| iso_offset(tile.as_vec2() + Vec2::new(x, y), IO_TILE_WIDTH))
+    });
+    let (min, max) = corners.fold((Vec2::MAX, Vec2::MIN), |(min, max), corner| {
+        (min.min(corner), max.max(corner))
+    });
+    // room for the sprite standing on the tiles and the buttons past the edges
+    let margin = Vec2::splat(IO_TILE_WIDTH);
+    let size = max - min + 2. * margin;
+    let at = |point: Vec2| iso_offset(point, IO_TILE_WIDTH) - min + margin;
+
+    parent
+        .spawn(Node {
+            width: Val::Px(size.x),
+            height: Val::Px(size.y),
+            flex_shrink: 0.,
+            ..default()
+        })
+        .with_children(|parent| {
+            // textures of the map are square and centered on their tile
+            let origin = at(Vec2::ZERO);
+            parent.spawn((
+                IoSpritePreview,
+                ImageNode::default(),
+                Node {
+                    position_type: PositionType::Absolute,
+                    left: Val::Px(origin.x - IO_TILE_WIDTH / 2.),
+                    top: Val::Px(origin.y - IO_TILE_WIDTH / 2.),
+                    width: Val::Px(IO_TILE_WIDTH),
+                    height: Val::Px(IO_TILE_WIDTH),
+                    ..default()
+                },
+            ));
+
+            for tile in tiles.iter() {
+                for edge in BeltDirection::ALL {
+                    if tiles.contains(&(*tile + edge.offset())) {
+                        continue;
+                    }
+
+                    let port = building
+                        .ports
+                        .iter()
+                        .find(|port| port.tile == *tile && port.edge == edge);
+                    let middle = at(tile.as_vec2() + edge.offset().as_vec2() / 2.);
+                    parent
+                        .spawn(Node {
+                            position_type: PositionType::Absolute,
+                            left: Val::Px(middle.x - UI_SCALE * 2.),
+                            top: Val::Px(middle.y - UI_SCALE * 2.),
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            editor_entry(
+                                port.map_or(" ", |port| match port.kind {
+                                    PortKind::Input => "in",
+                                    PortKind::Output => "out",
+                                }),
+                                port.is_some() && port == selected_port,
+                                IoEditorButton::Edge { tile: *tile, edge },
+                                (),
+                                parent,
+                                fonts,
+                                ui,
+                            );
+                        });
+                }
+            }
+        });
+}
+
+fn rebuild_io_editor(
+    mut cmd: Commands,
+    editor: Query<(Entity, &IoEditor, &IoEditorState), With<RebuildEditor>>,
+    body: Query<Entity, With<IoEditorBody>>,
+    profile: Query<&ModProfile, With<Level>>,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+) {
+    let (Ok((entity, editor, state)), Ok(body)) = (editor.get_single(), body.get_single()) else {
+        return;
+    };
+    cmd.entity(entity).remove::<RebuildEditor>();
+
+    let mods = profile_mods(&profile);
+
+    cmd.entity(body)
+        .despawn_descendants()
+        .with_children(|parent| {
+            // buildings of the mod
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(35.),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    mod_picker(
+                        &editor.0,
+                        &mods,
+                        IoEditorButton::EditMod,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+
+                    for (index, building) in state.buildings.iter().enumerate() {
+                        editor_entry(
+                            &building.item.0,
+                            state.selected == Some(index),
+                            IoEditorButton::Select(index),
+                            (),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    }
+
+                    editor_button("save", IoEditorButton::Save, parent, &fonts, &ui);
+                    body_text("", parent, &fonts).insert(IoEditorIssues);
+                });
+
+            // ports of the selected building
+            parent
+                .spawn((
+                    Node {
+                        flex_grow: 1.,
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    let Some(building) = state.selected() else {
+                        section_text("no building selected", parent, &fonts);
+                        return;
+                    };
+
+                    // drawn facing north, ports turn with the building once placed
+                    section_text("ports", parent, &fonts);
+                    spawn_port_drawing(building, state.selected_port(), parent, &fonts, &ui);
+                    separator(parent);
+
+                    let Some(port) = state.selected_port() else {
+                        body_text("click an edge to add or pick a port", parent, &fonts);
+                        return;
+                    };
+
+                    labeled_row("kind", parent, &fonts).with_children(|parent| {
+                        editor_button(
+                            port.kind.label(),
+                            IoEditorButton::PortKind,
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    });
+                    labeled_row(IoField::Filter.label(), parent, &fonts).with_children(|parent| {
+                        let filter = port
+                            .filter
+                            .iter()
+                            .map(|item| item.0.as_str())
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        spawn_text_field(
+                            &filter,
+                            IoFieldInput(IoField::Filter),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    });
+                    labeled_row(IoField::Priority.label(), parent, &fonts).with_children(
+                        |parent| {
+                            spawn_text_field(
+                                &port.priority.to_string(),
+                                IoFieldInput(IoField::Priority),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        },
+                    );
+                    separator(parent);
+
+                    editor_button(
+                        "remove port",
+                        IoEditorButton::RemovePort,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+                });
+        });
+}
+
+/// Keeps issues up to date while typing
+fn show_io_editor_state(
+    editor: Query<&IoEditorState>,
+    mut issues_text: Query<&mut Text, With<IoEditorIssues>>,
+    fields: Query<(), Changed<TextField>>,
+    items: Res<ItemRegistry>,
+) {
+    let Ok(state) = editor.get_single() else {
+        return;
+    };
+    if fields.is_empty() && issues_text.iter().all(|text| !text.0.is_empty()) {
+        return;
+    }
+
+    let issues = state.issues(&items);
+    let summary = issues_summary(&issues, state.unsaved);
+
+    for mut text in issues_text.iter_mut() {
+        if text.0 != summary {
+            text.0 = summary.clone();
+        }
+    }
+}
+
+fn preview_io_building_sprite(
+    editor: Query<&IoEditorState>,
+    mut previews: Query<&mut ImageNode, With<IoSpritePreview>>,
+    textures: Query<&ItemTextureMap, With<Level>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(state) = editor.get_single() else {
+        return;
+    };
+    let Some(building) = state.selected() else {
+        return;
+    };
+
+    let image = building_sprite(
+        &state.mod_path,
+        state.namespace,
+        building,
+        textures.get_single().ok(),
+        &asset_server,
+    );
+
+    for mut preview in previews.iter_mut() {
+        if preview.image != image {
+            preview.image = image.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs_tilemap::prelude::*;
+
+    fn editor() -> IoEditorState {
+        IoEditorState {
+            mod_path: PathBuf::new(),
+            namespace: Namespace::new("tyconic").unwrap(),
+            buildings: vec![BuildingDefinition {
+                footprint: vec![IVec2::ZERO, IVec2::Y],
+                components: vec![BuildingComponent::Inventory(4)],
+                ..ItemId::from("oven").into()
+            }],
+            selected: Some(0),
+            selected_port: None,
+            unsaved: false,
+        }
+    }
+
+    #[test]
+    fn drawing_follows_the_tilemap() {
+        let grid_size = TilemapGridSize { x: 32., y: 16. };
+        let map_type = TilemapType::Isometric(IsoCoordSystem::Diamond);
+        let origin = TilePos { x: 3, y: 3 }.center_in_world(&grid_size, &map_type);
+
+        for (x, y) in [(4, 3), (3, 4), (5, 1), (0, 6)] {
+            let world = TilePos { x, y }.center_in_world(&grid_size, &map_type) - origin;
+            let offset = iso_offset(Vec2::new(x as f32 - 3., y as f32 - 3.), 32.);
+            assert_eq!(offset, Vec2::new(world.x, -world.y));
+        }
+    }
+
+    #[test]
+    fn edges_pick_or_add_ports() {
+        let mut state = editor();
+
+        state.click_edge(IVec2::Y, BeltDirection::North);
+        state.toggle_kind();
+        state.set_field(IoField::Priority, "3").unwrap();
+        state.click_edge(IVec2::ZERO, BeltDirection::South);
+        state
+            .set_field(IoField::Filter, "cheese_wheel, , bread_loaf")
+            .unwrap();
+        assert!(state.set_field(IoField::Priority, "high").is_err());
+
+        state.click_edge(IVec2::Y, BeltDirection::North);
+        assert_eq!(state.selected_port, Some(0));
+        let ports = &state.selected().unwrap().ports;
+        assert_eq!(
+            ports,
+            &vec![
+                IoPort {
+                    priority: 3,
+                    ..IoPort::new(PortKind::Output, IVec2::Y, BeltDirection::North)
+                },
+                IoPort {
+                    filter: vec!["cheese_wheel".into(), "bread_loaf".into()],
+                    ..IoPort::new(PortKind::Input, IVec2::ZERO, BeltDirection::South)
+                },
+            ]
+        );
+        assert!(state.unsaved);
+
+        state.remove_port();
+        assert_eq!(state.selected().unwrap().ports.len(), 1);
+        assert_eq!(state.selected_port, None);
+    }
+
+    #[test]
+    fn ports_sit_on_outer_edges() {
+        let mut state = editor();
+        let items = ItemRegistry::default();
+
+        state.click_edge(IVec2::ZERO, BeltDirection::North);
+        state.click_edge(IVec2::new(1, 0), BeltDirection::East);
+        state.click_edge(IVec2::Y, BeltDirection::West);
+        state.set_field(IoField::Filter, "cheese_wheel").unwrap();
+
+        let oven = ItemId::from("oven");
+        assert_eq!(
+            state.issues(&items),
+            vec![
+                IoIssue::InnerEdge {
+                    building: oven.clone(),
+                    tile: IVec2::ZERO,
+                    edge: BeltDirection::North,
+                },
+                IoIssue::PortOffFootprint {
+                    building: oven.clone(),
+                    tile: IVec2::new(1, 0),
+                },
+                IoIssue::UnknownFilterItem {
+                    building: oven.clone(),
+                    item: "cheese_wheel".into(),
+                },
+            ]
+        );
+
+        state.buildings[0].components.clear();
+        assert!(state
+            .issues(&items)
+            .contains(&IoIssue::NoInventory(oven.clone())));
+        assert!(!IoIssue::NoInventory(oven).blocking());
+    }
+}
@@ -1,2 +1,1460 @@
 //! A.K.A. the pack editor; a windowed editor for making catalogue of items that can be expanded upon with further
 //! functionality
+
+use super::{
+    edit_mod, editor_button, editor_entry, issues_summary, labeled_row, load_editor, mod_picker,
+    profile_mods, replace, save_edits, spawn_editor_window, EditorState, ModEditor, RebuildEditor,
+};
+use crate::hud::*;
+use crate::loading::*;
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub struct ItemEditorPlugin;
+
+#[derive(Debug, Component)]
+pub struct ItemEditor(pub crate::Meta);
+
+impl Plugin for ItemEditorPlugin {
+    fn build(&self, app: &mut App) {
+        // read from `derivations/item_assets.ron` even when textures aren't loaded from it
+        app.register_type::<ItemTextureMapSource>()
+            .register_type::<ItemTextureSource>();
+
+        app.add_systems(
+            OnEnter(EnableHUD::ENABLED),
+            spawn_item_editor_window
+                .after(spawn_hud_backdrop)
+                .run_if(in_state(crate::DeveloperMode(true))),
+        )
+        .add_systems(
+            Update,
+            (
+                load_editor::<ItemEditor>,
+                handle_item_editor_buttons,
+                edit_item_fields,
+                edit_recipe_fields,
+                rebuild_item_editor,
+                show_item_editor_state,
+                preview_item_texture,
+            )
+                .chain()
+                .run_if(any_with_component::<ItemEditor>),
+        );
+    }
+}
+
+/// Kinds of texture source offered by the editor, clicking the kind moves to the next
+pub const TEXTURE_SOURCES: [&str; 3] = ["auto", "path", "auto with variants"];
+
+pub fn texture_source_kind(source: &ItemTextureSource) -> usize {
+    match source {
+        ItemTextureSource::Auto => 0,
+        ItemTextureSource::Path(_) => 1,
+        ItemTextureSource::AutoWithVariants(_) => 2,
+    }
+}
+
+pub fn texture_source_of_kind(kind: usize) -> ItemTextureSource {
+    match kind % TEXTURE_SOURCES.len() {
+        0 => ItemTextureSource::Auto,
+        1 => ItemTextureSource::Path(PathBuf::new()),
+        _ => ItemTextureSource::AutoWithVariants(vec![]),
+    }
+}
+
+/// Item entries written like `cheese_wheel x2, bread_loaf`, the quantity defaults to 1
+pub fn entries_text(entries: &[ItemEntry]) -> String {
+    entries
+        .iter()
+        .map(|entry| match entry.quantity {
+            1 => entry.item.0.clone(),
+            quantity => format!("{} x{}", entry.item.0, quantity),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+pub fn parse_entries(text: &str) -> Result<Vec<ItemEntry>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (item, quantity) = match entry.rsplit_once(" x") {
+                Some((item, quantity)) => (
+                    item.trim(),
+                    quantity
+                        .trim()
+                        .parse::<usize>()
+                        .map_err(|_| format!("`{}` isn't a quantity", quantity))?,
+                ),
+                None => (entry, 1),
+            };
+
+            Ok(ItemEntry {
+                item: ItemId(item.to_string()),
+                quantity,
+            })
+        })
+        .collect()
+}
+
+fn comma_separated(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Fields of an item edited as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ItemField {
+    Id,
+    Category,
+    StackSize,
+    /// path of [`ItemTextureSource::Path`] or comma separated variants of
+    /// [`ItemTextureSource::AutoWithVariants`]
+    Texture,
+}
+
+impl ItemField {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Category => "category",
+            Self::StackSize => "stack size",
+            Self::Texture => "texture",
+        }
+    }
+}
+
+/// Fields of a recipe edited as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecipeField {
+    Id,
+    Ingredients,
+    Output,
+    /// comma separated ids
+    ResearchRequired,
+    /// milliseconds
+    Duration,
+}
+
+impl RecipeField {
+    pub const ALL: [Self; 5] = [
+        Self::Id,
+        Self::Ingredients,
+        Self::Output,
+        Self::ResearchRequired,
+        Self::Duration,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::Ingredients => "ingredients",
+            Self::Output => "output",
+            Self::ResearchRequired => "requires",
+            Self::Duration => "duration ms",
+        }
+    }
+
+    pub fn get(&self, declared: &RecipeDeclared) -> String {
+        match self {
+            Self::Id => declared.id.0.clone(),
+            Self::Ingredients => entries_text(&declared.recipe.ingredients),
+            Self::Output => entries_text(&declared.recipe.output),
+            Self::ResearchRequired => declared
+                .recipe
+                .research_required
+                .iter()
+                .map(|id| id.0.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            Self::Duration => declared.recipe.duration.to_string(),
+        }
+    }
+
+    /// Returns whether the recipe changed
+    pub fn set(&self, declared: &mut RecipeDeclared, text: &str) -> Result<bool, String> {
+        let recipe = &mut declared.recipe;
+        Ok(match self {
+            Self::Id => replace(&mut declared.id.0, text.trim().to_string()),
+            Self::Ingredients => replace(&mut recipe.ingredients, parse_entries(text)?),
+            Self::Output => replace(&mut recipe.output, parse_entries(text)?),
+            Self::ResearchRequired => replace(
+                &mut recipe.research_required,
+                comma_separated(text).into_iter().map(ResearchId).collect(),
+            ),
+            Self::Duration => {
+                let duration = text
+                    .trim()
+                    .parse::<u32>()
+                    .map_err(|_| format!("`{}` isn't a duration", text))?;
+                replace(&mut recipe.duration, duration)
+            }
+        })
+    }
+}
+
+/// What is wrong with the items of a mod
+#[derive(Debug, Clone, PartialEq)]
+pub enum ItemIssue {
+    InvalidId {
+        id: String,
+        error: NamespaceError,
+    },
+    DuplicateId(ItemId),
+    ZeroStackSize(ItemId),
+    DuplicateRecipe(RecipeId),
+    /// a recipe of the mod takes or produces an item nobody declares
+    UnknownItem {
+        recipe: RecipeId,
+        item: ItemId,
+    },
+    NoTexture(ItemId),
+    MissingTexture {
+        item: ItemId,
+        path: PathBuf,
+    },
+    /// a texture source for an id the mod doesn't declare, usually a typo
+    UnusedTexture(String),
+}
+
+impl ItemIssue {
+    /// Whether the items can't be saved, textures may be drawn after the item is declared
+    pub fn blocking(&self) -> bool {
+        !matches!(
+            self,
+            Self::NoTexture(_) | Self::MissingTexture { .. } | Self::UnusedTexture(_)
+        )
+    }
+}
+
+impl fmt::Display for ItemIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidId { id, error } => write!(f, "item `{}` is invalid. {}", id, error),
+            Self::DuplicateId(id) => write!(f, "item `{}` is declared twice", id.0),
+            Self::ZeroStackSize(id) => write!(f, "item `{}` stacks by 0", id.0),
+            Self::DuplicateRecipe(id) => write!(f, "recipe `{}` is declared twice", id.0),
+            Self::UnknownItem { recipe, item } => {
+                write!(f, "recipe `{}` uses unknown item `{}`", recipe.0, item.0)
+            }
+            Self::NoTexture(id) => write!(f, "item `{}` has no texture", id.0),
+            Self::MissingTexture { item, path } => write!(
+                f,
+                "texture of `{}` is missing at {}",
+                item.0,
+                path.to_string_lossy()
+            ),
+            Self::UnusedTexture(id) => write!(f, "texture of `{}` belongs to no item", id),
+        }
+    }
+}
+
+/// Items of one mod along with their textures and the recipes of the mod
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct ItemEditorState {
+    pub mod_path: PathBuf,
+    pub namespace: Namespace,
+    /// every entry of `declarations/items.ron`, those patching or deleting items of other mods
+    /// are kept as is
+    pub items: Vec<ItemDeclaration>,
+    /// `derivations/item_assets.ron` keyed by the id as written
+    pub textures: BTreeMap<String, ItemTextureSource>,
+    /// textures as they were read, those unchanged are previewed from the [`ItemTextureMap`]
+    pub read_textures: BTreeMap<String, ItemTextureSource>,
+    /// every entry of `declarations/recipes.ron`
+    pub recipes: Vec<RecipeDeclaration>,
+    /// index within the items of the item being edited
+    pub selected: Option<usize>,
+    pub unsaved: bool,
+    /// recipes are only written when edited, a plain pack of ids is kept as is otherwise
+    pub recipes_edited: bool,
+}
+
+impl ItemEditorState {
+    pub fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<Self, String> {
+        let items = read_item_declarations(&mod_path, type_registry)?;
+        let textures = read_item_texture_sources(&mod_path, type_registry)?;
+        let recipes = read_recipe_declarations(&mod_path, type_registry)?;
+        let selected = items
+            .iter()
+            .position(|declaration| Self::editable(declaration).is_some());
+
+        Ok(Self {
+            mod_path,
+            namespace,
+            items,
+            read_textures: textures.clone(),
+            textures,
+            recipes,
+            selected,
+            unsaved: false,
+            recipes_edited: false,
+        })
+    }
+
+    fn editable(declaration: &ItemDeclaration) -> Option<&ItemDeclared> {
+        match declaration {
+            ItemDeclaration::Declare(declared) | ItemDeclaration::Override(declared) => {
+                Some(declared)
+            }
+            _ => None,
+        }
+    }
+
+    /// Items declared or overridden by the mod, with their index within the items
+    pub fn items(&self) -> impl Iterator<Item = (usize, &ItemDeclared)> {
+        self.items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, declaration)| Some((index, Self::editable(declaration)?)))
+    }
+
+    pub fn selected(&self) -> Option<&ItemDeclared> {
+        self.items.get(self.selected?).and_then(Self::editable)
+    }
+
+    fn selected_mut(&mut self) -> Option<&mut ItemDeclared> {
+        match self.items.get_mut(self.selected?)? {
+            ItemDeclaration::Declare(declared) | ItemDeclaration::Override(declared) => {
+                Some(declared)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn selected_texture(&self) -> Option<&ItemTextureSource> {
+        self.textures.get(&self.selected()?.id.0)
+    }
+
+    /// Whether an entry refers to `id` of this mod, written bare or namespaced
+    fn refers_to(&self, entry: &ItemEntry, id: &str) -> bool {
+        entry.item.0 == id || entry.item.0 == namespaced(self.namespace, id)
+    }
+
+    /// Recipes of the mod producing the item, with their index within the recipes
+    pub fn recipes_producing(&self, id: &str) -> Vec<(usize, &RecipeDeclared)> {
+        self.recipes
+            .iter()
+            .enumerate()
+            .filter_map(|(index, declaration)| match declaration {
+                RecipeDeclaration::Declare(declared) | RecipeDeclaration::Override(declared) => {
+                    Some((index, declared))
+                }
+                _ => None,
+            })
+            .filter(|(_, declared)| {
+                declared
+                    .recipe
+                    .output
+                    .iter()
+                    .any(|entry| self.refers_to(entry, id))
+            })
+            .collect()
+    }
+
+    pub fn recipe_mut(&mut self, index: usize) -> Option<&mut RecipeDeclared> {
+        match self.recipes.get_mut(index)? {
+            RecipeDeclaration::Declare(declared) | RecipeDeclaration::Override(declared) => {
+                Some(declared)
+            }
+            _ => None,
+        }
+    }
+
+    /// Sets a field of the selected item, returns whether it changed
+    pub fn set_field(&mut self, field: ItemField, text: &str) -> Result<bool, String> {
+        let Some(declared) = self.selected_mut() else {
+            return Ok(false);
+        };
+
+        let changed = match field {
+            ItemField::Id => {
+                let old = declared.id.0.clone();
+                return self.rename_item(&old, text);
+            }
+            ItemField::Category => replace(&mut declared.category, text.trim().to_string()),
+            ItemField::StackSize => {
+                let stack_size = text
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| format!("`{}` isn't a stack size", text))?;
+                replace(&mut declared.stack_size, stack_size)
+            }
+            ItemField::Texture => {
+                let id = declared.id.0.clone();
+                let source = match self.textures.get(&id) {
+                    Some(ItemTextureSource::Path(_)) => {
+                        ItemTextureSource::Path(PathBuf::from(text.trim()))
+                    }
+                    Some(ItemTextureSource::AutoWithVariants(_)) => {
+                        ItemTextureSource::AutoWithVariants(comma_separated(text))
+                    }
+                    _ => return Ok(false),
+                };
+                self.textures.insert(id, source.clone()) != Some(source)
+            }
+        };
+
+        self.unsaved |= changed;
+        Ok(changed)
+    }
+
+    /// Renames the item going by `old`, its texture and recipes of the mod follow. Returns
+    /// whether it changed, an id another item or texture already goes by is refused.
+    pub fn rename_item(&mut self, old: &str, new: &str) -> Result<bool, String> {
+        let new = new.trim();
+        if old == new {
+            return Ok(false);
+        }
+        if new.is_empty() {
+            return Err(format!("`{}` needs an id", old));
+        }
+        if self.items().any(|(_, declared)| declared.id.0 == new) {
+            return Err(format!("another item is already `{}`", new));
+        }
+        if self.textures.contains_key(new) {
+            return Err(format!("a texture is already kept for `{}`", new));
+        }
+
+        let Some(index) = self
+            .items()
+            .find(|(_, declared)| declared.id.0 == old)
+            .map(|(index, _)| index)
+        else {
+            return Err(format!("`{}` is no longer declared", old));
+        };
+        if let ItemDeclaration::Declare(declared) | ItemDeclaration::Override(declared) =
+            &mut self.items[index]
+        {
+            declared.id.0 = new.to_string();
+        }
+
+        if let Some(source) = self.textures.remove(old) {
+            self.textures.insert(new.to_string(), source);
+        }
+
+        let namespace = self.namespace;
+        for declaration in self.recipes.iter_mut() {
+            let (RecipeDeclaration::Declare(declared) | RecipeDeclaration::Override(declared)) =
+                declaration
+            else {
+                continue;
+            };
+
+            let entries = declared
+                .recipe
+                .ingredients
+                .iter_mut()
+                .chain(declared.recipe.output.iter_mut());
+            for entry in entries {
+                if entry.item.0 == old {
+                    entry.item.0 = new.to_string();
+                } else if entry.item.0 == namespaced(namespace, old) {
+                    entry.item.0 = namespaced(namespace, new);
+                } else {
+                    continue;
+                }
+                self.recipes_edited = true;
+            }
+        }
+
+        self.unsaved = true;
+        Ok(true)
+    }
+
+    /// Moves the texture of the selected item to the next kind of source, an item without
+    /// texture gets one inferred
+    pub fn cycle_texture_source(&mut self) {
+        let Some(id) = self.selected().map(|declared| declared.id.0.clone()) else {
+            return;
+        };
+
+        let source = match self.textures.get(&id) {
+            Some(source) => texture_source_of_kind(texture_source_kind(source) + 1),
+            None => ItemTextureSource::Auto,
+        };
+        self.textures.insert(id, source);
+        self.unsaved = true;
+    }
+
+    /// Declares an item with an id no other item of the mod has, inferring its texture, and
+    /// selects it
+    pub fn add_item(&mut self) -> usize {
+        let taken = self
+            .items()
+            .map(|(_, declared)| declared.id.0.clone())
+            .collect::<HashSet<_>>();
+        let id = (1..)
+            .map(|n| match n {
+                1 => "new_item".to_string(),
+                n => format!("new_item_{}", n),
+            })
+            .find(|id| !taken.contains(id) && !self.textures.contains_key(id))
+            .unwrap();
+
+        self.textures.insert(id.clone(), ItemTextureSource::Auto);
+        self.items.push(ItemDeclaration::Declare(ItemId(id).into()));
+        self.selected = Some(self.items.len() - 1);
+        self.unsaved = true;
+        self.items.len() - 1
+    }
+
+    /// Removes the item along with its texture, recipes producing it are kept
+    pub fn remove_item(&mut self, index: usize) {
+        if index >= self.items.len() {
+            return;
+        }
+
+        if let ItemDeclaration::Declare(declared) | ItemDeclaration::Override(declared) =
+            self.items.remove(index)
+        {
+            let still_declared = self.items().any(|(_, other)| other.id.0 == declared.id.0);
+            if !still_declared {
+                self.textures.remove(&declared.id.0);
+            }
+        }
+
+        let first = self.items().map(|(index, _)| index).next();
+        self.selected = first;
+        self.unsaved = true;
+    }
+
+    /// Declares a recipe producing one of the selected item
+    pub fn add_recipe(&mut self) -> Option<usize> {
+        let item = self.selected()?.id.clone();
+
+        let taken =
+            self.recipes
+                .iter()
+                .filter_map(|declaration| match declaration {
+                    RecipeDeclaration::Declare(declared)
+                    | RecipeDeclaration::Override(declared) => Some(declared.id.0.as_str()),
+                    _ => None,
+                })
+                .collect::<HashSet<_>>();
+        let id = (1..)
+            .map(|n| match n {
+                1 => item.0.clone(),
+                n => format!("{}_{}", item.0, n),
+            })
+            .find(|id| !taken.contains(id.as_str()))
+            .unwrap();
+
+        let mut declared = RecipeDeclared::from(RecipeId(id));
+        declared.recipe.output.push(ItemEntry { item, quantity: 1 });
+        self.recipes.push(RecipeDeclaration::Declare(declared));
+        self.unsaved = true;
+        self.recipes_edited = true;
+        Some(self.recipes.len() - 1)
+    }
+
+    pub fn remove_recipe(&mut self, index: usize) {
+        if index < self.recipes.len() {
+            self.recipes.remove(index);
+            self.unsaved = true;
+            self.recipes_edited = true;
+        }
+    }
+
+    /// Checks the items and recipes of the mod against items declared by every other mod
+    pub fn issues(&self, items: &ItemRegistry) -> Vec<ItemIssue> {
+        let mut issues = vec![];
+
+        // items as declared by other mods, this mod's are replaced by what is edited
+        let mut known = items
+            .iter()
+            .filter(|declared| {
+                items
+                    .touched_by(&declared.id.0)
+                    .is_none_or(|touch| touch.mod_name != self.namespace.as_str())
+            })
+            .map(|declared| declared.id.0.clone())
+            .collect::<HashSet<_>>();
+
+        let mut seen = HashSet::new();
+        for (_, declared) in self.items() {
+            let id = &declared.id;
+            if let Err(error) = NamespacedId::parse_in(self.namespace, &id.0) {
+                issues.push(ItemIssue::InvalidId {
+                    id: id.0.clone(),
+                    error,
+                });
+            }
+            if !seen.insert(id.0.clone()) {
+                issues.push(ItemIssue::DuplicateId(id.clone()));
+            }
+            if declared.stack_size == 0 {
+                issues.push(ItemIssue::ZeroStackSize(id.clone()));
+            }
+            known.insert(namespaced(self.namespace, &id.0));
+        }
+
+        let mut recipes = HashSet::new();
+        for declaration in self.recipes.iter() {
+            let (RecipeDeclaration::Declare(declared) | RecipeDeclaration::Override(declared)) =
+                declaration
+            else {
+                continue;
+            };
+
+            if !recipes.insert(namespaced(self.namespace, &declared.id.0)) {
+                issues.push(ItemIssue::DuplicateRecipe(declared.id.clone()));
+            }
+
+            let entries = declared
+                .recipe
+                .ingredients
+                .iter()
+                .chain(declared.recipe.output.iter());
+            for entry in entries {
+                if !known.contains(&namespaced(self.namespace, &entry.item.0)) {
+                    issues.push(ItemIssue::UnknownItem {
+                        recipe: declared.id.clone(),
+                        item: entry.item.clone(),
+                    });
+                }
+            }
+        }
+
+        for (_, declared) in self.items() {
+            match self.textures.get(&declared.id.0) {
+                None => issues.push(ItemIssue::NoTexture(declared.id.clone())),
+                Some(
+                    source @ (ItemTextureSource::Auto | ItemTextureSource::AutoWithVariants(_)),
+                ) => {
+                    let path = resolve_item_texture(&self.mod_path, &declared.id.0, source);
+                    if !path.exists() {
+                        issues.push(ItemIssue::MissingTexture {
+                            item: declared.id.clone(),
+                            path,
+                        });
+                    }
+                }
+                Some(ItemTextureSource::Path(_)) => {}
+            }
+        }
+
+        let mut unused = self
+            .textures
+            .keys()
+            .filter(|id| !seen.contains(*id))
+            .cloned()
+            .collect::<Vec<_>>();
+        unused.sort();
+        issues.extend(unused.into_iter().map(ItemIssue::UnusedTexture));
+
+        issues
+    }
+
+    pub fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        write_item_declarations(
+            &self.mod_path,
+            &ItemDeclarations(self.items.clone()),
+            type_registry,
+        )?;
+        write_item_texture_sources(
+            &self.mod_path,
+            &ItemTextureMapSource(self.textures.clone()),
+            type_registry,
+        )?;
+        if self.recipes_edited {
+            write_recipe_declarations(
+                &self.mod_path,
+                &RecipeDeclarations(self.recipes.clone()),
+                type_registry,
+            )?;
+        }
+
+        self.unsaved = false;
+        self.recipes_edited = false;
+        Ok(())
+    }
+}
+
+impl EditorState for ItemEditorState {
+    const KIND: &'static str = "items";
+
+    fn unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        ItemEditorState::save(self, type_registry)
+    }
+
+    fn path(&self) -> &Path {
+        &self.mod_path
+    }
+
+    fn summary(&self) -> String {
+        format!("{} items of {}", self.items().count(), self.namespace)
+    }
+}
+
+impl ModEditor for ItemEditor {
+    type State = ItemEditorState;
+
+    fn edited(&mut self) -> &mut Meta {
+        &mut self.0
+    }
+
+    fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<ItemEditorState, String> {
+        ItemEditorState::read(mod_path, namespace, type_registry)
+    }
+}
+
+/// Content of the editor window, respawned whenever what is edited changes shape
+#[derive(Debug, Component)]
+pub struct ItemEditorBody;
+
+#[derive(Debug, Component, Clone, PartialEq)]
+pub enum ItemEditorButton {
+    /// edit the items of the mod at this index of the profile
+    EditMod(usize),
+    Select(usize),
+    NewItem,
+    RemoveItem,
+    /// moves the texture source of the selected item to the next kind
+    TextureSource,
+    NewRecipe,
+    RemoveRecipe(usize),
+    Save,
+}
+
+#[derive(Debug, Component)]
+pub struct ItemFieldInput(pub ItemField);
+
+/// Id of an item, renamed from the id it had when the field was spawned once the field is left
+#[derive(Debug, Component)]
+pub struct ItemIdInput(pub String);
+
+#[derive(Debug, Component)]
+pub struct RecipeFieldInput {
+    /// index within the recipes of the mod
+    pub recipe: usize,
+    pub field: RecipeField,
+}
+
+/// Name of the item at this index of the items
+#[derive(Debug, Component)]
+pub struct ItemEntryLabel(pub usize);
+
+#[derive(Debug, Component)]
+pub struct ItemEditorIssues;
+
+/// Texture of the selected item
+#[derive(Debug, Component)]
+pub struct ItemTexturePreview;
+
+pub fn spawn_item_editor_window(
+    mut cmd: Commands,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+    hud_backdrop: HUDBackdropQuery,
+) {
+    let pack = crate::levels::pack::base_mod();
+
+    spawn_editor_window(
+        &mut cmd,
+        hud_backdrop.single(),
+        ItemEditor(pack.meta.clone()),
+        ItemEditorBody,
+        "item editor",
+        &ui,
+        &fonts,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_item_editor_buttons(
+    mut cmd: Commands,
+    buttons: Query<(&DepressButton, &ItemEditorButton), Changed<DepressButton>>,
+    mut editor: Query<(Entity, &mut ItemEditor, &mut ItemEditorState)>,
+    profile: Query<&ModProfile, With<Level>>,
+    items: Res<ItemRegistry>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok((entity, mut editor, mut state)) = editor.get_single_mut() else {
+        return;
+    };
+
+    let pressed = buttons
+        .iter()
+        .filter(|(depress, _)| depress.invoked())
+        .map(|(_, button)| button.clone())
+        .collect::<Vec<_>>();
+
+    for button in pressed {
+        let reshaped = match button {
+            ItemEditorButton::EditMod(index) => {
+                edit_mod(
+                    &mut cmd,
+                    entity,
+                    &mut editor.0,
+                    &*state,
+                    &profile,
+                    index,
+                    &mut notifications_channel,
+                );
+                false
+            }
+            ItemEditorButton::Select(index) => {
+                state.selected = Some(index);
+                true
+            }
+            ItemEditorButton::NewItem => {
+                state.add_item();
+                true
+            }
+            ItemEditorButton::RemoveItem => {
+                if let Some(selected) = state.selected {
+                    state.remove_item(selected);
+                }
+                true
+            }
+            ItemEditorButton::TextureSource => {
+                state.cycle_texture_source();
+                true
+            }
+            ItemEditorButton::NewRecipe => state.add_recipe().is_some(),
+            ItemEditorButton::RemoveRecipe(index) => {
+                state.remove_recipe(index);
+                true
+            }
+            ItemEditorButton::Save => {
+                save_items(
+                    &mut state,
+                    &items,
+                    &type_registry.read(),
+                    &mut notifications_channel,
+                );
+                false
+            }
+        };
+
+        if reshaped {
+            cmd.entity(entity).insert(RebuildEditor);
+        }
+    }
+}
+
+/// Items are only written once nothing blocking is wrong with them
+fn save_items(
+    state: &mut ItemEditorState,
+    items: &ItemRegistry,
+    type_registry: &TypeRegistry,
+    notifications_channel: &mut NotificationChannel,
+) {
+    let issues = state
+        .issues(items)
+        .into_iter()
+        .filter(ItemIssue::blocking)
+        .collect::<Vec<_>>();
+    save_edits(state, &issues, type_registry, notifications_channel);
+}
+
+fn edit_item_fields(
+    mut cmd: Commands,
+    fields: Query<(&TextField, &ItemFieldInput), Changed<TextField>>,
+    ids: Query<(&TextField, &ItemIdInput), Changed<TextField>>,
+    mut editor: Query<(Entity, &mut ItemEditorState)>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok((entity, mut state)) = editor.get_single_mut() else {
+        return;
+    };
+
+    for (field, ItemFieldInput(item_field)) in fields.iter() {
+        // half typed numbers are kept in the field until they parse
+        let _ = state.set_field(*item_field, &field.value);
+    }
+
+    // ids are committed on enter or once another field is focused, half typed ones would
+    // rename recipes along the way
+    for (field, ItemIdInput(id)) in ids.iter().filter(|(field, _)| !field.focused) {
+        match state.rename_item(id, &field.value) {
+            Ok(false) => continue,
+            Ok(true) => {}
+            Err(err) => {
+                Notification {
+                    title: "Item not renamed".into(),
+                    level: NotificationLevel::Warning,
+                    description: err,
+                }
+                .queue(Some(Duration::from_secs(5)), &mut notifications_channel);
+            }
+        }
+        // shows the recipes following the new id, or the id kept
+        cmd.entity(entity).insert(RebuildEditor);
+    }
+}
+
+fn edit_recipe_fields(
+    fields: Query<(&TextField, &RecipeFieldInput), Changed<TextField>>,
+    mut editor: Query<&mut ItemEditorState>,
+) {
+    let Ok(mut state) = editor.get_single_mut() else {
+        return;
+    };
+
+    for (field, input) in fields.iter() {
+        let Some(declared) = state.bypass_change_detection().recipe_mut(input.recipe) else {
+            continue;
+        };
+
+        if let Ok(true) = input.field.set(declared, &field.value) {
+            state.unsaved = true;
+            state.recipes_edited = true;
+        }
+    }
+}
+
+fn rebuild_item_editor(
+    mut cmd: Commands,
+    editor: Query<(Entity, &ItemEditor, &ItemEditorState), With<RebuildEditor>>,
+    body: Query<Entity, With<ItemEditorBody>>,
+    profile: Query<&ModProfile, With<Level>>,
+    recipes: Res<RecipeRegistry>,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+) {
+    let (Ok((entity, editor, state)), Ok(body)) = (editor.get_single(), body.get_single()) else {
+        return;
+    };
+    cmd.entity(entity).remove::<RebuildEditor>();
+
+    let mods = profile_mods(&profile);
+
+    cmd.entity(body)
+        .despawn_descendants()
+        .with_children(|parent| {
+            // items of the mod
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(35.),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    mod_picker(
+                        &editor.0,
+                        &mods,
+                        ItemEditorButton::EditMod,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+
+                    for (index, declared) in state.items() {
+                        editor_entry(
+                            &declared.id.0,
+                            state.selected == Some(index),
+                            ItemEditorButton::Select(index),
+                            ItemEntryLabel(index),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    }
+
+                    editor_button("new item", ItemEditorButton::NewItem, parent, &fonts, &ui);
+                    editor_button("save", ItemEditorButton::Save, parent, &fonts, &ui);
+                    body_text("", parent, &fonts).insert(ItemEditorIssues);
+                });
+
+            // the selected item
+            parent
+                .spawn((
+                    Node {
+                        flex_grow: 1.,
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    let Some(declared) = state.selected() else {
+                        section_text("no item selected", parent, &fonts);
+                        return;
+                    };
+
+                    parent.spawn((
+                        ItemTexturePreview,
+                        ImageNode::default(),
+                        Node {
+                            width: Val::Px(UI_SCALE * 8.),
+                            height: Val::Px(UI_SCALE * 8.),
+                            flex_shrink: 0.,
+                            ..default()
+                        },
+                    ));
+
+                    labeled_row(ItemField::Id.label(), parent, &fonts).with_children(|parent| {
+                        spawn_text_field(
+                            &declared.id.0,
+                            ItemIdInput(declared.id.0.clone()),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    });
+                    for (field, value) in [
+                        (ItemField::Category, declared.category.clone()),
+                        (ItemField::StackSize, declared.stack_size.to_string()),
+                    ] {
+                        labeled_row(field.label(), parent, &fonts).with_children(|parent| {
+                            spawn_text_field(&value, ItemFieldInput(field), parent, &fonts, &ui);
+                        });
+                    }
+
+                    let texture = state.selected_texture();
+                    labeled_row(ItemField::Texture.label(), parent, &fonts).with_children(
+                        |parent| {
+                            editor_button(
+                                texture.map_or("none", |source| {
+                                    TEXTURE_SOURCES[texture_source_kind(source)]
+                                }),
+                                ItemEditorButton::TextureSource,
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+
+                            let value = match texture {
+                                Some(ItemTextureSource::Path(path)) => {
+                                    path.to_string_lossy().to_string()
+                                }
+                                Some(ItemTextureSource::AutoWithVariants(variants)) => {
+                                    variants.join(", ")
+                                }
+                                _ => return,
+                            };
+                            spawn_text_field(
+                                &value,
+                                ItemFieldInput(ItemField::Texture),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        },
+                    );
+                    editor_button(
+                        "remove item",
+                        ItemEditorButton::RemoveItem,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+                    separator(parent);
+
+                    section_text("produced by", parent, &fonts);
+                    for (index, recipe) in state.recipes_producing(&declared.id.0) {
+                        for field in RecipeField::ALL {
+                            labeled_row(field.label(), parent, &fonts).with_children(|parent| {
+                                spawn_text_field(
+                                    &field.get(recipe),
+                                    RecipeFieldInput {
+                                        recipe: index,
+                                        field,
+                                    },
+                                    parent,
+                                    &fonts,
+                                    &ui,
+                                );
+                            });
+                        }
+                        editor_button(
+                            "remove recipe",
+                            ItemEditorButton::RemoveRecipe(index),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                        separator(parent);
+                    }
+
+                    // recipes of other mods producing the item can't be edited from here
+                    let id = namespaced(state.namespace, &declared.id.0);
+                    for recipe in recipes.iter().filter(|recipe| {
+                        recipes
+                            .touched_by(&recipe.id.0)
+                            .is_some_and(|touch| touch.mod_name != state.namespace.as_str())
+                            && recipe.recipe.output.iter().any(|entry| entry.item.0 == id)
+                    }) {
+                        body_text(
+                            &format!(
+                                "{}: {}",
+                                recipe.id.0,
+                                entries_text(&recipe.recipe.ingredients)
+                            ),
+                            parent,
+                            &fonts,
+                        );
+                    }
+
+                    editor_button(
+                        "new recipe",
+                        ItemEditorButton::NewRecipe,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+                });
+        });
+}
+
+/// Keeps item names and issues up to date while typing
+fn show_item_editor_state(
+    editor: Query<&ItemEditorState>,
+    mut labels: Query<(&ItemEntryLabel, &mut Text), Without<ItemEditorIssues>>,
+    mut issues_text: Query<&mut Text, With<ItemEditorIssues>>,
+    fields: Query<(), Changed<TextField>>,
+    items: Res<ItemRegistry>,
+) {
+    let Ok(state) = editor.get_single() else {
+        return;
+    };
+    if fields.is_empty() && issues_text.iter().all(|text| !text.0.is_empty()) {
+        return;
+    }
+
+    for (ItemEntryLabel(index), mut text) in labels.iter_mut() {
+        if let Some(ItemDeclaration::Declare(declared) | ItemDeclaration::Override(declared)) =
+            state.items.get(*index)
+        {
+            if text.0 != declared.id.0 {
+                text.0 = declared.id.0.clone();
+            }
+        }
+    }
+
+    let issues = state.issues(&items);
+    let summary = issues_summary(&issues, state.unsaved);
+
+    for mut text in issues_text.iter_mut() {
+        if text.0 != summary {
+            text.0 = summary.clone();
+        }
+    }
+}
+
+/// Textures left as they were read come from the [`ItemTextureMap`] of the level, edited ones
+/// are loaded from where they would be read
+fn preview_item_texture(
+    editor: Query<&ItemEditorState>,
+    mut previews: Query<&mut ImageNode, With<ItemTexturePreview>>,
+    textures: Query<&ItemTextureMap, With<Level>>,
+    asset_server: Res<AssetServer>,
+) {
+    let Ok(state) = editor.get_single() else {
+        return;
+    };
+    let Some(declared) = state.selected() else {
+        return;
+    };
+
+    let id = &declared.id.0;
+    let image = match state.selected_texture() {
+        None => Handle::default(),
+        Some(source) => textures
+            .get_single()
+            .ok()
+            .filter(|_| state.read_textures.get(id) == Some(source))
+            .and_then(|textures| textures.0.get(&namespaced(state.namespace, id)))
+            .cloned()
+            .unwrap_or_else(|| {
+                asset_server.load(resolve_item_texture(&state.mod_path, id, source))
+            }),
+    };
+
+    for mut preview in previews.iter_mut() {
+        if preview.image != image {
+            preview.image = image.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::*;
+
+    fn entry(item: &str, quantity: usize) -> ItemEntry {
+        ItemEntry {
+            item: ItemId(item.into()),
+            quantity,
+        }
+    }
+
+    fn recipe(id: &str, ingredients: Vec<ItemEntry>, output: Vec<ItemEntry>) -> RecipeDeclaration {
+        RecipeDeclaration::Declare(RecipeDeclared {
+            id: RecipeId(id.into()),
+            recipe: Recipe {
+                ingredients,
+                output,
+                research_required: vec![],
+                duration: 1000,
+            },
+        })
+    }
+
+    fn editor(items: &[&str], recipes: Vec<RecipeDeclaration>) -> ItemEditorState {
+        let textures = items
+            .iter()
+            .map(|id| {
+                (
+                    id.to_string(),
+                    ItemTextureSource::Path(format!("{}.png", id).into()),
+                )
+            })
+            .collect::<BTreeMap<_, _>>();
+
+        ItemEditorState {
+            mod_path: PathBuf::new(),
+            namespace: Namespace::new("tyconic").unwrap(),
+            items: items
+                .iter()
+                .map(|id| ItemDeclaration::Declare(ItemId(id.to_string()).into()))
+                .collect(),
+            read_textures: textures.clone(),
+            textures,
+            recipes,
+            selected: Some(0),
+            unsaved: false,
+            recipes_edited: false,
+        }
+    }
+
+    #[test]
+    fn entries_read_as_written() {
+        let entries = vec![entry("cheese_wheel", 2), entry("base::bread_loaf", 1)];
+
+        assert_eq!(entries_text(&entries), "cheese_wheel x2, base::bread_loaf");
+        assert_eq!(
+            parse_entries("cheese_wheel x2, base::bread_loaf,").unwrap(),
+            entries
+        );
+        assert!(parse_entries("cheese_wheel xtwo").is_err());
+        assert_eq!(parse_entries(" ").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn renamed_items_keep_texture_and_recipes() {
+        let mut state = editor(
+            &["hamburger_hand", "bread_loaf"],
+            vec![
+                recipe(
+                    "folk_burger",
+                    vec![entry("bread_loaf", 1)],
+                    vec![entry("tyconic::hamburger_hand", 2)],
+                ),
+                recipe("rustic_burger", vec![], vec![entry("hamburger_hand", 1)]),
+            ],
+        );
+
+        assert_eq!(state.recipes_producing("hamburger_hand").len(), 2);
+        assert!(state.set_field(ItemField::Id, "hamburger").unwrap());
+        assert!(state.unsaved && state.recipes_edited);
+        assert!(state.textures.contains_key("hamburger"));
+        assert!(!state.textures.contains_key("hamburger_hand"));
+        assert_eq!(
+            state
+                .recipes_producing("hamburger")
+                .into_iter()
+                .map(|(_, declared)| declared.recipe.output.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                vec![entry("tyconic::hamburger", 2)],
+                vec![entry("hamburger", 1)],
+            ]
+        );
+
+        // renaming onto another item is refused
+        assert!(state.set_field(ItemField::Id, "bread_loaf").is_err());
+        assert!(state.rename_item("hamburger", "bread_loaf").is_err());
+        assert_eq!(state.selected().unwrap().id.0, "hamburger");
+        assert!(state.textures.contains_key("hamburger"));
+        assert_eq!(state.recipes_producing("hamburger").len(), 2);
+
+        // the item renamed is the one the edit started on, whichever is selected
+        state.selected = Some(1);
+        assert!(state.rename_item("hamburger", "burger").unwrap());
+        assert_eq!(state.selected().unwrap().id.0, "bread_loaf");
+        assert_eq!(state.recipes_producing("burger").len(), 2);
+    }
+
+    #[test]
+    fn item_fields_are_parsed() {
+        let mut state = editor(&["pizza_slice"], vec![]);
+
+        assert!(state.set_field(ItemField::StackSize, "25").unwrap());
+        assert!(!state.set_field(ItemField::StackSize, "25").unwrap());
+        assert!(state.set_field(ItemField::StackSize, "25 slices").is_err());
+        assert!(state.set_field(ItemField::Category, "food").unwrap());
+        assert_eq!(state.selected().unwrap().stack_size, 25);
+
+        assert!(state.set_field(ItemField::Texture, "pizza.png").unwrap());
+        state.cycle_texture_source();
+        assert_eq!(
+            state.selected_texture(),
+            Some(&ItemTextureSource::AutoWithVariants(vec![]))
+        );
+        assert!(state
+            .set_field(ItemField::Texture, "back-facing, broken")
+            .unwrap());
+        assert_eq!(
+            state.selected_texture(),
+            Some(&ItemTextureSource::AutoWithVariants(vec![
+                "back-facing".into(),
+                "broken".into()
+            ]))
+        );
+        state.cycle_texture_source();
+        assert!(!state.set_field(ItemField::Texture, "ignored").unwrap());
+    }
+
+    #[test]
+    fn new_items_and_recipes_get_free_ids() {
+        let mut state = editor(&["new_item"], vec![recipe("new_item_2", vec![], vec![])]);
+
+        let index = state.add_item();
+        assert_eq!(state.selected, Some(index));
+        assert_eq!(state.selected().unwrap().id.0, "new_item_2");
+        assert_eq!(state.selected_texture(), Some(&ItemTextureSource::Auto));
+
+        let recipe = state.add_recipe().unwrap();
+        let producing = state.recipes_producing("new_item_2");
+        assert_eq!(producing.len(), 1);
+        assert_eq!(producing[0].0, recipe);
+        assert_eq!(producing[0].1.id.0, "new_item_2_2");
+
+        state.remove_item(index);
+        assert!(!state.textures.contains_key("new_item_2"));
+        assert_eq!(state.selected, Some(0));
+    }
+
+    #[test]
+    fn issues_name_typos() {
+        let items = item_registry(&["base::mover_belt"]);
+
+        let mut state = editor(
+            &["pizza_slice", "pizza_slice", "cheese wheel::"],
+            vec![
+                recipe(
+                    "folk_pizza",
+                    vec![entry("cheese_wheel", 1), entry("base::mover_belt", 1)],
+                    vec![entry("pizza_slice", 1)],
+                ),
+                recipe("folk_pizza", vec![], vec![]),
+            ],
+        );
+        state.textures.remove("cheese wheel::");
+        state
+            .textures
+            .insert("piza_slice".into(), ItemTextureSource::Auto);
+        state.set_field(ItemField::StackSize, "0").unwrap();
+
+        let issues = state.issues(&items);
+        assert_eq!(
+            issues,
+            vec![
+                ItemIssue::ZeroStackSize(ItemId::from("pizza_slice")),
+                ItemIssue::DuplicateId(ItemId::from("pizza_slice")),
+                ItemIssue::InvalidId {
+                    id: "cheese wheel::".into(),
+                    error: NamespaceError::InvalidNamespace("cheese wheel".into()),
+                },
+                ItemIssue::UnknownItem {
+                    recipe: RecipeId("folk_pizza".into()),
+                    item: ItemId::from("cheese_wheel"),
+                },
+                ItemIssue::DuplicateRecipe(RecipeId("folk_pizza".into())),
+                ItemIssue::NoTexture(ItemId::from("cheese wheel::")),
+                ItemIssue::UnusedTexture("piza_slice".into()),
+            ]
+        );
+        assert_eq!(issues.iter().filter(|issue| !issue.blocking()).count(), 2);
+    }
+
+    #[test]
+    fn saved_items_are_declared_by_the_mod() {
+        let mut type_registry = TypeRegistry::default();
+        type_registry.register::<ItemDeclarations>();
+        type_registry.register::<ItemPack>();
+        type_registry.register::<RecipeDeclarations>();
+        type_registry.register::<RecipePack>();
+        type_registry.register::<ItemTextureMapSource>();
+        type_registry.register::<ItemTextureSource>();
+
+        let mod_path = std::env::temp_dir()
+            .join(format!("tyconia_item_editor_{}", std::process::id()))
+            .join("tyconic");
+        let namespace = Namespace::new("tyconic").unwrap();
+
+        let mut state = ItemEditorState::read(mod_path.clone(), namespace, &type_registry).unwrap();
+        assert!(state.items.is_empty());
+
+        state.add_item();
+        state.set_field(ItemField::Id, "calzone").unwrap();
+        state.set_field(ItemField::StackSize, "4").unwrap();
+        state.add_recipe();
+        state.save(&type_registry).unwrap();
+        assert!(!state.unsaved && !state.recipes_edited);
+
+        let read = ItemEditorState::read(mod_path.clone(), namespace, &type_registry).unwrap();
+        assert_eq!(read.items, state.items);
+        assert_eq!(read.textures, state.textures);
+        assert_eq!(read.recipes, state.recipes);
+
+        let mut declarations = Declarations::default();
+        declare_mod(&mut declarations, namespace, &mod_path, &type_registry).unwrap();
+        let items = ItemRegistry::from(declarations.items);
+        let recipes = RecipeRegistry::from(declarations.recipes);
+        assert_eq!(
+            items
+                .item(&ItemId::from("tyconic::calzone"))
+                .unwrap()
+                .stack_size,
+            4
+        );
+        assert_eq!(
+            recipes
+                .recipe(&RecipeId("tyconic::calzone".into()))
+                .unwrap()
+                .recipe
+                .output,
+            vec![entry("tyconic::calzone", 1)]
+        );
+
+        std::fs::remove_dir_all(mod_path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,1418 @@
+//! windowed editor painting the tiles of isometric levels on the tilemaps, written back through
+//! [`post_processing::level`](crate::levels::post_processing::level) so levels stay editable by
+//! hand
+
+use super::{
+    edit_mod, edited_mod, editor_button, editor_entry, issues_summary, labeled_row, mod_picker,
+    profile_mods, replace, save_edits, spawn_editor_window, warn_unsaved, EditorState,
+    RebuildEditor,
+};
+use crate::actions::*;
+use crate::hud::*;
+use crate::levels::{read_map_with_positions, validate_maps};
+use crate::loading::*;
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use bevy::utils::HashMap;
+use bevy_ecs_tilemap::prelude::*;
+use std::collections::HashSet;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub struct LevelEditorPlugin;
+
+#[derive(Debug, Component)]
+pub struct LevelEditor(pub crate::Meta);
+
+impl Plugin for LevelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LevelIsometric>()
+            .add_systems(
+                OnEnter(EnableHUD::ENABLED),
+                spawn_level_editor_window
+                    .after(spawn_hud_backdrop)
+                    .run_if(in_state(crate::DeveloperMode(true))),
+            )
+            .add_systems(
+                Update,
+                (
+                    load_level_editor,
+                    handle_level_editor_buttons,
+                    edit_level_fields,
+                    undo_redo_level_edits.run_if(not(text_field_focused)),
+                    paint_level_tiles,
+                    rebuild_level_editor,
+                    show_level_editor_state,
+                    sync_level_tilemaps,
+                )
+                    .chain()
+                    .run_if(any_with_component::<LevelEditor>),
+            );
+    }
+}
+
+/// Levels of a mod, relative to the mod
+pub const LEVELS_PATH: &str = "assets/levels";
+/// Rows and columns of a new level
+pub const NEW_LEVEL_SIZE: UVec2 = UVec2::new(16, 16);
+/// Edits kept for undoing, the oldest are forgotten first
+pub const UNDO_LIMIT: usize = 100;
+
+/// Tiles of one [`SurfaceDeclared`], `None` for blank tiles
+#[derive(Debug, Clone, PartialEq)]
+pub struct SurfaceGrid {
+    pub position: Vec2,
+    /// columns and rows
+    pub size: UVec2,
+    /// index within the legend of the tile at `x + y * columns`, rows top to bottom
+    pub tiles: Vec<Option<usize>>,
+}
+
+impl SurfaceGrid {
+    pub fn blank(position: Vec2, size: UVec2) -> Self {
+        Self {
+            position,
+            size,
+            tiles: vec![None; (size.x * size.y) as usize],
+        }
+    }
+
+    /// Fails on tiles standing for no item of a legend of `legend` items
+    pub fn parse(surface: &SurfaceDeclared, size: UVec2, legend: usize) -> Result<Self, String> {
+        let mut grid = Self::blank(surface.position, size);
+        for (pos, index) in read_map_with_positions(&surface.content)? {
+            if index >= legend {
+                return Err(format!(
+                    "tile '{}' at line {} column {} stands for no item of the legend",
+                    LEGEND_CHARS[index],
+                    pos.y + 1,
+                    pos.x + 1
+                ));
+            }
+            grid.set(pos, Some(index));
+        }
+        Ok(grid)
+    }
+
+    fn index(&self, pos: TilePos) -> Option<usize> {
+        (pos.x < self.size.x && pos.y < self.size.y).then(|| (pos.x + pos.y * self.size.x) as usize)
+    }
+
+    pub fn get(&self, pos: TilePos) -> Option<usize> {
+        self.tiles[self.index(pos)?]
+    }
+
+    /// Sets a tile, returns whether it changed
+    pub fn set(&mut self, pos: TilePos, tile: Option<usize>) -> bool {
+        match self.index(pos) {
+            Some(index) => replace(&mut self.tiles[index], tile),
+            None => false,
+        }
+    }
+
+    /// Sets the tiles alike connected to `pos`, sides touching
+    pub fn fill(&mut self, pos: TilePos, tile: Option<usize>) -> bool {
+        let Some(start) = self.index(pos) else {
+            return false;
+        };
+        let target = self.tiles[start];
+        if target == tile {
+            return false;
+        }
+
+        let mut open = vec![pos];
+        while let Some(pos) = open.pop() {
+            if self.get(pos) != target || !self.set(pos, tile) {
+                continue;
+            }
+            open.extend(BeltDirection::ALL.iter().filter_map(|side| side.step(pos)));
+        }
+        true
+    }
+
+    /// Sets every tile of the rectangle with corners `from` and `to`
+    pub fn rectangle(&mut self, from: TilePos, to: TilePos, tile: Option<usize>) -> bool {
+        let mut changed = false;
+        for y in from.y.min(to.y)..=from.y.max(to.y) {
+            for x in from.x.min(to.x)..=from.x.max(to.x) {
+                changed |= self.set(TilePos { x, y }, tile);
+            }
+        }
+        changed
+    }
+
+    /// Rows of tiles as written in level files
+    pub fn content(&self) -> String {
+        self.tiles
+            .chunks(self.size.x.max(1) as usize)
+            .map(|row| {
+                row.iter()
+                    .map(|tile| tile.and_then(legend_char).unwrap_or(BLANK_TILE).to_string())
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    pub fn declared(&self) -> SurfaceDeclared {
+        (self.position, self.content()).into()
+    }
+}
+
+/// Tilemap a layer is painted on. Surfaces are listed top first, the last one is the floor
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LayerTilemap {
+    Floor,
+    Building,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum LevelTool {
+    /// paints every tile the cursor passes while pressed
+    #[default]
+    Brush,
+    /// paints the tiles alike connected to the one clicked
+    Fill,
+    /// paints the rectangle between where the cursor is pressed and released
+    Rectangle,
+}
+
+impl LevelTool {
+    pub const ALL: [Self; 3] = [Self::Brush, Self::Fill, Self::Rectangle];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Brush => "brush",
+            Self::Fill => "fill",
+            Self::Rectangle => "rectangle",
+        }
+    }
+}
+
+/// Fields of the level edited as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LevelField {
+    Label,
+    /// item added to the legend by the button next to it
+    LegendItem,
+}
+
+/// What is wrong with a level
+#[derive(Debug, Clone, PartialEq)]
+pub enum LevelIssue {
+    EmptyLabel,
+    UnknownItem(ItemId),
+    /// in the legend without a tile of it
+    UnusedItem(ItemId),
+}
+
+impl LevelIssue {
+    /// Whether the level can't be saved, items may be declared afterwards
+    pub fn blocking(&self) -> bool {
+        matches!(self, Self::EmptyLabel)
+    }
+}
+
+impl fmt::Display for LevelIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyLabel => write!(f, "the level has no label"),
+            Self::UnknownItem(id) => write!(f, "legend holds unknown `{}`", id.0),
+            Self::UnusedItem(id) => write!(f, "no tile of `{}` is placed", id.0),
+        }
+    }
+}
+
+/// A level being painted
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct LevelEditorState {
+    /// file the level is written to
+    pub path: PathBuf,
+    pub label: String,
+    pub resources: HashMap<String, f32>,
+    pub legend: Vec<ItemId>,
+    pub layers: Vec<SurfaceGrid>,
+    /// index within the layers of the layer painted on
+    pub layer: usize,
+    pub tool: LevelTool,
+    /// index within the legend of the tile painted, `None` erases
+    pub paint: Option<usize>,
+    /// whether clicking the map paints on it
+    pub painting: bool,
+    pub legend_input: String,
+    /// layers before each edit, the latest last
+    pub undo: Vec<Vec<SurfaceGrid>>,
+    /// layers before each undo, the latest last
+    pub redo: Vec<Vec<SurfaceGrid>>,
+    /// layers before the brush stroke under way
+    stroke: Option<Vec<SurfaceGrid>>,
+    /// where the rectangle under way started
+    anchor: Option<TilePos>,
+    pub unsaved: bool,
+}
+
+impl LevelEditorState {
+    fn with_layers(
+        path: PathBuf,
+        label: String,
+        resources: HashMap<String, f32>,
+        legend: Vec<ItemId>,
+        layers: Vec<SurfaceGrid>,
+    ) -> Self {
+        Self {
+            path,
+            label,
+            resources,
+            legend,
+            layers,
+            layer: 0,
+            tool: default(),
+            paint: None,
+            painting: false,
+            legend_input: String::new(),
+            undo: vec![],
+            redo: vec![],
+            stroke: None,
+            anchor: None,
+            unsaved: false,
+        }
+    }
+
+    pub fn from_level(path: PathBuf, level: &LevelIsometric) -> Result<Self, String> {
+        let contents = level
+            .surface
+            .iter()
+            .map(|surface| surface.content.clone())
+            .collect::<Vec<_>>();
+        let (columns, rows) =
+            validate_maps(&contents).map_err(|err| format!("surfaces differ, {:?}", err))?;
+        let size = UVec2::new(columns as u32, rows as u32);
+
+        let mut layers = level
+            .surface
+            .iter()
+            .map(|surface| SurfaceGrid::parse(surface, size, level.legend.len()))
+            .collect::<Result<Vec<_>, _>>()?;
+        if layers.is_empty() {
+            layers.push(SurfaceGrid::blank(Vec2::ZERO, NEW_LEVEL_SIZE));
+        }
+
+        Ok(Self::with_layers(
+            path,
+            level.label.clone(),
+            level.resources.clone(),
+            level.legend.clone(),
+            layers,
+        ))
+    }
+
+    /// A blank level of one layer, unsaved until written
+    pub fn new_level(path: PathBuf) -> Self {
+        let label = path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let mut state = Self::with_layers(
+            path,
+            label,
+            default(),
+            vec![],
+            vec![SurfaceGrid::blank(Vec2::ZERO, NEW_LEVEL_SIZE)],
+        );
+        state.unsaved = true;
+        state
+    }
+
+    pub fn read(path: PathBuf, type_registry: &TypeRegistry) -> Result<Self, String> {
+        let level = LevelIsometric::read(&path, type_registry)?;
+        Self::from_level(path, &level)
+    }
+
+    pub fn to_level(&self) -> LevelIsometric {
+        LevelIsometric {
+            label: self.label.clone(),
+            resources: self.resources.clone(),
+            legend: self.legend.clone(),
+            surface: self.layers.iter().map(SurfaceGrid::declared).collect(),
+        }
+    }
+
+    pub fn layer_tilemap(&self, layer: usize) -> LayerTilemap {
+        if layer + 1 == self.layers.len() {
+            LayerTilemap::Floor
+        } else {
+            LayerTilemap::Building
+        }
+    }
+
+    /// Tiles shown on a tilemap, the layer listed first wins where layers of the tilemap overlap
+    pub fn composed(&self, tilemap: LayerTilemap) -> HashMap<TilePos, usize> {
+        let mut tiles = HashMap::new();
+        for (layer, grid) in self.layers.iter().enumerate().rev() {
+            if self.layer_tilemap(layer) != tilemap {
+                continue;
+            }
+            for y in 0..grid.size.y {
+                for x in 0..grid.size.x {
+                    let pos = TilePos { x, y };
+                    if let Some(tile) = grid.get(pos) {
+                        tiles.insert(pos, tile);
+                    }
+                }
+            }
+        }
+        tiles
+    }
+
+    /// Runs an edit of the layers, kept for undoing when it changes something
+    fn edit(&mut self, edit: impl FnOnce(&mut Vec<SurfaceGrid>, usize) -> bool) -> bool {
+        let before = self.layers.clone();
+        if !edit(&mut self.layers, self.layer) {
+            return false;
+        }
+
+        self.record(before);
+        true
+    }
+
+    fn record(&mut self, before: Vec<SurfaceGrid>) {
+        self.undo.push(before);
+        if self.undo.len() > UNDO_LIMIT {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+        self.unsaved = true;
+    }
+
+    /// The cursor is pressed over a tile of the layer
+    pub fn press(&mut self, pos: TilePos) {
+        let paint = self.paint;
+        match self.tool {
+            LevelTool::Brush => {
+                self.stroke = Some(self.layers.clone());
+                self.drag(pos);
+            }
+            LevelTool::Fill => {
+                self.edit(|layers, layer| layers[layer].fill(pos, paint));
+            }
+            LevelTool::Rectangle => self.anchor = Some(pos),
+        }
+    }
+
+    /// The cursor moves over a tile while pressed
+    pub fn drag(&mut self, pos: TilePos) {
+        if self.stroke.is_some() {
+            let (paint, layer) = (self.paint, self.layer);
+            self.layers[layer].set(pos, paint);
+        }
+    }
+
+    /// The cursor is released, over a tile of the layer or elsewhere
+    pub fn release(&mut self, pos: Option<TilePos>) {
+        if let Some(before) = self.stroke.take() {
+            if before != self.layers {
+                self.record(before);
+            }
+        }
+
+        if let (Some(from), Some(to)) = (self.anchor.take(), pos) {
+            let paint = self.paint;
+            self.edit(|layers, layer| layers[layer].rectangle(from, to, paint));
+        }
+    }
+
+    /// Whether a stroke or rectangle is under way
+    pub fn pressed(&self) -> bool {
+        self.stroke.is_some() || self.anchor.is_some()
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let Some(before) = self.undo.pop() else {
+            return false;
+        };
+
+        self.redo.push(std::mem::replace(&mut self.layers, before));
+        self.layer = self.layer.min(self.layers.len() - 1);
+        self.unsaved = true;
+        true
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let Some(after) = self.redo.pop() else {
+            return false;
+        };
+
+        self.undo.push(std::mem::replace(&mut self.layers, after));
+        self.layer = self.layer.min(self.layers.len() - 1);
+        self.unsaved = true;
+        true
+    }
+
+    /// Adds a blank layer on top of the one painted on and paints on it
+    pub fn add_layer(&mut self) {
+        let layer = self.layer;
+        let (position, size) = (self.layers[layer].position, self.layers[layer].size);
+        self.edit(|layers, layer| {
+            layers.insert(layer, SurfaceGrid::blank(position, size));
+            true
+        });
+    }
+
+    /// Removes the layer painted on, a level keeps at least one layer
+    pub fn remove_layer(&mut self) {
+        if self.layers.len() == 1 {
+            return;
+        }
+
+        self.edit(|layers, layer| {
+            layers.remove(layer);
+            true
+        });
+        self.layer = self.layer.min(self.layers.len() - 1);
+    }
+
+    /// Adds an item to the legend unless it is there already and paints with it
+    pub fn add_legend_item(&mut self, id: &str) -> Result<usize, String> {
+        let id = ItemId(id.trim().to_string());
+        if id.0.is_empty() {
+            return Err("no item to add".into());
+        }
+
+        let index = match self.legend.iter().position(|held| *held == id) {
+            Some(index) => index,
+            None if self.legend.len() < LEGEND_CHARS.len() => {
+                self.legend.push(id);
+                self.unsaved = true;
+                self.legend.len() - 1
+            }
+            None => return Err(format!("a legend holds {} items", LEGEND_CHARS.len())),
+        };
+
+        self.paint = Some(index);
+        Ok(index)
+    }
+
+    /// Sets a field of the level, returns whether it changed
+    pub fn set_field(&mut self, field: LevelField, text: &str) -> bool {
+        match field {
+            LevelField::Label => {
+                let changed = replace(&mut self.label, text.trim().to_string());
+                self.unsaved |= changed;
+                changed
+            }
+            LevelField::LegendItem => replace(&mut self.legend_input, text.to_string()),
+        }
+    }
+
+    pub fn issues(&self, items: &ItemRegistry) -> Vec<LevelIssue> {
+        let mut issues = vec![];
+        if self.label.is_empty() {
+            issues.push(LevelIssue::EmptyLabel);
+        }
+
+        let placed = self
+            .layers
+            .iter()
+            .flat_map(|grid| grid.tiles.iter().flatten().copied())
+            .collect::<HashSet<_>>();
+        for (index, id) in self.legend.iter().enumerate() {
+            if items.item(id).is_err() {
+                issues.push(LevelIssue::UnknownItem(id.clone()));
+            }
+            if !placed.contains(&index) {
+                issues.push(LevelIssue::UnusedItem(id.clone()));
+            }
+        }
+
+        issues
+    }
+
+    pub fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        self.to_level().write(&self.path, type_registry)?;
+
+        self.unsaved = false;
+        Ok(())
+    }
+}
+
+/// Level files of the mod at `mod_path`
+pub fn level_files(mod_path: &Path) -> Vec<PathBuf> {
+    let mut files = std::fs::read_dir(mod_path.join(LEVELS_PATH))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "ron"))
+        .collect::<Vec<_>>();
+    files.sort();
+    files
+}
+
+/// Level files of the mod being edited
+#[derive(Debug, Component, Default)]
+pub struct LevelFiles {
+    pub mod_path: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+impl LevelFiles {
+    /// File for a new level not taken by another
+    pub fn new_level_path(&self) -> PathBuf {
+        (1..)
+            .map(|n| match n {
+                1 => "new_level.ron".to_string(),
+                n => format!("new_level_{}.ron", n),
+            })
+            .map(|name| self.mod_path.join(LEVELS_PATH).join(name))
+            .find(|path| !self.files.contains(path) && !path.exists())
+            .unwrap()
+    }
+}
+
+/// Tiles last put on each tilemap by the editor, only tiles that differ are touched
+#[derive(Debug, Component, Default)]
+pub struct LevelTilemapSync(pub HashMap<LayerTilemap, HashMap<TilePos, usize>>);
+
+impl EditorState for LevelEditorState {
+    const KIND: &'static str = "level";
+
+    fn unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        LevelEditorState::save(self, type_registry)
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn summary(&self) -> String {
+        format!("{} layers of {}", self.layers.len(), self.label)
+    }
+}
+
+/// Content of the editor window, respawned whenever what is edited changes shape
+#[derive(Debug, Component)]
+pub struct LevelEditorBody;
+
+#[derive(Debug, Component, Clone, PartialEq)]
+pub enum LevelEditorButton {
+    /// edit the levels of the mod at this index of the profile
+    EditMod(usize),
+    /// open the level file at this index of the files
+    Open(usize),
+    NewLevel,
+    Tool(LevelTool),
+    /// paint with the item at this index of the legend, erase with `None`
+    Paint(Option<usize>),
+    AddLegendItem,
+    /// paint on the layer at this index
+    Layer(usize),
+    AddLayer,
+    RemoveLayer,
+    Painting,
+    Undo,
+    Redo,
+    Save,
+}
+
+#[derive(Debug, Component)]
+pub struct LevelFieldInput(pub LevelField);
+
+#[derive(Debug, Component)]
+pub struct LevelEditorIssues;
+
+pub fn spawn_level_editor_window(
+    mut cmd: Commands,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+    hud_backdrop: HUDBackdropQuery,
+) {
+    let pack = crate::levels::pack::base_mod();
+
+    spawn_editor_window(
+        &mut cmd,
+        hud_backdrop.single(),
+        (LevelEditor(pack.meta.clone()), LevelTilemapSync::default()),
+        LevelEditorBody,
+        "level editor",
+        &ui,
+        &fonts,
+    );
+}
+
+/// Opens the first level of the mod being edited, the first mod of the profile when it isn't
+/// loaded. A mod without levels starts a new one
+fn load_level_editor(
+    mut cmd: Commands,
+    mut editors: Query<(Entity, &mut LevelEditor), Without<LevelEditorState>>,
+    profile: Query<&ModProfile, With<Level>>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok(profile) = profile.get_single() else {
+        return;
+    };
+
+    for (entity, mut editor) in editors.iter_mut() {
+        let Some((mod_pack, mod_path)) = edited_mod(profile, &editor.0) else {
+            continue;
+        };
+        editor.0 = mod_pack.mod_id.clone();
+
+        let files = LevelFiles {
+            mod_path: mod_path.clone(),
+            files: level_files(mod_path),
+        };
+        let state = match files.files.first() {
+            Some(path) => LevelEditorState::read(path.clone(), &type_registry.read()),
+            None => Ok(LevelEditorState::new_level(files.new_level_path())),
+        };
+
+        match state {
+            Ok(state) => {
+                cmd.entity(entity).insert((state, files, RebuildEditor));
+            }
+            Err(err) => {
+                error!("unable to edit levels of {}. {}", editor.0, err);
+                Notification {
+                    title: "Unable to edit levels".into(),
+                    level: NotificationLevel::Error,
+                    description: format!("{}: {}", editor.0, err),
+                }
+                .queue(None, &mut notifications_channel);
+                cmd.entity(entity).insert((
+                    LevelEditorState::new_level(files.new_level_path()),
+                    files,
+                    RebuildEditor,
+                ));
+            }
+        }
+    }
+}
+
+fn handle_level_editor_buttons(
+    mut cmd: Commands,
+    buttons: Query<(&DepressButton, &LevelEditorButton), Changed<DepressButton>>,
+    mut editor: Query<(
+        Entity,
+        &mut LevelEditor,
+        &mut LevelEditorState,
+        &mut LevelFiles,
+    )>,
+    profile: Query<&ModProfile, With<Level>>,
+    items: Res<ItemRegistry>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok((entity, mut editor, mut state, mut files)) = editor.get_single_mut() else {
+        return;
+    };
+
+    let pressed = buttons
+        .iter()
+        .filter(|(depress, _)| depress.invoked())
+        .map(|(_, button)| button.clone())
+        .collect::<Vec<_>>();
+
+    for button in pressed {
+        let reshaped = match button {
+            LevelEditorButton::EditMod(index) => {
+                edit_mod(
+                    &mut cmd,
+                    entity,
+                    &mut editor.0,
+                    &*state,
+                    &profile,
+                    index,
+                    &mut notifications_channel,
+                );
+                false
+            }
+            LevelEditorButton::Open(index) => {
+                let Some(path) = files.files.get(index) else {
+                    continue;
+                };
+                match LevelEditorState::read(path.clone(), &type_registry.read()) {
+                    Ok(opened) => {
+                        warn_unsaved(&*state, &state.label, &mut notifications_channel);
+                        *state = opened;
+                    }
+                    Err(err) => {
+                        error!("unable to open {}. {}", path.to_string_lossy(), err);
+                        Notification {
+                            title: "Unable to open level".into(),
+                            level: NotificationLevel::Error,
+                            description: err,
+                        }
+                        .queue(None, &mut notifications_channel);
+                    }
+                }
+                true
+            }
+            LevelEditorButton::NewLevel => {
+                warn_unsaved(&*state, &state.label, &mut notifications_channel);
+                *state = LevelEditorState::new_level(files.new_level_path());
+                true
+            }
+            LevelEditorButton::Tool(tool) => {
+                state.tool = tool;
+                true
+            }
+            LevelEditorButton::Paint(paint) => {
+                state.paint = paint;
+                true
+            }
+            LevelEditorButton::AddLegendItem => {
+                let id = state.legend_input.clone();
+                if let Err(err) = state.add_legend_item(&id) {
+                    Notification {
+                        title: "Item not added to the legend".into(),
+                        level: NotificationLevel::Warning,
+                        description: err,
+                    }
+                    .queue(Some(Duration::from_secs(5)), &mut notifications_channel);
+                }
+                true
+            }
+            LevelEditorButton::Layer(layer) => {
+                state.layer = layer.min(state.layers.len() - 1);
+                true
+            }
+            LevelEditorButton::AddLayer => {
+                state.add_layer();
+                true
+            }
+            LevelEditorButton::RemoveLayer => {
+                state.remove_layer();
+                true
+            }
+            LevelEditorButton::Painting => {
+                state.painting = !state.painting;
+                true
+            }
+            LevelEditorButton::Undo => state.undo(),
+            LevelEditorButton::Redo => state.redo(),
+            LevelEditorButton::Save => {
+                save_level(
+                    &mut state,
+                    &items,
+                    &type_registry.read(),
+                    &mut notifications_channel,
+                );
+                if !files.files.contains(&state.path) && !state.unsaved {
+                    files.files.push(state.path.clone());
+                    files.files.sort();
+                }
+                true
+            }
+        };
+
+        if reshaped {
+            cmd.entity(entity).insert(RebuildEditor);
+        }
+    }
+}
+
+/// Levels are only written once nothing blocking is wrong with them
+fn save_level(
+    state: &mut LevelEditorState,
+    items: &ItemRegistry,
+    type_registry: &TypeRegistry,
+    notifications_channel: &mut NotificationChannel,
+) {
+    let issues = state
+        .issues(items)
+        .into_iter()
+        .filter(LevelIssue::blocking)
+        .collect::<Vec<_>>();
+    save_edits(state, &issues, type_registry, notifications_channel);
+}
+
+fn edit_level_fields(
+    fields: Query<(&TextField, &LevelFieldInput), Changed<TextField>>,
+    mut editor: Query<&mut LevelEditorState>,
+) {
+    let Ok(mut state) = editor.get_single_mut() else {
+        return;
+    };
+
+    for (field, LevelFieldInput(level_field)) in fields.iter() {
+        state.set_field(*level_field, &field.value);
+    }
+}
+
+/// Ctrl+Z undoes, Ctrl+Y or Ctrl+Shift+Z redoes
+fn undo_redo_level_edits(
+    keys: Res<ButtonInput<KeyCode>>,
+    mut editor: Query<&mut LevelEditorState>,
+) {
+    let Ok(mut state) = editor.get_single_mut() else {
+        return;
+    };
+    if !keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if keys.just_pressed(KeyCode::KeyY) || (shift && keys.just_pressed(KeyCode::KeyZ)) {
+        state.redo();
+    } else if keys.just_pressed(KeyCode::KeyZ) {
+        state.undo();
+    }
+}
+
+/// Tilemaps painted by the editor
+type LevelTilemapQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static TilemapSize,
+        &'static TilemapGridSize,
+        &'static TilemapType,
+        &'static Transform,
+        Has<FloorTilemap>,
+    ),
+    Or<(With<FloorTilemap>, With<BuildingTilemap>)>,
+>;
+
+/// Paints the layer with the mouse on the tilemap it is shown on, unless the cursor is over
+/// the interface
+fn paint_level_tiles(
+    mouse: Res<ButtonInput<MouseButton>>,
+    cursor: Res<CursorWorldPosition>,
+    tilemaps: LevelTilemapQuery,
+    interactions: Query<&Interaction>,
+    mut editor: Query<&mut LevelEditorState>,
+) {
+    let Ok(mut state) = editor.get_single_mut() else {
+        return;
+    };
+    if !state.painting {
+        return;
+    }
+
+    let floor = state.layer_tilemap(state.layer) == LayerTilemap::Floor;
+    let pos = tilemaps
+        .iter()
+        .find(|(.., is_floor)| *is_floor == floor)
+        .and_then(|(size, grid_size, map_type, transform, _)| {
+            cursor_tile_position(&cursor, size, grid_size, map_type, transform)
+        });
+
+    if mouse.just_released(MouseButton::Left) {
+        state.release(pos);
+        return;
+    }
+
+    let Some(pos) = pos else {
+        return;
+    };
+    if mouse.just_pressed(MouseButton::Left) {
+        let over_ui = interactions
+            .iter()
+            .any(|interaction| *interaction != Interaction::None);
+        if !over_ui {
+            state.press(pos);
+        }
+    } else if mouse.pressed(MouseButton::Left) && state.pressed() {
+        state.drag(pos);
+    }
+}
+
+/// Tiles and textures of the tilemaps painted by the editor
+type LevelTilemapTileQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static mut TileStorage,
+        &'static mut TilemapTexture,
+        Has<FloorTilemap>,
+    ),
+    Or<(With<FloorTilemap>, With<BuildingTilemap>)>,
+>;
+
+/// Mirrors the layers onto the tilemaps, erased tiles are despawned
+fn sync_level_tilemaps(
+    mut cmd: Commands,
+    mut editor: Query<(&LevelEditorState, &mut LevelTilemapSync), Changed<LevelEditorState>>,
+    mut tilemaps: LevelTilemapTileQuery,
+    mut tiles: Query<&mut TileTextureIndex>,
+    textures: Query<&ItemTextureMap, With<Level>>,
+) {
+    let Ok((state, mut sync)) = editor.get_single_mut() else {
+        return;
+    };
+    let Ok(textures) = textures.get_single() else {
+        return;
+    };
+
+    for (tilemap, mut storage, mut texture, is_floor) in tilemaps.iter_mut() {
+        let target = match is_floor {
+            true => LayerTilemap::Floor,
+            false => LayerTilemap::Building,
+        };
+        let composed = state.composed(target);
+        let shown = sync.0.entry(target).or_default();
+
+        let positions = composed
+            .keys()
+            .chain(shown.keys())
+            .copied()
+            .collect::<HashSet<_>>();
+        for pos in positions {
+            let tile = composed.get(&pos).copied();
+            if tile == shown.get(&pos).copied() {
+                continue;
+            }
+
+            let image = tile
+                .and_then(|tile| state.legend.get(tile))
+                .and_then(|id| textures.0.get(&id.0));
+            let existing = storage.checked_get(&pos);
+            match (image, existing) {
+                (Some(image), Some(entity)) => {
+                    let Some(index) = texture_index_of(&mut texture, image) else {
+                        continue;
+                    };
+                    match tiles.get_mut(entity) {
+                        Ok(mut shown_index) => *shown_index = index,
+                        Err(_) => {
+                            cmd.entity(entity).insert(index);
+                        }
+                    }
+                }
+                (Some(image), None) => {
+                    let Some(index) = texture_index_of(&mut texture, image) else {
+                        continue;
+                    };
+                    let entity = cmd
+                        .spawn(TileBundle {
+                            position: pos,
+                            tilemap_id: TilemapId(tilemap),
+                            texture_index: index,
+                            ..default()
+                        })
+                        .id();
+                    cmd.entity(tilemap).add_child(entity);
+                    storage.checked_set(&pos, entity);
+                }
+                (None, Some(entity)) => {
+                    cmd.entity(entity).despawn_recursive();
+                    storage.checked_remove(&pos);
+                }
+                (None, None) => {}
+            }
+        }
+
+        *shown = composed;
+    }
+}
+
+fn rebuild_level_editor(
+    mut cmd: Commands,
+    editor: Query<(Entity, &LevelEditor, &LevelEditorState, &LevelFiles), With<RebuildEditor>>,
+    body: Query<Entity, With<LevelEditorBody>>,
+    profile: Query<&ModProfile, With<Level>>,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+) {
+    let (Ok((entity, editor, state, files)), Ok(body)) = (editor.get_single(), body.get_single())
+    else {
+        return;
+    };
+    cmd.entity(entity).remove::<RebuildEditor>();
+
+    let mods = profile_mods(&profile);
+
+    cmd.entity(body)
+        .despawn_descendants()
+        .with_children(|parent| {
+            // levels of the mod
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(35.),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    mod_picker(
+                        &editor.0,
+                        &mods,
+                        LevelEditorButton::EditMod,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+
+                    for (index, path) in files.files.iter().enumerate() {
+                        editor_entry(
+                            &path.file_name().unwrap_or_default().to_string_lossy(),
+                            *path == state.path,
+                            LevelEditorButton::Open(index),
+                            (),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    }
+                    editor_button(
+                        "new level",
+                        LevelEditorButton::NewLevel,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+                    separator(parent);
+
+                    labeled_row("label", parent, &fonts).with_children(|parent| {
+                        spawn_text_field(
+                            &state.label,
+                            LevelFieldInput(LevelField::Label),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    });
+                    parent
+                        .spawn(Node {
+                            column_gap: Val::Px(UI_SCALE),
+                            flex_shrink: 0.,
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            editor_button("undo", LevelEditorButton::Undo, parent, &fonts, &ui);
+                            editor_button("redo", LevelEditorButton::Redo, parent, &fonts, &ui);
+                            editor_button("save", LevelEditorButton::Save, parent, &fonts, &ui);
+                        });
+                    body_text("", parent, &fonts).insert(LevelEditorIssues);
+                });
+
+            // tools, layers and palette
+            parent
+                .spawn((
+                    Node {
+                        flex_grow: 1.,
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    labeled_row("paint on map", parent, &fonts).with_children(|parent| {
+                        editor_entry(
+                            if state.painting { "on" } else { "off" },
+                            state.painting,
+                            LevelEditorButton::Painting,
+                            (),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    });
+                    labeled_row("tool", parent, &fonts).with_children(|parent| {
+                        for tool in LevelTool::ALL {
+                            editor_entry(
+                                tool.label(),
+                                state.tool == tool,
+                                LevelEditorButton::Tool(tool),
+                                (),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        }
+                    });
+                    separator(parent);
+
+                    // listed top first like in the level file
+                    section_text("layers", parent, &fonts);
+                    for layer in 0..state.layers.len() {
+                        let name = match state.layer_tilemap(layer) {
+                            LayerTilemap::Floor => format!("layer {} (floor)", layer + 1),
+                            LayerTilemap::Building => format!("layer {}", layer + 1),
+                        };
+                        editor_entry(
+                            &name,
+                            state.layer == layer,
+                            LevelEditorButton::Layer(layer),
+                            (),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    }
+                    parent
+                        .spawn(Node {
+                            column_gap: Val::Px(UI_SCALE),
+                            flex_shrink: 0.,
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            editor_button(
+                                "+ layer",
+                                LevelEditorButton::AddLayer,
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                            editor_button(
+                                "remove layer",
+                                LevelEditorButton::RemoveLayer,
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        });
+                    separator(parent);
+
+                    section_text("palette", parent, &fonts);
+                    parent
+                        .spawn(Node {
+                            column_gap: Val::Px(UI_SCALE),
+                            row_gap: Val::Px(UI_SCALE),
+                            flex_wrap: FlexWrap::Wrap,
+                            flex_shrink: 0.,
+                            ..default()
+                        })
+                        .with_children(|parent| {
+                            editor_entry(
+                                &format!("{} erase", BLANK_TILE),
+                                state.paint.is_none(),
+                                LevelEditorButton::Paint(None),
+                                (),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                            for (index, id) in state.legend.iter().enumerate() {
+                                editor_entry(
+                                    &format!(
+                                        "{} {}",
+                                        legend_char(index).unwrap_or(BLANK_TILE),
+                                        id.0
+                                    ),
+                                    state.paint == Some(index),
+                                    LevelEditorButton::Paint(Some(index)),
+                                    (),
+                                    parent,
+                                    &fonts,
+                                    &ui,
+                                );
+                            }
+                        });
+                    labeled_row("item", parent, &fonts).with_children(|parent| {
+                        spawn_text_field(
+                            &state.legend_input,
+                            LevelFieldInput(LevelField::LegendItem),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                        editor_button(
+                            "add to legend",
+                            LevelEditorButton::AddLegendItem,
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    });
+                });
+        });
+}
+
+/// Keeps issues up to date while typing and painting
+fn show_level_editor_state(
+    editor: Query<&LevelEditorState>,
+    changed: Query<(), Changed<LevelEditorState>>,
+    mut issues_text: Query<&mut Text, With<LevelEditorIssues>>,
+    items: Res<ItemRegistry>,
+) {
+    let Ok(state) = editor.get_single() else {
+        return;
+    };
+    if changed.is_empty() && issues_text.iter().all(|text| !text.0.is_empty()) {
+        return;
+    }
+
+    let issues = state.issues(&items);
+    let summary = issues_summary(&issues, state.unsaved);
+
+    for mut text in issues_text.iter_mut() {
+        if text.0 != summary {
+            text.0 = summary.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: u32, y: u32) -> TilePos {
+        TilePos { x, y }
+    }
+
+    fn editor() -> LevelEditorState {
+        let level = LevelIsometric {
+            label: "kitchen".into(),
+            resources: HashMap::from_iter([("money".to_string(), 200.)]),
+            legend: vec!["base::auto_arm".into(), "base::mover_belt".into()],
+            surface: vec![
+                (Vec2::ZERO, "_ | 0 | _\n_ | _ | _\n_ | _ | _".to_string()).into(),
+                (Vec2::ZERO, "1 | 1 | 1\n1 | _ | 1\n1 | 1 | 1".to_string()).into(),
+            ],
+        };
+        LevelEditorState::from_level(PathBuf::from("kitchen.ron"), &level).unwrap()
+    }
+
+    #[test]
+    fn grids_read_and_write_level_rows() {
+        let state = editor();
+        let floor = &state.layers[1];
+
+        assert_eq!(floor.size, UVec2::new(3, 3));
+        assert_eq!(floor.get(pos(1, 1)), None);
+        assert_eq!(floor.get(pos(2, 0)), Some(1));
+        assert_eq!(floor.content(), "1 | 1 | 1\n1 | _ | 1\n1 | 1 | 1");
+        assert_eq!(state.layers[0].content(), "_ | 0 | _\n_ | _ | _\n_ | _ | _");
+    }
+
+    #[test]
+    fn fill_and_rectangle() {
+        let mut grid = editor().layers[1].clone();
+
+        // the blank middle isn't connected to the ring
+        assert!(grid.fill(pos(0, 0), Some(0)));
+        assert_eq!(grid.content(), "0 | 0 | 0\n0 | _ | 0\n0 | 0 | 0");
+        assert!(!grid.fill(pos(0, 0), Some(0)));
+
+        assert!(grid.rectangle(pos(2, 2), pos(1, 1), None));
+        assert_eq!(grid.content(), "0 | 0 | 0\n0 | _ | _\n0 | _ | _");
+        assert!(!grid.set(pos(3, 0), Some(1)));
+    }
+
+    #[test]
+    fn strokes_undo_at_once() {
+        let mut state = editor();
+        state.layer = 1;
+        state.paint = Some(0);
+
+        state.press(pos(0, 0));
+        state.drag(pos(1, 0));
+        state.drag(pos(1, 1));
+        state.release(Some(pos(1, 1)));
+        assert_eq!(state.layers[1].content(), "0 | 0 | 1\n1 | 0 | 1\n1 | 1 | 1");
+        assert_eq!(state.undo.len(), 1);
+
+        state.tool = LevelTool::Rectangle;
+        state.paint = None;
+        state.press(pos(2, 2));
+        state.release(Some(pos(2, 1)));
+        assert_eq!(state.layers[1].content(), "0 | 0 | 1\n1 | 0 | _\n1 | 1 | _");
+
+        assert!(state.undo());
+        assert!(state.undo());
+        assert_eq!(state.layers, editor().layers);
+        assert!(!state.undo());
+
+        assert!(state.redo());
+        assert_eq!(state.layers[1].content(), "0 | 0 | 1\n1 | 0 | 1\n1 | 1 | 1");
+
+        // a new edit forgets what was undone
+        state.tool = LevelTool::Fill;
+        state.press(pos(0, 2));
+        assert!(!state.redo());
+    }
+
+    #[test]
+    fn layers_compose_onto_tilemaps() {
+        let mut state = editor();
+        state.add_layer();
+        state.paint = Some(1);
+        state.press(pos(1, 0));
+        state.release(None);
+
+        assert_eq!(state.layer_tilemap(2), LayerTilemap::Floor);
+        // the layer listed first is on top
+        assert_eq!(
+            state.composed(LayerTilemap::Building),
+            HashMap::from_iter([(pos(1, 0), 1)])
+        );
+        assert_eq!(state.composed(LayerTilemap::Floor).len(), 8);
+
+        state.remove_layer();
+        assert_eq!(
+            state.composed(LayerTilemap::Building),
+            HashMap::from_iter([(pos(1, 0), 0)])
+        );
+        assert!(state.undo());
+        assert_eq!(state.layers.len(), 3);
+    }
+
+    #[test]
+    fn legend_grows_up_to_its_characters() {
+        let mut state = editor();
+
+        assert_eq!(state.add_legend_item("base::mover_belt"), Ok(1));
+        assert_eq!(state.add_legend_item(" base::infinite_io "), Ok(2));
+        assert_eq!(state.paint, Some(2));
+        for n in state.legend.len()..LEGEND_CHARS.len() {
+            state.add_legend_item(&format!("base::item_{}", n)).unwrap();
+        }
+        assert!(state.add_legend_item("base::one_too_many").is_err());
+        assert!(state.add_legend_item("").is_err());
+    }
+
+    #[test]
+    fn hand_written_typos_are_refused() {
+        let level = |content: &str| LevelIsometric {
+            label: "kitchen".into(),
+            resources: HashMap::default(),
+            legend: vec!["base::auto_arm".into()],
+            surface: vec![(Vec2::ZERO, content.to_string()).into()],
+        };
+
+        assert!(LevelEditorState::from_level(PathBuf::new(), &level("0 | _\n_ | 0")).is_ok());
+        assert!(LevelEditorState::from_level(PathBuf::new(), &level("0 | F\n_ | 0")).is_err());
+        assert!(LevelEditorState::from_level(PathBuf::new(), &level("0 | 1\n_ | 0")).is_err());
+    }
+
+    #[test]
+    fn levels_round_trip_through_post_processing() {
+        let mut type_registry = TypeRegistry::new();
+        type_registry.register::<LevelIsometric>();
+        let mut state = editor();
+        state.add_layer();
+        state.paint = Some(1);
+        state.press(pos(2, 2));
+        state.release(None);
+
+        let ron = state.to_level().to_ron(&type_registry).unwrap();
+        assert!(ron.contains("r#\"_ | _ | _"));
+        assert!(ron.contains("// For every tile character is an index"));
+
+        let level = LevelIsometric::from_ron(&ron, &type_registry).unwrap();
+        let read = LevelEditorState::from_level(state.path.clone(), &level).unwrap();
+        assert_eq!(read.layers, state.layers);
+        assert_eq!(read.legend, state.legend);
+        assert_eq!(read.label, state.label);
+        assert_eq!(read.resources, state.resources);
+    }
+}
@@ -1,9 +1,412 @@
 mod research_editor;
 pub use research_editor::*;
 mod building_editor;
+pub use building_editor::*;
 mod io_editor;
+pub use io_editor::*;
 mod item_editor;
+pub use item_editor::*;
+mod level_editor;
+pub use level_editor::*;
 mod tool_bar;
 pub use tool_bar::*;
 mod mods;
 pub use mods::*;
+
+use crate::loading::{FontAssets, UiAssets};
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// What an editor edits, written back to its mod on save
+pub trait EditorState: Component {
+    /// what is edited, such as `items`, named in notifications
+    const KIND: &'static str;
+
+    fn unsaved(&self) -> bool;
+
+    fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String>;
+
+    /// file or mod directory written to
+    fn path(&self) -> &Path;
+
+    /// what was saved, such as `4 items of tyconic`
+    fn summary(&self) -> String;
+}
+
+/// Window of an editor, naming the mod whose [`EditorState`] is edited
+pub trait ModEditor: Component {
+    type State: EditorState;
+
+    fn edited(&mut self) -> &mut Meta;
+
+    fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<Self::State, String>;
+}
+
+/// Flags an editor for respawning its content
+#[derive(Debug, Component)]
+pub struct RebuildEditor;
+
+/// Window of an editor, its body is filled whenever the editor rebuilds
+pub(crate) fn spawn_editor_window(
+    cmd: &mut Commands,
+    hud_backdrop: Entity,
+    editor: impl Bundle,
+    body: impl Bundle,
+    title: &str,
+    ui: &Res<UiAssets>,
+    fonts: &Res<FontAssets>,
+) {
+    let mut body = Some(body);
+    cmd.entity(hud_backdrop).with_children(|parent| {
+        spawn_window(
+            parent,
+            editor,
+            (),
+            ui,
+            fonts,
+            WindowMeta::new(title.into(), 500., 16. / 9.),
+            |parent| {
+                let Some(body) = body.take() else {
+                    return;
+                };
+                parent.spawn((
+                    body,
+                    Node {
+                        flex_grow: 1.,
+                        margin: UiRect::all(Val::Px(UI_SCALE * 2.)),
+                        column_gap: Val::Px(UI_SCALE * 2.),
+                        overflow: Overflow::clip(),
+                        ..default()
+                    },
+                ));
+            },
+        );
+    });
+}
+
+/// Mod of the profile going by the name of `edited`, the first one when it isn't loaded
+pub(crate) fn edited_mod<'a>(
+    profile: &'a ModProfile,
+    edited: &Meta,
+) -> Option<&'a (ModPack, PathBuf)> {
+    profile
+        .0
+        .iter()
+        .find(|(mod_pack, _)| mod_pack.mod_id.mod_name == edited.mod_name)
+        .or_else(|| profile.0.first())
+}
+
+/// Names of the mods of the level, in load order
+pub(crate) fn profile_mods(profile: &Query<&ModProfile, With<Level>>) -> Vec<String> {
+    profile
+        .get_single()
+        .map(|profile| {
+            profile
+                .0
+                .iter()
+                .map(|(mod_pack, _)| mod_pack.mod_id.mod_name.clone())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Reads what the editor edits from its mod, a window with nothing to edit is closed
+pub(crate) fn load_editor<E: ModEditor>(
+    mut cmd: Commands,
+    mut editors: Query<(Entity, &mut E), Without<E::State>>,
+    profile: Query<&ModProfile, With<Level>>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok(profile) = profile.get_single() else {
+        return;
+    };
+
+    for (entity, mut editor) in editors.iter_mut() {
+        let Some((mod_pack, mod_path)) = edited_mod(profile, editor.edited()) else {
+            continue;
+        };
+        let edited = editor.edited();
+        *edited = mod_pack.mod_id.clone();
+
+        let state = edited
+            .namespace()
+            .map_err(|err| err.to_string())
+            .and_then(|namespace| E::read(mod_path.clone(), namespace, &type_registry.read()));
+
+        match state {
+            Ok(state) => {
+                cmd.entity(entity).insert((state, RebuildEditor));
+            }
+            Err(err) => {
+                let kind = <E::State as EditorState>::KIND;
+                error!("unable to edit {} of {}. {}", kind, edited, err);
+                Notification {
+                    title: format!("Unable to edit {}", kind),
+                    level: NotificationLevel::Error,
+                    description: format!("{}: {}", edited, err),
+                }
+                .queue(None, &mut notifications_channel);
+                cmd.entity(entity).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Moves the editor onto the mod at this index of the profile, it's read again as the state is
+/// removed
+pub(crate) fn edit_mod<S: EditorState>(
+    cmd: &mut Commands,
+    entity: Entity,
+    edited: &mut Meta,
+    state: &S,
+    profile: &Query<&ModProfile, With<Level>>,
+    index: usize,
+    notifications_channel: &mut NotificationChannel,
+) {
+    let Some((mod_pack, _)) = profile.get_single().ok().and_then(|p| p.0.get(index)) else {
+        return;
+    };
+
+    warn_unsaved(state, &*edited, notifications_channel);
+    *edited = mod_pack.mod_id.clone();
+    cmd.entity(entity).remove::<S>();
+}
+
+/// Warns that the changes to `edited` are discarded, if there are any
+pub(crate) fn warn_unsaved<S: EditorState>(
+    state: &S,
+    edited: impl fmt::Display,
+    notifications_channel: &mut NotificationChannel,
+) {
+    if state.unsaved() {
+        Notification {
+            title: format!("Unsaved {} discarded", S::KIND),
+            level: NotificationLevel::Warning,
+            description: format!("changes to {} were not saved", edited),
+        }
+        .queue(Some(Duration::from_secs(5)), notifications_channel);
+    }
+}
+
+/// Written only once nothing blocking is wrong with what is edited
+pub(crate) fn save_edits<S: EditorState>(
+    state: &mut S,
+    blocking: &[impl fmt::Display],
+    type_registry: &TypeRegistry,
+    notifications_channel: &mut NotificationChannel,
+) {
+    if let Some(issue) = blocking.first() {
+        Notification {
+            title: format!("{} not saved", capitalized(S::KIND)),
+            level: NotificationLevel::Warning,
+            description: match blocking.len() {
+                1 => issue.to_string(),
+                n => format!("{} and {} more issues", issue, n - 1),
+            },
+        }
+        .queue(Some(Duration::from_secs(10)), notifications_channel);
+        return;
+    }
+
+    match state.save(type_registry) {
+        Ok(()) => {
+            info!("{} saved to {}", S::KIND, state.path().to_string_lossy());
+            Notification {
+                title: format!("{} saved", capitalized(S::KIND)),
+                level: NotificationLevel::Info,
+                description: state.summary(),
+            }
+            .queue(Some(Duration::from_secs(5)), notifications_channel);
+        }
+        Err(err) => {
+            error!("unable to save {}. {}", S::KIND, err);
+            Notification {
+                title: format!("Unable to save {}", S::KIND),
+                level: NotificationLevel::Error,
+                description: err,
+            }
+            .queue(None, notifications_channel);
+        }
+    }
+}
+
+fn capitalized(text: &str) -> String {
+    let mut chars = text.chars();
+    chars
+        .next()
+        .map(|first| first.to_uppercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Every issue on its own line, or whether anything is left to save
+pub(crate) fn issues_summary(issues: &[impl fmt::Display], unsaved: bool) -> String {
+    if issues.is_empty() {
+        match unsaved {
+            true => "unsaved changes".to_string(),
+            false => "no issues".to_string(),
+        }
+    } else {
+        issues
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Names the mod edited, followed by a button to edit each mod of the profile instead
+pub(crate) fn mod_picker<B: Bundle>(
+    edited: &Meta,
+    mods: &[String],
+    button: impl Fn(usize) -> B,
+    parent: &mut ChildBuilder,
+    fonts: &Res<FontAssets>,
+    ui: &Res<UiAssets>,
+) {
+    section_text(&format!("editing `{}`", edited), parent, fonts);
+    parent
+        .spawn(Node {
+            column_gap: Val::Px(UI_SCALE),
+            flex_wrap: FlexWrap::Wrap,
+            flex_shrink: 0.,
+            ..default()
+        })
+        .with_children(|parent| {
+            for (index, mod_name) in mods.iter().enumerate() {
+                editor_button(mod_name, button(index), parent, fonts, ui);
+            }
+        });
+    separator(parent);
+}
+
+/// Sets `slot` to `value`, returns whether it changed
+pub(crate) fn replace<T: PartialEq>(slot: &mut T, value: T) -> bool {
+    if *slot == value {
+        false
+    } else {
+        *slot = value;
+        true
+    }
+}
+
+/// Text button of the editors, `components` go on the button
+pub(crate) fn editor_button(
+    text: &str,
+    components: impl Bundle,
+    parent: &mut ChildBuilder,
+    fonts: &Res<FontAssets>,
+    ui: &Res<UiAssets>,
+) {
+    parent
+        .spawn(Node {
+            height: Val::Px(UI_SCALE * 5.),
+            flex_shrink: 0.,
+            ..default()
+        })
+        .with_children(|parent| {
+            spawn_button(
+                ButtonType::Text {
+                    text: text.into(),
+                    font_size: SMALL_MEDIUM_FONT,
+                },
+                components,
+                parent,
+                fonts,
+                ui,
+            );
+        });
+}
+
+/// Entry of the list of what an editor edits, `label` goes on the text so it can follow edits
+pub(crate) fn editor_entry(
+    text: &str,
+    selected: bool,
+    components: impl Bundle,
+    label: impl Bundle,
+    parent: &mut ChildBuilder,
+    fonts: &Res<FontAssets>,
+    ui: &Res<UiAssets>,
+) {
+    let skins = ButtonSkins::from(ui);
+    parent
+        .spawn((
+            components,
+            DepressButton::default(),
+            Node {
+                padding: UiRect::axes(Val::Px(UI_SCALE * 2.), Val::Px(UI_SCALE)),
+                flex_shrink: 0.,
+                ..default()
+            },
+            ImageNode {
+                image: if selected {
+                    skins.active.clone()
+                } else {
+                    skins.normal.clone()
+                },
+                image_mode: BUTTON_IMG_MODE_SLICED,
+                ..default()
+            },
+            skins,
+        ))
+        .with_children(|parent| {
+            body_text(text, parent, fonts).insert(label);
+        });
+}
+
+/// Row starting with a label, the field follows as children
+pub(crate) fn labeled_row<'a>(
+    label: &str,
+    parent: &'a mut ChildBuilder,
+    fonts: &Res<FontAssets>,
+) -> EntityCommands<'a> {
+    let mut row = parent.spawn(Node {
+        column_gap: Val::Px(UI_SCALE),
+        align_items: AlignItems::Center,
+        flex_shrink: 0.,
+        ..default()
+    });
+    row.with_children(|parent| {
+        section_text(label, parent, fonts);
+    });
+    row
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    /// Items declared by the mods of their namespaces
+    pub fn item_registry(ids: &[&'static str]) -> ItemRegistry {
+        let mut merged = Merged::default();
+        for id in ids {
+            let (mod_name, _) = id.split_once("::").unwrap();
+            merged.apply(
+                mod_name,
+                Declaration::Declare(ItemDeclared::from(ItemId::from(*id))),
+            );
+        }
+        ItemRegistry::from(merged)
+    }
+
+    #[test]
+    fn issues_are_summarized() {
+        let none: [String; 0] = [];
+        assert_eq!(issues_summary(&none, false), "no issues");
+        assert_eq!(issues_summary(&none, true), "unsaved changes");
+        assert_eq!(
+            issues_summary(&["no texture", "unknown item"], true),
+            "no texture\nunknown item"
+        );
+        assert_eq!(capitalized("items"), "Items");
+    }
+}
@@ -1,10 +1,19 @@
 //! windowed editor for making catalogue of research progression and unlocking recipes which then unlocks
 //! new items
 
+use super::{
+    edit_mod, editor_button, editor_entry, issues_summary, labeled_row, load_editor, mod_picker,
+    profile_mods, replace, save_edits, spawn_editor_window, EditorState, ModEditor, RebuildEditor,
+};
 use crate::hud::*;
 use crate::loading::*;
 use crate::ui::*;
+use crate::*;
 use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
 
 pub struct ResearchEditorPlugin;
 
@@ -15,14 +24,736 @@ impl Plugin for ResearchEditorPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(
             OnEnter(EnableHUD::ENABLED),
-            spawn_editor_window
+            spawn_research_editor_window
                 .after(spawn_hud_backdrop)
                 .run_if(in_state(crate::DeveloperMode(true))),
+        )
+        .add_systems(
+            Update,
+            (
+                load_editor::<ResearchEditor>,
+                handle_research_editor_buttons,
+                edit_research_fields,
+                edit_condition_fields,
+                rebuild_research_editor,
+                show_research_editor_state,
+            )
+                .chain()
+                .run_if(any_with_component::<ResearchEditor>),
         );
     }
 }
 
-pub fn spawn_editor_window(
+/// Kinds of condition offered by the editor, clicking the kind of a condition moves to the next
+pub const CONDITION_KINDS: [&str; 12] = [
+    "all of",
+    "any of",
+    "none of",
+    "reached",
+    "under",
+    "between",
+    "ratio at least",
+    "ratio under",
+    "research completed",
+    "has item",
+    "rate at least",
+    "sustained for",
+];
+
+pub fn condition_kind(condition: &ConditionFlag) -> usize {
+    match condition {
+        ConditionFlag::SatisfyAll(_) => 0,
+        ConditionFlag::SatisfyAny(_) => 1,
+        ConditionFlag::Not(_) => 2,
+        ConditionFlag::ReachedMetric { .. } => 3,
+        ConditionFlag::UnderMetric { .. } => 4,
+        ConditionFlag::Between { .. } => 5,
+        ConditionFlag::RatioAtLeast { .. } => 6,
+        ConditionFlag::RatioUnder { .. } => 7,
+        ConditionFlag::ResearchCompleted(_) => 8,
+        ConditionFlag::HasItem { .. } => 9,
+        ConditionFlag::RateAtLeast { .. } => 10,
+        ConditionFlag::SustainedFor { .. } => 11,
+    }
+}
+
+pub fn condition_children(condition: &ConditionFlag) -> Option<&Vec<ConditionFlag>> {
+    match condition {
+        ConditionFlag::SatisfyAll(conditions)
+        | ConditionFlag::SatisfyAny(conditions)
+        | ConditionFlag::Not(conditions)
+        | ConditionFlag::SustainedFor { conditions, .. } => Some(conditions),
+        _ => None,
+    }
+}
+
+pub fn condition_children_mut(condition: &mut ConditionFlag) -> Option<&mut Vec<ConditionFlag>> {
+    match condition {
+        ConditionFlag::SatisfyAll(conditions)
+        | ConditionFlag::SatisfyAny(conditions)
+        | ConditionFlag::Not(conditions)
+        | ConditionFlag::SustainedFor { conditions, .. } => Some(conditions),
+        _ => None,
+    }
+}
+
+/// Metric compared by the condition, the numerator for ratios
+fn condition_metric(condition: &ConditionFlag) -> Option<&str> {
+    match condition {
+        ConditionFlag::ReachedMetric { metric, .. }
+        | ConditionFlag::UnderMetric { metric, .. }
+        | ConditionFlag::Between { metric, .. }
+        | ConditionFlag::RateAtLeast { metric, .. }
+        | ConditionFlag::RatioAtLeast {
+            numerator: metric, ..
+        }
+        | ConditionFlag::RatioUnder {
+            numerator: metric, ..
+        } => Some(metric),
+        _ => None,
+    }
+}
+
+/// The condition turned into another kind, nested conditions and the metric carry over
+pub fn convert_condition(condition: &ConditionFlag, kind: usize) -> ConditionFlag {
+    let conditions = condition_children(condition).cloned().unwrap_or_default();
+    let metric = condition_metric(condition).unwrap_or_default().to_string();
+
+    match kind % CONDITION_KINDS.len() {
+        0 => ConditionFlag::SatisfyAll(conditions),
+        1 => ConditionFlag::SatisfyAny(conditions),
+        2 => ConditionFlag::Not(conditions),
+        3 => ConditionFlag::ReachedMetric { metric, value: 0. },
+        4 => ConditionFlag::UnderMetric { metric, value: 0. },
+        5 => ConditionFlag::Between {
+            metric,
+            min: 0.,
+            max: 0.,
+        },
+        6 => ConditionFlag::RatioAtLeast {
+            numerator: metric,
+            denominator: String::new(),
+            value: 0.,
+        },
+        7 => ConditionFlag::RatioUnder {
+            numerator: metric,
+            denominator: String::new(),
+            value: 0.,
+        },
+        8 => ConditionFlag::ResearchCompleted(ResearchId(String::new())),
+        9 => ConditionFlag::HasItem {
+            item: ItemId(String::new()),
+            quantity: 1,
+        },
+        10 => ConditionFlag::RateAtLeast {
+            metric,
+            value: 0.,
+            per_seconds: 60.,
+        },
+        _ => ConditionFlag::SustainedFor {
+            conditions,
+            seconds: 60.,
+        },
+    }
+}
+
+/// Condition nested at `path`, each index picking a nested condition of the one before
+pub fn condition_at_mut<'a>(
+    condition: &'a mut ConditionFlag,
+    path: &[usize],
+) -> Option<&'a mut ConditionFlag> {
+    path.iter().try_fold(condition, |condition, &index| {
+        condition_children_mut(condition)?.get_mut(index)
+    })
+}
+
+/// Every condition of the tree in reading order, along with its path
+pub fn condition_rows(condition: &ConditionFlag) -> Vec<(Vec<usize>, &ConditionFlag)> {
+    fn visit<'a>(
+        condition: &'a ConditionFlag,
+        path: &mut Vec<usize>,
+        rows: &mut Vec<(Vec<usize>, &'a ConditionFlag)>,
+    ) {
+        rows.push((path.clone(), condition));
+        for (index, nested) in condition_children(condition)
+            .into_iter()
+            .flatten()
+            .enumerate()
+        {
+            path.push(index);
+            visit(nested, path, rows);
+            path.pop();
+        }
+    }
+
+    let mut rows = vec![];
+    visit(condition, &mut vec![], &mut rows);
+    rows
+}
+
+/// Nests a new condition under the one at `path`, false when it can't hold any
+pub fn add_condition(condition: &mut ConditionFlag, path: &[usize]) -> bool {
+    let Some(conditions) = condition_at_mut(condition, path).and_then(condition_children_mut)
+    else {
+        return false;
+    };
+
+    conditions.push(ConditionFlag::ReachedMetric {
+        metric: String::new(),
+        value: 0.,
+    });
+    true
+}
+
+/// Removes the condition at `path`, the outermost condition stays
+pub fn remove_condition(condition: &mut ConditionFlag, path: &[usize]) -> bool {
+    let Some((&index, parent)) = path.split_last() else {
+        return false;
+    };
+    let Some(conditions) = condition_at_mut(condition, parent).and_then(condition_children_mut)
+    else {
+        return false;
+    };
+
+    if index < conditions.len() {
+        conditions.remove(index);
+        true
+    } else {
+        false
+    }
+}
+
+/// Editable values of a condition as written in the RON, nested conditions left out
+pub fn condition_fields(condition: &ConditionFlag) -> Vec<(&'static str, String)> {
+    match condition {
+        ConditionFlag::SatisfyAll(_) | ConditionFlag::SatisfyAny(_) | ConditionFlag::Not(_) => {
+            vec![]
+        }
+        ConditionFlag::ReachedMetric { metric, value }
+        | ConditionFlag::UnderMetric { metric, value } => {
+            vec![("metric", metric.clone()), ("value", value.to_string())]
+        }
+        ConditionFlag::Between { metric, min, max } => vec![
+            ("metric", metric.clone()),
+            ("min", min.to_string()),
+            ("max", max.to_string()),
+        ],
+        ConditionFlag::RatioAtLeast {
+            numerator,
+            denominator,
+            value,
+        }
+        | ConditionFlag::RatioUnder {
+            numerator,
+            denominator,
+            value,
+        } => vec![
+            ("numerator", numerator.clone()),
+            ("denominator", denominator.clone()),
+            ("value", value.to_string()),
+        ],
+        ConditionFlag::ResearchCompleted(research) => vec![("research", research.0.clone())],
+        ConditionFlag::HasItem { item, quantity } => {
+            vec![("item", item.0.clone()), ("quantity", quantity.to_string())]
+        }
+        ConditionFlag::RateAtLeast {
+            metric,
+            value,
+            per_seconds,
+        } => vec![
+            ("metric", metric.clone()),
+            ("value", value.to_string()),
+            ("per seconds", per_seconds.to_string()),
+        ],
+        ConditionFlag::SustainedFor { seconds, .. } => vec![("seconds", seconds.to_string())],
+    }
+}
+
+/// Sets one of the [`condition_fields`] from what was typed, returns whether it changed
+pub fn set_condition_field(
+    condition: &mut ConditionFlag,
+    field: &str,
+    text: &str,
+) -> Result<bool, String> {
+    let number = |text: &str| {
+        text.trim()
+            .parse::<f32>()
+            .map_err(|_| format!("`{}` isn't a number", text))
+    };
+    let kind = CONDITION_KINDS[condition_kind(condition)];
+
+    let changed = match (condition, field) {
+        (
+            ConditionFlag::ReachedMetric { metric, .. }
+            | ConditionFlag::UnderMetric { metric, .. }
+            | ConditionFlag::Between { metric, .. }
+            | ConditionFlag::RateAtLeast { metric, .. },
+            "metric",
+        ) => replace(metric, text.trim().to_string()),
+        (
+            ConditionFlag::ReachedMetric { value, .. }
+            | ConditionFlag::UnderMetric { value, .. }
+            | ConditionFlag::RatioAtLeast { value, .. }
+            | ConditionFlag::RatioUnder { value, .. }
+            | ConditionFlag::RateAtLeast { value, .. },
+            "value",
+        ) => replace(value, number(text)?),
+        (ConditionFlag::Between { min, .. }, "min") => replace(min, number(text)?),
+        (ConditionFlag::Between { max, .. }, "max") => replace(max, number(text)?),
+        (
+            ConditionFlag::RatioAtLeast { numerator, .. }
+            | ConditionFlag::RatioUnder { numerator, .. },
+            "numerator",
+        ) => replace(numerator, text.trim().to_string()),
+        (
+            ConditionFlag::RatioAtLeast { denominator, .. }
+            | ConditionFlag::RatioUnder { denominator, .. },
+            "denominator",
+        ) => replace(denominator, text.trim().to_string()),
+        (ConditionFlag::ResearchCompleted(research), "research") => {
+            replace(&mut research.0, text.trim().to_string())
+        }
+        (ConditionFlag::HasItem { item, .. }, "item") => {
+            replace(&mut item.0, text.trim().to_string())
+        }
+        (ConditionFlag::HasItem { quantity, .. }, "quantity") => {
+            let parsed = text
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("`{}` isn't a quantity", text))?;
+            replace(quantity, parsed)
+        }
+        (ConditionFlag::RateAtLeast { per_seconds, .. }, "per seconds") => {
+            replace(per_seconds, number(text)?)
+        }
+        (ConditionFlag::SustainedFor { seconds, .. }, "seconds") => replace(seconds, number(text)?),
+        _ => return Err(format!("`{}` conditions have no {}", kind, field)),
+    };
+
+    Ok(changed)
+}
+
+/// Fields of a research edited as text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResearchField {
+    Id,
+    DisplayName,
+    FlavorText,
+    /// comma separated ids
+    RequiredResearch,
+}
+
+impl ResearchField {
+    pub const ALL: [Self; 4] = [
+        Self::Id,
+        Self::DisplayName,
+        Self::FlavorText,
+        Self::RequiredResearch,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Id => "id",
+            Self::DisplayName => "display name",
+            Self::FlavorText => "flavor text",
+            Self::RequiredResearch => "requires",
+        }
+    }
+
+    pub fn get(&self, declared: &ResearchDeclared) -> String {
+        match self {
+            Self::Id => declared.id.0.clone(),
+            Self::DisplayName => declared.display_name.clone(),
+            Self::FlavorText => declared.flavor_text.clone(),
+            Self::RequiredResearch => declared
+                .required_research
+                .iter()
+                .map(|id| id.0.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    }
+
+    /// Returns whether the research changed
+    pub fn set(&self, declared: &mut ResearchDeclared, text: &str) -> bool {
+        match self {
+            Self::Id => replace(&mut declared.id.0, text.trim().to_string()),
+            Self::DisplayName => replace(&mut declared.display_name, text.to_string()),
+            Self::FlavorText => replace(&mut declared.flavor_text, text.to_string()),
+            Self::RequiredResearch => {
+                let required = text
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(|id| ResearchId(id.to_string()))
+                    .collect();
+                replace(&mut declared.required_research, required)
+            }
+        }
+    }
+}
+
+/// Why research can't be saved
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResearchIssue {
+    DuplicateId(ResearchId),
+    UnknownRequirement {
+        research: ResearchId,
+        required: ResearchId,
+    },
+    /// a [`ConditionFlag::ResearchCompleted`] waiting on research nobody declares
+    UnknownResearch {
+        research: ResearchId,
+        awaited: ResearchId,
+    },
+    UnknownItem {
+        research: ResearchId,
+        item: ItemId,
+    },
+    /// research requiring itself, the first research repeated at the end
+    Cycle(Vec<ResearchId>),
+}
+
+impl fmt::Display for ResearchIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::DuplicateId(id) => write!(f, "research `{}` is declared twice", id.0),
+            Self::UnknownRequirement { research, required } => write!(
+                f,
+                "`{}` requires unknown research `{}`",
+                research.0, required.0
+            ),
+            Self::UnknownResearch { research, awaited } => write!(
+                f,
+                "`{}` waits on unknown research `{}`",
+                research.0, awaited.0
+            ),
+            Self::UnknownItem { research, item } => {
+                write!(f, "`{}` waits on unknown item `{}`", research.0, item.0)
+            }
+            Self::Cycle(cycle) => write!(
+                f,
+                "research requires itself through {}",
+                cycle
+                    .iter()
+                    .map(|id| format!("`{}`", id.0))
+                    .collect::<Vec<_>>()
+                    .join(" -> ")
+            ),
+        }
+    }
+}
+
+/// Cycles of requirements reachable from `from`, each listed once starting with its smallest id
+fn requirement_cycles(graph: &HashMap<String, Vec<String>>, from: &[String]) -> Vec<Vec<String>> {
+    fn visit(
+        id: &str,
+        graph: &HashMap<String, Vec<String>>,
+        stack: &mut Vec<String>,
+        done: &mut HashSet<String>,
+        cycles: &mut Vec<Vec<String>>,
+    ) {
+        if let Some(start) = stack.iter().position(|visiting| visiting == id) {
+            let mut cycle = stack[start..].to_vec();
+            let smallest = (0..cycle.len()).min_by_key(|&i| &cycle[i]).unwrap_or(0);
+            cycle.rotate_left(smallest);
+            if !cycles.contains(&cycle) {
+                cycles.push(cycle);
+            }
+            return;
+        }
+        if done.contains(id) {
+            return;
+        }
+
+        stack.push(id.to_string());
+        for required in graph.get(id).into_iter().flatten() {
+            visit(required, graph, stack, done, cycles);
+        }
+        stack.pop();
+        done.insert(id.to_string());
+    }
+
+    let mut cycles = vec![];
+    let mut done = HashSet::new();
+    for id in from {
+        visit(id, graph, &mut vec![], &mut done, &mut cycles);
+    }
+    cycles
+}
+
+/// Research of one mod as written in its `declarations/research.ron`
+#[derive(Component, Debug, Clone, PartialEq)]
+pub struct ResearchEditorState {
+    pub mod_path: PathBuf,
+    pub namespace: Namespace,
+    /// every entry of the file, those patching or deleting research of other mods are kept as is
+    pub declarations: Vec<ResearchDeclaration>,
+    /// index within the declarations of the research being edited
+    pub selected: Option<usize>,
+    pub unsaved: bool,
+}
+
+impl ResearchEditorState {
+    pub fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<Self, String> {
+        let declarations = read_research_declarations(&mod_path, type_registry)?;
+        let selected = declarations
+            .iter()
+            .position(|declaration| Self::editable(declaration).is_some());
+
+        Ok(Self {
+            mod_path,
+            namespace,
+            declarations,
+            selected,
+            unsaved: false,
+        })
+    }
+
+    fn editable(declaration: &ResearchDeclaration) -> Option<&ResearchDeclared> {
+        match declaration {
+            ResearchDeclaration::Declare(declared) | ResearchDeclaration::Override(declared) => {
+                Some(declared)
+            }
+            _ => None,
+        }
+    }
+
+    /// Research declared or overridden by the mod, with its index within the declarations
+    pub fn research(&self) -> impl Iterator<Item = (usize, &ResearchDeclared)> {
+        self.declarations
+            .iter()
+            .enumerate()
+            .filter_map(|(index, declaration)| Some((index, Self::editable(declaration)?)))
+    }
+
+    pub fn selected(&self) -> Option<&ResearchDeclared> {
+        self.declarations
+            .get(self.selected?)
+            .and_then(Self::editable)
+    }
+
+    pub fn selected_mut(&mut self) -> Option<&mut ResearchDeclared> {
+        match self.declarations.get_mut(self.selected?)? {
+            ResearchDeclaration::Declare(declared) | ResearchDeclaration::Override(declared) => {
+                Some(declared)
+            }
+            _ => None,
+        }
+    }
+
+    /// Declares research with an id no other research of the mod has, and selects it
+    pub fn add_research(&mut self) -> usize {
+        let taken = self
+            .research()
+            .map(|(_, declared)| declared.id.0.clone())
+            .collect::<HashSet<_>>();
+        let id = (1..)
+            .map(|n| match n {
+                1 => "new_research".to_string(),
+                n => format!("new_research_{}", n),
+            })
+            .find(|id| !taken.contains(id))
+            .unwrap();
+
+        self.declarations
+            .push(ResearchDeclaration::Declare(ResearchId(id).into()));
+        self.selected = Some(self.declarations.len() - 1);
+        self.unsaved = true;
+        self.declarations.len() - 1
+    }
+
+    pub fn remove_research(&mut self, index: usize) {
+        if index >= self.declarations.len() {
+            return;
+        }
+
+        self.declarations.remove(index);
+        let first = self.research().map(|(index, _)| index).next();
+        self.selected = first;
+        self.unsaved = true;
+    }
+
+    /// Checks the research of the mod against research declared by every other mod
+    pub fn issues(&self, research: &ResearchRegistry, items: &ItemRegistry) -> Vec<ResearchIssue> {
+        let edited = self
+            .research()
+            .map(|(_, declared)| {
+                let mut declared = declared.clone();
+                declared.namespace(self.namespace);
+                declared
+            })
+            .collect::<Vec<_>>();
+
+        // research as declared by other mods, this mod's is replaced by what is edited
+        let mut graph = research
+            .iter()
+            .filter(|declared| {
+                research
+                    .touched_by(&declared.id.0)
+                    .is_none_or(|touch| touch.mod_name != self.namespace.as_str())
+            })
+            .map(|declared| {
+                (
+                    declared.id.0.clone(),
+                    declared
+                        .required_research
+                        .iter()
+                        .map(|id| id.0.clone())
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        let mut issues = vec![];
+        let mut seen = HashSet::new();
+        for declared in edited.iter() {
+            if !seen.insert(declared.id.0.clone()) {
+                issues.push(ResearchIssue::DuplicateId(declared.id.clone()));
+            }
+            graph.insert(
+                declared.id.0.clone(),
+                declared
+                    .required_research
+                    .iter()
+                    .map(|id| id.0.clone())
+                    .collect(),
+            );
+        }
+
+        for declared in edited.iter() {
+            for required in declared.required_research.iter() {
+                if !graph.contains_key(&required.0) {
+                    issues.push(ResearchIssue::UnknownRequirement {
+                        research: declared.id.clone(),
+                        required: required.clone(),
+                    });
+                }
+            }
+
+            for (_, condition) in condition_rows(&declared.unlock_condition) {
+                match condition {
+                    ConditionFlag::ResearchCompleted(awaited)
+                        if !graph.contains_key(&awaited.0) =>
+                    {
+                        issues.push(ResearchIssue::UnknownResearch {
+                            research: declared.id.clone(),
+                            awaited: awaited.clone(),
+                        });
+                    }
+                    ConditionFlag::HasItem { item, .. } if !items.contains(&item.0) => {
+                        issues.push(ResearchIssue::UnknownItem {
+                            research: declared.id.clone(),
+                            item: item.clone(),
+                        });
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        let from = edited
+            .iter()
+            .map(|declared| declared.id.0.clone())
+            .collect::<Vec<_>>();
+        issues.extend(
+            requirement_cycles(&graph, &from)
+                .into_iter()
+                .map(|mut cycle| {
+                    cycle.push(cycle[0].clone());
+                    ResearchIssue::Cycle(cycle.into_iter().map(ResearchId).collect())
+                }),
+        );
+
+        issues
+    }
+
+    pub fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        write_research_declarations(
+            &self.mod_path,
+            &ResearchDeclarations(self.declarations.clone()),
+            type_registry,
+        )?;
+        self.unsaved = false;
+        Ok(())
+    }
+}
+
+impl EditorState for ResearchEditorState {
+    const KIND: &'static str = "research";
+
+    fn unsaved(&self) -> bool {
+        self.unsaved
+    }
+
+    fn save(&mut self, type_registry: &TypeRegistry) -> Result<(), String> {
+        ResearchEditorState::save(self, type_registry)
+    }
+
+    fn path(&self) -> &Path {
+        &self.mod_path
+    }
+
+    fn summary(&self) -> String {
+        format!("{} research of {}", self.research().count(), self.namespace)
+    }
+}
+
+impl ModEditor for ResearchEditor {
+    type State = ResearchEditorState;
+
+    fn edited(&mut self) -> &mut Meta {
+        &mut self.0
+    }
+
+    fn read(
+        mod_path: PathBuf,
+        namespace: Namespace,
+        type_registry: &TypeRegistry,
+    ) -> Result<ResearchEditorState, String> {
+        ResearchEditorState::read(mod_path, namespace, type_registry)
+    }
+}
+
+/// Content of the editor window, respawned whenever what is edited changes shape
+#[derive(Debug, Component)]
+pub struct ResearchEditorBody;
+
+#[derive(Debug, Component, Clone, PartialEq)]
+pub enum ResearchEditorButton {
+    /// edit the research of the mod at this index of the profile
+    EditMod(usize),
+    Select(usize),
+    NewResearch,
+    RemoveResearch,
+    Save,
+    /// moves the condition at the path to the next kind
+    ConditionKind(Vec<usize>),
+    AddCondition(Vec<usize>),
+    RemoveCondition(Vec<usize>),
+}
+
+#[derive(Debug, Component)]
+pub struct ResearchFieldInput(pub ResearchField);
+
+#[derive(Debug, Component)]
+pub struct ConditionFieldInput {
+    pub path: Vec<usize>,
+    pub field: &'static str,
+}
+
+/// Name of the research at this index of the declarations
+#[derive(Debug, Component)]
+pub struct ResearchEntryLabel(pub usize);
+
+#[derive(Debug, Component)]
+pub struct ResearchEditorIssues;
+
+pub fn spawn_research_editor_window(
     mut cmd: Commands,
     ui: Res<UiAssets>,
     fonts: Res<FontAssets>,
@@ -30,23 +761,632 @@ pub fn spawn_editor_window(
 ) {
     let pack = crate::levels::pack::base_mod();
 
-    cmd.entity(hud_backdrop.single())
-        .with_children(|mut parent| {
-            spawn_window(
-                &mut parent,
-                ResearchEditor(pack.meta.clone()),
-                (),
-                &ui,
-                &fonts,
-                WindowMeta::new(
-                    format!(
-                        "research editor `{}_{}`",
-                        pack.meta.mod_name, pack.meta.version
-                    ),
-                    400.,
-                    9. / 16.,
-                ),
-                |parent| {},
-            );
+    spawn_editor_window(
+        &mut cmd,
+        hud_backdrop.single(),
+        ResearchEditor(pack.meta.clone()),
+        ResearchEditorBody,
+        "research editor",
+        &ui,
+        &fonts,
+    );
+}
+
+#[allow(clippy::too_many_arguments)]
+fn handle_research_editor_buttons(
+    mut cmd: Commands,
+    buttons: Query<(&DepressButton, &ResearchEditorButton), Changed<DepressButton>>,
+    mut editor: Query<(Entity, &mut ResearchEditor, &mut ResearchEditorState)>,
+    profile: Query<&ModProfile, With<Level>>,
+    research: Res<ResearchRegistry>,
+    items: Res<ItemRegistry>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok((entity, mut editor, mut state)) = editor.get_single_mut() else {
+        return;
+    };
+
+    let pressed = buttons
+        .iter()
+        .filter(|(depress, _)| depress.invoked())
+        .map(|(_, button)| button.clone())
+        .collect::<Vec<_>>();
+
+    for button in pressed {
+        let reshaped = match button {
+            ResearchEditorButton::EditMod(index) => {
+                edit_mod(
+                    &mut cmd,
+                    entity,
+                    &mut editor.0,
+                    &*state,
+                    &profile,
+                    index,
+                    &mut notifications_channel,
+                );
+                false
+            }
+            ResearchEditorButton::Select(index) => {
+                state.selected = Some(index);
+                true
+            }
+            ResearchEditorButton::NewResearch => {
+                state.add_research();
+                true
+            }
+            ResearchEditorButton::RemoveResearch => {
+                if let Some(selected) = state.selected {
+                    state.remove_research(selected);
+                }
+                true
+            }
+            ResearchEditorButton::Save => {
+                save_research(
+                    &mut state,
+                    &research,
+                    &items,
+                    &type_registry.read(),
+                    &mut notifications_channel,
+                );
+                false
+            }
+            ResearchEditorButton::ConditionKind(path) => {
+                state.selected_mut().is_some_and(|declared| {
+                    let Some(condition) = condition_at_mut(&mut declared.unlock_condition, &path)
+                    else {
+                        return false;
+                    };
+                    *condition = convert_condition(condition, condition_kind(condition) + 1);
+                    true
+                })
+            }
+            ResearchEditorButton::AddCondition(path) => state
+                .selected_mut()
+                .is_some_and(|declared| add_condition(&mut declared.unlock_condition, &path)),
+            ResearchEditorButton::RemoveCondition(path) => state
+                .selected_mut()
+                .is_some_and(|declared| remove_condition(&mut declared.unlock_condition, &path)),
+        };
+
+        if reshaped {
+            state.unsaved = true;
+            cmd.entity(entity).insert(RebuildEditor);
+        }
+    }
+}
+
+/// Research is only written once nothing is wrong with it
+fn save_research(
+    state: &mut ResearchEditorState,
+    research: &ResearchRegistry,
+    items: &ItemRegistry,
+    type_registry: &TypeRegistry,
+    notifications_channel: &mut NotificationChannel,
+) {
+    let issues = state.issues(research, items);
+    save_edits(state, &issues, type_registry, notifications_channel);
+}
+
+fn edit_research_fields(
+    fields: Query<(&TextField, &ResearchFieldInput), Changed<TextField>>,
+    mut editor: Query<&mut ResearchEditorState>,
+) {
+    let Ok(mut state) = editor.get_single_mut() else {
+        return;
+    };
+
+    for (field, ResearchFieldInput(research_field)) in fields.iter() {
+        let changed = state
+            .bypass_change_detection()
+            .selected_mut()
+            .is_some_and(|declared| research_field.set(declared, &field.value));
+        if changed {
+            state.unsaved = true;
+        }
+    }
+}
+
+fn edit_condition_fields(
+    fields: Query<(&TextField, &ConditionFieldInput), Changed<TextField>>,
+    mut editor: Query<&mut ResearchEditorState>,
+) {
+    let Ok(mut state) = editor.get_single_mut() else {
+        return;
+    };
+
+    for (field, input) in fields.iter() {
+        let Some(condition) = state
+            .bypass_change_detection()
+            .selected_mut()
+            .and_then(|declared| condition_at_mut(&mut declared.unlock_condition, &input.path))
+        else {
+            continue;
+        };
+
+        // half typed numbers are kept in the field until they parse
+        if let Ok(true) = set_condition_field(condition, input.field, &field.value) {
+            state.unsaved = true;
+        }
+    }
+}
+
+fn rebuild_research_editor(
+    mut cmd: Commands,
+    editor: Query<(Entity, &ResearchEditor, &ResearchEditorState), With<RebuildEditor>>,
+    body: Query<Entity, With<ResearchEditorBody>>,
+    profile: Query<&ModProfile, With<Level>>,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+) {
+    let (Ok((entity, editor, state)), Ok(body)) = (editor.get_single(), body.get_single()) else {
+        return;
+    };
+    cmd.entity(entity).remove::<RebuildEditor>();
+
+    let mods = profile_mods(&profile);
+
+    cmd.entity(body)
+        .despawn_descendants()
+        .with_children(|parent| {
+            // research of the mod
+            parent
+                .spawn((
+                    Node {
+                        width: Val::Percent(35.),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    mod_picker(
+                        &editor.0,
+                        &mods,
+                        ResearchEditorButton::EditMod,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+
+                    for (index, declared) in state.research() {
+                        editor_entry(
+                            &declared.id.0,
+                            state.selected == Some(index),
+                            ResearchEditorButton::Select(index),
+                            ResearchEntryLabel(index),
+                            parent,
+                            &fonts,
+                            &ui,
+                        );
+                    }
+
+                    editor_button(
+                        "new research",
+                        ResearchEditorButton::NewResearch,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+                    editor_button("save", ResearchEditorButton::Save, parent, &fonts, &ui);
+                    body_text("", parent, &fonts).insert(ResearchEditorIssues);
+                });
+
+            // the selected research
+            parent
+                .spawn((
+                    Node {
+                        flex_grow: 1.,
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Interaction::default(),
+                    Scrollable,
+                ))
+                .with_children(|parent| {
+                    let Some(declared) = state.selected() else {
+                        section_text("no research selected", parent, &fonts);
+                        return;
+                    };
+
+                    for field in ResearchField::ALL {
+                        labeled_row(field.label(), parent, &fonts).with_children(|parent| {
+                            spawn_text_field(
+                                &field.get(declared),
+                                ResearchFieldInput(field),
+                                parent,
+                                &fonts,
+                                &ui,
+                            );
+                        });
+                    }
+                    editor_button(
+                        "remove research",
+                        ResearchEditorButton::RemoveResearch,
+                        parent,
+                        &fonts,
+                        &ui,
+                    );
+                    separator(parent);
+
+                    section_text("unlock condition", parent, &fonts);
+                    for (path, condition) in condition_rows(&declared.unlock_condition) {
+                        parent
+                            .spawn(Node {
+                                margin: UiRect::left(Val::Px(UI_SCALE * 3. * path.len() as f32)),
+                                column_gap: Val::Px(UI_SCALE),
+                                align_items: AlignItems::Center,
+                                flex_shrink: 0.,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                editor_button(
+                                    CONDITION_KINDS[condition_kind(condition)],
+                                    ResearchEditorButton::ConditionKind(path.clone()),
+                                    parent,
+                                    &fonts,
+                                    &ui,
+                                );
+
+                                for (field, value) in condition_fields(condition) {
+                                    body_text(field, parent, &fonts);
+                                    spawn_text_field(
+                                        &value,
+                                        ConditionFieldInput {
+                                            path: path.clone(),
+                                            field,
+                                        },
+                                        parent,
+                                        &fonts,
+                                        &ui,
+                                    );
+                                }
+
+                                if condition_children(condition).is_some() {
+                                    editor_button(
+                                        "+",
+                                        ResearchEditorButton::AddCondition(path.clone()),
+                                        parent,
+                                        &fonts,
+                                        &ui,
+                                    );
+                                }
+                                if !path.is_empty() {
+                                    editor_button(
+                                        "x",
+                                        ResearchEditorButton::RemoveCondition(path.clone()),
+                                        parent,
+                                        &fonts,
+                                        &ui,
+                                    );
+                                }
+                            });
+                    }
+                });
         });
 }
+
+/// Keeps research names and issues up to date while typing
+fn show_research_editor_state(
+    editor: Query<&ResearchEditorState>,
+    mut labels: Query<(&ResearchEntryLabel, &mut Text), Without<ResearchEditorIssues>>,
+    mut issues_text: Query<&mut Text, With<ResearchEditorIssues>>,
+    fields: Query<(), Changed<TextField>>,
+    research: Res<ResearchRegistry>,
+    items: Res<ItemRegistry>,
+) {
+    let Ok(state) = editor.get_single() else {
+        return;
+    };
+    if fields.is_empty() && issues_text.iter().all(|text| !text.0.is_empty()) {
+        return;
+    }
+
+    for (ResearchEntryLabel(index), mut text) in labels.iter_mut() {
+        if let Some(
+            ResearchDeclaration::Declare(declared) | ResearchDeclaration::Override(declared),
+        ) = state.declarations.get(*index)
+        {
+            if text.0 != declared.id.0 {
+                text.0 = declared.id.0.clone();
+            }
+        }
+    }
+
+    let issues = state.issues(&research, &items);
+    let summary = issues_summary(&issues, state.unsaved);
+
+    for mut text in issues_text.iter_mut() {
+        if text.0 != summary {
+            text.0 = summary.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::*;
+    use super::*;
+
+    fn metric(metric: &str, value: f32) -> ConditionFlag {
+        ConditionFlag::ReachedMetric {
+            metric: metric.into(),
+            value,
+        }
+    }
+
+    fn declared(id: &str, required: &[&str], unlock_condition: ConditionFlag) -> ResearchDeclared {
+        ResearchDeclared {
+            id: ResearchId(id.into()),
+            display_name: id.into(),
+            flavor_text: String::new(),
+            unlock_condition,
+            required_research: required
+                .iter()
+                .map(|required| ResearchId(required.to_string()))
+                .collect(),
+        }
+    }
+
+    fn editor(declarations: Vec<ResearchDeclaration>) -> ResearchEditorState {
+        ResearchEditorState {
+            mod_path: PathBuf::new(),
+            namespace: Namespace::new("tyconic").unwrap(),
+            declarations,
+            selected: Some(0),
+            unsaved: false,
+        }
+    }
+
+    #[test]
+    fn condition_trees_are_edited_by_path() {
+        let mut condition = ConditionFlag::SatisfyAll(vec![
+            metric("money", 10.),
+            ConditionFlag::SatisfyAny(vec![metric("tyconic::cheese_wheel__total_produced", 3.)]),
+        ]);
+
+        let paths = condition_rows(&condition)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(paths, vec![vec![], vec![0], vec![1], vec![1, 0]]);
+
+        assert!(add_condition(&mut condition, &[1]));
+        assert!(!add_condition(&mut condition, &[0]));
+        assert_eq!(condition_rows(&condition).len(), 5);
+
+        assert!(set_condition_field(
+            &mut condition_at_mut(&mut condition, &[1, 1]).unwrap(),
+            "metric",
+            "money"
+        )
+        .unwrap());
+        assert!(set_condition_field(
+            condition_at_mut(&mut condition, &[1, 1]).unwrap(),
+            "value",
+            "250"
+        )
+        .unwrap());
+        assert!(!set_condition_field(
+            condition_at_mut(&mut condition, &[1, 1]).unwrap(),
+            "value",
+            "250.0"
+        )
+        .unwrap());
+        assert!(set_condition_field(
+            condition_at_mut(&mut condition, &[1, 1]).unwrap(),
+            "value",
+            "25k"
+        )
+        .is_err());
+        assert!(set_condition_field(
+            condition_at_mut(&mut condition, &[1, 1]).unwrap(),
+            "seconds",
+            "1"
+        )
+        .is_err());
+        assert_eq!(
+            condition_at_mut(&mut condition, &[1, 1]).cloned(),
+            Some(metric("money", 250.))
+        );
+
+        assert!(remove_condition(&mut condition, &[1, 0]));
+        assert!(!remove_condition(&mut condition, &[]));
+        assert!(!remove_condition(&mut condition, &[7]));
+        assert_eq!(
+            condition,
+            ConditionFlag::SatisfyAll(vec![
+                metric("money", 10.),
+                ConditionFlag::SatisfyAny(vec![metric("money", 250.)]),
+            ])
+        );
+    }
+
+    #[test]
+    fn converting_conditions_keeps_what_fits() {
+        let all = ConditionFlag::SatisfyAll(vec![metric("money", 10.)]);
+        assert_eq!(
+            convert_condition(&all, condition_kind(&all) + 1),
+            ConditionFlag::SatisfyAny(vec![metric("money", 10.)])
+        );
+        assert_eq!(
+            convert_condition(&all, 11),
+            ConditionFlag::SustainedFor {
+                conditions: vec![metric("money", 10.)],
+                seconds: 60.,
+            }
+        );
+
+        let reached = metric("money", 10.);
+        let ratio = convert_condition(&reached, 6);
+        assert_eq!(
+            condition_fields(&ratio),
+            vec![
+                ("numerator", "money".to_string()),
+                ("denominator", String::new()),
+                ("value", "0".to_string()),
+            ]
+        );
+
+        // cycling through every kind comes back around
+        let mut condition = reached.clone();
+        for _ in 0..CONDITION_KINDS.len() {
+            condition = convert_condition(&condition, condition_kind(&condition) + 1);
+        }
+        assert_eq!(condition_kind(&condition), condition_kind(&reached));
+    }
+
+    #[test]
+    fn research_fields_parse_requirements() {
+        let mut research = declared("ovens", &[], ConditionFlag::SatisfyAll(vec![]));
+
+        assert!(ResearchField::RequiredResearch.set(&mut research, "belts, base::arms,, "));
+        assert_eq!(
+            research.required_research,
+            vec![ResearchId("belts".into()), ResearchId("base::arms".into())]
+        );
+        assert_eq!(
+            ResearchField::RequiredResearch.get(&research),
+            "belts, base::arms"
+        );
+        assert!(!ResearchField::DisplayName.set(&mut research, "ovens"));
+    }
+
+    #[test]
+    fn new_research_gets_a_free_id() {
+        let mut state = editor(vec![ResearchDeclaration::Declare(
+            ResearchId("new_research".into()).into(),
+        )]);
+
+        let index = state.add_research();
+        assert_eq!(state.selected, Some(index));
+        assert_eq!(state.selected().unwrap().id.0, "new_research_2");
+        assert!(state.unsaved);
+
+        state.remove_research(0);
+        assert_eq!(state.selected().unwrap().id.0, "new_research_2");
+    }
+
+    #[test]
+    fn issues_name_unknown_ids_and_cycles() {
+        let mut merged = Merged::default();
+        merged.apply(
+            "base",
+            Declaration::Declare(declared("base::belts", &[], metric("money", 1.))),
+        );
+        let research = ResearchRegistry::from(merged);
+        let items = item_registry(&["tyconic::pizza_slice"]);
+
+        let state = editor(vec![
+            ResearchDeclaration::Declare(declared(
+                "ovens",
+                &["base::belts", "pizzeria"],
+                ConditionFlag::SatisfyAll(vec![
+                    ConditionFlag::ResearchCompleted(ResearchId("base::belts".into())),
+                    ConditionFlag::HasItem {
+                        item: ItemId::from("tyconic::pizza_slice"),
+                        quantity: 1,
+                    },
+                ]),
+            )),
+            ResearchDeclaration::Declare(declared("pizzeria", &["ovens"], metric("money", 1.))),
+            ResearchDeclaration::Delete(ResearchId("base::arms".into())),
+        ]);
+        assert_eq!(
+            state.issues(&research, &items),
+            vec![ResearchIssue::Cycle(vec![
+                ResearchId("tyconic::ovens".into()),
+                ResearchId("tyconic::pizzeria".into()),
+                ResearchId("tyconic::ovens".into()),
+            ])]
+        );
+
+        let state = editor(vec![
+            ResearchDeclaration::Declare(declared(
+                "ovens",
+                &["base::arms"],
+                ConditionFlag::SatisfyAll(vec![
+                    ConditionFlag::ResearchCompleted(ResearchId("ovens_i".into())),
+                    ConditionFlag::HasItem {
+                        item: ItemId::from("tyconic::calzone"),
+                        quantity: 1,
+                    },
+                ]),
+            )),
+            ResearchDeclaration::Override(declared("ovens", &[], metric("money", 1.))),
+        ]);
+        assert_eq!(
+            state.issues(&research, &items),
+            vec![
+                ResearchIssue::DuplicateId(ResearchId("tyconic::ovens".into())),
+                ResearchIssue::UnknownRequirement {
+                    research: ResearchId("tyconic::ovens".into()),
+                    required: ResearchId("base::arms".into()),
+                },
+                ResearchIssue::UnknownResearch {
+                    research: ResearchId("tyconic::ovens".into()),
+                    awaited: ResearchId("ovens_i".into()),
+                },
+                ResearchIssue::UnknownItem {
+                    research: ResearchId("tyconic::ovens".into()),
+                    item: ItemId::from("tyconic::calzone"),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn saved_research_is_declared_by_the_mod() {
+        let mut type_registry = TypeRegistry::default();
+        type_registry.register::<ResearchDeclarations>();
+        type_registry.register::<ResearchPack>();
+
+        let mod_path = std::env::temp_dir()
+            .join(format!("tyconia_research_editor_{}", std::process::id()))
+            .join("tyconic");
+        let namespace = Namespace::new("tyconic").unwrap();
+
+        let mut state =
+            ResearchEditorState::read(mod_path.clone(), namespace, &type_registry).unwrap();
+        assert!(state.declarations.is_empty());
+        assert_eq!(state.selected, None);
+
+        state.add_research();
+        let declared = state.selected_mut().unwrap();
+        ResearchField::Id.set(declared, "pizzeria");
+        ResearchField::RequiredResearch.set(declared, "base::belts");
+        declared.unlock_condition = ConditionFlag::SustainedFor {
+            conditions: vec![ConditionFlag::Not(vec![metric(
+                "tyconic::pizza_slice__wasted_per_minute",
+                1.,
+            )])],
+            seconds: 600.,
+        };
+        state
+            .declarations
+            .push(ResearchDeclaration::Delete(ResearchId("base::arms".into())));
+        state.save(&type_registry).unwrap();
+        assert!(!state.unsaved);
+
+        let read = ResearchEditorState::read(mod_path.clone(), namespace, &type_registry).unwrap();
+        assert_eq!(read.declarations, state.declarations);
+
+        let mut declarations = Declarations::default();
+        declare_mod(&mut declarations, namespace, &mod_path, &type_registry).unwrap();
+        let research = ResearchRegistry::from(declarations.research);
+        let pizzeria = research
+            .research(&ResearchId("tyconic::pizzeria".into()))
+            .unwrap();
+        assert_eq!(
+            pizzeria.required_research,
+            vec![ResearchId("base::belts".into())]
+        );
+
+        std::fs::remove_dir_all(mod_path.parent().unwrap()).unwrap();
+    }
+}
@@ -0,0 +1,752 @@
+//! Auto arms pick items from the tile behind them and drop them on the tile they face. Whatever
+//! sits on those tiles takes part through [`ArmEndpoint`], such as a chest [`Inventory`] or a
+//! [`MoverBelt`]. A [`CraftingMachine`] only gives up the output of its recipe and an
+//! [`InfiniteIo`] source takes nothing in. Buildings with [`IoPorts`] are only reached through
+//! the ports facing the arm.
+
+use crate::actions::*;
+use crate::levels::*;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_tilemap::prelude::*;
+
+pub struct ArmPlugin;
+
+impl Plugin for ArmPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AutoArm>()
+            .register_type::<ArmConfiguration>()
+            .init_resource::<ArmClipboard>()
+            .add_systems(Simulation, run_auto_arms.after(run_mover_belts))
+            .add_systems(
+                Update,
+                copy_paste_arm_configuration.run_if(in_state(crate::InGameState::Normal)),
+            );
+    }
+}
+
+/// Settings of an arm the player can copy from one arm to another
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+pub struct ArmConfiguration {
+    /// items carried per swing at most
+    pub stack_size: usize,
+    /// only these items are picked, any item when empty
+    pub filter: Vec<ItemId>,
+    /// keep picking until the hand holds `stack_size` before swinging
+    pub wait_for_full_hand: bool,
+}
+
+impl Default for ArmConfiguration {
+    fn default() -> Self {
+        Self {
+            stack_size: 1,
+            filter: vec![],
+            wait_for_full_hand: false,
+        }
+    }
+}
+
+impl ArmConfiguration {
+    pub fn allows(&self, item: &ItemId) -> bool {
+        self.filter.is_empty() || self.filter.contains(item)
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Reflect)]
+pub enum ArmState {
+    /// over the tile behind, filling the hand
+    #[default]
+    Picking,
+    /// ticks spent swinging to the tile in front
+    Swinging(u32),
+    /// over the tile in front, waiting for room to drop everything held
+    Dropping,
+    /// ticks spent swinging back
+    Returning(u32),
+}
+
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+pub struct AutoArm {
+    /// drops towards this direction, picks from the opposite one
+    pub direction: BeltDirection,
+    /// simulation ticks a swing takes one way
+    pub swing_ticks: u32,
+    pub config: ArmConfiguration,
+    pub hand: Option<ItemEntry>,
+    pub state: ArmState,
+}
+
+impl Default for AutoArm {
+    fn default() -> Self {
+        Self::new(BeltDirection::default())
+    }
+}
+
+impl AutoArm {
+    pub fn new(direction: BeltDirection) -> Self {
+        Self {
+            direction,
+            swing_ticks: 20,
+            config: default(),
+            hand: None,
+            state: default(),
+        }
+    }
+
+    /// Tile items are picked from
+    pub fn pick_tile(&self, pos: TilePos) -> Option<TilePos> {
+        self.direction.opposite().step(pos)
+    }
+
+    /// Tile items are dropped on
+    pub fn drop_tile(&self, pos: TilePos) -> Option<TilePos> {
+        self.direction.step(pos)
+    }
+
+    fn held(&self) -> usize {
+        self.hand.as_ref().map_or(0, |hand| hand.quantity)
+    }
+
+    /// Runs the arm for one simulation tick, an arm does one thing per tick
+    pub fn step(
+        &mut self,
+        pick: Option<&mut dyn ArmEndpoint>,
+        drop: Option<&mut dyn ArmEndpoint>,
+        stack_size: impl Fn(&ItemId) -> usize,
+    ) {
+        match self.state {
+            ArmState::Picking => {
+                let room = self.config.stack_size.saturating_sub(self.held());
+                if let (Some(pick), true) = (pick, room > 0) {
+                    // a hand holds a single kind of item
+                    let holding = self.hand.as_ref().map(|hand| hand.item.clone());
+                    let filter = |item: &ItemId| {
+                        self.config.allows(item) && holding.as_ref().is_none_or(|held| held == item)
+                    };
+
+                    if let Some(taken) = pick.take(&filter, room) {
+                        match self.hand.as_mut() {
+                            Some(hand) => hand.quantity += taken.quantity,
+                            None => self.hand = Some(taken),
+                        }
+                    }
+                }
+
+                let full = self.held() >= self.config.stack_size;
+                if self.held() > 0 && (full || !self.config.wait_for_full_hand) {
+                    self.state = ArmState::Swinging(0);
+                }
+            }
+            ArmState::Swinging(ticks) => {
+                self.state = if ticks + 1 >= self.swing_ticks {
+                    ArmState::Dropping
+                } else {
+                    ArmState::Swinging(ticks + 1)
+                };
+            }
+            ArmState::Dropping => {
+                if let (Some(drop), Some(hand)) = (drop, self.hand.take()) {
+                    let size = stack_size(&hand.item);
+                    self.hand = drop.put(hand, size, self.direction);
+                }
+
+                if self.hand.is_none() {
+                    self.state = ArmState::Returning(0);
+                }
+            }
+            ArmState::Returning(ticks) => {
+                self.state = if ticks + 1 >= self.swing_ticks {
+                    ArmState::Picking
+                } else {
+                    ArmState::Returning(ticks + 1)
+                };
+            }
+        }
+    }
+}
+
+/// Something an arm picks items from or drops items on
+pub trait ArmEndpoint {
+    /// Takes up to `max` of the first item passing `filter`
+    fn take(&mut self, filter: &dyn Fn(&ItemId) -> bool, max: usize) -> Option<ItemEntry>;
+
+    /// Puts what it can of `entry` coming from an arm facing `towards`, returns the rest
+    fn put(
+        &mut self,
+        entry: ItemEntry,
+        stack_size: usize,
+        towards: BeltDirection,
+    ) -> Option<ItemEntry>;
+}
+
+impl ArmEndpoint for Inventory {
+    fn take(&mut self, filter: &dyn Fn(&ItemId) -> bool, max: usize) -> Option<ItemEntry> {
+        let item = self
+            .0
+            .iter()
+            .flatten()
+            .find(|held| filter(&held.item))?
+            .item
+            .clone();
+
+        let entry = ItemEntry {
+            quantity: self.count(&item).min(max),
+            item,
+        };
+        self.remove(&entry).then_some(entry)
+    }
+
+    fn put(
+        &mut self,
+        entry: ItemEntry,
+        stack_size: usize,
+        _towards: BeltDirection,
+    ) -> Option<ItemEntry> {
+        self.insert(entry, stack_size)
+    }
+}
+
+impl ArmEndpoint for MoverBelt {
+    /// One item at a time, the one furthest along
+    fn take(&mut self, filter: &dyn Fn(&ItemId) -> bool, _max: usize) -> Option<ItemEntry> {
+        let (lane, index) = self
+            .lanes
+            .iter()
+            .enumerate()
+            .flat_map(|(lane, items)| {
+                items
+                    .0
+                    .iter()
+                    .enumerate()
+                    .map(move |(index, held)| (lane, index, held))
+            })
+            .filter(|(_, _, held)| filter(&held.item))
+            .max_by_key(|(lane, _, held)| (held.progress, std::cmp::Reverse(*lane)))
+            .map(|(lane, index, _)| (lane, index))?;
+
+        let held = self.lanes[lane].0.remove(index);
+        Some(ItemEntry {
+            item: held.item,
+            quantity: 1,
+        })
+    }
+
+    /// One item at a time, halfway along the far lane
+    fn put(
+        &mut self,
+        mut entry: ItemEntry,
+        _stack_size: usize,
+        towards: BeltDirection,
+    ) -> Option<ItemEntry> {
+        let lane = if self.direction.left() == towards {
+            0
+        } else {
+            1
+        };
+
+        if entry.quantity > 0 && self.insert(lane, BELT_LENGTH / 2, entry.item.clone()) {
+            entry.quantity -= 1;
+        }
+        (entry.quantity > 0).then_some(entry)
+    }
+}
+
+/// Inventory of a [`CraftingMachine`], which only gives up the output of its recipe so an arm
+/// never pulls the ingredients another arm brought in
+pub struct MachineEndpoint<'a> {
+    pub inventory: &'a mut Inventory,
+    pub output: Vec<ItemId>,
+}
+
+impl ArmEndpoint for MachineEndpoint<'_> {
+    fn take(&mut self, filter: &dyn Fn(&ItemId) -> bool, max: usize) -> Option<ItemEntry> {
+        let output = &self.output;
+        self.inventory
+            .take(&|item: &ItemId| output.contains(item) && filter(item), max)
+    }
+
+    fn put(
+        &mut self,
+        entry: ItemEntry,
+        stack_size: usize,
+        towards: BeltDirection,
+    ) -> Option<ItemEntry> {
+        self.inventory.put(entry, stack_size, towards)
+    }
+}
+
+type EndpointQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Option<&'static mut Inventory>,
+        Option<&'static mut MoverBelt>,
+        Option<&'static CraftingMachine>,
+        Option<&'static InfiniteIo>,
+    ),
+    (With<TilePos>, Without<AutoArm>),
+>;
+
+type EndpointTileQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static TilePos, Option<&'static IoPorts>),
+    (Or<(With<Inventory>, With<MoverBelt>)>, Without<AutoArm>),
+>;
+
+type EndpointItem<'w> = (
+    Option<Mut<'w, Inventory>>,
+    Option<Mut<'w, MoverBelt>>,
+    Option<&'w CraftingMachine>,
+    Option<&'w InfiniteIo>,
+);
+
+/// What an arm reaches on a tile
+enum Reached<'a> {
+    Inventory(&'a mut Inventory),
+    Machine(MachineEndpoint<'a>),
+    Source(SourceEndpoint<'a>),
+    Belt(&'a mut MoverBelt),
+}
+
+impl Reached<'_> {
+    fn endpoint(&mut self) -> &mut dyn ArmEndpoint {
+        match self {
+            Self::Inventory(inventory) => &mut **inventory,
+            Self::Machine(machine) => machine,
+            Self::Source(source) => source,
+            Self::Belt(belt) => &mut **belt,
+        }
+    }
+}
+
+fn reached<'a>(item: &'a mut EndpointItem, recipes: &RecipeRegistry) -> Option<Reached<'a>> {
+    match item {
+        (Some(inventory), _, machine, io) => {
+            let inventory = inventory.as_mut();
+            Some(match (machine, io) {
+                (Some(machine), _) => Reached::Machine(MachineEndpoint {
+                    inventory,
+                    output: machine.output(recipes),
+                }),
+                (_, Some(io)) if matches!(io.mode, InfiniteIoMode::Source(_)) => {
+                    Reached::Source(SourceEndpoint(inventory))
+                }
+                _ => Reached::Inventory(inventory),
+            })
+        }
+        (_, Some(belt), ..) => Some(Reached::Belt(belt.as_mut())),
+        _ => None,
+    }
+}
+
+/// An endpoint reached only through `ports`, any way when `None`
+fn through_ports<'a>(
+    reached: &'a mut Option<Reached<'_>>,
+    ports: Option<Vec<PlacedPort>>,
+) -> Option<PortedEndpoint<'a>> {
+    reached.as_mut().map(|reached| PortedEndpoint {
+        endpoint: reached.endpoint(),
+        ports,
+    })
+}
+
+fn as_dyn<'a>(endpoint: &'a mut Option<PortedEndpoint<'_>>) -> Option<&'a mut dyn ArmEndpoint> {
+    endpoint
+        .as_mut()
+        .map(|endpoint| endpoint as &mut dyn ArmEndpoint)
+}
+
+/// Arms move in entity order so that two arms reaching for the same items always resolve
+/// the same way. Arms pick through output ports facing them and drop through input ports
+pub fn run_auto_arms(
+    items: Res<ItemRegistry>,
+    recipes: Res<RecipeRegistry>,
+    mut arms: Query<(Entity, &TilePos, &mut AutoArm)>,
+    tiles: EndpointTileQuery,
+    mut endpoints: EndpointQuery,
+) {
+    let mut reach = tiles
+        .iter()
+        .map(|(entity, pos, _)| (*pos, entity))
+        .collect::<HashMap<_, _>>();
+    reach.extend(port_tiles(
+        tiles
+            .iter()
+            .filter_map(|(entity, _, ports)| Some((entity, ports?))),
+    ));
+    let ports_at = |entity: Entity, tile: TilePos, edge: BeltDirection, kind: PortKind| {
+        let (_, _, ports) = tiles.get(entity).ok()?;
+        ports?
+            .reach(tile, edge, kind)
+            .map(|reached| reached.into_iter().cloned().collect::<Vec<_>>())
+    };
+    let stack_size = stack_size_of(&items);
+
+    let mut order = arms.iter().map(|(entity, ..)| entity).collect::<Vec<_>>();
+    order.sort();
+
+    for arm in order {
+        let Ok((_, pos, mut arm)) = arms.get_mut(arm) else {
+            continue;
+        };
+        let pick = arm.pick_tile(*pos).and_then(|tile| {
+            let entity = *reach.get(&tile)?;
+            Some((
+                entity,
+                ports_at(entity, tile, arm.direction, PortKind::Output),
+            ))
+        });
+        let drop = arm.drop_tile(*pos).and_then(|tile| {
+            let entity = *reach.get(&tile)?;
+            let edge = arm.direction.opposite();
+            Some((entity, ports_at(entity, tile, edge, PortKind::Input)))
+        });
+
+        match (pick, drop) {
+            (Some((pick, pick_ports)), Some((drop, drop_ports))) => {
+                let Ok([mut pick, mut drop]) = endpoints.get_many_mut([pick, drop]) else {
+                    continue;
+                };
+                let mut pick = reached(&mut pick, &recipes);
+                let mut drop = reached(&mut drop, &recipes);
+                let mut pick = through_ports(&mut pick, pick_ports);
+                let mut drop = through_ports(&mut drop, drop_ports);
+                arm.step(as_dyn(&mut pick), as_dyn(&mut drop), &stack_size);
+            }
+            (Some((pick, pick_ports)), None) => {
+                let Ok(mut pick) = endpoints.get_mut(pick) else {
+                    continue;
+                };
+                let mut pick = reached(&mut pick, &recipes);
+                let mut pick = through_ports(&mut pick, pick_ports);
+                arm.step(as_dyn(&mut pick), None, &stack_size);
+            }
+            (None, Some((drop, drop_ports))) => {
+                let Ok(mut drop) = endpoints.get_mut(drop) else {
+                    continue;
+                };
+                let mut drop = reached(&mut drop, &recipes);
+                let mut drop = through_ports(&mut drop, drop_ports);
+                arm.step(None, as_dyn(&mut drop), &stack_size);
+            }
+            (None, None) => arm.step(None, None, &stack_size),
+        }
+    }
+}
+
+/// Configuration copied from an arm, pasted onto others
+#[derive(Resource, Debug, Default)]
+pub struct ArmClipboard(pub Option<ArmConfiguration>);
+
+fn copy_paste_arm_configuration(
+    mut inter_actions: EventReader<InterAction>,
+    cursor: Res<CursorWorldPosition>,
+    building_tilemap: BuildingTilemapQuery,
+    mut arms: Query<&mut AutoArm>,
+    mut clipboard: ResMut<ArmClipboard>,
+) {
+    for inter_action in inter_actions.read() {
+        if !matches!(
+            inter_action,
+            InterAction::CopyConfiguration | InterAction::PasteConfiguration
+        ) {
+            continue;
+        }
+
+        let (_, tile_storage, tile_pos) = building_tilemap.cursor_tile_position(&cursor);
+        let Some(mut arm) = tile_pos
+            .and_then(|tile_pos| tile_storage.get(&tile_pos))
+            .and_then(|tile| arms.get_mut(tile).ok())
+        else {
+            continue;
+        };
+
+        match inter_action {
+            InterAction::CopyConfiguration => clipboard.0 = Some(arm.config.clone()),
+            InterAction::PasteConfiguration => {
+                if let Some(config) = clipboard.0.clone() {
+                    arm.config = config;
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InGameState;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    fn entry(item: &'static str, quantity: usize) -> ItemEntry {
+        ItemEntry {
+            item: item.into(),
+            quantity,
+        }
+    }
+
+    fn inventory(items: Vec<ItemEntry>) -> Inventory {
+        let mut inventory = Inventory::with_capacity(2);
+        inventory.dump(items);
+        inventory
+    }
+
+    fn step(arm: &mut AutoArm, pick: &mut dyn ArmEndpoint, drop: &mut dyn ArmEndpoint) {
+        arm.step(Some(pick), Some(drop), |_| 10);
+    }
+
+    /// Ticks for the arm to come back over the tile behind
+    fn cycle(arm: &mut AutoArm, pick: &mut dyn ArmEndpoint, drop: &mut dyn ArmEndpoint) -> usize {
+        let mut ticks = 0;
+        loop {
+            step(arm, pick, drop);
+            ticks += 1;
+            if arm.state == ArmState::Picking {
+                return ticks;
+            }
+        }
+    }
+
+    #[test]
+    fn moves_items_between_inventories() {
+        let mut arm = AutoArm::new(BeltDirection::East);
+        arm.swing_ticks = 3;
+        let mut from = inventory(vec![entry("tyconic::cheese_wheel", 2)]);
+        let mut to = inventory(vec![]);
+
+        // a pick, 3 ticks swinging, a drop and 3 ticks back
+        assert_eq!(cycle(&mut arm, &mut from, &mut to), 8);
+        assert_eq!(to.count(&"tyconic::cheese_wheel".into()), 1);
+        assert_eq!(from.count(&"tyconic::cheese_wheel".into()), 1);
+
+        cycle(&mut arm, &mut from, &mut to);
+        assert_eq!(to.count(&"tyconic::cheese_wheel".into()), 2);
+
+        // nothing left to pick, the arm waits
+        for _ in 0..10 {
+            step(&mut arm, &mut from, &mut to);
+        }
+        assert_eq!(arm.state, ArmState::Picking);
+        assert_eq!(arm.hand, None);
+    }
+
+    #[test]
+    fn filter_and_stack_size() {
+        let mut arm = AutoArm::new(BeltDirection::East);
+        arm.config = ArmConfiguration {
+            stack_size: 3,
+            filter: vec!["tyconic::bread_loaf".into()],
+            wait_for_full_hand: false,
+        };
+        let mut from = inventory(vec![
+            entry("tyconic::cheese_wheel", 5),
+            entry("tyconic::bread_loaf", 5),
+        ]);
+        let mut to = inventory(vec![]);
+
+        step(&mut arm, &mut from, &mut to);
+        assert_eq!(arm.hand, Some(entry("tyconic::bread_loaf", 3)));
+        assert_eq!(arm.state, ArmState::Swinging(0));
+        assert_eq!(from.count(&"tyconic::cheese_wheel".into()), 5);
+    }
+
+    #[test]
+    fn waits_for_full_hand() {
+        let mut arm = AutoArm::new(BeltDirection::North);
+        arm.config.stack_size = 3;
+        arm.config.wait_for_full_hand = true;
+        let mut belt = MoverBelt::new(BeltDirection::East);
+        belt.insert(0, 200, "base::burger".into());
+        belt.insert(1, 100, "base::burger".into());
+        let mut to = inventory(vec![]);
+
+        // belts hand over one item per tick
+        step(&mut arm, &mut belt, &mut to);
+        step(&mut arm, &mut belt, &mut to);
+        assert_eq!(arm.hand, Some(entry("base::burger", 2)));
+        assert_eq!(arm.state, ArmState::Picking);
+
+        for _ in 0..5 {
+            step(&mut arm, &mut belt, &mut to);
+        }
+        assert_eq!(arm.state, ArmState::Picking);
+
+        belt.insert(0, 0, "base::burger".into());
+        step(&mut arm, &mut belt, &mut to);
+        assert_eq!(arm.hand, Some(entry("base::burger", 3)));
+        assert_eq!(arm.state, ArmState::Swinging(0));
+    }
+
+    #[test]
+    fn blocked_drop_keeps_hand() {
+        let mut arm = AutoArm::new(BeltDirection::North);
+        arm.swing_ticks = 1;
+        let mut from = inventory(vec![entry("base::burger", 5)]);
+        // a belt going east has its left lane to the north, the far side of this arm
+        let mut belt = MoverBelt::new(BeltDirection::East);
+        belt.insert(0, BELT_LENGTH / 2, "base::fries".into());
+
+        for _ in 0..10 {
+            step(&mut arm, &mut from, &mut belt);
+        }
+        assert_eq!(arm.state, ArmState::Dropping);
+        assert_eq!(arm.hand, Some(entry("base::burger", 1)));
+
+        belt.take(0);
+        step(&mut arm, &mut from, &mut belt);
+        assert_eq!(arm.state, ArmState::Returning(0));
+        assert_eq!(
+            belt.lanes[0].0,
+            vec![BeltItem {
+                item: "base::burger".into(),
+                progress: BELT_LENGTH / 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn arms_feed_belts_in_simulation() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(
+                SimulationSettings::default().tick(),
+            ))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeRegistry>()
+            .init_resource::<CursorWorldPosition>()
+            .add_event::<InterAction>()
+            .add_plugins((SimulationPlugin, BeltPlugin, ArmPlugin));
+
+        let chest = app
+            .world_mut()
+            .spawn((
+                inventory(vec![entry("base::burger", 3)]),
+                TilePos { x: 0, y: 0 },
+            ))
+            .id();
+        app.world_mut()
+            .spawn((AutoArm::new(BeltDirection::East), TilePos { x: 1, y: 0 }));
+        let belt = app
+            .world_mut()
+            .spawn((MoverBelt::new(BeltDirection::North), TilePos { x: 2, y: 0 }))
+            .id();
+
+        for _ in 0..200 {
+            app.update();
+        }
+
+        assert_eq!(
+            app.world()
+                .get::<Inventory>(chest)
+                .unwrap()
+                .count(&"base::burger".into()),
+            0
+        );
+        // dropped on the far lane and backed up at the end of the belt
+        let belt = app.world().get::<MoverBelt>(belt).unwrap();
+        assert!(belt.lanes[0].0.is_empty());
+        assert_eq!(
+            belt.lanes[1]
+                .0
+                .iter()
+                .map(|held| held.progress)
+                .collect::<Vec<_>>(),
+            vec![
+                BELT_LENGTH,
+                BELT_LENGTH - ITEM_SPACING,
+                BELT_LENGTH - 2 * ITEM_SPACING
+            ]
+        );
+    }
+
+    #[test]
+    fn arms_between_machines_move_only_outputs() {
+        let mut recipes = Merged::<RecipeDeclared>::default();
+        recipes.apply(
+            "tyconic",
+            Declaration::Declare(RecipeDeclared {
+                id: RecipeId("tyconic::folk_pizza".into()),
+                recipe: Recipe {
+                    ingredients: vec![
+                        entry("tyconic::cheese_wheel", 1),
+                        entry("tyconic::bread_loaf", 2),
+                    ],
+                    output: vec![entry("tyconic::pizza_slice", 4)],
+                    research_required: vec![],
+                    duration: 1000,
+                },
+            }),
+        );
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(
+                SimulationSettings::default().tick(),
+            ))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .init_resource::<ItemRegistry>()
+            .insert_resource(RecipeRegistry::from(recipes))
+            .init_resource::<CursorWorldPosition>()
+            .add_event::<InterAction>()
+            .add_plugins((SimulationPlugin, ArmPlugin));
+
+        // the ingredients sit first, an arm taking anything would grab those
+        let mut stock = Inventory::with_capacity(3);
+        stock.dump(vec![
+            entry("tyconic::cheese_wheel", 1),
+            entry("tyconic::bread_loaf", 2),
+            entry("tyconic::pizza_slice", 2),
+        ]);
+        let oven = app
+            .world_mut()
+            .spawn((
+                CraftingMachine::new(RecipeId("tyconic::folk_pizza".into())),
+                stock,
+                TilePos { x: 0, y: 0 },
+            ))
+            .id();
+        app.world_mut()
+            .spawn((AutoArm::new(BeltDirection::East), TilePos { x: 1, y: 0 }));
+        let next = app
+            .world_mut()
+            .spawn((
+                CraftingMachine::default(),
+                inventory(vec![]),
+                TilePos { x: 2, y: 0 },
+            ))
+            .id();
+        // a machine without a recipe gives up nothing
+        app.world_mut()
+            .spawn((AutoArm::new(BeltDirection::East), TilePos { x: 3, y: 0 }));
+        let chest = app
+            .world_mut()
+            .spawn((inventory(vec![]), TilePos { x: 4, y: 0 }))
+            .id();
+
+        for _ in 0..200 {
+            app.update();
+        }
+
+        let oven = app.world().get::<Inventory>(oven).unwrap();
+        assert_eq!(oven.count(&"tyconic::cheese_wheel".into()), 1);
+        assert_eq!(oven.count(&"tyconic::bread_loaf".into()), 2);
+        assert_eq!(oven.count(&"tyconic::pizza_slice".into()), 0);
+        let next = app.world().get::<Inventory>(next).unwrap();
+        assert_eq!(next.count(&"tyconic::pizza_slice".into()), 2);
+        assert_eq!(next.count(&"tyconic::cheese_wheel".into()), 0);
+        assert_eq!(next.count(&"tyconic::bread_loaf".into()), 0);
+        assert!(app
+            .world()
+            .get::<Inventory>(chest)
+            .unwrap()
+            .0
+            .iter()
+            .all(Option::is_none));
+    }
+}
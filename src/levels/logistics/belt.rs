@@ -0,0 +1,543 @@
+//! Mover belts carry items along their facing direction on two lanes, handing them over to
+//! the belt they face or to an input port of the building they face. Positions along a belt are integers so that stepping the same belts
+//! always gives the same result, [`step_belts`] holds all the movement logic.
+
+use crate::levels::*;
+use crate::loading::*;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_tilemap::prelude::*;
+
+pub struct BeltPlugin;
+
+impl Plugin for BeltPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MoverBelt>()
+            .add_systems(Simulation, run_mover_belts)
+            .add_systems(
+                Update,
+                render_belt_items.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Length of a belt tile in progress units
+pub const BELT_LENGTH: u32 = 256;
+/// Room an item takes on a lane, 4 items fit on a lane of a tile
+pub const ITEM_SPACING: u32 = 64;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum BeltDirection {
+    /// towards increasing y
+    #[default]
+    North,
+    /// towards increasing x
+    East,
+    South,
+    West,
+}
+
+impl BeltDirection {
+    pub const ALL: [Self; 4] = [Self::North, Self::East, Self::South, Self::West];
+
+    pub fn offset(&self) -> IVec2 {
+        match self {
+            Self::North => IVec2::Y,
+            Self::East => IVec2::X,
+            Self::South => IVec2::NEG_Y,
+            Self::West => IVec2::NEG_X,
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Self::North => Self::South,
+            Self::East => Self::West,
+            Self::South => Self::North,
+            Self::West => Self::East,
+        }
+    }
+
+    /// Left hand side when travelling this direction
+    pub fn left(&self) -> Self {
+        match self {
+            Self::North => Self::West,
+            Self::East => Self::North,
+            Self::South => Self::East,
+            Self::West => Self::South,
+        }
+    }
+
+    /// Neighbouring tile, none past the edge of the map
+    pub fn step(&self, pos: TilePos) -> Option<TilePos> {
+        let offset = self.offset();
+        Some(TilePos {
+            x: pos.x.checked_add_signed(offset.x)?,
+            y: pos.y.checked_add_signed(offset.y)?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+pub struct BeltItem {
+    pub item: ItemId,
+    /// from 0 where the item enters the tile to [`BELT_LENGTH`] where it leaves
+    pub progress: u32,
+}
+
+/// Items on one side of a belt, the item furthest along first
+#[derive(Debug, Default, Clone, PartialEq, Eq, Reflect)]
+pub struct BeltLane(pub Vec<BeltItem>);
+
+impl BeltLane {
+    /// Whether an item placed at `progress` keeps its distance to the others
+    pub fn has_room(&self, progress: u32) -> bool {
+        self.0
+            .iter()
+            .all(|held| held.progress.abs_diff(progress) >= ITEM_SPACING)
+    }
+
+    fn insert(&mut self, progress: u32, item: ItemId) -> bool {
+        if !self.has_room(progress) {
+            return false;
+        }
+
+        let index = self
+            .0
+            .iter()
+            .position(|held| held.progress < progress)
+            .unwrap_or(self.0.len());
+        self.0.insert(index, BeltItem { item, progress });
+        true
+    }
+
+    /// Items close up on the one ahead, the first one stops at the end of the tile
+    fn advance(&mut self, speed: u32) {
+        let mut ahead: Option<u32> = None;
+        for held in self.0.iter_mut() {
+            let limit = ahead.map_or(BELT_LENGTH, |ahead| ahead.saturating_sub(ITEM_SPACING));
+            held.progress = (held.progress + speed).min(limit).max(held.progress);
+            ahead = Some(held.progress);
+        }
+    }
+}
+
+/// A belt tile of the [`BuildingTilemap`], lane 0 is on the left of the direction of travel
+#[derive(Component, Debug, Clone, PartialEq, Eq, Reflect)]
+pub struct MoverBelt {
+    pub direction: BeltDirection,
+    pub lanes: [BeltLane; 2],
+    /// progress units travelled per simulation tick
+    pub speed: u32,
+}
+
+impl Default for MoverBelt {
+    fn default() -> Self {
+        Self::new(BeltDirection::default())
+    }
+}
+
+impl MoverBelt {
+    pub fn new(direction: BeltDirection) -> Self {
+        Self {
+            direction,
+            lanes: default(),
+            speed: 8,
+        }
+    }
+
+    /// Places an item on a lane if there is room for it
+    pub fn insert(&mut self, lane: usize, progress: u32, item: ItemId) -> bool {
+        self.lanes[lane].insert(progress.min(BELT_LENGTH), item)
+    }
+
+    /// Takes the item furthest along a lane
+    pub fn take(&mut self, lane: usize) -> Option<ItemId> {
+        let lane = &mut self.lanes[lane].0;
+        (!lane.is_empty()).then(|| lane.remove(0).item)
+    }
+
+    /// Every item on the belt with its lane
+    pub fn items(&self) -> impl Iterator<Item = (usize, &BeltItem)> {
+        self.lanes
+            .iter()
+            .enumerate()
+            .flat_map(|(lane, items)| items.0.iter().map(move |held| (lane, held)))
+    }
+}
+
+/// How items leave a belt onto the belt it faces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Feed {
+    /// same direction, lanes are kept
+    Straight,
+    /// the belt ahead turns and nothing feeds it from behind, lanes are kept
+    Corner,
+    /// the belt ahead is fed from behind, both lanes merge onto its near lane halfway
+    SideLoad(usize),
+}
+
+impl Feed {
+    /// Lane and progress items of `lane` enter at
+    fn entry(&self, lane: usize) -> (usize, u32) {
+        match self {
+            Self::Straight | Self::Corner => (lane, 0),
+            Self::SideLoad(near) => (*near, BELT_LENGTH / 2),
+        }
+    }
+}
+
+/// Belt fed by the belt at `pos`, belts facing each other don't feed one another
+fn feed_target(belts: &HashMap<TilePos, MoverBelt>, pos: TilePos) -> Option<(TilePos, Feed)> {
+    let belt = belts.get(&pos)?;
+    let next = belt.direction.step(pos)?;
+    let target = belts.get(&next)?;
+
+    if target.direction == belt.direction {
+        return Some((next, Feed::Straight));
+    }
+    if target.direction == belt.direction.opposite() {
+        return None;
+    }
+
+    let fed_from_behind = target
+        .direction
+        .opposite()
+        .step(next)
+        .and_then(|behind| belts.get(&behind))
+        .is_some_and(|behind| behind.direction == target.direction);
+
+    if !fed_from_behind {
+        return Some((next, Feed::Corner));
+    }
+
+    let near = if belt.direction.opposite() == target.direction.left() {
+        0
+    } else {
+        1
+    };
+    Some((next, Feed::SideLoad(near)))
+}
+
+/// Runs the belts for a tick. Items at the end of a belt move onto the belt they face when
+/// there is room for them, then every lane moves up, items waiting behind blocked ones
+pub fn step_belts(belts: &mut HashMap<TilePos, MoverBelt>) {
+    step_belts_into(belts, |_, _, _| false);
+}
+
+/// [`step_belts`], handing items at the end of a belt facing something else than a belt to
+/// `deliver` with the tile faced and the edge of it items enter through. Items `deliver`
+/// accepts leave the belt
+pub fn step_belts_into(
+    belts: &mut HashMap<TilePos, MoverBelt>,
+    mut deliver: impl FnMut(TilePos, BeltDirection, &ItemId) -> bool,
+) {
+    let mut positions = belts.keys().copied().collect::<Vec<_>>();
+    positions.sort_by_key(|pos| (pos.y, pos.x));
+
+    for pos in positions {
+        let Some((next, feed)) = feed_target(belts, pos) else {
+            let direction = belts[&pos].direction;
+            let Some(next) = direction.step(pos) else {
+                continue;
+            };
+            if belts.contains_key(&next) {
+                continue;
+            }
+
+            let belt = belts.get_mut(&pos).unwrap();
+            for lane in belt.lanes.iter_mut() {
+                let delivered = lane.0.first().is_some_and(|front| {
+                    front.progress >= BELT_LENGTH
+                        && deliver(next, direction.opposite(), &front.item)
+                });
+                if delivered {
+                    lane.0.remove(0);
+                }
+            }
+            continue;
+        };
+
+        for lane in 0..2 {
+            let Some(front) = belts[&pos].lanes[lane].0.first() else {
+                continue;
+            };
+            if front.progress < BELT_LENGTH {
+                continue;
+            }
+
+            let item = front.item.clone();
+            let (target_lane, entry) = feed.entry(lane);
+            let target = belts.get_mut(&next).unwrap();
+            if target.lanes[target_lane].insert(entry, item) {
+                belts.get_mut(&pos).unwrap().lanes[lane].0.remove(0);
+            }
+        }
+    }
+
+    for belt in belts.values_mut() {
+        let speed = belt.speed;
+        for lane in belt.lanes.iter_mut() {
+            lane.advance(speed);
+        }
+    }
+}
+
+/// Belts hand items over to buildings through their input ports only
+pub fn run_mover_belts(
+    items: Res<ItemRegistry>,
+    mut belts: Query<(&TilePos, &mut MoverBelt)>,
+    mut buildings: Query<(Entity, &IoPorts, &mut Inventory), Without<MoverBelt>>,
+) {
+    let mut stepped = belts
+        .iter()
+        .map(|(pos, belt)| (*pos, belt.clone()))
+        .collect::<HashMap<_, _>>();
+    let ported = port_tiles(buildings.iter().map(|(entity, ports, _)| (entity, ports)));
+    let stack_size = stack_size_of(&items);
+
+    step_belts_into(&mut stepped, |tile, edge, item| {
+        let Some((_, ports, mut inventory)) = ported
+            .get(&tile)
+            .and_then(|building| buildings.get_mut(*building).ok())
+        else {
+            return false;
+        };
+        let accepts = ports
+            .reach(tile, edge, PortKind::Input)
+            .is_some_and(|reached| reached.iter().any(|port| port.allows(item)));
+
+        accepts
+            && inventory
+                .insert(
+                    ItemEntry {
+                        item: item.clone(),
+                        quantity: 1,
+                    },
+                    stack_size(item),
+                )
+                .is_none()
+    });
+
+    for (pos, mut belt) in belts.iter_mut() {
+        if let Some(stepped) = stepped.remove(pos) {
+            belt.set_if_neq(stepped);
+        }
+    }
+}
+
+/// Sprite of an item riding a belt, reused from frame to frame
+#[derive(Component)]
+pub struct BeltItemSprite;
+
+fn render_belt_items(
+    mut cmd: Commands,
+    building_tilemap: BuildingTilemapQuery,
+    item_map: Query<&ItemTextureMap, With<Level>>,
+    belts: Query<(&TilePos, &MoverBelt)>,
+    mut sprites: Query<
+        (&mut Sprite, &mut Transform, &mut Visibility),
+        (With<BeltItemSprite>, Without<BuildingTilemap>),
+    >,
+) {
+    let Ok((_, _, grid_size, map_type, _, map_transform)) = building_tilemap.get_single() else {
+        return;
+    };
+    let Ok(item_map) = item_map.get_single() else {
+        return;
+    };
+
+    // the isometric projection is linear, fractional tile positions map the same way
+    let origin = TilePos { x: 0, y: 0 }.center_in_world(grid_size, map_type);
+    let x_axis = TilePos { x: 1, y: 0 }.center_in_world(grid_size, map_type) - origin;
+    let y_axis = TilePos { x: 0, y: 1 }.center_in_world(grid_size, map_type) - origin;
+    let to_world = |tile: Vec2| {
+        map_transform.translation.truncate() + origin + tile.x * x_axis + tile.y * y_axis
+    };
+
+    let mut placed = belts.iter().flat_map(|(pos, belt)| {
+        belt.items().filter_map(move |(lane, held)| {
+            let image = item_map.0.get(&held.item.0)?;
+
+            let along = held.progress as f32 / BELT_LENGTH as f32 - 0.5;
+            let side = if lane == 0 { 0.25 } else { -0.25 };
+            let tile = Vec2::new(pos.x as f32, pos.y as f32)
+                + belt.direction.offset().as_vec2() * along
+                + belt.direction.left().offset().as_vec2() * side;
+
+            Some((image.clone(), to_world(tile)))
+        })
+    });
+
+    let z = map_transform.translation.z + 1.;
+    for (mut sprite, mut transform, mut visibility) in sprites.iter_mut() {
+        match placed.next() {
+            Some((image, at)) => {
+                sprite.image = image;
+                transform.translation = at.extend(z);
+                *visibility = Visibility::Inherited;
+            }
+            None => *visibility = Visibility::Hidden,
+        }
+    }
+
+    for (image, at) in placed {
+        cmd.spawn((
+            BeltItemSprite,
+            StateScoped(GameState::Playing),
+            Sprite { image, ..default() },
+            Transform::from_translation(at.extend(z)).with_scale(Vec3::splat(0.3)),
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pos(x: u32, y: u32) -> TilePos {
+        TilePos { x, y }
+    }
+
+    fn line(belts: &mut HashMap<TilePos, MoverBelt>, tiles: &[(u32, u32, BeltDirection)]) {
+        for (x, y, direction) in tiles {
+            belts.insert(pos(*x, *y), MoverBelt::new(*direction));
+        }
+    }
+
+    fn step(belts: &mut HashMap<TilePos, MoverBelt>, ticks: usize) {
+        for _ in 0..ticks {
+            step_belts(belts);
+        }
+    }
+
+    fn lane(belts: &HashMap<TilePos, MoverBelt>, at: TilePos, lane: usize) -> Vec<u32> {
+        belts[&at].lanes[lane]
+            .0
+            .iter()
+            .map(|held| held.progress)
+            .collect()
+    }
+
+    #[test]
+    fn items_chain_across_straight_belts() {
+        use BeltDirection::*;
+        let mut belts = HashMap::new();
+        line(&mut belts, &[(0, 0, East), (1, 0, East), (2, 0, East)]);
+        assert!(belts
+            .get_mut(&pos(0, 0))
+            .unwrap()
+            .insert(1, 0, "base::burger".into()));
+
+        // 32 ticks to cross a tile and one more to change tiles
+        step(&mut belts, 33);
+        assert!(lane(&belts, pos(0, 0), 1).is_empty());
+        assert_eq!(lane(&belts, pos(1, 0), 1), vec![8]);
+
+        // the last belt holds it at its end
+        step(&mut belts, 100);
+        assert_eq!(lane(&belts, pos(2, 0), 1), vec![BELT_LENGTH]);
+        assert!(lane(&belts, pos(2, 0), 0).is_empty());
+    }
+
+    #[test]
+    fn blocked_lane_backs_up() {
+        let mut belts = HashMap::new();
+        line(&mut belts, &[(0, 0, BeltDirection::North)]);
+
+        let mut inserted = 0;
+        for _ in 0..100 {
+            if belts
+                .get_mut(&pos(0, 0))
+                .unwrap()
+                .insert(0, 0, "base::burger".into())
+            {
+                inserted += 1;
+            }
+            step(&mut belts, 1);
+        }
+
+        assert_eq!(inserted, 5);
+        assert_eq!(lane(&belts, pos(0, 0), 0), vec![256, 192, 128, 64, 0]);
+        assert!(!belts[&pos(0, 0)].lanes[0].has_room(0));
+    }
+
+    #[test]
+    fn corners_keep_lanes() {
+        use BeltDirection::*;
+        let mut belts = HashMap::new();
+        line(&mut belts, &[(0, 0, East), (1, 0, North)]);
+        let first = belts.get_mut(&pos(0, 0)).unwrap();
+        first.insert(0, BELT_LENGTH, "base::burger".into());
+        first.insert(1, BELT_LENGTH, "base::fries".into());
+
+        step(&mut belts, 1);
+        assert_eq!(
+            belts[&pos(1, 0)]
+                .items()
+                .map(|(lane, held)| (lane, held.item.0.as_str()))
+                .collect::<Vec<_>>(),
+            vec![(0, "base::burger"), (1, "base::fries")]
+        );
+    }
+
+    #[test]
+    fn side_loading_merges_onto_near_lane() {
+        use BeltDirection::*;
+        let mut belts = HashMap::new();
+        // a straight line going north fed from the west by a belt going east
+        line(&mut belts, &[(1, 0, North), (1, 1, North), (0, 1, East)]);
+        let side = belts.get_mut(&pos(0, 1)).unwrap();
+        side.insert(0, BELT_LENGTH, "base::burger".into());
+        side.insert(1, BELT_LENGTH, "base::fries".into());
+
+        step(&mut belts, 1);
+        // the second item has to wait for room on the lane
+        assert_eq!(lane(&belts, pos(1, 1), 0), vec![BELT_LENGTH / 2 + 8]);
+        assert!(lane(&belts, pos(1, 1), 1).is_empty());
+        assert_eq!(lane(&belts, pos(0, 1), 1), vec![BELT_LENGTH]);
+
+        step(&mut belts, 8);
+        assert_eq!(lane(&belts, pos(1, 1), 0), vec![200, 136]);
+        assert!(belts[&pos(0, 1)].lanes[1].0.is_empty());
+    }
+
+    #[test]
+    fn facing_belts_dont_exchange() {
+        use BeltDirection::*;
+        let mut belts = HashMap::new();
+        line(&mut belts, &[(0, 0, East), (1, 0, West)]);
+        belts
+            .get_mut(&pos(0, 0))
+            .unwrap()
+            .insert(0, BELT_LENGTH, "base::burger".into());
+
+        step(&mut belts, 10);
+        assert_eq!(lane(&belts, pos(0, 0), 0), vec![BELT_LENGTH]);
+        assert!(belts[&pos(1, 0)].items().next().is_none());
+    }
+
+    #[test]
+    fn belt_ends_deliver_what_is_accepted() {
+        use BeltDirection::*;
+        let mut belts = HashMap::new();
+        line(&mut belts, &[(0, 0, East)]);
+        let belt = belts.get_mut(&pos(0, 0)).unwrap();
+        belt.insert(0, BELT_LENGTH, "base::burger".into());
+        belt.insert(1, BELT_LENGTH, "base::fries".into());
+
+        let mut delivered = vec![];
+        step_belts_into(&mut belts, |tile, edge, item| {
+            assert_eq!((tile, edge), (pos(1, 0), West));
+            let accepted = item.0 == "base::burger";
+            if accepted {
+                delivered.push(item.clone());
+            }
+            accepted
+        });
+
+        assert_eq!(delivered, vec![ItemId::from("base::burger")]);
+        assert!(belts[&pos(0, 0)].lanes[0].0.is_empty());
+        assert_eq!(lane(&belts, pos(0, 0), 1), vec![BELT_LENGTH]);
+    }
+}
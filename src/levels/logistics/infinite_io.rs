@@ -0,0 +1,366 @@
+//! Infinite IO buildings conjure or swallow items through their [`Inventory`], so anything
+//! moving items such as arms or the transport network works with them unchanged. They keep
+//! count of what went through to benchmark production chains.
+
+use crate::levels::*;
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+pub struct InfiniteIoPlugin;
+
+impl Plugin for InfiniteIoPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<InfiniteIo>()
+            .add_event::<ItemFlow>()
+            .add_systems(
+                Simulation,
+                run_infinite_io
+                    .after(run_auto_arms)
+                    .after(deliver_shipments),
+            )
+            .add_systems(
+                Update,
+                update_throughput_text
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(crate::DeveloperMode(true))),
+            );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Reflect)]
+pub enum InfiniteIoMode {
+    /// keeps its inventory full of the item
+    Source(ItemId),
+    /// counts and deletes whatever it receives
+    Sink,
+}
+
+#[derive(Component, Debug, Clone, PartialEq, Reflect)]
+#[require(Inventory(infinite_io_inventory))]
+pub struct InfiniteIo {
+    pub mode: InfiniteIoMode,
+    /// items supplied or received
+    pub throughput: Throughput,
+}
+
+fn infinite_io_inventory() -> Inventory {
+    Inventory::with_capacity(4)
+}
+
+impl InfiniteIo {
+    pub fn source(item: ItemId) -> Self {
+        Self {
+            mode: InfiniteIoMode::Source(item),
+            throughput: default(),
+        }
+    }
+
+    pub fn sink() -> Self {
+        Self {
+            mode: InfiniteIoMode::Sink,
+            throughput: default(),
+        }
+    }
+
+    /// Switching modes starts counting over
+    pub fn set_mode(&mut self, mode: InfiniteIoMode) {
+        if self.mode != mode {
+            *self = Self {
+                mode,
+                throughput: default(),
+            };
+        }
+    }
+
+    /// Refills or empties the inventory, returns how many items went through since last time.
+    /// Sources only refill empty slots and slots of their item, anything else is left alone
+    pub fn exchange(&self, inventory: &mut Inventory, stack_size: usize) -> usize {
+        match &self.mode {
+            InfiniteIoMode::Source(item) => inventory
+                .0
+                .iter_mut()
+                .filter(|slot| slot.as_ref().is_none_or(|held| held.item == *item))
+                .map(|slot| {
+                    let held = slot.as_ref().map_or(0, |held| held.quantity);
+                    *slot = Some(ItemEntry {
+                        item: item.clone(),
+                        quantity: stack_size,
+                    });
+                    stack_size.saturating_sub(held)
+                })
+                .sum(),
+            InfiniteIoMode::Sink => inventory
+                .0
+                .iter_mut()
+                .filter_map(Option::take)
+                .map(|entry| entry.quantity)
+                .sum(),
+        }
+    }
+}
+
+/// Inventory of an [`InfiniteIo`] source, arms pick from it but can't put anything in
+pub struct SourceEndpoint<'a>(pub &'a mut Inventory);
+
+impl ArmEndpoint for SourceEndpoint<'_> {
+    fn take(&mut self, filter: &dyn Fn(&ItemId) -> bool, max: usize) -> Option<ItemEntry> {
+        self.0.take(filter, max)
+    }
+
+    fn put(
+        &mut self,
+        entry: ItemEntry,
+        _stack_size: usize,
+        _towards: BeltDirection,
+    ) -> Option<ItemEntry> {
+        Some(entry)
+    }
+}
+
+/// Items counted over a sliding minute of simulated time
+#[derive(Debug, Default, Clone, PartialEq, Reflect)]
+pub struct Throughput {
+    pub total: u64,
+    /// ticks items went through within the last minute, with their quantity
+    recent: VecDeque<(u64, usize)>,
+    /// tick counting started
+    since: Option<u64>,
+}
+
+impl Throughput {
+    /// Ticks in a minute of simulated time
+    pub fn window(settings: &SimulationSettings) -> u64 {
+        60 * settings.tick_rate as u64
+    }
+
+    /// Forgets the last minute, keeping the total
+    pub fn restart(&mut self) {
+        self.recent.clear();
+        self.since = None;
+    }
+
+    pub fn record(&mut self, tick: u64, quantity: usize, settings: &SimulationSettings) {
+        self.since.get_or_insert(tick);
+        if quantity > 0 {
+            self.total += quantity as u64;
+            self.recent.push_back((tick, quantity));
+        }
+
+        let window = Self::window(settings);
+        while self
+            .recent
+            .front()
+            .is_some_and(|(recorded, _)| recorded + window <= tick)
+        {
+            self.recent.pop_front();
+        }
+    }
+
+    /// Items per minute over the last minute, extrapolated while less than a minute was counted
+    pub fn per_minute(&self, tick: u64, settings: &SimulationSettings) -> f32 {
+        let Some(since) = self.since else {
+            return 0.;
+        };
+        let window = Self::window(settings);
+        let counted = (tick + 1).saturating_sub(since).clamp(1, window);
+
+        let recent: usize = self
+            .recent
+            .iter()
+            .filter(|(recorded, _)| recorded + window > tick)
+            .map(|(_, quantity)| quantity)
+            .sum();
+
+        recent as f32 * window as f32 / counted as f32
+    }
+}
+
+pub fn run_infinite_io(
+    time: Res<SimulationTime>,
+    settings: Res<SimulationSettings>,
+    items: Res<ItemRegistry>,
+    mut ios: Query<(&mut InfiniteIo, &mut Inventory)>,
+    mut flows: EventWriter<ItemFlow>,
+) {
+    let stack_size = stack_size_of(&items);
+
+    for (mut io, mut inventory) in ios.iter_mut() {
+        let size = match &io.mode {
+            InfiniteIoMode::Source(item) => stack_size(item),
+            InfiniteIoMode::Sink => {
+                // whatever a sink receives is gone for good
+                flows.send_batch(inventory.0.iter().flatten().map(|entry| ItemFlow {
+                    item: entry.item.clone(),
+                    quantity: entry.quantity,
+                    kind: ItemFlowKind::Wasted,
+                }));
+                0
+            }
+        };
+        let exchanged = io.exchange(&mut inventory, size);
+        io.throughput.record(time.tick, exchanged, &settings);
+    }
+}
+
+#[derive(Component)]
+pub struct ThroughputText;
+
+/// Mode and items per minute of every infinite io, for the logistics window
+pub fn throughput_summary(ios: impl Iterator<Item = (Entity, InfiniteIoMode, f32)>) -> String {
+    let mut lines = ios
+        .map(|(entity, mode, per_minute)| {
+            let mode = match mode {
+                InfiniteIoMode::Source(item) => format!("supplies {}", item.0),
+                InfiniteIoMode::Sink => "sinks".to_string(),
+            };
+            format!("{} {} at {:.1}/min", entity, mode, per_minute)
+        })
+        .collect::<Vec<_>>();
+    lines.sort();
+
+    if lines.is_empty() {
+        "none".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+pub fn update_throughput_text(
+    time: Res<SimulationTime>,
+    settings: Res<SimulationSettings>,
+    ios: Query<(Entity, &InfiniteIo)>,
+    mut text: Query<&mut Text, With<ThroughputText>>,
+) {
+    let summary = throughput_summary(ios.iter().map(|(entity, io)| {
+        (
+            entity,
+            io.mode.clone(),
+            io.throughput.per_minute(time.tick, &settings),
+        )
+    }));
+
+    for mut text in text.iter_mut() {
+        if text.0 != summary {
+            text.0 = summary.clone();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::*;
+    use crate::InGameState;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+    use bevy_ecs_tilemap::prelude::*;
+
+    #[test]
+    fn throughput_over_sliding_minute() {
+        let settings = SimulationSettings {
+            tick_rate: 10,
+            ..default()
+        };
+        let mut throughput = Throughput::default();
+
+        // 3 items every second for half a minute
+        for tick in 0..300 {
+            throughput.record(tick, if tick % 10 == 0 { 3 } else { 0 }, &settings);
+        }
+        assert_eq!(throughput.total, 90);
+        assert_eq!(throughput.per_minute(299, &settings), 180.);
+
+        // then nothing for the next minute
+        for tick in 300..600 {
+            throughput.record(tick, 0, &settings);
+        }
+        assert_eq!(throughput.per_minute(599, &settings), 90.);
+        for tick in 600..900 {
+            throughput.record(tick, 0, &settings);
+        }
+        assert_eq!(throughput.per_minute(899, &settings), 0.);
+        assert_eq!(throughput.total, 90);
+    }
+
+    #[test]
+    fn sources_refill_and_sinks_empty() {
+        let source = InfiniteIo::source("base::burger".into());
+        let mut inventory = infinite_io_inventory();
+        assert_eq!(source.exchange(&mut inventory, 10), 40);
+
+        inventory.remove(&ItemEntry {
+            item: "base::burger".into(),
+            quantity: 15,
+        });
+        assert_eq!(source.exchange(&mut inventory, 10), 15);
+        assert_eq!(inventory.count(&"base::burger".into()), 40);
+
+        let sink = InfiniteIo::sink();
+        assert_eq!(sink.exchange(&mut inventory, 10), 40);
+        assert!(inventory.0.iter().all(Option::is_none));
+    }
+
+    #[test]
+    fn sources_keep_what_they_didnt_supply() {
+        let source = InfiniteIo::source("base::burger".into());
+        let mut inventory = infinite_io_inventory();
+        inventory.0[1] = Some(ItemEntry {
+            item: "base::fries".into(),
+            quantity: 3,
+        });
+        assert_eq!(source.exchange(&mut inventory, 10), 30);
+        assert_eq!(inventory.count(&"base::fries".into()), 3);
+        assert_eq!(inventory.count(&"base::burger".into()), 30);
+
+        let fries = ItemEntry {
+            item: "base::fries".into(),
+            quantity: 1,
+        };
+        let mut endpoint = SourceEndpoint(&mut inventory);
+        assert_eq!(
+            endpoint.put(fries.clone(), 10, BeltDirection::East),
+            Some(fries)
+        );
+        assert_eq!(inventory.count(&"base::fries".into()), 3);
+    }
+
+    #[test]
+    fn arm_between_source_and_sink() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(
+                SimulationSettings::default().tick(),
+            ))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeRegistry>()
+            .init_resource::<CursorWorldPosition>()
+            .add_event::<InterAction>()
+            .add_plugins((SimulationPlugin, ArmPlugin, InfiniteIoPlugin));
+
+        app.world_mut().spawn((
+            InfiniteIo::source("base::burger".into()),
+            TilePos { x: 0, y: 0 },
+        ));
+        let mut arm = AutoArm::new(BeltDirection::East);
+        arm.swing_ticks = 9;
+        app.world_mut().spawn((arm, TilePos { x: 1, y: 0 }));
+        let sink = app
+            .world_mut()
+            .spawn((InfiniteIo::sink(), TilePos { x: 2, y: 0 }))
+            .id();
+
+        // an arm swinging 9 ticks each way moves an item every 20 ticks
+        while app.world().resource::<SimulationTime>().tick < 3600 {
+            app.update();
+        }
+
+        let sink = app.world().get::<InfiniteIo>(sink).unwrap();
+        assert_eq!(sink.throughput.total, 180);
+        let per_minute = sink
+            .throughput
+            .per_minute(3600, &SimulationSettings::default());
+        assert!((per_minute - 180.).abs() <= 1., "{}", per_minute);
+    }
+}
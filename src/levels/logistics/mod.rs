@@ -1,3 +1,11 @@
+mod arm;
+mod belt;
+mod infinite_io;
+mod ports;
 mod transport;
 
+pub use arm::*;
+pub use belt::*;
+pub use infinite_io::*;
+pub use ports::*;
 pub use transport::*;
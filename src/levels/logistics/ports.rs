@@ -0,0 +1,429 @@
+//! Ports are where a building takes items in or lets them out, on one edge of one tile of its
+//! footprint. Arms and belts only reach a building through its [`IoPorts`], a building without
+//! ports is reached on every edge of the tile it stands on.
+
+use crate::levels::*;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use bevy_ecs_tilemap::prelude::*;
+
+pub struct PortsPlugin;
+
+impl Plugin for PortsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<IoPorts>().add_systems(
+            Simulation,
+            run_output_ports
+                .after(run_mover_belts)
+                .before(run_auto_arms),
+        );
+    }
+}
+
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PortKind {
+    #[default]
+    Input,
+    Output,
+}
+
+impl PortKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Input => "input",
+            Self::Output => "output",
+        }
+    }
+
+    pub fn other(&self) -> Self {
+        match self {
+            Self::Input => Self::Output,
+            Self::Output => Self::Input,
+        }
+    }
+}
+
+/// Port of a building as defined facing north
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct IoPort {
+    /// tile of the footprint, relative to the tile the building is placed on
+    pub tile: IVec2,
+    /// edge of the tile items pass
+    pub edge: BeltDirection,
+    pub kind: PortKind,
+    /// only these items pass, any item when empty
+    pub filter: Vec<ItemId>,
+    /// ports of a higher priority are served first
+    pub priority: i32,
+}
+
+/// Turns a direction of a building facing north towards `facing`, clockwise
+pub fn rotate_direction(direction: BeltDirection, facing: BeltDirection) -> BeltDirection {
+    let index = |direction: BeltDirection| {
+        BeltDirection::ALL
+            .iter()
+            .position(|turned| *turned == direction)
+            .unwrap_or_default()
+    };
+    BeltDirection::ALL[(index(direction) + index(facing)) % BeltDirection::ALL.len()]
+}
+
+impl IoPort {
+    pub fn new(kind: PortKind, tile: IVec2, edge: BeltDirection) -> Self {
+        Self {
+            tile,
+            edge,
+            kind,
+            filter: vec![],
+            priority: 0,
+        }
+    }
+
+    pub fn namespace(&mut self, namespace: Namespace) {
+        for item in self.filter.iter_mut() {
+            item.0 = namespaced(namespace, &item.0);
+        }
+    }
+
+    /// The port of a building placed on `origin` facing `facing`, `None` past the map
+    pub fn placed(&self, origin: TilePos, facing: BeltDirection) -> Option<PlacedPort> {
+        let tile = IVec2::new(origin.x as i32, origin.y as i32) + rotate_offset(self.tile, facing);
+
+        Some(PlacedPort {
+            tile: TilePos {
+                x: u32::try_from(tile.x).ok()?,
+                y: u32::try_from(tile.y).ok()?,
+            },
+            edge: rotate_direction(self.edge, facing),
+            kind: self.kind,
+            filter: self.filter.clone(),
+            priority: self.priority,
+        })
+    }
+}
+
+/// A port where its building stands on the map
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct PlacedPort {
+    pub tile: TilePos,
+    pub edge: BeltDirection,
+    pub kind: PortKind,
+    pub filter: Vec<ItemId>,
+    pub priority: i32,
+}
+
+impl PlacedPort {
+    pub fn allows(&self, item: &ItemId) -> bool {
+        self.filter.is_empty() || self.filter.contains(item)
+    }
+
+    /// Tile on the other side of the edge
+    pub fn facing_tile(&self) -> Option<TilePos> {
+        self.edge.step(self.tile)
+    }
+}
+
+/// Ports of a placed building, the highest priority first
+#[derive(Component, Reflect, Debug, Clone, Default, PartialEq)]
+pub struct IoPorts(pub Vec<PlacedPort>);
+
+impl IoPorts {
+    pub fn place(ports: &[IoPort], origin: TilePos, facing: BeltDirection) -> Self {
+        let mut placed = ports
+            .iter()
+            .filter_map(|port| port.placed(origin, facing))
+            .collect::<Vec<_>>();
+        placed.sort_by_key(|port| std::cmp::Reverse(port.priority));
+        Self(placed)
+    }
+
+    /// Ports of `kind` reached across `edge` of `tile`, `None` when the building has no ports
+    /// and is reached anywhere
+    pub fn reach(
+        &self,
+        tile: TilePos,
+        edge: BeltDirection,
+        kind: PortKind,
+    ) -> Option<Vec<&PlacedPort>> {
+        (!self.0.is_empty()).then(|| {
+            self.0
+                .iter()
+                .filter(|port| port.tile == tile && port.edge == edge && port.kind == kind)
+                .collect()
+        })
+    }
+}
+
+/// An endpoint reached through the ports on one of its edges, items leave through output ports
+/// and enter through input ports that allow them
+pub struct PortedEndpoint<'a> {
+    pub endpoint: &'a mut dyn ArmEndpoint,
+    /// `None` for a building without ports
+    pub ports: Option<Vec<PlacedPort>>,
+}
+
+impl ArmEndpoint for PortedEndpoint<'_> {
+    fn take(&mut self, filter: &dyn Fn(&ItemId) -> bool, max: usize) -> Option<ItemEntry> {
+        let Some(ports) = &self.ports else {
+            return self.endpoint.take(filter, max);
+        };
+
+        ports
+            .iter()
+            .filter(|port| port.kind == PortKind::Output)
+            .find_map(|port| {
+                self.endpoint
+                    .take(&|item: &ItemId| port.allows(item) && filter(item), max)
+            })
+    }
+
+    fn put(
+        &mut self,
+        entry: ItemEntry,
+        stack_size: usize,
+        towards: BeltDirection,
+    ) -> Option<ItemEntry> {
+        let Some(ports) = &self.ports else {
+            return self.endpoint.put(entry, stack_size, towards);
+        };
+
+        match ports
+            .iter()
+            .any(|port| port.kind == PortKind::Input && port.allows(&entry.item))
+        {
+            true => self.endpoint.put(entry, stack_size, towards),
+            false => Some(entry),
+        }
+    }
+}
+
+/// Tiles covered by ports, buildings are reached on these besides the tile they stand on
+pub fn port_tiles<'a>(
+    buildings: impl Iterator<Item = (Entity, &'a IoPorts)>,
+) -> HashMap<TilePos, Entity> {
+    buildings
+        .flat_map(|(entity, ports)| ports.0.iter().map(move |port| (port.tile, entity)))
+        .collect()
+}
+
+/// Output ports put one item per tick on the belt past their edge, unless that belt runs into
+/// the building. Machines only put out the output of their recipe
+pub fn run_output_ports(
+    items: Res<ItemRegistry>,
+    recipes: Res<RecipeRegistry>,
+    mut buildings: Query<(&IoPorts, &mut Inventory, Option<&CraftingMachine>), Without<MoverBelt>>,
+    mut belts: Query<(&TilePos, &mut MoverBelt)>,
+) {
+    let belt_tiles = belts
+        .iter()
+        .enumerate()
+        .map(|(index, (pos, _))| (*pos, index))
+        .collect::<HashMap<_, _>>();
+    if belt_tiles.is_empty() {
+        return;
+    }
+    let stack_size = stack_size_of(&items);
+
+    let mut belts = belts.iter_mut().collect::<Vec<_>>();
+    for (ports, mut inventory, machine) in buildings.iter_mut() {
+        let output = machine.map(|machine| machine.output(&recipes));
+        let gives_up = |item: &ItemId| output.as_ref().is_none_or(|output| output.contains(item));
+        for port in ports.0.iter().filter(|port| port.kind == PortKind::Output) {
+            let Some((_, belt)) = port
+                .facing_tile()
+                .and_then(|tile| belt_tiles.get(&tile))
+                .map(|index| &mut belts[*index])
+            else {
+                continue;
+            };
+            if belt.direction == port.edge.opposite() {
+                continue;
+            }
+
+            let Some(entry) =
+                inventory.take(&|item: &ItemId| gives_up(item) && port.allows(item), 1)
+            else {
+                continue;
+            };
+            if let Some(rest) = belt.put(entry, 1, port.edge) {
+                let size = stack_size(&rest.item);
+                inventory.insert(rest, size);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InGameState;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    fn entry(item: &'static str, quantity: usize) -> ItemEntry {
+        ItemEntry {
+            item: item.into(),
+            quantity,
+        }
+    }
+
+    fn oven_ports() -> Vec<IoPort> {
+        vec![
+            IoPort {
+                filter: vec!["tyconic::cheese_wheel".into()],
+                ..IoPort::new(PortKind::Input, IVec2::ZERO, BeltDirection::West)
+            },
+            IoPort {
+                priority: 2,
+                ..IoPort::new(PortKind::Output, IVec2::Y, BeltDirection::North)
+            },
+        ]
+    }
+
+    #[test]
+    fn ports_turn_with_the_building() {
+        let ports = IoPorts::place(&oven_ports(), TilePos { x: 4, y: 4 }, BeltDirection::East);
+
+        // the highest priority comes first
+        assert_eq!(ports.0[0].kind, PortKind::Output);
+        assert_eq!(ports.0[0].tile, TilePos { x: 5, y: 4 });
+        assert_eq!(ports.0[0].edge, BeltDirection::East);
+        assert_eq!(ports.0[1].tile, TilePos { x: 4, y: 4 });
+        assert_eq!(ports.0[1].edge, BeltDirection::North);
+
+        assert_eq!(
+            ports
+                .reach(
+                    TilePos { x: 4, y: 4 },
+                    BeltDirection::North,
+                    PortKind::Input
+                )
+                .map(|reached| reached.len()),
+            Some(1)
+        );
+        assert_eq!(
+            ports
+                .reach(TilePos { x: 4, y: 4 }, BeltDirection::West, PortKind::Input)
+                .map(|reached| reached.len()),
+            Some(0)
+        );
+        assert_eq!(
+            IoPorts::default().reach(TilePos { x: 4, y: 4 }, BeltDirection::West, PortKind::Input),
+            None
+        );
+    }
+
+    #[test]
+    fn ported_endpoints_pass_what_ports_allow() {
+        let ports = IoPorts::place(&oven_ports(), TilePos { x: 0, y: 0 }, BeltDirection::North);
+        let mut inventory = Inventory::with_capacity(2);
+        inventory.dump(vec![entry("tyconic::hamburger", 1)]);
+
+        let input = ports
+            .reach(TilePos { x: 0, y: 0 }, BeltDirection::West, PortKind::Input)
+            .map(|reached| reached.into_iter().cloned().collect());
+        let mut endpoint = PortedEndpoint {
+            endpoint: &mut inventory,
+            ports: input,
+        };
+        assert_eq!(
+            endpoint.put(entry("tyconic::bread_loaf", 1), 10, BeltDirection::East),
+            Some(entry("tyconic::bread_loaf", 1))
+        );
+        assert_eq!(
+            endpoint.put(entry("tyconic::cheese_wheel", 1), 10, BeltDirection::East),
+            None
+        );
+        // an input port lets nothing out
+        assert_eq!(endpoint.take(&|_| true, 1), None);
+
+        let output = ports
+            .reach(
+                TilePos { x: 0, y: 1 },
+                BeltDirection::North,
+                PortKind::Output,
+            )
+            .map(|reached| reached.into_iter().cloned().collect());
+        let mut endpoint = PortedEndpoint {
+            endpoint: &mut inventory,
+            ports: output,
+        };
+        assert_eq!(
+            endpoint.take(&|_| true, 1),
+            Some(entry("tyconic::hamburger", 1))
+        );
+
+        let mut endpoint = PortedEndpoint {
+            endpoint: &mut inventory,
+            ports: None,
+        };
+        assert_eq!(
+            endpoint.take(&|_| true, 1),
+            Some(entry("tyconic::cheese_wheel", 1))
+        );
+    }
+
+    #[test]
+    fn belts_carry_from_output_to_input_ports() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(
+                SimulationSettings::default().tick(),
+            ))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeRegistry>()
+            .add_plugins((SimulationPlugin, BeltPlugin, PortsPlugin));
+
+        let output = [IoPort::new(
+            PortKind::Output,
+            IVec2::ZERO,
+            BeltDirection::North,
+        )];
+        let source = app
+            .world_mut()
+            .spawn((
+                {
+                    let mut inventory = Inventory::with_capacity(2);
+                    inventory.dump(vec![entry("base::burger", 2), entry("base::fries", 1)]);
+                    inventory
+                },
+                TilePos { x: 0, y: 0 },
+                IoPorts::place(&output, TilePos { x: 0, y: 0 }, BeltDirection::North),
+            ))
+            .id();
+        for y in 1..3 {
+            app.world_mut()
+                .spawn((MoverBelt::new(BeltDirection::North), TilePos { x: 0, y }));
+        }
+        let input = [IoPort {
+            filter: vec!["base::burger".into()],
+            ..IoPort::new(PortKind::Input, IVec2::ZERO, BeltDirection::South)
+        }];
+        let sink = app
+            .world_mut()
+            .spawn((
+                Inventory::with_capacity(2),
+                TilePos { x: 0, y: 3 },
+                IoPorts::place(&input, TilePos { x: 0, y: 3 }, BeltDirection::North),
+            ))
+            .id();
+
+        for _ in 0..200 {
+            app.update();
+        }
+
+        let count = |entity: Entity, item: &'static str| {
+            app.world()
+                .get::<Inventory>(entity)
+                .unwrap()
+                .count(&item.into())
+        };
+        assert_eq!(count(source, "base::burger"), 0);
+        assert_eq!(count(sink, "base::burger"), 2);
+        // the input port refuses fries, they wait at the end of the belt
+        assert_eq!(count(sink, "base::fries"), 0);
+        assert_eq!(count(source, "base::fries"), 0);
+    }
+}
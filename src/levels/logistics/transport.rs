@@ -1,73 +1,640 @@
+//! Logistics network moving items between inventories. Providers advertise what their
+//! [`Inventory`] holds, requesters post a [`TransportDemand`] and every simulation tick the
+//! matcher dispatches shipments from the nearest providers, arriving after a transit latency
+//! as [`TransportInbound`].
+
+use crate::hud::*;
 use crate::levels::*;
+use crate::loading::*;
+use crate::ui::*;
 use bevy::prelude::*;
+use bevy_ecs_tilemap::prelude::*;
 
 pub struct TransportPlugin;
 
 impl Plugin for TransportPlugin {
     fn build(&self, app: &mut App) {
-        //app.add_systems(
-        //    Update,
-        //    (initiate_transfer,).run_if(in_state(InGameState::Normal)),
-        //);
+        app.register_type::<LogisticsSettings>()
+            .init_resource::<LogisticsSettings>()
+            .add_observer(handle_transport)
+            .add_systems(
+                Simulation,
+                (dispatch_shipments, deliver_shipments)
+                    .chain()
+                    .after(advance_level_tick),
+            )
+            .add_systems(
+                OnEnter(EnableHUD::ENABLED),
+                spawn_logistics_window
+                    .after(spawn_hud_backdrop)
+                    .run_if(in_state(crate::DeveloperMode(true))),
+            )
+            .add_systems(
+                Update,
+                (update_pending_demands_text, draw_shipments)
+                    .run_if(in_state(GameState::Playing))
+                    .run_if(in_state(crate::DeveloperMode(true))),
+            );
+    }
+}
+
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+pub struct LogisticsSettings {
+    /// ticks every shipment takes, however close the provider is
+    pub base_latency: u64,
+    /// ticks added per tile between provider and requester
+    pub ticks_per_tile: u64,
+}
+
+impl Default for LogisticsSettings {
+    fn default() -> Self {
+        Self {
+            base_latency: 30,
+            ticks_per_tile: 6,
+        }
+    }
+}
+
+impl LogisticsSettings {
+    /// Entities without a tile position are considered next to each other
+    pub fn latency(&self, from: Option<&TilePos>, to: Option<&TilePos>) -> u64 {
+        self.base_latency + self.ticks_per_tile * distance(from, to) as u64
+    }
+}
+
+/// Manhattan distance in tiles
+fn distance(from: Option<&TilePos>, to: Option<&TilePos>) -> u32 {
+    match (from, to) {
+        (Some(from), Some(to)) => from.x.abs_diff(to.x) + from.y.abs_diff(to.y),
+        _ => 0,
     }
 }
 
-/// component to be attached for receiver
-#[derive(Component, Debug)]
+/// Items on their way to a requester
+#[derive(Debug, Clone, PartialEq, Reflect)]
+pub struct Shipment {
+    pub item: ItemEntry,
+    pub from: Entity,
+    /// simulation tick the shipment left the provider
+    pub dispatched_at: u64,
+    /// simulation tick the shipment reaches the requester
+    pub arrives_at: u64,
+}
+
+/// component to be attached for receiver, shipments in transit to its inventory
+#[derive(Component, Debug, Default)]
 #[require(Inventory)]
-pub struct TransportInbound(pub Vec<ItemEntry>);
+pub struct TransportInbound(pub Vec<Shipment>);
+
+impl TransportInbound {
+    /// Quantity of `item` in transit
+    pub fn count(&self, item: &ItemId) -> usize {
+        self.0
+            .iter()
+            .filter(|shipment| shipment.item.item == *item)
+            .map(|shipment| shipment.item.quantity)
+            .sum()
+    }
+}
 
 /// Items requested for tranfer, acts like filter.
-/// this is created when the entity is created or is refreshed
-/// if an entity is created pointing to this
+/// The requester is kept stocked with the quantity of each entry, counting what is in transit
 #[derive(Component, Debug, Default)]
 pub struct TransportDemand(pub Vec<ItemEntry>);
 
-/// storage buffer of entity
-//#[derive(Component, Debug, Default)]
-//pub struct Inventory(pub Vec<ItemEntry>);
-
-// at this part of the lifecycle, a source entity is given a demand which is a combination of
-// different entities requesting different items
-// after checking availability, item is subtracted from inventory and committed to the receiver entity as a TransportInbound component
-//pub fn initiate_transfer(
-//    mut cmd: Commands,
-//    transport: Query<(Entity, &Inventory, &TransportDemand)>,
-//) {
-//    for (source, inventory, demand) in transport.iter() {
-//        for (receiver, item) in demand.0.iter() {
-//            if let Some(index) = inventory
-//                .0
-//                .iter()
-//                .position(|(item_inventory, quantity)| item == item_inventory && *quantity > 0)
-//            {
-//                // finally subtract one item from inventory
-//                cmd.entity(source)
-//                    .entry::<Inventory>()
-//                    .and_modify(move |mut inventory| {
-//                        inventory.0.get_mut(index).unwrap().1 -= 1;
-//                    });
-//
-//                // confront receiver entity
-//                let item = item.clone();
-//                cmd.entity(*receiver)
-//                    .entry::<TransportInbound>()
-//                    .and_modify(|mut inbound| inbound.0.push(item));
-//            }
-//        }
-//    }
-//}
-
-#[derive(Event)]
+/// Offers its inventory to the network
+#[derive(Component, Debug, Default)]
+#[require(Inventory)]
+pub struct TransportProvider;
+
+/// Pulls the items of its [`TransportDemand`] from the network
+#[derive(Component, Debug, Default)]
+#[require(TransportDemand, TransportInbound)]
+pub struct TransportRequester;
+
+/// Joins the targeted entity to the network, an entity is either a provider or a requester
+///
+/// ```ignore
+/// cmd.trigger_targets(Transport::Provider, entity);
+/// ```
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Transport {
     Provider,
     Requester,
 }
 
-pub fn handle_transport(trigger: Trigger<Transport>) {}
+pub fn handle_transport(trigger: Trigger<Transport>, mut cmd: Commands) {
+    let entity = trigger.entity();
+    if entity == Entity::PLACEHOLDER {
+        warn!(
+            "{:?} was triggered without a target entity",
+            trigger.event()
+        );
+        return;
+    }
+
+    match trigger.event() {
+        Transport::Provider => {
+            cmd.entity(entity)
+                .remove::<TransportRequester>()
+                .insert(TransportProvider);
+        }
+        Transport::Requester => {
+            cmd.entity(entity)
+                .remove::<TransportProvider>()
+                .insert(TransportRequester);
+        }
+    }
+}
+
+/// Quantity of a demanded item neither stocked nor in transit
+#[derive(Debug, Clone, PartialEq)]
+pub struct PendingDemand {
+    pub requester: Entity,
+    pub item: ItemEntry,
+}
+
+/// What each requester still misses, sorted by requester
+pub fn pending_demands<'a>(
+    requesters: impl Iterator<
+        Item = (
+            Entity,
+            &'a TransportDemand,
+            &'a TransportInbound,
+            &'a Inventory,
+        ),
+    >,
+) -> Vec<PendingDemand> {
+    let mut pending = requesters
+        .flat_map(|(requester, demand, inbound, inventory)| {
+            demand.0.iter().filter_map(move |wanted| {
+                let held = inventory.count(&wanted.item) + inbound.count(&wanted.item);
+                (held < wanted.quantity).then(|| PendingDemand {
+                    requester,
+                    item: ItemEntry {
+                        item: wanted.item.clone(),
+                        quantity: wanted.quantity - held,
+                    },
+                })
+            })
+        })
+        .collect::<Vec<_>>();
+    pending.sort_by_key(|pending| pending.requester);
+    pending
+}
+
+type ProviderQuery<'w, 's> = Query<
+    'w,
+    's,
+    (Entity, &'static mut Inventory, Option<&'static TilePos>),
+    (With<TransportProvider>, Without<TransportRequester>),
+>;
+
+type RequesterQuery<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static TransportDemand,
+        &'static mut TransportInbound,
+        &'static Inventory,
+        Option<&'static TilePos>,
+    ),
+    (With<TransportRequester>, Without<TransportProvider>),
+>;
+
+/// Matches pending demands with the nearest providers holding the item. Entities are visited
+/// in order so the same level dispatches the same shipments
+pub fn dispatch_shipments(
+    time: Res<SimulationTime>,
+    settings: Res<LogisticsSettings>,
+    mut providers: ProviderQuery,
+    mut requesters: RequesterQuery,
+) {
+    let pending = pending_demands(
+        requesters
+            .iter()
+            .map(|(requester, demand, inbound, inventory, _)| {
+                (requester, demand, inbound, inventory)
+            }),
+    );
+    if pending.is_empty() {
+        return;
+    }
+
+    let mut provider_positions = providers
+        .iter()
+        .map(|(provider, _, position)| (provider, position.copied()))
+        .collect::<Vec<_>>();
+    provider_positions.sort_by_key(|(provider, _)| *provider);
+
+    for PendingDemand { requester, item } in pending {
+        let Ok((_, _, mut inbound, _, to)) = requesters.get_mut(requester) else {
+            continue;
+        };
+        let to = to.copied();
+
+        let mut nearest = provider_positions.clone();
+        nearest.sort_by_key(|(_, from)| distance(from.as_ref(), to.as_ref()));
+
+        let mut missing = item.quantity;
+        for (provider, from) in nearest {
+            let Ok((_, mut inventory, _)) = providers.get_mut(provider) else {
+                continue;
+            };
+
+            let quantity = inventory.count(&item.item).min(missing);
+            if quantity == 0 {
+                continue;
+            }
+
+            let shipped = ItemEntry {
+                item: item.item.clone(),
+                quantity,
+            };
+            inventory.remove(&shipped);
+            inbound.0.push(Shipment {
+                item: shipped,
+                from: provider,
+                dispatched_at: time.tick,
+                arrives_at: time.tick + settings.latency(from.as_ref(), to.as_ref()),
+            });
+
+            missing -= quantity;
+            if missing == 0 {
+                break;
+            }
+        }
+    }
+}
+
+/// Unloads arrived shipments into the requester's inventory, what doesn't fit waits at the door
+pub fn deliver_shipments(
+    time: Res<SimulationTime>,
+    items: Res<ItemRegistry>,
+    mut requesters: Query<(&mut TransportInbound, &mut Inventory)>,
+) {
+    let stack_size = stack_size_of(&items);
 
-pub fn observers(mut cmd: Commands) {
-    let entity_1 = cmd.spawn_empty().id();
+    for (mut inbound, mut inventory) in requesters.iter_mut() {
+        if inbound
+            .0
+            .iter()
+            .all(|shipment| shipment.arrives_at > time.tick)
+        {
+            continue;
+        }
 
-    let entity_2 = cmd.spawn(Observer::new(handle_transport).with_entity(entity_1));
+        inbound.0.retain_mut(|shipment| {
+            if shipment.arrives_at > time.tick {
+                return true;
+            }
+
+            let size = stack_size(&shipment.item.item);
+            match inventory.insert(shipment.item.clone(), size) {
+                Some(leftover) => {
+                    shipment.item = leftover;
+                    true
+                }
+                None => false,
+            }
+        });
+    }
+}
+
+#[derive(Component)]
+pub struct PendingDemandsText;
+
+pub fn spawn_logistics_window(
+    mut cmd: Commands,
+    hud_backdrop: HUDBackdropQuery,
+    ui: Res<UiAssets>,
+    fonts: Res<FontAssets>,
+) {
+    cmd.entity(hud_backdrop.single()).with_children(|parent| {
+        spawn_window(
+            parent,
+            (),
+            (),
+            &ui,
+            &fonts,
+            WindowMeta::new("logistics".into(), 400., 9. / 16.),
+            |parent| {
+                parent
+                    .spawn((
+                        Node {
+                            margin: UiRect::all(Val::Px(UI_SCALE)),
+                            flex_direction: FlexDirection::Column,
+                            row_gap: Val::Px(UI_SCALE),
+                            ..default()
+                        },
+                        Scrollable,
+                    ))
+                    .with_children(|parent| {
+                        section_text("pending demands", parent, &fonts);
+                        body_text("", parent, &fonts).insert(PendingDemandsText);
+                        section_text("infinite io throughput", parent, &fonts);
+                        body_text("", parent, &fonts).insert(ThroughputText);
+                    });
+            },
+        );
+    });
+}
+
+fn update_pending_demands_text(
+    requesters: Query<(Entity, &TransportDemand, &TransportInbound, &Inventory)>,
+    mut text: Query<&mut Text, With<PendingDemandsText>>,
+) {
+    let pending = pending_demands(requesters.iter());
+    let summary = if pending.is_empty() {
+        "none".to_string()
+    } else {
+        pending
+            .iter()
+            .map(|pending| {
+                format!(
+                    "{} wants {} {}",
+                    pending.requester, pending.item.quantity, pending.item.item.0
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    for mut text in text.iter_mut() {
+        if text.0 != summary {
+            text.0 = summary.clone();
+        }
+    }
+}
+
+/// Lines from provider to requester with a dot for how far each shipment travelled
+fn draw_shipments(
+    mut gizmos: Gizmos,
+    time: Res<SimulationTime>,
+    building_tilemap: BuildingTilemapQuery,
+    positions: Query<&TilePos>,
+    requesters: Query<(Entity, &TransportInbound)>,
+) {
+    let Ok((_, _, grid_size, map_type, _, transform)) = building_tilemap.get_single() else {
+        return;
+    };
+    let world = |entity: Entity| {
+        positions.get(entity).ok().map(|position| {
+            transform.translation.truncate() + position.center_in_world(grid_size, map_type)
+        })
+    };
+
+    for (requester, inbound) in requesters.iter() {
+        let Some(to) = world(requester) else {
+            continue;
+        };
+
+        for shipment in inbound.0.iter() {
+            let Some(from) = world(shipment.from) else {
+                continue;
+            };
+
+            let travel = shipment
+                .arrives_at
+                .saturating_sub(shipment.dispatched_at)
+                .max(1);
+            let travelled = time.tick.saturating_sub(shipment.dispatched_at).min(travel);
+            let at = from.lerp(to, travelled as f32 / travel as f32);
+
+            gizmos.line_2d(from, to, Color::srgba(1., 0.8, 0.2, 0.4));
+            gizmos.circle_2d(at, 3., Color::srgb(1., 0.8, 0.2));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InGameState;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn entry(item: &'static str, quantity: usize) -> ItemEntry {
+        ItemEntry {
+            item: item.into(),
+            quantity,
+        }
+    }
+
+    /// Headless app running one simulation tick per update, shipments take 2 ticks plus
+    /// 1 per tile
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(
+                SimulationSettings::default().tick(),
+            ))
+            .insert_state(GameState::Playing)
+            .insert_state(crate::DeveloperMode(false))
+            .add_sub_state::<InGameState>()
+            .init_resource::<ItemRegistry>()
+            .insert_resource(LogisticsSettings {
+                base_latency: 2,
+                ticks_per_tile: 1,
+            })
+            .add_plugins((SimulationPlugin, TransportPlugin));
+        // the first update has no frame time
+        app.update();
+        app
+    }
+
+    fn provider(app: &mut App, items: Vec<ItemEntry>, position: TilePos) -> Entity {
+        let mut inventory = Inventory::with_capacity(4);
+        inventory.dump(items);
+        let provider = app.world_mut().spawn((inventory, position)).id();
+        app.world_mut()
+            .trigger_targets(Transport::Provider, provider);
+        provider
+    }
+
+    fn requester(app: &mut App, demand: Vec<ItemEntry>, position: TilePos) -> Entity {
+        let requester = app
+            .world_mut()
+            .spawn((
+                Inventory::with_capacity(2),
+                TransportDemand(demand),
+                position,
+            ))
+            .id();
+        app.world_mut()
+            .trigger_targets(Transport::Requester, requester);
+        requester
+    }
+
+    fn count(app: &App, entity: Entity, item: &'static str) -> usize {
+        app.world()
+            .get::<Inventory>(entity)
+            .unwrap()
+            .count(&item.into())
+    }
+
+    fn update(app: &mut App, times: usize) {
+        for _ in 0..times {
+            app.update();
+        }
+    }
+
+    #[test]
+    fn shipments_arrive_after_latency() {
+        let mut app = app();
+        let provider = provider(
+            &mut app,
+            vec![entry("tyconic::cheese_wheel", 8)],
+            TilePos { x: 0, y: 0 },
+        );
+        let requester = requester(
+            &mut app,
+            vec![entry("tyconic::cheese_wheel", 5)],
+            TilePos { x: 2, y: 1 },
+        );
+
+        update(&mut app, 1);
+        assert_eq!(count(&app, provider, "tyconic::cheese_wheel"), 3);
+        let inbound = app.world().get::<TransportInbound>(requester).unwrap();
+        assert_eq!(inbound.count(&"tyconic::cheese_wheel".into()), 5);
+        assert_eq!(inbound.0[0].arrives_at - inbound.0[0].dispatched_at, 5);
+
+        // in transit counts towards the demand
+        update(&mut app, 4);
+        assert_eq!(count(&app, requester, "tyconic::cheese_wheel"), 0);
+        assert_eq!(count(&app, provider, "tyconic::cheese_wheel"), 3);
+
+        update(&mut app, 1);
+        assert_eq!(count(&app, requester, "tyconic::cheese_wheel"), 5);
+        assert!(app
+            .world()
+            .get::<TransportInbound>(requester)
+            .unwrap()
+            .0
+            .is_empty());
+    }
+
+    #[test]
+    fn nearest_provider_first() {
+        let mut app = app();
+        let far = provider(
+            &mut app,
+            vec![entry("tyconic::bread_loaf", 10)],
+            TilePos { x: 9, y: 9 },
+        );
+        let near = provider(
+            &mut app,
+            vec![entry("tyconic::bread_loaf", 3)],
+            TilePos { x: 1, y: 0 },
+        );
+        let requester = requester(
+            &mut app,
+            vec![entry("tyconic::bread_loaf", 5)],
+            TilePos { x: 0, y: 0 },
+        );
+
+        update(&mut app, 1);
+        assert_eq!(count(&app, near, "tyconic::bread_loaf"), 0);
+        assert_eq!(count(&app, far, "tyconic::bread_loaf"), 8);
+
+        let inbound = app.world().get::<TransportInbound>(requester).unwrap();
+        assert_eq!(
+            inbound
+                .0
+                .iter()
+                .map(|shipment| (shipment.from, shipment.item.quantity))
+                .collect::<Vec<_>>(),
+            vec![(near, 3), (far, 2)]
+        );
+    }
+
+    #[test]
+    fn unmet_demands_stay_pending() {
+        let mut app = app();
+        provider(
+            &mut app,
+            vec![entry("tyconic::cheese_wheel", 2)],
+            TilePos { x: 0, y: 0 },
+        );
+        let requester = requester(
+            &mut app,
+            vec![
+                entry("tyconic::cheese_wheel", 4),
+                entry("tyconic::pizza_slice", 1),
+            ],
+            TilePos { x: 0, y: 0 },
+        );
+
+        update(&mut app, 5);
+        assert_eq!(count(&app, requester, "tyconic::cheese_wheel"), 2);
+
+        let pending = pending_demands(
+            app.world_mut()
+                .query::<(Entity, &TransportDemand, &TransportInbound, &Inventory)>()
+                .iter(app.world()),
+        );
+        assert_eq!(
+            pending,
+            vec![
+                PendingDemand {
+                    requester,
+                    item: entry("tyconic::cheese_wheel", 2),
+                },
+                PendingDemand {
+                    requester,
+                    item: entry("tyconic::pizza_slice", 1),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn full_requester_keeps_shipment() {
+        let mut app = app();
+        provider(
+            &mut app,
+            vec![entry("tyconic::pork_slab", 30)],
+            TilePos { x: 0, y: 0 },
+        );
+        // two slots of the default stack size hold 20
+        let requester = requester(
+            &mut app,
+            vec![entry("tyconic::pork_slab", 30)],
+            TilePos { x: 0, y: 0 },
+        );
+
+        update(&mut app, 5);
+        assert_eq!(count(&app, requester, "tyconic::pork_slab"), 20);
+        let inbound = app.world().get::<TransportInbound>(requester).unwrap();
+        assert_eq!(inbound.count(&"tyconic::pork_slab".into()), 10);
+
+        app.world_mut()
+            .get_mut::<Inventory>(requester)
+            .unwrap()
+            .remove(&entry("tyconic::pork_slab", 10));
+        update(&mut app, 1);
+        assert_eq!(count(&app, requester, "tyconic::pork_slab"), 20);
+        assert_eq!(
+            app.world()
+                .get::<TransportInbound>(requester)
+                .unwrap()
+                .count(&"tyconic::pork_slab".into()),
+            0
+        );
+    }
+
+    #[test]
+    fn switching_roles() {
+        let mut app = app();
+        let entity = provider(&mut app, vec![], TilePos { x: 0, y: 0 });
+        app.world_mut()
+            .trigger_targets(Transport::Requester, entity);
+        app.world_mut().flush();
+
+        assert!(app.world().get::<TransportProvider>(entity).is_none());
+        assert!(app.world().get::<TransportRequester>(entity).is_some());
+        assert!(app.world().get::<TransportDemand>(entity).is_some());
+    }
 }
@@ -0,0 +1,567 @@
+//! Metrics of a level, the values research and achievement conditions are checked against.
+//!
+//! Metrics are named after what they measure:
+//! - level resources go by their bare name, such as `money`
+//! - item metrics are the namespaced item id, [`METRIC_SEPARATOR`] and a statistic, such as
+//!   `tyconic::cheese_wheel__total_produced` or `tyconic::cheese_wheel__produced_per_minute`
+//! - metrics registered by mods are namespaced ids of their own, such as
+//!   `tyconic::customers_served`
+
+use crate::levels::*;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::{fmt, str::FromStr};
+
+pub struct MetricsPlugin;
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LevelMetrics>()
+            .add_event::<ItemFlow>()
+            .add_systems(
+                Simulation,
+                track_item_flows
+                    .after(advance_level_tick)
+                    .after(run_crafting_machines)
+                    .after(run_infinite_io),
+            );
+    }
+}
+
+/// Separates the item id from the statistic in `tyconic::cheese_wheel__total_produced`
+pub const METRIC_SEPARATOR: &str = "__";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum ItemFlowKind {
+    /// output of a craft
+    Produced,
+    /// ingredient of a craft
+    Consumed,
+    /// destroyed without being used, such as by a sink
+    Wasted,
+}
+
+impl ItemFlowKind {
+    pub const ALL: [Self; 3] = [Self::Produced, Self::Consumed, Self::Wasted];
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Produced => "produced",
+            Self::Consumed => "consumed",
+            Self::Wasted => "wasted",
+        }
+    }
+}
+
+/// Sent by whatever creates or destroys items within a simulation tick
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ItemFlow {
+    pub item: ItemId,
+    pub quantity: usize,
+    pub kind: ItemFlowKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ItemStat {
+    /// `total_produced`, since the level started
+    Total(ItemFlowKind),
+    /// `produced_per_minute`, over the last minute of simulated time
+    PerMinute(ItemFlowKind),
+}
+
+impl fmt::Display for ItemStat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Total(kind) => write!(f, "total_{}", kind.as_str()),
+            Self::PerMinute(kind) => write!(f, "{}_per_minute", kind.as_str()),
+        }
+    }
+}
+
+impl FromStr for ItemStat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ItemFlowKind::ALL
+            .into_iter()
+            .flat_map(|kind| [Self::Total(kind), Self::PerMinute(kind)])
+            .find(|stat| stat.to_string() == s)
+            .ok_or(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Metric {
+    /// level resource such as `money`
+    Resource(String),
+    /// `tyconic::cheese_wheel__total_produced`
+    Item { item: ItemId, stat: ItemStat },
+    /// registered by a mod, such as `tyconic::customers_served`
+    Custom(String),
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Resource(name) | Self::Custom(name) => write!(f, "{}", name),
+            Self::Item { item, stat } => write!(f, "{}{}{}", item.0, METRIC_SEPARATOR, stat),
+        }
+    }
+}
+
+impl FromStr for Metric {
+    type Err = MetricError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some((item, stat)) = s.rsplit_once(METRIC_SEPARATOR) {
+            let stat = stat.parse().map_err(|_| MetricError::UnknownStat {
+                metric: s.to_string(),
+                stat: stat.to_string(),
+            })?;
+            item.parse::<NamespacedId>()
+                .map_err(|error| MetricError::InvalidId {
+                    metric: s.to_string(),
+                    error,
+                })?;
+
+            return Ok(Self::Item {
+                item: ItemId(item.to_string()),
+                stat,
+            });
+        }
+
+        if s.contains(NAMESPACE_SEPARATOR) {
+            s.parse::<NamespacedId>()
+                .map_err(|error| MetricError::InvalidId {
+                    metric: s.to_string(),
+                    error,
+                })?;
+            return Ok(Self::Custom(s.to_string()));
+        }
+
+        Ok(Self::Resource(s.to_string()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum MetricError {
+    /// `tyconic::cheese_wheel__total_sold`
+    UnknownStat {
+        metric: String,
+        stat: String,
+    },
+    InvalidId {
+        metric: String,
+        error: NamespaceError,
+    },
+    /// only namespaced ids which aren't item metrics can be registered
+    NotCustom(String),
+    Unregistered(String),
+    AlreadyRegistered(String),
+}
+
+impl fmt::Display for MetricError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownStat { metric, stat } => {
+                let stats = ItemFlowKind::ALL
+                    .into_iter()
+                    .flat_map(|kind| [ItemStat::Total(kind), ItemStat::PerMinute(kind)])
+                    .map(|stat| stat.to_string())
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "unknown statistic `{}` in metric `{}`, expected one of {}",
+                    stat,
+                    metric,
+                    stats.join(", ")
+                )
+            }
+            Self::InvalidId { metric, error } => {
+                write!(f, "metric `{}` is invalid. {}", metric, error)
+            }
+            Self::NotCustom(metric) => write!(
+                f,
+                "`{}` can't be registered, custom metrics are namespaced like `mod{}name`",
+                metric, NAMESPACE_SEPARATOR
+            ),
+            Self::Unregistered(metric) => write!(f, "metric `{}` isn't registered", metric),
+            Self::AlreadyRegistered(metric) => {
+                write!(f, "metric `{}` is already registered", metric)
+            }
+        }
+    }
+}
+
+/// How much of an item went through the level
+#[derive(Debug, Default, Clone, PartialEq, Reflect)]
+pub struct ItemMetrics {
+    pub produced: Throughput,
+    pub consumed: Throughput,
+    pub wasted: Throughput,
+}
+
+impl ItemMetrics {
+    /// Forgets the last minute of every flow, keeping the totals
+    pub fn restart(&mut self) {
+        self.produced.restart();
+        self.consumed.restart();
+        self.wasted.restart();
+    }
+
+    pub fn flow(&self, kind: ItemFlowKind) -> &Throughput {
+        match kind {
+            ItemFlowKind::Produced => &self.produced,
+            ItemFlowKind::Consumed => &self.consumed,
+            ItemFlowKind::Wasted => &self.wasted,
+        }
+    }
+
+    fn flow_mut(&mut self, kind: ItemFlowKind) -> &mut Throughput {
+        match kind {
+            ItemFlowKind::Produced => &mut self.produced,
+            ItemFlowKind::Consumed => &mut self.consumed,
+            ItemFlowKind::Wasted => &mut self.wasted,
+        }
+    }
+}
+
+/// Tracked on the [`Level`], next to its resources
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+pub struct LevelMetrics {
+    pub items: HashMap<ItemId, ItemMetrics>,
+    custom: HashMap<String, f32>,
+}
+
+impl LevelMetrics {
+    pub fn restore(items: HashMap<ItemId, ItemMetrics>, custom: HashMap<String, f32>) -> Self {
+        Self { items, custom }
+    }
+
+    /// Metrics registered by mods with their value
+    pub fn custom(&self) -> &HashMap<String, f32> {
+        &self.custom
+    }
+
+    /// Records the flows of a tick, only items that moved get a sample. Rates of the others
+    /// drop when read since they only count samples of the last minute
+    pub fn track<'a>(
+        &mut self,
+        flows: impl Iterator<Item = &'a ItemFlow>,
+        tick: u64,
+        settings: &SimulationSettings,
+    ) {
+        let mut moved = HashMap::<(ItemId, ItemFlowKind), usize>::default();
+        for flow in flows {
+            *moved.entry((flow.item.clone(), flow.kind)).or_default() += flow.quantity;
+        }
+
+        for ((item, kind), quantity) in moved {
+            self.items
+                .entry(item)
+                .or_default()
+                .flow_mut(kind)
+                .record(tick, quantity, settings);
+        }
+    }
+
+    /// Makes a mod's own metric available to conditions, starting at 0
+    pub fn register(&mut self, metric: &str) -> Result<(), MetricError> {
+        if !matches!(metric.parse::<Metric>()?, Metric::Custom(_)) {
+            return Err(MetricError::NotCustom(metric.to_string()));
+        }
+        if self.custom.contains_key(metric) {
+            return Err(MetricError::AlreadyRegistered(metric.to_string()));
+        }
+
+        self.custom.insert(metric.to_string(), 0.);
+        Ok(())
+    }
+
+    pub fn set(&mut self, metric: &str, value: f32) -> Result<(), MetricError> {
+        let current = self
+            .custom
+            .get_mut(metric)
+            .ok_or_else(|| MetricError::Unregistered(metric.to_string()))?;
+        *current = value;
+        Ok(())
+    }
+
+    pub fn add(&mut self, metric: &str, delta: f32) -> Result<(), MetricError> {
+        let current = self
+            .custom
+            .get_mut(metric)
+            .ok_or_else(|| MetricError::Unregistered(metric.to_string()))?;
+        *current += delta;
+        Ok(())
+    }
+
+    /// Current value of a metric, none for resources and custom metrics never seen. Items that
+    /// never moved read as 0
+    pub fn get(
+        &self,
+        metric: &Metric,
+        level: &Level,
+        settings: &SimulationSettings,
+    ) -> Option<f32> {
+        match metric {
+            Metric::Resource(name) => level.resources.get(name).copied(),
+            Metric::Custom(name) => self.custom.get(name).copied(),
+            Metric::Item { item, stat } => {
+                let Some(metrics) = self.items.get(item) else {
+                    return Some(0.);
+                };
+                Some(match stat {
+                    ItemStat::Total(kind) => metrics.flow(*kind).total as f32,
+                    ItemStat::PerMinute(kind) => {
+                        metrics.flow(*kind).per_minute(level.tick, settings)
+                    }
+                })
+            }
+        }
+    }
+
+    /// Every metric by name, as read by [`crate::evaluate_condition`]
+    pub fn tracked(
+        &self,
+        level: &Level,
+        settings: &SimulationSettings,
+    ) -> std::collections::HashMap<String, f32> {
+        let resources = level
+            .resources
+            .iter()
+            .map(|(name, value)| (name.clone(), *value));
+        let custom = self
+            .custom
+            .iter()
+            .map(|(name, value)| (name.clone(), *value));
+        let items = self.items.iter().flat_map(|(item, metrics)| {
+            ItemFlowKind::ALL.into_iter().flat_map(move |kind| {
+                let flow = metrics.flow(kind);
+                [
+                    (
+                        Metric::Item {
+                            item: item.clone(),
+                            stat: ItemStat::Total(kind),
+                        },
+                        flow.total as f32,
+                    ),
+                    (
+                        Metric::Item {
+                            item: item.clone(),
+                            stat: ItemStat::PerMinute(kind),
+                        },
+                        flow.per_minute(level.tick, settings),
+                    ),
+                ]
+                .map(|(metric, value)| (metric.to_string(), value))
+            })
+        });
+
+        resources.chain(custom).chain(items).collect()
+    }
+}
+
+pub fn track_item_flows(
+    settings: Res<SimulationSettings>,
+    mut flows: EventReader<ItemFlow>,
+    mut level: Query<(&Level, &mut LevelMetrics)>,
+) {
+    // leaves `Changed<LevelMetrics>` to ticks items moved on
+    if flows.is_empty() {
+        return;
+    }
+    let Ok((level, mut metrics)) = level.get_single_mut() else {
+        flows.clear();
+        return;
+    };
+
+    metrics.track(flows.read(), level.tick, &settings);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InGameState;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    fn entry(item: &'static str, quantity: usize) -> ItemEntry {
+        ItemEntry {
+            item: item.into(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn metric_names() {
+        for name in [
+            "money",
+            "tyconic::cheese_wheel__total_produced",
+            "tyconic::cheese_wheel__wasted_per_minute",
+            "tyconic::customers_served",
+        ] {
+            assert_eq!(name.parse::<Metric>().unwrap().to_string(), name);
+        }
+
+        assert_eq!(
+            "tyconic::kitchen_waste__total_consumed".parse::<Metric>(),
+            Ok(Metric::Item {
+                item: "tyconic::kitchen_waste".into(),
+                stat: ItemStat::Total(ItemFlowKind::Consumed),
+            })
+        );
+        assert!(matches!(
+            "tyconic::cheese_wheel__total_sold".parse::<Metric>(),
+            Err(MetricError::UnknownStat { stat, .. }) if stat == "total_sold"
+        ));
+        assert!(matches!(
+            "cheese_wheel__total_produced".parse::<Metric>(),
+            Err(MetricError::InvalidId { .. })
+        ));
+    }
+
+    #[test]
+    fn custom_metrics_registered_by_mods() {
+        let mut metrics = LevelMetrics::default();
+        let level = Level::default();
+        let settings = SimulationSettings::default();
+
+        assert_eq!(
+            metrics.add("tyconic::customers_served", 1.),
+            Err(MetricError::Unregistered(
+                "tyconic::customers_served".into()
+            ))
+        );
+        metrics.register("tyconic::customers_served").unwrap();
+        metrics.add("tyconic::customers_served", 3.).unwrap();
+        assert_eq!(
+            metrics.get(
+                &"tyconic::customers_served".parse().unwrap(),
+                &level,
+                &settings
+            ),
+            Some(3.)
+        );
+
+        assert_eq!(
+            metrics.register("tyconic::customers_served"),
+            Err(MetricError::AlreadyRegistered(
+                "tyconic::customers_served".into()
+            ))
+        );
+        assert_eq!(
+            metrics.register("money"),
+            Err(MetricError::NotCustom("money".into()))
+        );
+        assert_eq!(
+            metrics.register("tyconic::pizza_slice__total_produced"),
+            Err(MetricError::NotCustom(
+                "tyconic::pizza_slice__total_produced".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn only_moved_items_are_sampled() {
+        let mut metrics = LevelMetrics::default();
+        let settings = SimulationSettings::default();
+        let produced = ItemFlow {
+            item: "tyconic::cheese_wheel".into(),
+            quantity: 2,
+            kind: ItemFlowKind::Produced,
+        };
+
+        metrics.track([&produced].into_iter(), 0, &settings);
+        for tick in 1..10 {
+            metrics.track(std::iter::empty(), tick, &settings);
+        }
+
+        let cheese = &metrics.items[&ItemId::from("tyconic::cheese_wheel")];
+        assert_eq!(cheese.produced.total, 2);
+        assert_eq!(cheese.consumed, Throughput::default());
+        assert_eq!(metrics.items.len(), 1);
+
+        // rates drop once the sample is over a minute old, without samples in between
+        let mut level = Level {
+            tick: Throughput::window(&settings) + 5,
+            ..default()
+        };
+        let rate = "tyconic::cheese_wheel__produced_per_minute"
+            .parse()
+            .unwrap();
+        assert_eq!(metrics.get(&rate, &level, &settings), Some(0.));
+        level.tick = 5;
+        assert!(metrics.get(&rate, &level, &settings).unwrap() > 0.);
+
+        let never_moved = "tyconic::bread_loaf__total_consumed".parse().unwrap();
+        assert_eq!(metrics.get(&never_moved, &level, &settings), Some(0.));
+    }
+
+    #[test]
+    fn crafting_feeds_metrics() {
+        let mut recipes = Merged::<RecipeDeclared>::default();
+        recipes.apply(
+            "tyconic",
+            Declaration::Declare(RecipeDeclared {
+                id: RecipeId("tyconic::folk_pizza".into()),
+                recipe: Recipe {
+                    ingredients: vec![entry("tyconic::cheese_wheel", 1)],
+                    output: vec![entry("tyconic::pizza_slice", 4)],
+                    research_required: vec![],
+                    duration: 1000,
+                },
+            }),
+        );
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .insert_resource(RecipeRegistry::from(recipes))
+            .init_resource::<ItemRegistry>()
+            .add_plugins((SimulationPlugin, CraftingPlugin, MetricsPlugin));
+
+        let mut level = Level::default();
+        level.resources.insert("money".into(), 150.);
+        let level = app.world_mut().spawn(level).id();
+
+        let mut inventory = Inventory::with_capacity(4);
+        inventory.dump(vec![entry("tyconic::cheese_wheel", 3)]);
+        app.world_mut().spawn((
+            CraftingMachine::new(RecipeId("tyconic::folk_pizza".into())),
+            inventory,
+        ));
+
+        // a bit over 3 seconds, enough for all 3 crafts
+        for _ in 0..35 {
+            app.update();
+        }
+
+        let world = app.world();
+        let tracked = world.get::<LevelMetrics>(level).unwrap().tracked(
+            world.get::<Level>(level).unwrap(),
+            world.resource::<SimulationSettings>(),
+        );
+
+        assert_eq!(tracked["money"], 150.);
+        assert_eq!(tracked["tyconic::pizza_slice__total_produced"], 12.);
+        assert_eq!(tracked["tyconic::cheese_wheel__total_consumed"], 3.);
+        assert_eq!(tracked["tyconic::cheese_wheel__total_produced"], 0.);
+        // 3 crafts within the first 3.4 seconds extrapolate to about 53 a minute
+        let per_minute = tracked["tyconic::cheese_wheel__consumed_per_minute"];
+        assert!((50. ..56.).contains(&per_minute), "{}", per_minute);
+
+        assert!(crate::evaluate_condition(
+            &ConditionFlag::ReachedMetric {
+                metric: "tyconic::pizza_slice__total_produced".into(),
+                value: 12.,
+            },
+            &tracked
+        ));
+    }
+}
@@ -1,21 +1,36 @@
+mod autosave;
 mod chunks;
 mod config;
+mod crafting;
 mod editor;
 mod logistics;
+mod metrics;
 mod mini_game;
 mod pack;
+mod save;
+mod simulation;
 
 use crate::GameState;
 use bevy::prelude::*;
+use bevy::reflect::serde::{ReflectDeserializer, ReflectSerializer};
+use bevy::reflect::TypeRegistry;
 use bevy::utils::HashMap;
+use serde::de::DeserializeSeed;
+use std::fs;
+use std::path::Path;
 use std::time::*;
 
+pub use autosave::*;
 pub use chunks::*;
 pub use config::*;
+pub use crafting::*;
 pub use editor::*;
 pub use logistics::*;
+pub use metrics::*;
 pub use mini_game::*;
 pub use pack::*;
+pub use save::*;
+pub use simulation::*;
 
 pub struct LevelsPlugin;
 
@@ -25,10 +40,33 @@ impl Plugin for LevelsPlugin {
             OnEnter(GameState::Playing),
             (spawn_level, init_map, spawn_map).chain(),
         )
+        .add_systems(
+            Update,
+            tick_play_time.run_if(in_state(crate::InGameState::Normal)),
+        )
         .add_plugins((
             //ChunkPlugin,
             TransportPlugin,
-            ResearchEditorPlugin,
+            BeltPlugin,
+            ArmPlugin,
+            InfiniteIoPlugin,
+            PortsPlugin,
+            SavePlugin,
+            AutosavePlugin,
+            DeclarationsPlugin,
+            crate::loading::BuildingAssetMapPlugin,
+            SimulationPlugin,
+            CraftingPlugin,
+            MetricsPlugin,
+            ResearchPlugin,
+            AchievementsPlugin,
+            (
+                ResearchEditorPlugin,
+                ItemEditorPlugin,
+                BuildingEditorPlugin,
+                IoEditorPlugin,
+                LevelEditorPlugin,
+            ),
             //ModsMenuPlugin,
             //ToolBarPlugin,
         ));
@@ -46,9 +84,13 @@ pub struct LevelManager {
 }
 
 #[derive(Component, Reflect)]
+#[require(LevelMetrics, ResearchProgress)]
 pub struct Level {
     //pub created_at: Instant,
     pub total_play_time: Duration,
+    pub resources: HashMap<String, f32>,
+    /// simulation ticks run since the level started
+    pub tick: u64,
 }
 
 #[derive(Component, Reflect, Debug)]
@@ -153,6 +195,47 @@ impl LevelIsometric {
             .collect::<Vec<_>>()
     }
 
+    /// The level as written to its file, pretty printed through [`post_processing::level`] so
+    /// that it stays editable by hand
+    pub fn to_ron(&self, type_registry: &TypeRegistry) -> Result<String, String> {
+        let serialized = ron::ser::to_string_pretty(
+            &ReflectSerializer::new(self, type_registry),
+            ron::ser::PrettyConfig::new()
+                .depth_limit(4)
+                .indentor("  ".into()),
+        )
+        .map_err(|err| err.to_string())?;
+
+        Ok(post_processing::level(&serialized))
+    }
+
+    pub fn from_ron(ron: &str, type_registry: &TypeRegistry) -> Result<Self, String> {
+        let mut deserializer = ron::de::Deserializer::from_str(ron).map_err(|err| err.to_string())?;
+        let value = ReflectDeserializer::new(type_registry)
+            .deserialize(&mut deserializer)
+            .map_err(|err| err.to_string())?;
+
+        Self::from_reflect(&*value).ok_or_else(|| "not an isometric level".to_string())
+    }
+
+    pub fn read(path: &Path, type_registry: &TypeRegistry) -> Result<Self, String> {
+        let ron = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        Self::from_ron(&ron, type_registry)
+    }
+
+    /// Written next to the real file first so a crash can't leave it half written
+    pub fn write(&self, path: &Path, type_registry: &TypeRegistry) -> Result<(), String> {
+        let serialized = self.to_ron(type_registry)?;
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+
+        let temp = temp_path(path);
+        fs::write(&temp, serialized).map_err(|err| err.to_string())?;
+        fs::rename(&temp, path).map_err(|err| err.to_string())
+    }
+
     pub fn tiles_with_texture_index(&self) -> Result<RawMaps, ValidationError> {
         let maps: Vec<String> = self
             .surface
@@ -184,57 +267,31 @@ impl From<(Vec2, String)> for SurfaceDeclared {
     }
 }
 
+/// Characters of the legend in the order of the items they stand for, see [`legend_to_index`]
+pub const LEGEND_CHARS: [char; 38] = [
+    '0', '1', '2', '3', '4', '5', '6', '7', '8', '9', 'A', 'B', 'C', 'D', 'E', 'G', 'H', 'K', 'N',
+    'O', 'P', 'Q', 'R', 'S', 'U', 'Z', '@', '#', '$', '%', '&', '*', '+', '=', '^', '~', '?', '!',
+];
+
+/// Character of a tile left empty
+pub const BLANK_TILE: char = '_';
+
+/// Character standing for the item at `index` of the legend, none past the last character
+pub fn legend_char(index: usize) -> Option<char> {
+    LEGEND_CHARS.get(index).copied()
+}
+
+/// Index within the legend of the item `c` stands for, none for characters outside the legend
+pub fn legend_index(c: char) -> Option<usize> {
+    LEGEND_CHARS.iter().position(|legend| *legend == c)
+}
+
+/// Index within the legend of the item `c` stands for, [`usize::MAX`] for a [`BLANK_TILE`]
 fn legend_to_index(c: char) -> usize {
-    match c {
-        // Digits first (indices 0–9)
-        '0' => 0,
-        '1' => 1,
-        '2' => 2,
-        '3' => 3,
-        '4' => 4,
-        '5' => 5,
-        '6' => 6,
-        '7' => 7,
-        '8' => 8,
-        '9' => 9,
-
-        // Letters next (indices 10+)
-        'A' => 10,
-        'B' => 11,
-        'C' => 12,
-        'D' => 13,
-        'E' => 14,
-        'G' => 15,
-        'H' => 16,
-        'K' => 17,
-        'N' => 18,
-        'O' => 19,
-        'P' => 20,
-        'Q' => 21,
-        'R' => 22,
-        'S' => 23,
-        'U' => 24,
-        'Z' => 25,
-
-        // Symbols (indices 26+)
-        '@' => 26,
-        '#' => 27,
-        '$' => 28,
-        '%' => 29,
-        '&' => 30,
-        '*' => 31,
-        '+' => 32,
-        '=' => 33,
-        '^' => 34,
-        '~' => 35,
-        '?' => 36,
-        '!' => 37,
-
-        // Blank
-        '_' => usize::MAX,
-
-        _ => panic!("Undefined tile '{}'", c),
+    if c == BLANK_TILE {
+        return usize::MAX;
     }
+    legend_index(c).unwrap_or_else(|| panic!("Undefined tile '{}'", c))
 }
 
 use bevy_ecs_tilemap::prelude::*;
@@ -284,6 +341,12 @@ fn validate_maps(maps: &[String]) -> ValidationResult {
 }
 
 fn parse_map_with_positions(map_str: &str) -> Vec<(TilePos, usize)> {
+    read_map_with_positions(map_str).unwrap_or_else(|err| panic!("{}", err))
+}
+
+/// Tiles of a map with the index within the legend of each, blanks left out. Characters outside
+/// the legend are refused
+fn read_map_with_positions(map_str: &str) -> Result<Vec<(TilePos, usize)>, String> {
     let mut output = Vec::new();
 
     // Iterate over lines with enumeration for the y coordinate.
@@ -304,18 +367,20 @@ fn parse_map_with_positions(map_str: &str) -> Vec<(TilePos, usize)> {
                     x: x as u32,
                     y: y as u32,
                 };
-                let index = legend_to_index(c);
                 // skip blanks
-                if index == usize::MAX {
+                if c == BLANK_TILE {
                     continue;
                 }
+                let index = legend_index(c).ok_or_else(|| {
+                    format!("Undefined tile '{}' at line {} column {}", c, y + 1, x + 1)
+                })?;
                 output.push((pos, index));
             } else {
                 error!("Empty token encountered at line {} column {}", y, x);
             }
         }
     }
-    output
+    Ok(output)
 }
 
 #[derive(Component)]
@@ -329,10 +394,19 @@ impl Default for Level {
         Self {
             //created_at: Instant::now(),
             total_play_time: Duration::from_secs(1),
+            resources: HashMap::default(),
+            tick: 0,
         }
     }
 }
 
+/// Play time only accumulates while the simulation is running
+pub fn tick_play_time(time: Res<Time>, mut level: Query<&mut Level>) {
+    for mut level in level.iter_mut() {
+        level.bypass_change_detection().total_play_time += time.delta();
+    }
+}
+
 pub fn spawn_level(mut cmd: Commands) {
     cmd.spawn((
         Level::default(),
@@ -856,6 +930,16 @@ _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ | _ |
         assert!(result.is_err(), "Expected panic on undefined symbol");
     }
 
+    #[test]
+    fn legend_chars_follow_legend_indices() {
+        for (index, c) in LEGEND_CHARS.iter().enumerate() {
+            assert_eq!(legend_to_index(*c), index);
+            assert_eq!(legend_char(index), Some(*c));
+        }
+        assert_eq!(legend_to_index(BLANK_TILE), usize::MAX);
+        assert_eq!(legend_char(LEGEND_CHARS.len()), None);
+    }
+
     #[test]
     fn test_parse_map_with_positions() {
         let map_str = r#"_ | A | 1 | G | %
@@ -0,0 +1,552 @@
+//! Buildings placed from items, as defined by mods in `derivations/buildings.ron`.
+//! A building covers a footprint of tiles, faces one of the directions it allows and is spawned
+//! with the components making it work.
+
+use crate::loading::BuildingAssetSource;
+use crate::*;
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use bevy::reflect::TypeRegistry;
+use bevy::utils::HashMap;
+use bevy_ecs_tilemap::prelude::*;
+use std::fmt;
+use std::path::Path;
+
+pub const BUILDINGS_RON: &str = "derivations/buildings.ron";
+
+/// Separates the id from the state in sprite names, like `auto_arm--broken.png`
+pub const STATE_SEPARATOR: &str = "--";
+/// Sprite of a building facing away from the camera, south or west
+pub const BACK_FACING: &str = "back-facing";
+pub const BROKEN: &str = "broken";
+
+/// Surface a building has to stand on
+#[derive(Reflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PlacementRule {
+    #[default]
+    Anywhere,
+    /// on the floor, not on a countertop
+    FloorOnly,
+    CountertopOnly,
+}
+
+impl PlacementRule {
+    pub const ALL: [Self; 3] = [Self::Anywhere, Self::FloorOnly, Self::CountertopOnly];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Anywhere => "anywhere",
+            Self::FloorOnly => "floor only",
+            Self::CountertopOnly => "countertop only",
+        }
+    }
+}
+
+/// Component a building is spawned with
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum BuildingComponent {
+    /// inventory of so many slots
+    Inventory(usize),
+    /// crafts the recipe, one is picked in game when `None`
+    Crafter(Option<RecipeId>),
+    /// swings towards the facing of the building
+    AutoArm,
+    /// moves items towards the facing of the building
+    MoverBelt,
+    InfiniteIo,
+    TransportProvider,
+    TransportRequester,
+}
+
+impl BuildingComponent {
+    /// One of each kind, as added by the editor
+    pub fn kinds() -> [Self; 7] {
+        [
+            Self::Inventory(4),
+            Self::Crafter(None),
+            Self::AutoArm,
+            Self::MoverBelt,
+            Self::InfiniteIo,
+            Self::TransportProvider,
+            Self::TransportRequester,
+        ]
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Inventory(_) => "inventory",
+            Self::Crafter(_) => "crafter",
+            Self::AutoArm => "auto arm",
+            Self::MoverBelt => "mover belt",
+            Self::InfiniteIo => "infinite io",
+            Self::TransportProvider => "transport provider",
+            Self::TransportRequester => "transport requester",
+        }
+    }
+
+    pub fn same_kind(&self, other: &Self) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    pub fn insert(&self, building: &mut EntityCommands, facing: BeltDirection) {
+        match self {
+            Self::Inventory(slots) => building.insert(Inventory::with_capacity(*slots)),
+            Self::Crafter(Some(recipe)) => building.insert(CraftingMachine::new(recipe.clone())),
+            Self::Crafter(None) => building.insert(CraftingMachine::default()),
+            Self::AutoArm => building.insert(AutoArm::new(facing)),
+            Self::MoverBelt => building.insert(MoverBelt::new(facing)),
+            Self::InfiniteIo => building.insert(InfiniteIo::sink()),
+            Self::TransportProvider => building.insert(TransportProvider),
+            Self::TransportRequester => building.insert(TransportRequester),
+        };
+    }
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct BuildingDefinition {
+    /// item placing the building
+    pub item: ItemId,
+    /// tiles covered when facing north, relative to the tile the building is placed on
+    pub footprint: Vec<IVec2>,
+    /// directions the building may face, the first one unless another is asked for
+    pub rotations: Vec<BeltDirection>,
+    pub texture: BuildingAssetSource,
+    pub placement: PlacementRule,
+    pub components: Vec<BuildingComponent>,
+    /// where items go in and out, reached on every edge when empty
+    #[reflect(default)]
+    pub ports: Vec<IoPort>,
+}
+
+impl From<ItemId> for BuildingDefinition {
+    fn from(item: ItemId) -> Self {
+        Self {
+            item,
+            footprint: vec![IVec2::ZERO],
+            rotations: vec![BeltDirection::North],
+            texture: BuildingAssetSource::Infer,
+            placement: default(),
+            components: vec![],
+            ports: vec![],
+        }
+    }
+}
+
+/// Turns an offset of a footprint facing north towards `facing`, clockwise
+pub fn rotate_offset(offset: IVec2, facing: BeltDirection) -> IVec2 {
+    match facing {
+        BeltDirection::North => offset,
+        BeltDirection::East => IVec2::new(offset.y, -offset.x),
+        BeltDirection::South => -offset,
+        BeltDirection::West => IVec2::new(-offset.y, offset.x),
+    }
+}
+
+/// Key of a sprite within the [`BuildingAssetMap`], `base::auto_arm--broken` for a state
+pub fn sprite_key(item: &str, state: Option<&str>) -> String {
+    match state {
+        Some(state) => format!("{}{}{}", item, STATE_SEPARATOR, state),
+        None => item.to_string(),
+    }
+}
+
+/// What lies on a tile a building would cover
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TileSurface {
+    pub floor: bool,
+    pub countertop: bool,
+    /// another building stands there
+    pub occupied: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlacementError {
+    Facing(BeltDirection),
+    OutOfBounds,
+    Occupied(TilePos),
+    NoFloor(TilePos),
+    NoCountertop(TilePos),
+}
+
+impl fmt::Display for PlacementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Facing(facing) => write!(f, "can't face {:?}", facing),
+            Self::OutOfBounds => write!(f, "doesn't fit on the map"),
+            Self::Occupied(pos) => write!(f, "{},{} is taken", pos.x, pos.y),
+            Self::NoFloor(pos) => write!(f, "{},{} isn't floor", pos.x, pos.y),
+            Self::NoCountertop(pos) => write!(f, "{},{} isn't a countertop", pos.x, pos.y),
+        }
+    }
+}
+
+impl BuildingDefinition {
+    pub fn namespace(&mut self, namespace: Namespace) {
+        self.item.0 = namespaced(namespace, &self.item.0);
+        for component in self.components.iter_mut() {
+            if let BuildingComponent::Crafter(Some(recipe)) = component {
+                recipe.0 = namespaced(namespace, &recipe.0);
+            }
+        }
+        for port in self.ports.iter_mut() {
+            port.namespace(namespace);
+        }
+    }
+
+    /// `wanted` when the building may face it, its first rotation otherwise
+    pub fn facing(&self, wanted: Option<BeltDirection>) -> BeltDirection {
+        wanted
+            .filter(|facing| self.rotations.contains(facing))
+            .or_else(|| self.rotations.first().copied())
+            .unwrap_or_default()
+    }
+
+    pub fn has(&self, component: &BuildingComponent) -> bool {
+        self.components
+            .iter()
+            .any(|declared| declared.same_kind(component))
+    }
+
+    /// Tiles covered when placed on `origin`, which is always covered, `None` past the map
+    pub fn cells(&self, origin: TilePos, facing: BeltDirection) -> Option<Vec<TilePos>> {
+        let origin = IVec2::new(origin.x as i32, origin.y as i32);
+        let mut cells = vec![];
+        for offset in std::iter::once(IVec2::ZERO).chain(self.footprint.iter().copied()) {
+            let cell = origin + rotate_offset(offset, facing);
+            let cell = TilePos {
+                x: u32::try_from(cell.x).ok()?,
+                y: u32::try_from(cell.y).ok()?,
+            };
+            if !cells.contains(&cell) {
+                cells.push(cell);
+            }
+        }
+        Some(cells)
+    }
+
+    /// Tiles the building would cover, once every one of them allows it
+    pub fn placement(
+        &self,
+        origin: TilePos,
+        facing: BeltDirection,
+        size: &TilemapSize,
+        surface: impl Fn(TilePos) -> TileSurface,
+    ) -> Result<Vec<TilePos>, PlacementError> {
+        if !self.rotations.is_empty() && !self.rotations.contains(&facing) {
+            return Err(PlacementError::Facing(facing));
+        }
+
+        let cells = self
+            .cells(origin, facing)
+            .filter(|cells| cells.iter().all(|cell| cell.within_map_bounds(size)))
+            .ok_or(PlacementError::OutOfBounds)?;
+
+        for cell in cells.iter().copied() {
+            let tile = surface(cell);
+            if tile.occupied {
+                return Err(PlacementError::Occupied(cell));
+            }
+            match self.placement {
+                PlacementRule::Anywhere => {}
+                PlacementRule::FloorOnly if !tile.floor || tile.countertop => {
+                    return Err(PlacementError::NoFloor(cell));
+                }
+                PlacementRule::CountertopOnly if !tile.countertop => {
+                    return Err(PlacementError::NoCountertop(cell));
+                }
+                _ => {}
+            }
+        }
+
+        Ok(cells)
+    }
+
+    /// States drawn beside the sprite of the building
+    pub fn states(&self) -> &[String] {
+        match &self.texture {
+            BuildingAssetSource::InferWithStates(states) => states,
+            _ => &[],
+        }
+    }
+
+    /// Key of the sprite shown and whether it is mirrored, a building facing east or south
+    /// shows the sprite of west or north mirrored
+    pub fn sprite(&self, facing: BeltDirection, state: Option<&str>) -> (String, bool) {
+        let flip = matches!(facing, BeltDirection::East | BeltDirection::South);
+        let has_state = |state: &str| self.states().iter().any(|declared| declared == state);
+
+        let state = match state {
+            Some(state) if has_state(state) => Some(state),
+            _ if matches!(facing, BeltDirection::South | BeltDirection::West)
+                && has_state(BACK_FACING) =>
+            {
+                Some(BACK_FACING)
+            }
+            _ => None,
+        };
+
+        (sprite_key(&self.item.0, state), flip)
+    }
+
+    /// Tile of the building and the item it is built from, its sprite is set once spawned
+    pub fn bundle(
+        &self,
+        tilemap: Entity,
+        origin: TilePos,
+        facing: BeltDirection,
+    ) -> (TileBundle, Building, ItemId) {
+        (
+            TileBundle {
+                position: origin,
+                tilemap_id: TilemapId(tilemap),
+                ..default()
+            },
+            Building {
+                facing,
+                state: None,
+            },
+            self.item.clone(),
+        )
+    }
+
+    pub fn spawn(
+        &self,
+        cmd: &mut Commands,
+        tilemap: Entity,
+        origin: TilePos,
+        facing: BeltDirection,
+    ) -> Entity {
+        let mut building = cmd.spawn(self.bundle(tilemap, origin, facing));
+        for component in self.components.iter() {
+            component.insert(&mut building, facing);
+        }
+        if !self.ports.is_empty() {
+            building.insert(IoPorts::place(&self.ports, origin, facing));
+        }
+        building.id()
+    }
+}
+
+/// A tile built from a [`BuildingDefinition`], every tile of its footprint holds the building
+#[derive(Component, Reflect, Debug, Clone, PartialEq)]
+pub struct Building {
+    pub facing: BeltDirection,
+    /// sprite state shown over the facing one, like [`BROKEN`]
+    pub state: Option<String>,
+}
+
+/// Content of `derivations/buildings.ron`
+#[derive(Reflect, Debug, Clone, PartialEq, Default)]
+pub struct BuildingDefinitions(pub Vec<BuildingDefinition>);
+
+/// Buildings defined by every mod of the profile, keyed by the namespaced item placing them.
+/// A later mod defining a building for the same item replaces it.
+#[derive(Resource, Debug, Clone, Default)]
+pub struct BuildingRegistry(HashMap<String, BuildingDefinition>);
+
+impl BuildingRegistry {
+    pub fn define(&mut self, definition: BuildingDefinition) {
+        self.0.insert(definition.item.0.clone(), definition);
+    }
+
+    pub fn building(&self, item: &ItemId) -> Option<&BuildingDefinition> {
+        self.0.get(&item.0)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &BuildingDefinition> {
+        self.0.values()
+    }
+}
+
+/// Buildings of one mod as written in its `derivations/buildings.ron`, ids left as written
+pub fn read_building_definitions(
+    mod_path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<Vec<BuildingDefinition>, String> {
+    let path = mod_path.join(BUILDINGS_RON);
+    let Some(value) = read_ron_file(&path, type_registry)? else {
+        return Ok(vec![]);
+    };
+
+    BuildingDefinitions::from_reflect(&*value)
+        .map(|BuildingDefinitions(definitions)| definitions)
+        .ok_or_else(|| format!("{} holds no buildings", path.to_string_lossy()))
+}
+
+pub fn write_building_definitions(
+    mod_path: &Path,
+    definitions: &BuildingDefinitions,
+    type_registry: &TypeRegistry,
+) -> Result<(), String> {
+    write_ron_file(&mod_path.join(BUILDINGS_RON), definitions, type_registry)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counter() -> BuildingDefinition {
+        BuildingDefinition {
+            footprint: vec![IVec2::ZERO, IVec2::Y],
+            rotations: vec![BeltDirection::North, BeltDirection::East],
+            placement: PlacementRule::CountertopOnly,
+            texture: BuildingAssetSource::InferWithStates(vec![BACK_FACING.into()]),
+            ..BuildingDefinition::from(ItemId::from("tyconic::oven"))
+        }
+    }
+
+    #[test]
+    fn footprints_turn_with_the_building() {
+        let oven = counter();
+        let origin = TilePos { x: 4, y: 4 };
+
+        assert_eq!(
+            oven.cells(origin, BeltDirection::North),
+            Some(vec![origin, TilePos { x: 4, y: 5 }])
+        );
+        assert_eq!(
+            oven.cells(origin, BeltDirection::East),
+            Some(vec![origin, TilePos { x: 5, y: 4 }])
+        );
+        assert_eq!(
+            oven.cells(origin, BeltDirection::South),
+            Some(vec![origin, TilePos { x: 4, y: 3 }])
+        );
+        assert_eq!(
+            oven.cells(TilePos { x: 0, y: 0 }, BeltDirection::West),
+            None
+        );
+
+        assert_eq!(oven.facing(Some(BeltDirection::East)), BeltDirection::East);
+        assert_eq!(oven.facing(Some(BeltDirection::West)), BeltDirection::North);
+        assert_eq!(oven.facing(None), BeltDirection::North);
+    }
+
+    #[test]
+    fn placement_follows_the_rule() {
+        let oven = counter();
+        let size = TilemapSize { x: 8, y: 8 };
+        let countertop = |pos: TilePos| TileSurface {
+            floor: true,
+            countertop: pos.x == 4,
+            occupied: pos == TilePos { x: 4, y: 7 },
+        };
+
+        assert_eq!(
+            oven.placement(
+                TilePos { x: 4, y: 2 },
+                BeltDirection::North,
+                &size,
+                countertop
+            ),
+            Ok(vec![TilePos { x: 4, y: 2 }, TilePos { x: 4, y: 3 }])
+        );
+        assert_eq!(
+            oven.placement(
+                TilePos { x: 4, y: 2 },
+                BeltDirection::East,
+                &size,
+                countertop
+            ),
+            Err(PlacementError::NoCountertop(TilePos { x: 5, y: 2 }))
+        );
+        assert_eq!(
+            oven.placement(
+                TilePos { x: 4, y: 6 },
+                BeltDirection::North,
+                &size,
+                countertop
+            ),
+            Err(PlacementError::Occupied(TilePos { x: 4, y: 7 }))
+        );
+        assert_eq!(
+            oven.placement(
+                TilePos { x: 4, y: 7 },
+                BeltDirection::East,
+                &size,
+                countertop
+            ),
+            Err(PlacementError::Occupied(TilePos { x: 4, y: 7 }))
+        );
+        assert_eq!(
+            oven.placement(
+                TilePos { x: 7, y: 7 },
+                BeltDirection::North,
+                &size,
+                countertop
+            ),
+            Err(PlacementError::OutOfBounds)
+        );
+        assert_eq!(
+            oven.placement(
+                TilePos { x: 4, y: 2 },
+                BeltDirection::South,
+                &size,
+                countertop
+            ),
+            Err(PlacementError::Facing(BeltDirection::South))
+        );
+
+        let floor_only = BuildingDefinition {
+            placement: PlacementRule::FloorOnly,
+            ..oven
+        };
+        assert_eq!(
+            floor_only.placement(
+                TilePos { x: 3, y: 2 },
+                BeltDirection::East,
+                &size,
+                countertop
+            ),
+            Err(PlacementError::NoFloor(TilePos { x: 4, y: 2 }))
+        );
+    }
+
+    #[test]
+    fn sprites_follow_facing_and_state() {
+        let oven = counter();
+
+        assert_eq!(
+            oven.sprite(BeltDirection::North, None),
+            ("tyconic::oven".into(), false)
+        );
+        assert_eq!(
+            oven.sprite(BeltDirection::South, None),
+            ("tyconic::oven--back-facing".into(), true)
+        );
+        // states the building doesn't draw fall back to the facing
+        assert_eq!(
+            oven.sprite(BeltDirection::West, Some(BROKEN)),
+            ("tyconic::oven--back-facing".into(), false)
+        );
+
+        let plain = BuildingDefinition::from(ItemId::from("base::auto_arm"));
+        assert_eq!(
+            plain.sprite(BeltDirection::West, None),
+            ("base::auto_arm".into(), false)
+        );
+    }
+
+    #[test]
+    fn base_buildings_are_readable() {
+        let mut type_registry = TypeRegistry::default();
+        type_registry.register::<BuildingDefinitions>();
+
+        let definitions =
+            read_building_definitions(Path::new("assets/mods/base"), &type_registry).unwrap();
+        let mut registry = BuildingRegistry::default();
+        for mut definition in definitions {
+            definition.namespace(Namespace::new("base").unwrap());
+            registry.define(definition);
+        }
+
+        let belt = registry
+            .building(&ItemId::from("base::mover_belt"))
+            .unwrap();
+        assert!(belt.has(&BuildingComponent::MoverBelt));
+        assert_eq!(belt.rotations.len(), 4);
+        assert!(registry
+            .building(&ItemId::from("base::infinite_io"))
+            .is_some_and(|io| io.has(&BuildingComponent::InfiniteIo)));
+    }
+}
@@ -0,0 +1,1003 @@
+//! Items, recipes, research and achievements declared by every mod of the profile, merged in load order.
+//! A later mod may override, patch or delete what an earlier one declared.
+
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use bevy::reflect::serde::{ReflectDeserializer, ReflectSerializer};
+use bevy::reflect::TypeRegistry;
+use bevy::utils::HashMap;
+use serde::de::DeserializeSeed;
+use std::path::Path;
+use std::time::Duration;
+use std::{fmt, fs};
+
+pub struct DeclarationsPlugin;
+
+impl Plugin for DeclarationsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ItemPack>()
+            .register_type::<RecipePack>()
+            .register_type::<ResearchPack>()
+            .register_type::<ItemDeclarations>()
+            .register_type::<RecipeDeclarations>()
+            .register_type::<ResearchDeclarations>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeRegistry>()
+            .init_resource::<ResearchRegistry>()
+            .init_resource::<Namespaces>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                load_declarations.after(load_mods_from_profile),
+            );
+    }
+}
+
+pub const DECLARATIONS_PATH: &str = "declarations";
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct ItemDeclared {
+    pub id: ItemId,
+    pub category: String,
+    pub stack_size: usize,
+}
+
+/// Fields left `None` keep what was declared before
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct ItemPatch {
+    pub id: ItemId,
+    pub category: Option<String>,
+    pub stack_size: Option<usize>,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct RecipeDeclared {
+    pub id: RecipeId,
+    pub recipe: Recipe,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct RecipePatch {
+    pub id: RecipeId,
+    pub ingredients: Option<Vec<ItemEntry>>,
+    pub output: Option<Vec<ItemEntry>>,
+    pub research_required: Option<Vec<ResearchId>>,
+    pub duration: Option<u32>,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct ResearchPatch {
+    pub id: ResearchId,
+    pub display_name: Option<String>,
+    pub flavor_text: Option<String>,
+    pub unlock_condition: Option<ConditionFlag>,
+    pub required_research: Option<Vec<ResearchId>>,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum ItemDeclaration {
+    Declare(ItemDeclared),
+    Override(ItemDeclared),
+    Patch(ItemPatch),
+    Delete(ItemId),
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum RecipeDeclaration {
+    Declare(RecipeDeclared),
+    Override(RecipeDeclared),
+    Patch(RecipePatch),
+    Delete(RecipeId),
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum ResearchDeclaration {
+    Declare(ResearchDeclared),
+    Override(ResearchDeclared),
+    Patch(ResearchPatch),
+    Delete(ResearchId),
+}
+
+/// Content of `declarations/items.ron`. A plain [`ItemPack`] is read as declaring every id
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct ItemDeclarations(pub Vec<ItemDeclaration>);
+
+/// Content of `declarations/recipes.ron`. A plain [`RecipePack`] is read as declaring every id
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct RecipeDeclarations(pub Vec<RecipeDeclaration>);
+
+/// Content of `declarations/research.ron`. A plain [`ResearchPack`] is read as declaring every id
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct ResearchDeclarations(pub Vec<ResearchDeclaration>);
+
+impl From<ItemId> for ItemDeclared {
+    fn from(id: ItemId) -> Self {
+        Self {
+            id,
+            category: String::new(),
+            stack_size: StackSize::default().0,
+        }
+    }
+}
+
+impl From<RecipeId> for RecipeDeclared {
+    fn from(id: RecipeId) -> Self {
+        Self {
+            id,
+            recipe: Recipe {
+                ingredients: vec![],
+                output: vec![],
+                research_required: vec![],
+                duration: 0,
+            },
+        }
+    }
+}
+
+impl From<ResearchId> for ResearchDeclared {
+    fn from(id: ResearchId) -> Self {
+        Self {
+            display_name: id.0.clone(),
+            id,
+            flavor_text: String::new(),
+            unlock_condition: ConditionFlag::SatisfyAll(vec![]),
+            required_research: vec![],
+        }
+    }
+}
+
+/// Anything a mod can declare and later mods can patch
+pub trait Declarable: Clone + fmt::Debug + PartialEq + Send + Sync + 'static {
+    type Patch: Clone + fmt::Debug + PartialEq;
+
+    /// what is declared, as shown in errors
+    const KIND: &'static str;
+
+    fn id(&self) -> &str;
+    fn patch_id(patch: &Self::Patch) -> &str;
+    fn patch(&mut self, patch: &Self::Patch);
+
+    /// Places the id and every id referenced by bare name in `namespace`
+    fn namespace(&mut self, namespace: Namespace);
+    fn namespace_patch(patch: &mut Self::Patch, namespace: Namespace);
+}
+
+fn namespace_entries(entries: &mut [ItemEntry], namespace: Namespace) {
+    for entry in entries.iter_mut() {
+        entry.item.0 = namespaced(namespace, &entry.item.0);
+    }
+}
+
+fn namespace_research(ids: &mut [ResearchId], namespace: Namespace) {
+    for id in ids.iter_mut() {
+        id.0 = namespaced(namespace, &id.0);
+    }
+}
+
+impl Declarable for ItemDeclared {
+    type Patch = ItemPatch;
+
+    const KIND: &'static str = "item";
+
+    fn id(&self) -> &str {
+        &self.id.0
+    }
+
+    fn patch_id(patch: &Self::Patch) -> &str {
+        &patch.id.0
+    }
+
+    fn patch(&mut self, patch: &Self::Patch) {
+        if let Some(category) = &patch.category {
+            self.category = category.clone();
+        }
+        if let Some(stack_size) = patch.stack_size {
+            self.stack_size = stack_size;
+        }
+    }
+
+    fn namespace(&mut self, namespace: Namespace) {
+        self.id.0 = namespaced(namespace, &self.id.0);
+    }
+
+    fn namespace_patch(patch: &mut Self::Patch, namespace: Namespace) {
+        patch.id.0 = namespaced(namespace, &patch.id.0);
+    }
+}
+
+impl Declarable for RecipeDeclared {
+    type Patch = RecipePatch;
+
+    const KIND: &'static str = "recipe";
+
+    fn id(&self) -> &str {
+        &self.id.0
+    }
+
+    fn patch_id(patch: &Self::Patch) -> &str {
+        &patch.id.0
+    }
+
+    fn patch(&mut self, patch: &Self::Patch) {
+        if let Some(ingredients) = &patch.ingredients {
+            self.recipe.ingredients = ingredients.clone();
+        }
+        if let Some(output) = &patch.output {
+            self.recipe.output = output.clone();
+        }
+        if let Some(research_required) = &patch.research_required {
+            self.recipe.research_required = research_required.clone();
+        }
+        if let Some(duration) = patch.duration {
+            self.recipe.duration = duration;
+        }
+    }
+
+    fn namespace(&mut self, namespace: Namespace) {
+        self.id.0 = namespaced(namespace, &self.id.0);
+        namespace_entries(&mut self.recipe.ingredients, namespace);
+        namespace_entries(&mut self.recipe.output, namespace);
+        namespace_research(&mut self.recipe.research_required, namespace);
+    }
+
+    fn namespace_patch(patch: &mut Self::Patch, namespace: Namespace) {
+        patch.id.0 = namespaced(namespace, &patch.id.0);
+        if let Some(ingredients) = &mut patch.ingredients {
+            namespace_entries(ingredients, namespace);
+        }
+        if let Some(output) = &mut patch.output {
+            namespace_entries(output, namespace);
+        }
+        if let Some(research_required) = &mut patch.research_required {
+            namespace_research(research_required, namespace);
+        }
+    }
+}
+
+impl Declarable for ResearchDeclared {
+    type Patch = ResearchPatch;
+
+    const KIND: &'static str = "research";
+
+    fn id(&self) -> &str {
+        &self.id.0
+    }
+
+    fn patch_id(patch: &Self::Patch) -> &str {
+        &patch.id.0
+    }
+
+    fn patch(&mut self, patch: &Self::Patch) {
+        if let Some(display_name) = &patch.display_name {
+            self.display_name = display_name.clone();
+        }
+        if let Some(flavor_text) = &patch.flavor_text {
+            self.flavor_text = flavor_text.clone();
+        }
+        if let Some(unlock_condition) = &patch.unlock_condition {
+            self.unlock_condition = unlock_condition.clone();
+        }
+        if let Some(required_research) = &patch.required_research {
+            self.required_research = required_research.clone();
+        }
+    }
+
+    fn namespace(&mut self, namespace: Namespace) {
+        self.id.0 = namespaced(namespace, &self.id.0);
+        namespace_research(&mut self.required_research, namespace);
+    }
+
+    fn namespace_patch(patch: &mut Self::Patch, namespace: Namespace) {
+        patch.id.0 = namespaced(namespace, &patch.id.0);
+        if let Some(required_research) = &mut patch.required_research {
+            namespace_research(required_research, namespace);
+        }
+    }
+}
+
+/// One entry of a declarations file regardless of what is declared
+#[derive(Debug, Clone, PartialEq)]
+pub enum Declaration<T: Declarable> {
+    Declare(T),
+    Override(T),
+    Patch(T::Patch),
+    Delete(String),
+}
+
+impl<T: Declarable> Declaration<T> {
+    /// Ids written without namespace belong to the mod declaring them
+    pub fn namespaced(self, namespace: Namespace) -> Self {
+        match self {
+            Self::Declare(mut declared) => {
+                declared.namespace(namespace);
+                Self::Declare(declared)
+            }
+            Self::Override(mut declared) => {
+                declared.namespace(namespace);
+                Self::Override(declared)
+            }
+            Self::Patch(mut patch) => {
+                T::namespace_patch(&mut patch, namespace);
+                Self::Patch(patch)
+            }
+            Self::Delete(id) => Self::Delete(namespaced(namespace, &id)),
+        }
+    }
+}
+
+impl From<ItemDeclaration> for Declaration<ItemDeclared> {
+    fn from(declaration: ItemDeclaration) -> Self {
+        match declaration {
+            ItemDeclaration::Declare(declared) => Self::Declare(declared),
+            ItemDeclaration::Override(declared) => Self::Override(declared),
+            ItemDeclaration::Patch(patch) => Self::Patch(patch),
+            ItemDeclaration::Delete(id) => Self::Delete(id.0),
+        }
+    }
+}
+
+impl From<RecipeDeclaration> for Declaration<RecipeDeclared> {
+    fn from(declaration: RecipeDeclaration) -> Self {
+        match declaration {
+            RecipeDeclaration::Declare(declared) => Self::Declare(declared),
+            RecipeDeclaration::Override(declared) => Self::Override(declared),
+            RecipeDeclaration::Patch(patch) => Self::Patch(patch),
+            RecipeDeclaration::Delete(id) => Self::Delete(id.0),
+        }
+    }
+}
+
+impl From<ResearchDeclaration> for Declaration<ResearchDeclared> {
+    fn from(declaration: ResearchDeclaration) -> Self {
+        match declaration {
+            ResearchDeclaration::Declare(declared) => Self::Declare(declared),
+            ResearchDeclaration::Override(declared) => Self::Override(declared),
+            ResearchDeclaration::Patch(patch) => Self::Patch(patch),
+            ResearchDeclaration::Delete(id) => Self::Delete(id.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeclarationAction {
+    Declared,
+    Overridden,
+    Patched,
+    Deleted,
+}
+
+/// Mod that last changed a declaration and how
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeclarationTouch {
+    pub mod_name: String,
+    pub action: DeclarationAction,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeclarationIssue {
+    /// `Declare` of an id another mod already declared, `Override` is needed to replace it
+    AlreadyDeclared {
+        kind: &'static str,
+        id: String,
+        mod_name: String,
+        declared_by: String,
+    },
+    /// `Override`, `Patch` or `Delete` of an id nobody declared
+    Undeclared {
+        kind: &'static str,
+        id: String,
+        mod_name: String,
+        action: DeclarationAction,
+    },
+}
+
+impl fmt::Display for DeclarationIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlreadyDeclared {
+                kind,
+                id,
+                mod_name,
+                declared_by,
+            } => write!(
+                f,
+                "{} declares {} `{}` which {} already declared, use Override instead",
+                mod_name, kind, id, declared_by
+            ),
+            Self::Undeclared {
+                kind,
+                id,
+                mod_name,
+                action,
+            } => write!(
+                f,
+                "{} {:?} {} `{}` which is not declared",
+                mod_name, action, kind, id
+            ),
+        }
+    }
+}
+
+/// Declarations of one kind merged across mods, kept in declaration order
+#[derive(Debug, Clone)]
+pub struct Merged<T: Declarable> {
+    pub declared: Vec<T>,
+    /// last change of every id ever declared, deleted ones included
+    pub touched: HashMap<String, DeclarationTouch>,
+    pub issues: Vec<DeclarationIssue>,
+}
+
+impl<T: Declarable> Default for Merged<T> {
+    fn default() -> Self {
+        Self {
+            declared: vec![],
+            touched: HashMap::default(),
+            issues: vec![],
+        }
+    }
+}
+
+impl<T: Declarable> Merged<T> {
+    pub fn get(&self, id: &str) -> Option<&T> {
+        self.declared.iter().find(|declared| declared.id() == id)
+    }
+
+    fn position(&self, id: &str) -> Option<usize> {
+        self.declared
+            .iter()
+            .position(|declared| declared.id() == id)
+    }
+
+    fn touch(&mut self, id: &str, mod_name: &str, action: DeclarationAction) {
+        self.touched.insert(
+            id.to_string(),
+            DeclarationTouch {
+                mod_name: mod_name.to_string(),
+                action,
+            },
+        );
+    }
+
+    fn undeclared(&mut self, id: &str, mod_name: &str, action: DeclarationAction) {
+        self.issues.push(DeclarationIssue::Undeclared {
+            kind: T::KIND,
+            id: id.to_string(),
+            mod_name: mod_name.to_string(),
+            action,
+        });
+    }
+
+    pub fn apply(&mut self, mod_name: &str, declaration: Declaration<T>) {
+        match declaration {
+            Declaration::Declare(declared) => {
+                let id = declared.id().to_string();
+                if self.position(&id).is_some() {
+                    let declared_by = self
+                        .touched
+                        .get(&id)
+                        .map(|touch| touch.mod_name.clone())
+                        .unwrap_or_default();
+                    self.issues.push(DeclarationIssue::AlreadyDeclared {
+                        kind: T::KIND,
+                        id,
+                        mod_name: mod_name.to_string(),
+                        declared_by,
+                    });
+                    return;
+                }
+
+                self.declared.push(declared);
+                self.touch(&id, mod_name, DeclarationAction::Declared);
+            }
+            Declaration::Override(declared) => {
+                let id = declared.id().to_string();
+                match self.position(&id) {
+                    Some(index) => self.declared[index] = declared,
+                    None => {
+                        // still declared, overriding a missing mod shouldn't lose content
+                        self.undeclared(&id, mod_name, DeclarationAction::Overridden);
+                        self.declared.push(declared);
+                    }
+                }
+                self.touch(&id, mod_name, DeclarationAction::Overridden);
+            }
+            Declaration::Patch(patch) => {
+                let id = T::patch_id(&patch).to_string();
+                match self.position(&id) {
+                    Some(index) => {
+                        self.declared[index].patch(&patch);
+                        self.touch(&id, mod_name, DeclarationAction::Patched);
+                    }
+                    None => self.undeclared(&id, mod_name, DeclarationAction::Patched),
+                }
+            }
+            Declaration::Delete(id) => match self.position(&id) {
+                Some(index) => {
+                    self.declared.remove(index);
+                    self.touch(&id, mod_name, DeclarationAction::Deleted);
+                }
+                None => self.undeclared(&id, mod_name, DeclarationAction::Deleted),
+            },
+        }
+    }
+}
+
+/// Everything declared by the mod profile of a level, before it becomes the registries
+#[derive(Debug, Clone, Default)]
+pub struct Declarations {
+    pub items: Merged<ItemDeclared>,
+    pub recipes: Merged<RecipeDeclared>,
+    pub research: Merged<ResearchDeclared>,
+    pub achievements: Merged<AchievementDeclared>,
+}
+
+impl Declarations {
+    pub fn issues(&self) -> impl Iterator<Item = &DeclarationIssue> {
+        self.items
+            .issues
+            .iter()
+            .chain(self.recipes.issues.iter())
+            .chain(self.research.issues.iter())
+            .chain(self.achievements.issues.iter())
+    }
+}
+
+/// Which mod last touched each id, sorted by id
+impl fmt::Display for Declarations {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (kind, touched) in [
+            ("items", &self.items.touched),
+            ("recipes", &self.recipes.touched),
+            ("research", &self.research.touched),
+            ("achievements", &self.achievements.touched),
+        ] {
+            writeln!(f, "{}:", kind)?;
+
+            let mut touched = touched.iter().collect::<Vec<_>>();
+            touched.sort_by_key(|(id, _)| *id);
+            for (id, touch) in touched {
+                writeln!(f, "  {} {:?} by {}", id, touch.action, touch.mod_name)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// `None` when the mod doesn't ship the file or leaves it empty, a file that can't be read is
+/// an error so editors never overwrite it
+pub fn read_ron_file(
+    path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<Option<Box<dyn PartialReflect>>, String> {
+    let ron = match fs::read_to_string(path) {
+        Ok(ron) => ron,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(format!("{}. {}", path.to_string_lossy(), err)),
+    };
+
+    if ron.trim().is_empty() {
+        return Ok(None);
+    }
+
+    let mut deserializer = ron::de::Deserializer::from_str(&ron).map_err(|err| err.to_string())?;
+    let reflect_deserializer = ReflectDeserializer::new(type_registry);
+
+    reflect_deserializer
+        .deserialize(&mut deserializer)
+        .map(Some)
+        .map_err(|err| err.to_string())
+}
+
+/// Entries of a file holding either `F` or a plain pack `P` of ids
+fn read_entries<D, F, P>(
+    path: &Path,
+    type_registry: &TypeRegistry,
+    file: impl Fn(F) -> Vec<D>,
+    pack: impl Fn(P) -> Vec<D>,
+) -> Result<Vec<D>, String>
+where
+    F: FromReflect,
+    P: FromReflect,
+{
+    let Some(value) = read_ron_file(path, type_registry)? else {
+        return Ok(vec![]);
+    };
+
+    F::from_reflect(&*value)
+        .map(file)
+        .or_else(|| P::from_reflect(&*value).map(pack))
+        .ok_or_else(|| format!("{} holds no declarations", path.to_string_lossy()))
+}
+
+fn read_declarations<T, F, P>(
+    path: &Path,
+    type_registry: &TypeRegistry,
+    file: impl Fn(F) -> Vec<Declaration<T>>,
+    pack: impl Fn(P) -> Vec<T>,
+) -> Result<Vec<Declaration<T>>, String>
+where
+    T: Declarable,
+    F: FromReflect,
+    P: FromReflect,
+{
+    read_entries(path, type_registry, file, |ids| {
+        pack(ids).into_iter().map(Declaration::Declare).collect()
+    })
+}
+
+/// Pretty printing of declaration files, whether written by the tests or by the editors
+pub fn declarations_pretty_config() -> ron::ser::PrettyConfig {
+    ron::ser::PrettyConfig::new().depth_limit(6)
+}
+
+/// Written next to the real file first so a crash can't leave it half written
+pub fn write_ron_file(
+    path: &Path,
+    value: &dyn PartialReflect,
+    type_registry: &TypeRegistry,
+) -> Result<(), String> {
+    let serialized = ron::ser::to_string_pretty(
+        &ReflectSerializer::new(value, type_registry),
+        declarations_pretty_config(),
+    )
+    .map_err(|err| err.to_string())?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+    }
+
+    let temp = temp_path(path);
+    fs::write(&temp, serialized).map_err(|err| err.to_string())?;
+    fs::rename(&temp, path).map_err(|err| err.to_string())
+}
+
+/// Items of one mod as written in its `declarations/items.ron`, ids left as written
+pub fn read_item_declarations(
+    mod_path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<Vec<ItemDeclaration>, String> {
+    read_entries(
+        &mod_path.join(DECLARATIONS_PATH).join("items.ron"),
+        type_registry,
+        |ItemDeclarations(file)| file,
+        |ItemPack(ids)| {
+            ids.into_iter()
+                .map(|id| ItemDeclaration::Declare(id.into()))
+                .collect()
+        },
+    )
+}
+
+pub fn write_item_declarations(
+    mod_path: &Path,
+    declarations: &ItemDeclarations,
+    type_registry: &TypeRegistry,
+) -> Result<(), String> {
+    write_ron_file(
+        &mod_path.join(DECLARATIONS_PATH).join("items.ron"),
+        declarations,
+        type_registry,
+    )
+}
+
+/// Recipes of one mod as written in its `declarations/recipes.ron`, ids left as written
+pub fn read_recipe_declarations(
+    mod_path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<Vec<RecipeDeclaration>, String> {
+    read_entries(
+        &mod_path.join(DECLARATIONS_PATH).join("recipes.ron"),
+        type_registry,
+        |RecipeDeclarations(file)| file,
+        |RecipePack(ids)| {
+            ids.into_iter()
+                .map(|id| RecipeDeclaration::Declare(id.into()))
+                .collect()
+        },
+    )
+}
+
+pub fn write_recipe_declarations(
+    mod_path: &Path,
+    declarations: &RecipeDeclarations,
+    type_registry: &TypeRegistry,
+) -> Result<(), String> {
+    write_ron_file(
+        &mod_path.join(DECLARATIONS_PATH).join("recipes.ron"),
+        declarations,
+        type_registry,
+    )
+}
+
+/// Research of one mod as written in its `declarations/research.ron`, ids left as written
+pub fn read_research_declarations(
+    mod_path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<Vec<ResearchDeclaration>, String> {
+    read_entries(
+        &mod_path.join(DECLARATIONS_PATH).join("research.ron"),
+        type_registry,
+        |ResearchDeclarations(file)| file,
+        |ResearchPack(ids)| {
+            ids.into_iter()
+                .map(|id| ResearchDeclaration::Declare(id.into()))
+                .collect()
+        },
+    )
+}
+
+pub fn write_research_declarations(
+    mod_path: &Path,
+    declarations: &ResearchDeclarations,
+    type_registry: &TypeRegistry,
+) -> Result<(), String> {
+    write_ron_file(
+        &mod_path.join(DECLARATIONS_PATH).join("research.ron"),
+        declarations,
+        type_registry,
+    )
+}
+
+/// Reads the declarations of one mod into the merged ones
+pub fn declare_mod(
+    declarations: &mut Declarations,
+    namespace: Namespace,
+    mod_path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<(), String> {
+    let mod_name = namespace.as_str();
+    let declarations_path = mod_path.join(DECLARATIONS_PATH);
+
+    for declaration in read_declarations(
+        &declarations_path.join("items.ron"),
+        type_registry,
+        |ItemDeclarations(file)| file.into_iter().map(Into::into).collect(),
+        |ItemPack(ids)| ids.into_iter().map(Into::into).collect(),
+    )? {
+        declarations
+            .items
+            .apply(mod_name, declaration.namespaced(namespace));
+    }
+
+    for declaration in read_declarations(
+        &declarations_path.join("recipes.ron"),
+        type_registry,
+        |RecipeDeclarations(file)| file.into_iter().map(Into::into).collect(),
+        |RecipePack(ids)| ids.into_iter().map(Into::into).collect(),
+    )? {
+        declarations
+            .recipes
+            .apply(mod_name, declaration.namespaced(namespace));
+    }
+
+    for declaration in read_declarations(
+        &declarations_path.join("research.ron"),
+        type_registry,
+        |ResearchDeclarations(file)| file.into_iter().map(Into::into).collect(),
+        |ResearchPack(ids)| ids.into_iter().map(Into::into).collect(),
+    )? {
+        declarations
+            .research
+            .apply(mod_name, declaration.namespaced(namespace));
+    }
+
+    for declaration in read_declarations(
+        &declarations_path.join("achievements.ron"),
+        type_registry,
+        |AchievementDeclarations(file)| file.into_iter().map(Into::into).collect(),
+        |AchievementPack(declared)| declared,
+    )? {
+        declarations
+            .achievements
+            .apply(mod_name, declaration.namespaced(namespace));
+    }
+
+    Ok(())
+}
+
+pub fn load_declarations(
+    mut cmd: Commands,
+    profile: Query<&ModProfile, With<Level>>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok(profile) = profile.get_single() else {
+        return;
+    };
+    let type_registry = type_registry.read();
+
+    let mut declarations = Declarations::default();
+    let mut namespaces = Namespaces::default();
+
+    for (mod_pack, mod_path) in profile.0.iter() {
+        let mod_name = &mod_pack.mod_id.mod_name;
+
+        let namespace = match namespaces.register(&mod_pack.mod_id) {
+            Ok(namespace) => namespace,
+            Err(err) => {
+                error!("{} has no namespace. {}", mod_name, err);
+                Notification {
+                    title: "Unable to register namespace".into(),
+                    level: NotificationLevel::Error,
+                    description: format!("{}: {}", mod_name, err),
+                }
+                .queue(None, &mut notifications_channel);
+                continue;
+            }
+        };
+
+        if let Err(err) = declare_mod(&mut declarations, namespace, mod_path, &type_registry) {
+            error!("unable to read declarations of {}. {}", mod_name, err);
+            Notification {
+                title: "Unable to read declarations".into(),
+                level: NotificationLevel::Error,
+                description: format!("{}: {}", mod_name, err),
+            }
+            .queue(None, &mut notifications_channel);
+        }
+    }
+
+    for issue in declarations.issues() {
+        warn!("{}", issue);
+        Notification {
+            title: "Conflicting declarations".into(),
+            level: NotificationLevel::Warning,
+            description: issue.to_string(),
+        }
+        .queue(Some(Duration::from_secs(10)), &mut notifications_channel);
+    }
+
+    info!("declarations merged\n{}", declarations);
+
+    let Declarations {
+        items,
+        recipes,
+        research,
+        achievements,
+    } = declarations;
+    let items = ItemRegistry::from(items);
+    let mut recipes = RecipeRegistry::from(recipes);
+    let research = ResearchRegistry::from(research);
+    let incomplete = recipes.reject_incomplete();
+    let achievements = AchievementRegistry::from(achievements);
+
+    let errors = items
+        .invalid_ids(&namespaces)
+        .into_iter()
+        .chain(recipes.invalid_ids(&namespaces))
+        .chain(research.invalid_ids(&namespaces))
+        .chain(achievements.invalid_ids(&namespaces))
+        .chain(incomplete)
+        .chain(unresolved_references(&items, &recipes, &research));
+
+    for err in errors {
+        warn!("{}", err);
+        Notification {
+            title: "Unresolved declaration".into(),
+            level: NotificationLevel::Warning,
+            description: err.to_string(),
+        }
+        .queue(Some(Duration::from_secs(10)), &mut notifications_channel);
+    }
+
+    cmd.insert_resource(items);
+    cmd.insert_resource(recipes);
+    cmd.insert_resource(research);
+    cmd.insert_resource(achievements);
+    cmd.insert_resource(DeclaredAchievementProfile {
+        key: mod_profile_key(profile),
+        mod_profile: profile.clone(),
+    });
+    cmd.insert_resource(namespaces);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &'static str, stack_size: usize) -> ItemDeclared {
+        ItemDeclared {
+            id: id.into(),
+            category: "food".into(),
+            stack_size,
+        }
+    }
+
+    #[test]
+    fn later_mods_override_patch_and_delete() {
+        let mut items = Merged::<ItemDeclared>::default();
+
+        items.apply("base", Declaration::Declare(item("cheese_wheel", 10)));
+        items.apply("base", Declaration::Declare(item("bread_loaf", 10)));
+        items.apply("base", Declaration::Declare(item("pork_slab", 10)));
+
+        items.apply("overhaul", Declaration::Override(item("cheese_wheel", 50)));
+        items.apply(
+            "overhaul",
+            Declaration::Patch(ItemPatch {
+                id: "bread_loaf".into(),
+                category: Some("bakery".into()),
+                stack_size: None,
+            }),
+        );
+        items.apply("overhaul", Declaration::Delete("pork_slab".into()));
+
+        assert!(items.issues.is_empty());
+        assert_eq!(items.get("cheese_wheel"), Some(&item("cheese_wheel", 50)));
+        assert_eq!(
+            items.get("bread_loaf"),
+            Some(&ItemDeclared {
+                id: "bread_loaf".into(),
+                category: "bakery".into(),
+                stack_size: 10,
+            })
+        );
+        assert_eq!(items.get("pork_slab"), None);
+
+        assert_eq!(
+            items.touched.get("pork_slab"),
+            Some(&DeclarationTouch {
+                mod_name: "overhaul".into(),
+                action: DeclarationAction::Deleted,
+            })
+        );
+        // declaration order is kept for overridden ids
+        assert_eq!(items.declared[0].id, "cheese_wheel".into());
+    }
+
+    #[test]
+    fn conflicting_declarations_reported() {
+        let mut items = Merged::<ItemDeclared>::default();
+
+        items.apply("base", Declaration::Declare(item("cheese_wheel", 10)));
+        items.apply("tyconic", Declaration::Declare(item("cheese_wheel", 20)));
+        items.apply("tyconic", Declaration::Delete("missing".into()));
+
+        assert_eq!(items.get("cheese_wheel"), Some(&item("cheese_wheel", 10)));
+        assert_eq!(
+            items.issues,
+            vec![
+                DeclarationIssue::AlreadyDeclared {
+                    kind: "item",
+                    id: "cheese_wheel".into(),
+                    mod_name: "tyconic".into(),
+                    declared_by: "base".into(),
+                },
+                DeclarationIssue::Undeclared {
+                    kind: "item",
+                    id: "missing".into(),
+                    mod_name: "tyconic".into(),
+                    action: DeclarationAction::Deleted,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_missing_files_read_as_empty() {
+        let type_registry = TypeRegistry::default();
+        let dir = std::env::temp_dir().join("tyconia_only_missing_files_read_as_empty");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        assert!(matches!(
+            read_ron_file(&dir.join("missing.ron"), &type_registry),
+            Ok(None)
+        ));
+
+        fs::write(dir.join("blank.ron"), "  \n").unwrap();
+        assert!(matches!(
+            read_ron_file(&dir.join("blank.ron"), &type_registry),
+            Ok(None)
+        ));
+
+        // not UTF-8
+        fs::write(dir.join("binary.ron"), [0xff, 0xfe, 0x00]).unwrap();
+        assert!(read_ron_file(&dir.join("binary.ron"), &type_registry).is_err());
+        // a directory can't be read as a file
+        assert!(read_ron_file(&dir, &type_registry).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -54,6 +54,101 @@ impl Inventory {
             }
         }
     }
+
+    /// Quantity of `item` across every slot
+    pub fn count(&self, item: &ItemId) -> usize {
+        self.0
+            .iter()
+            .flatten()
+            .filter(|entry| entry.item == *item)
+            .map(|entry| entry.quantity)
+            .sum()
+    }
+
+    /// Whether every entry is available at once, entries of the same item add up
+    pub fn contains(&self, entries: &[ItemEntry]) -> bool {
+        entries.iter().all(|entry| {
+            let required: usize = entries
+                .iter()
+                .filter(|other| other.item == entry.item)
+                .map(|other| other.quantity)
+                .sum();
+            self.count(&entry.item) >= required
+        })
+    }
+
+    /// Takes the quantity out of the last slots holding the item first, nothing is taken
+    /// when there isn't enough
+    pub fn remove(&mut self, entry: &ItemEntry) -> bool {
+        if self.count(&entry.item) < entry.quantity {
+            return false;
+        }
+
+        let mut remaining = entry.quantity;
+        for slot in self.0.iter_mut().rev() {
+            let Some(held) = slot.as_mut().filter(|held| held.item == entry.item) else {
+                continue;
+            };
+
+            let taken = held.quantity.min(remaining);
+            held.quantity -= taken;
+            remaining -= taken;
+            if held.quantity == 0 {
+                *slot = None;
+            }
+            if remaining == 0 {
+                break;
+            }
+        }
+
+        true
+    }
+
+    /// Stacks onto slots holding the item then fills empty slots, returns what didn't fit.
+    /// Nothing is inserted for an empty entry, it would hold on to a slot
+    pub fn insert(&mut self, mut entry: ItemEntry, stack_size: usize) -> Option<ItemEntry> {
+        if entry.quantity == 0 {
+            return None;
+        }
+        if stack_size == 0 {
+            return Some(entry);
+        }
+
+        for held in self.0.iter_mut().flatten() {
+            if held.item == entry.item && held.quantity < stack_size {
+                let moved = (stack_size - held.quantity).min(entry.quantity);
+                held.quantity += moved;
+                entry.quantity -= moved;
+            }
+            if entry.quantity == 0 {
+                return None;
+            }
+        }
+
+        for slot in self.0.iter_mut().filter(|slot| slot.is_none()) {
+            let moved = stack_size.min(entry.quantity);
+            *slot = Some(ItemEntry {
+                item: entry.item.clone(),
+                quantity: moved,
+            });
+            entry.quantity -= moved;
+            if entry.quantity == 0 {
+                return None;
+            }
+        }
+
+        Some(entry)
+    }
+
+    /// Whether all entries could be inserted together
+    pub fn fits(&self, entries: &[ItemEntry], stack_size: impl Fn(&ItemId) -> usize) -> bool {
+        let mut inventory = self.clone();
+        entries.iter().all(|entry| {
+            inventory
+                .insert(entry.clone(), stack_size(&entry.item))
+                .is_none()
+        })
+    }
 }
 
 /// Maximum amount of x item per stack
@@ -65,3 +160,42 @@ impl Default for StackSize {
         Self(10)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(item: &'static str, quantity: usize) -> ItemEntry {
+        ItemEntry {
+            item: item.into(),
+            quantity,
+        }
+    }
+
+    #[test]
+    fn inserts_stack_then_fill_empty_slots() {
+        let mut inventory = Inventory::with_capacity(3);
+        inventory.dump(vec![entry("base::burger", 8)]);
+
+        assert_eq!(
+            inventory.insert(entry("base::burger", 25), 10),
+            Some(entry("base::burger", 3))
+        );
+        assert_eq!(inventory.count(&"base::burger".into()), 30);
+    }
+
+    #[test]
+    fn empty_entries_take_no_slot() {
+        let mut inventory = Inventory::with_capacity(2);
+
+        assert_eq!(inventory.insert(entry("base::burger", 0), 10), None);
+        assert_eq!(inventory, Inventory::with_capacity(2));
+
+        // nothing fits a stack of nothing
+        assert_eq!(
+            inventory.insert(entry("base::burger", 1), 0),
+            Some(entry("base::burger", 1))
+        );
+        assert_eq!(inventory, Inventory::with_capacity(2));
+    }
+}
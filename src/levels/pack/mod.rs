@@ -1,15 +1,23 @@
 mod base;
+mod building;
+mod declarations;
 mod item;
 mod namespace;
 mod recipe;
+mod registry;
 mod research;
+mod version;
 
 pub use base::*;
+pub use building::*;
+pub use declarations::*;
 pub use item::*;
 pub use namespace::*;
 pub use recipe::*;
+pub use registry::*;
 pub use research::*;
 use std::{fmt, str::FromStr};
+pub use version::*;
 
 use bevy::prelude::*;
 
@@ -87,22 +95,6 @@ pub struct MetaAttributions {
     pub credits: Vec<String>,
 }
 
-#[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone, Copy)]
-pub struct SemVer {
-    major: u8,
-    minor: u8,
-    patch: u8,
-    stage: SemverStage,
-}
-
-#[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone, Copy)]
-pub enum SemverStage {
-    Dev,
-    Nightly,
-    ReleaseCandidate(u8),
-    Stable,
-}
-
 #[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone)]
 pub struct MetaSource {
     pub id: MetaShorthand,
@@ -124,83 +116,6 @@ pub enum MetaSources {
     Git(String),
 }
 
-impl fmt::Display for SemVer {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match &self.stage {
-            SemverStage::Stable => write!(f, "{}.{}.{}", self.major, self.minor, self.patch),
-            SemverStage::Dev => write!(f, "{}.{}.{}-dev", self.major, self.minor, self.patch),
-            SemverStage::ReleaseCandidate(rc) => {
-                write!(f, "{}.{}.{}-rc{}", self.major, self.minor, self.patch, rc)
-            }
-            SemverStage::Nightly => {
-                write!(f, "{}.{}.{}-nightly", self.major, self.minor, self.patch)
-            }
-        }
-    }
-}
-
-#[derive(Debug)]
-pub struct ParseSemVerError;
-
-impl fmt::Display for ParseSemVerError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "invalid semver format")
-    }
-}
-
-impl FromStr for SemVer {
-    type Err = ParseSemVerError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let mut parts = s.split('-');
-        let version_part = parts.next().ok_or(ParseSemVerError)?;
-        let stage_part = parts.next(); // Optional stage
-
-        let mut version_numbers = version_part.split('.');
-        let major = version_numbers
-            .next()
-            .and_then(|v| v.parse().ok())
-            .ok_or(ParseSemVerError)?;
-        let minor = version_numbers
-            .next()
-            .and_then(|v| v.parse().ok())
-            .ok_or(ParseSemVerError)?;
-        let patch = version_numbers
-            .next()
-            .and_then(|v| v.parse().ok())
-            .ok_or(ParseSemVerError)?;
-
-        let stage = match stage_part {
-            Some("dev") => SemverStage::Dev,
-            Some("nightly") => SemverStage::Nightly,
-            Some(rc) if rc.starts_with("rc") => {
-                let rc_num = rc[2..].parse().map_err(|_| ParseSemVerError)?;
-                SemverStage::ReleaseCandidate(rc_num)
-            }
-            Some(_) => return Err(ParseSemVerError),
-            None => SemverStage::Stable,
-        };
-
-        Ok(SemVer {
-            major,
-            minor,
-            patch,
-            stage,
-        })
-    }
-}
-
-impl From<(u8, u8, u8)> for SemVer {
-    fn from((major, minor, patch): (u8, u8, u8)) -> Self {
-        Self {
-            major,
-            minor,
-            patch,
-            stage: SemverStage::Dev,
-        }
-    }
-}
-
 pub fn to_snake_case(input: &str) -> String {
     let mut result = String::new();
     let mut prev_was_upper = false;
@@ -239,12 +154,7 @@ mod tests {
         let input = "some_mod_1.2.3";
         let expected = Meta {
             mod_name: "some_mod".to_string(),
-            version: SemVer {
-                major: 1,
-                minor: 2,
-                patch: 3,
-                stage: SemverStage::Stable,
-            },
+            version: SemVer::new(1, 2, 3),
         };
 
         let parsed = input.parse::<Meta>().unwrap();
@@ -257,10 +167,8 @@ mod tests {
         let expected = Meta {
             mod_name: "mega_factory".to_string(),
             version: SemVer {
-                major: 10,
-                minor: 4,
-                patch: 1,
-                stage: SemverStage::Nightly,
+                pre: vec!["nightly".into()],
+                ..SemVer::new(10, 4, 1)
             },
         };
 
@@ -273,10 +181,8 @@ mod tests {
         let meta = Meta {
             mod_name: "cooking_time".to_string(),
             version: SemVer {
-                major: 2,
-                minor: 0,
-                patch: 0,
-                stage: SemverStage::ReleaseCandidate(3),
+                pre: vec!["rc3".into()],
+                ..SemVer::new(2, 0, 0)
             },
         };
 
@@ -284,6 +190,17 @@ mod tests {
         assert_eq!(formatted, "cooking_time_2.0.0-rc3");
     }
 
+    #[test]
+    fn meta_parsing_with_prerelease_and_build() {
+        let input = "mega_factory_1.0.0-x-y.7+build-5.sha";
+        let parsed = input.parse::<Meta>().unwrap();
+
+        assert_eq!(parsed.mod_name, "mega_factory");
+        assert_eq!(parsed.version.pre, vec!["x-y", "7"]);
+        assert_eq!(parsed.version.build, vec!["build-5", "sha"]);
+        assert_eq!(parsed.to_string(), input);
+    }
+
     #[test]
     fn meta_parsing_invalid_missing_version() {
         let input = "mod_without_version";
@@ -1,501 +1,312 @@
-use bevy::prelude::*;
-use std::hash::Hash;
+//! Namespaces are the names of the loaded mods, ids they declare are written `tyconic::cheese_wheel`
 
+use super::Meta;
+use bevy::ecs::intern::{Interned, Interner};
+use bevy::prelude::*;
+use bevy::utils::HashMap;
 use std::{fmt, str::FromStr};
 
+/// Separates the namespace from the id in `tyconic::cheese_wheel`
+pub const NAMESPACE_SEPARATOR: &str = "::";
+
+static NAMESPACES: Interner<str> = Interner::new();
+
+/// The namespace where an item resides on. Cheap to copy and compare, the name is interned
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Namespace(Interned<str>);
+
+impl Namespace {
+    /// Lowercase ascii letters, digits and `_`, the same as a mod name
+    pub fn new(name: &str) -> Result<Self, NamespaceError> {
+        if name.is_empty() {
+            return Err(NamespaceError::EmptyNamespace);
+        }
+
+        let valid = name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+        if !valid {
+            return Err(NamespaceError::InvalidNamespace(name.to_string()));
+        }
+
+        Ok(Self(NAMESPACES.intern(name)))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0 .0
+    }
+}
+
+impl fmt::Debug for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Namespace({})", self.as_str())
+    }
+}
+
 impl fmt::Display for Namespace {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "{:?}", self)
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Namespace {
+    type Err = NamespaceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+impl PartialOrd for Namespace {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
     }
 }
 
-#[derive(Debug)]
-pub struct ParseNamespaceError;
+impl Ord for Namespace {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl Meta {
+    pub fn namespace(&self) -> Result<Namespace, NamespaceError> {
+        Namespace::new(&self.mod_name)
+    }
+}
 
-impl fmt::Display for ParseNamespaceError {
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NamespaceError {
+    EmptyNamespace,
+    InvalidNamespace(String),
+    /// `cheese_wheel` instead of `tyconic::cheese_wheel`
+    MissingNamespace(String),
+    InvalidId(String),
+    /// no loaded mod goes by this namespace
+    Unregistered(Namespace),
+    /// two mods of the profile would share a namespace
+    AlreadyRegistered {
+        namespace: Namespace,
+        registered_by: String,
+    },
+}
+
+impl fmt::Display for NamespaceError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "invalid namespace")
+        match self {
+            Self::EmptyNamespace => write!(f, "namespace is empty"),
+            Self::InvalidNamespace(namespace) => write!(
+                f,
+                "invalid namespace `{}`, only lowercase letters, digits and `_` are allowed",
+                namespace
+            ),
+            Self::MissingNamespace(id) => write!(
+                f,
+                "`{}` has no namespace, write it as `mod{}{}`",
+                id, NAMESPACE_SEPARATOR, id
+            ),
+            Self::InvalidId(id) => write!(f, "invalid id `{}`", id),
+            Self::Unregistered(namespace) => {
+                write!(f, "no loaded mod provides the namespace `{}`", namespace)
+            }
+            Self::AlreadyRegistered {
+                namespace,
+                registered_by,
+            } => write!(
+                f,
+                "namespace `{}` is already registered by {}",
+                namespace, registered_by
+            ),
+        }
     }
 }
 
-impl FromStr for Namespace {
-    type Err = ParseNamespaceError;
+/// An id such as `tyconic::cheese_wheel`
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct NamespacedId {
+    pub namespace: Namespace,
+    pub id: String,
+}
+
+impl NamespacedId {
+    /// Ids written without a namespace are placed in `namespace`
+    pub fn parse_in(namespace: Namespace, s: &str) -> Result<Self, NamespaceError> {
+        if s.contains(NAMESPACE_SEPARATOR) {
+            s.parse()
+        } else {
+            validate_id(s)?;
+            Ok(Self {
+                namespace,
+                id: s.to_string(),
+            })
+        }
+    }
+}
+
+/// Anything but blank, padded with whitespace or holding another separator
+fn validate_id(id: &str) -> Result<(), NamespaceError> {
+    if id.trim().is_empty()
+        || id.trim() != id
+        || id.contains(NAMESPACE_SEPARATOR)
+        || id.chars().any(char::is_control)
+    {
+        return Err(NamespaceError::InvalidId(id.to_string()));
+    }
+
+    Ok(())
+}
+
+impl fmt::Display for NamespacedId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.namespace, NAMESPACE_SEPARATOR, self.id)
+    }
+}
+
+impl FromStr for NamespacedId {
+    type Err = NamespaceError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        match s {
-            "Vanilla" => Ok(Namespace::Vanilla),
-            "ArcaneEssence" => Ok(Namespace::ArcaneEssence),
-            "NebulaShard" => Ok(Namespace::NebulaShard),
-            "EldritchRune" => Ok(Namespace::EldritchRune),
-            "TemporalAnchor" => Ok(Namespace::TemporalAnchor),
-            "BiotechCore" => Ok(Namespace::BiotechCore),
-            "ObsidianTalon" => Ok(Namespace::ObsidianTalon),
-            "VoidWhisper" => Ok(Namespace::VoidWhisper),
-            "PhantomAether" => Ok(Namespace::PhantomAether),
-            "IroncladKey" => Ok(Namespace::IroncladKey),
-            "DragonFang" => Ok(Namespace::DragonFang),
-            "EthericCrystal" => Ok(Namespace::EthericCrystal),
-            "StarborneRelic" => Ok(Namespace::StarborneRelic),
-            "AlienPod" => Ok(Namespace::AlienPod),
-            "InfernalTorch" => Ok(Namespace::InfernalTorch),
-            _ => Err(ParseNamespaceError),
+        let Some((namespace, id)) = s.split_once(NAMESPACE_SEPARATOR) else {
+            return Err(NamespaceError::MissingNamespace(s.to_string()));
+        };
+
+        let namespace = Namespace::new(namespace)?;
+        validate_id(id)?;
+
+        Ok(Self {
+            namespace,
+            id: id.to_string(),
+        })
+    }
+}
+
+/// Places a bare id in the namespace of the mod declaring it, ids already namespaced are kept
+pub fn namespaced(namespace: Namespace, id: &str) -> String {
+    if id.contains(NAMESPACE_SEPARATOR) {
+        id.to_string()
+    } else {
+        format!("{}{}{}", namespace, NAMESPACE_SEPARATOR, id)
+    }
+}
+
+/// Namespaces of the mods loaded for the level, with the mod registering each
+#[derive(Resource, Debug, Clone, Default)]
+pub struct Namespaces(HashMap<Namespace, Meta>);
+
+impl Namespaces {
+    pub fn register(&mut self, meta: &Meta) -> Result<Namespace, NamespaceError> {
+        let namespace = meta.namespace()?;
+
+        if let Some(registered) = self.0.get(&namespace) {
+            return Err(NamespaceError::AlreadyRegistered {
+                namespace,
+                registered_by: registered.to_string(),
+            });
         }
+
+        self.0.insert(namespace, meta.clone());
+        Ok(namespace)
+    }
+
+    pub fn contains(&self, namespace: Namespace) -> bool {
+        self.0.contains_key(&namespace)
+    }
+
+    /// Mod which registered `namespace`
+    pub fn provider(&self, namespace: Namespace) -> Option<&Meta> {
+        self.0.get(&namespace)
+    }
+
+    /// Sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = Namespace> {
+        let mut namespaces = self.0.keys().copied().collect::<Vec<_>>();
+        namespaces.sort();
+        namespaces.into_iter()
+    }
+
+    /// Parses `ns::id` and checks a loaded mod provides `ns`
+    pub fn parse(&self, s: &str) -> Result<NamespacedId, NamespaceError> {
+        let id = s.parse::<NamespacedId>()?;
+
+        if !self.contains(id.namespace) {
+            return Err(NamespaceError::Unregistered(id.namespace));
+        }
+
+        Ok(id)
     }
 }
 
-/// The namespace where an item resides on
-#[derive(Component, Debug, Reflect, PartialEq, Eq, Hash, Clone)]
-pub enum Namespace {
-    Vanilla,
-    ArcaneEssence,
-    NebulaShard,
-    EldritchRune,
-    TemporalAnchor,
-    BiotechCore,
-    ObsidianTalon,
-    VoidWhisper,
-    PhantomAether,
-    IroncladKey,
-    DragonFang,
-    EthericCrystal,
-    StarborneRelic,
-    AlienPod,
-    InfernalTorch,
-    //CelestialHymn,
-    //VoidMote,
-    //ArcaneDust,
-    //QuantumVortex,
-    //NecroticHeart,
-    //RadiantGlyph,
-    //PlasmaSphere,
-    //EldritchTotem,
-    //ShardOfNight,
-    //ArcaneLocus,
-    //SynthBlade,
-    //MysticSeed,
-    //SolsticeOrb,
-    //WraithBone,
-    //ChronoFragment,
-    //CursedCharm,
-    //NeutronFlare,
-    //EternalSigil,
-    //HyperCore,
-    //AstralShard,
-    //LeviathanEye,
-    //BiofluidVial,
-    //ArcaneAshes,
-    //VoidCluster,
-    //ElementalSeal,
-    //BioHusk,
-    //ForbiddenTomes,
-    //EtherealFragment,
-    //OmegaDust,
-    //ObsidianWitch,
-    //SolarFury,
-    //HallowedTalisman,
-    //GravitonFist,
-    //LunarEmber,
-    //Bloodstone,
-    //PlasmaPulse,
-    //GhostFlesh,
-    //PhantomShard,
-    //NexusGem,
-    //PsionicCore,
-    //NovaFragment,
-    //WraithShroud,
-    //RadiantStone,
-    //InsectoidCluster,
-    //AetherBloom,
-    //RuneCloak,
-    //CryoShard,
-    //InfinityLoop,
-    //FallenGale,
-    //SoulHarbinger,
-    //AstralCloak,
-    //LunarAmulet,
-    //VortexWings,
-    //MysteriousScrawl,
-    //Necrosphere,
-    //QuantumSeed,
-    //ArcaneElixir,
-    //GravitationalLens,
-    //EthericTotem,
-    //AetheriumCrystal,
-    //InfinityCodex,
-    //ShatterCore,
-    //VoidFirestone,
-    //NecroticVine,
-    //StellarShards,
-    //CyberWorm,
-    //ToxicToxin,
-    //ArcanePlasma,
-    //GloomShade,
-    //PlasmaEye,
-    //MidnightBlade,
-    //ElementalVessel,
-    //AstralVortex,
-    //DreadCore,
-    //AetherShards,
-    //SolarTear,
-    //RadiantTear,
-    //AbyssalFlame,
-    //TransmutedOre,
-    //RunicFrost,
-    //GloomStone,
-    //TracerData,
-    //HeartOfVoid,
-    //CyclonicFragment,
-    //AntiMatterShard,
-    //SpiritLink,
-    //CrimsonDew,
-    //StarlightVine,
-    //BloodVessel,
-    //GhostWanderer,
-    //EtherFang,
-    //SubspaceEcho,
-    //SpectralFragment,
-    //WarpEngine,
-    //OmegaLens,
-    //DoomCore,
-    //GlacialFlare,
-    //NightmareRoot,
-    //TemporalFlame,
-    //AstralStitch,
-    //MindEater,
-    //ToxicFlesh,
-    //InfernalPulse,
-    //ShadowLoom,
-    //EternalRest,
-    //ElementalSphere,
-    //FractalCore,
-    //CrypticAnomaly,
-    //BioluminescentFungus,
-    //SpacetimeShard,
-    //ArcaneMap,
-    //SubzeroGem,
-    //CyberFlesh,
-    //PlasmaOrb,
-    //TimeWanderer,
-    //StarfallRune,
-    //NeuroCrystal,
-    //EchoRune,
-    //VenomFang,
-    //GalacticCore,
-    //StarFury,
-    //ChaosCore,
-    //DreamShard,
-    //WarpFragment,
-    //BloodRitual,
-    //AntiMatterCore,
-    //EldritchCharm,
-    //DreadRavenFeather,
-    //EtherWing,
-    //ElementalMark,
-    //ChronoGlyph,
-    //FluxShard,
-    //StasisCore,
-    //NebulaRobe,
-    //EtherealBloom,
-    //MysticEther,
-    //SoulVessel,
-    //ExoShell,
-    //CursedGlyph,
-    //ShadowOrb,
-    //StarfallGem,
-    //CobaltCrystal,
-    //BloodShard,
-    //RadiantShard,
-    //InfernoCore,
-    //TemporalBloom,
-    //VortexWand,
-    //DreadStone,
-    //PsyTechWires,
-    //AstralEssence,
-    //ArcaneNexus,
-    //VoidLoom,
-    //GalacticAnomaly,
-    //FractalKey,
-    //FluxWings,
-    //MirageShard,
-    //TidalRune,
-    //CrypticCrystal,
-    //TwilightEmber,
-    //NexusCore,
-    //VoidBringer,
-    //FrostDew,
-    //PsyCore,
-    //LunarShard,
-    //NecroticVessel,
-    //ObsidianRune,
-    //RunicEssence,
-    //StarDustWings,
-    //EtherWhispers,
-    //IronLeaf,
-    //PhantomEssence,
-    //TemporalCore,
-    //VoidFang,
-    //HolographicShard,
-    //SpiritRune,
-    //CursedBeak,
-    //BloodPetal,
-    //CycloneFragment,
-    //GlimmerDust,
-    //ShadowSigil,
-    //OmegaShards,
-    //AshenVessel,
-    //SpectralDust,
-    //VoidWhisperer,
-    //CryoGem,
-    //RadiantPhoenix,
-    //BiotechShards,
-    //NebulaEmber,
-    //FractalRune,
-    //CursedMoss,
-    //ArcaneInferno,
-    //TemporalRune,
-    //BiomeEssence,
-    //WraithCore,
-    //GravitationalShard,
-    //ArcaneVessel,
-    //SolarCrystals,
-    //PsychoShard,
-    //AstralGlove,
-    //StarlightCore,
-    //DarkEclipse,
-    //TemporalFrost,
-    //Mindstone,
-    //EternalKey,
-    //EclipseFragment,
-    //SpecterTear,
-    //QuantumWings,
-    //InfinityCore,
-    //PhantomLurker,
-    //AetherEssence,
-    //GhostFragment,
-    //TwilightRune,
-    //StellarCore,
-    //SolarShard,
-    //PsychicLens,
-    //EbonFeather,
-    //WarpTalon,
-    //Deathroot,
-    //LunarFury,
-    //HexDust,
-    //DreadFragment,
-    //VoidLens,
-    //StarboundVessel,
-    //QuantumVine,
-    //AetherWings,
-    //NeuroShards,
-    //ShatteredGems,
-    //TemporalEssence,
-    //PhantomGlimmer,
-    //BioEssence,
-    //ArcaneObelisk,
-    //AstralRune,
-    //DarkWhisper,
-    //NebulaGlyph,
-    //ShadowLeaf,
-    //SolarVessel,
-    //LunarTalisman,
-    //DeathOrb,
-    //ChaosTalon,
-    //SpectralWings,
-    //RadiantVessel,
-    //AstralFeather,
-    //AbyssStone,
-    //FluxBlade,
-    //CelestialSoul,
-    //TimeShards,
-    //BloodNectar,
-    //EthericCore,
-    //EtherShard,
-    //SolarWing,
-    //ShadowMist,
-    //MindHollow,
-    //TemporalLoop,
-    //AbyssalRoot,
-    //LunarTear,
-    //RadiantScepter,
-    //BioShard,
-    //EldritchGrimoire,
-    //ArcaneWings,
-    //FragmentedSoul,
-    //VoidElement,
-    //BloodShackle,
-    //TemporalDusk,
-    //FrostVessel,
-    //EtherTear,
-    //SolarAegis,
-    //StellarTalon,
-    //CelestialLoom,
-    //PsionicWeb,
-    //TemporalNight,
-    //PhantasmalOrb,
-    //ArcaneCatalyst,
-    //CosmicOrb,
-    //GravitonVine,
-    //ShadowFang,
-    //CrypticSeed,
-    //AbyssalCloak,
-    //VoidOrb,
-    //WyrmFeather,
-    //PhantomVisage,
-    //TemporalWanderer,
-    //BioVessel,
-    //EtherDew,
-    //VoidCipher,
-    //ArcaneGlyph,
-    //RadiantTalisman,
-    //HallowedKey,
-    //StellarAura,
-    //TemporalShroud,
-    //MindWitch,
-    //ChronoMark,
-    //Starblade,
-    //DreamGrimoire,
-    //PhantomFang,
-    //SolarGlimmer,
-    //AstralHex,
-    //AbyssalFragment,
-    //BloodSigil,
-    //ExoShard,
-    //QuantumRelic,
-    //StellarBloom,
-    //CosmicLens,
-    //ElementalVial,
-    //ArcaneSoulstone,
-    //VoidCatalyst,
-    //TemporalVessel,
-    //SpectralEssence,
-    //ShadowTear,
-    //ChronoShroud,
-    //RadiantWanderer,
-    //LunarEmbrace,
-    //SpectralShard,
-    //CosmicFlesh,
-    //TemporalMark,
-    //StarSeeker,
-    //EldritchCrown,
-    //ArcaneChisel,
-    //PhantomMane,
-    //LunarLattice,
-    //InfernalWings,
-    //EtherVortex,
-    //BioluminescentShards,
-    //VoidHunter,
-    //AstralMist,
-    //ShadowShard,
-    //GravitationalCore,
-    //NebulaCloak,
-    //BloodFlesh,
-    //RadiantSoulstone,
-    //SolarMist,
-    //QuantumAether,
-    //VoidScepter,
-    //DarkEssence,
-    //EthericBloom,
-    //ArcanePortal,
-    //EternalGale,
-    //TimeFlare,
-    //VoidVine,
-    //PsychedelicDust,
-    //AbyssCloak,
-    //EldritchRift,
-    //TemporalGlyph,
-    //SolarCrown,
-    //SpectralVial,
-    //RadiantEcho,
-    //BloodWisp,
-    //ShatteredChronicle,
-    //AstralCage,
-    //VoidThorn,
-    //CelestialEclipse,
-    //ShadowSoul,
-    //GalacticRune,
-    //QuantumVessel,
-    //ElementalShard,
-    //RadiantSigil,
-    //SoulBloom,
-    //NebulaCore,
-    //WraithWing,
-    //StellarSoul,
-    //SolarPhoenix,
-    //RadiantFlare,
-    //TemporalMirror,
-    //BloodWanderer,
-    //ChronoShard,
-    //AbyssalFang,
-    //GravitonSeed,
-    //ArcaneNest,
-    //VoidFragment,
-    //TemporalWing,
-    //ShadowMark,
-    //CelestialGlyph,
-    //SpectralTear,
-    //EtherSoul,
-    //StellarRune,
-    //VoidStone,
-    //QuantumTalon,
-    //CosmicVessel,
-    //AbyssCore,
-    //RadiantThorn,
-    //PhantomWings,
-    //GravitationalSoul,
-    //BloodShroud,
-    //ArcaneGlyphs,
-    //StellarShroud,
-    //EtherBloom,
-    //SoulTear,
-    //QuantumEmber,
-    //TemporalCloak,
-    //VoidMirror,
-    //AstralPhantom,
-    //EternalDew,
-    //SoulDrift,
-    //GravitonGlyph,
-    //VoidPetal,
-    //BloodCrypt,
-    //RadiantFragment,
-    //QuantumShards,
-    //TemporalCage,
-    //StellarEssence,
-    //ChronoVessel,
-    //ArcaneShards,
-    //VoidShroud,
-    //EtherPhantom,
-    //CosmicCore,
-    //AbyssalSoul,
-    //ArcaneRing,
-    //TemporalRoot,
-    //StellarTear,
-    //SolarFragment,
-    //LunarKey,
-    //QuantumRoot,
-    //AbyssalSeed,
-    //ArcaneElement,
-    //AstralGlimmer,
-    //VoidRune,
-    //SolarFlame,
-    //GravitationalFlare,
-    //StellarVessel,
-    //RadiantEye,
-    //TimeShard,
-    //AbyssSoul,
-    //ElementalCloak,
-    //ArcaneFeather,
-    //TemporalLeaf,
-    //QuantumThorn,
-    //BloodMark,
-    //StellarScepter,
-    //ShadowShards,
-    //EthericGlyph,
-    //AbyssGlyph,
-    //StellarEmber,
-    //VoidSear,
-    //CosmicVein,
-    //GravitationalAura,
-    //TemporalTalon,
-    //ArcaneRune,
-    //BloodFang,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namespaces_interned() {
+        let a = Namespace::new("cooking_time").unwrap();
+        let b = "cooking_time".parse::<Namespace>().unwrap();
+
+        assert_eq!(a, b);
+        assert!(std::ptr::eq(a.as_str(), b.as_str()));
+        assert_eq!(a.to_string(), "cooking_time");
+    }
+
+    #[test]
+    fn namespaced_ids_validated() {
+        let id = "base::auto_arm".parse::<NamespacedId>().unwrap();
+        assert_eq!(id.namespace.as_str(), "base");
+        assert_eq!(id.id, "auto_arm");
+        assert_eq!(id.to_string(), "base::auto_arm");
+
+        let spaced = "tyconic::Lemon stand I".parse::<NamespacedId>().unwrap();
+        assert_eq!(spaced.id, "Lemon stand I");
+
+        assert_eq!(
+            "auto_arm".parse::<NamespacedId>(),
+            Err(NamespaceError::MissingNamespace("auto_arm".into()))
+        );
+        assert_eq!(
+            "::auto_arm".parse::<NamespacedId>(),
+            Err(NamespaceError::EmptyNamespace)
+        );
+        assert_eq!(
+            "Base::auto_arm".parse::<NamespacedId>(),
+            Err(NamespaceError::InvalidNamespace("Base".into()))
+        );
+        assert_eq!(
+            "base::".parse::<NamespacedId>(),
+            Err(NamespaceError::InvalidId("".into()))
+        );
+        assert_eq!(
+            "base::auto::arm".parse::<NamespacedId>(),
+            Err(NamespaceError::InvalidId("auto::arm".into()))
+        );
+
+        let tyconic = Namespace::new("tyconic").unwrap();
+        assert_eq!(
+            NamespacedId::parse_in(tyconic, "cheese_wheel")
+                .unwrap()
+                .to_string(),
+            "tyconic::cheese_wheel"
+        );
+    }
+
+    #[test]
+    fn namespaces_registered_by_mods() {
+        let mut namespaces = Namespaces::default();
+        let base = namespaces.register(&"base_0.1.0".parse().unwrap()).unwrap();
+
+        assert!(namespaces.parse("base::auto_arm").is_ok());
+        assert_eq!(
+            namespaces.parse("dlc::auto_arm"),
+            Err(NamespaceError::Unregistered(Namespace::new("dlc").unwrap()))
+        );
+        assert!(matches!(
+            namespaces.register(&"base_0.2.0".parse().unwrap()),
+            Err(NamespaceError::AlreadyRegistered { namespace, .. }) if namespace == base
+        ));
+        assert!(matches!(
+            namespaces.register(&"Third Party_1.0.0".parse().unwrap()),
+            Err(NamespaceError::InvalidNamespace(_))
+        ));
+    }
 }
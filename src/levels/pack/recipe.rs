@@ -20,3 +20,19 @@ pub struct Recipe {
     /// duration in milliseconds
     pub duration: u32,
 }
+
+impl Recipe {
+    /// What the recipe lacks to be crafted, a recipe without it would craft from nothing or
+    /// instantly
+    pub fn missing(&self) -> Option<&'static str> {
+        if self.ingredients.is_empty() {
+            Some("ingredients")
+        } else if self.output.is_empty() {
+            Some("output")
+        } else if self.duration == 0 {
+            Some("duration")
+        } else {
+            None
+        }
+    }
+}
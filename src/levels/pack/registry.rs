@@ -0,0 +1,444 @@
+//! Everything the mods of a level declared, looked up by namespaced id such as
+//! `tyconic::cheese_wheel`
+
+use super::*;
+use bevy::utils::HashMap;
+
+pub type ItemRegistry = Registry<ItemDeclared>;
+pub type RecipeRegistry = Registry<RecipeDeclared>;
+pub type ResearchRegistry = Registry<ResearchDeclared>;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RegistryError {
+    Missing {
+        kind: &'static str,
+        id: String,
+    },
+    /// a declared id which isn't a valid `ns::id` of a loaded mod
+    InvalidId {
+        kind: &'static str,
+        id: String,
+        error: NamespaceError,
+    },
+    /// a declaration refers to an id which isn't declared
+    Unresolved {
+        kind: &'static str,
+        id: String,
+        referenced_by: String,
+    },
+    /// a declaration lacks what it needs to be used, it is left out of the registry
+    Incomplete {
+        kind: &'static str,
+        id: String,
+        missing: &'static str,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Missing { kind, id } => {
+                if id.contains(NAMESPACE_SEPARATOR) {
+                    write!(f, "no {} `{}` is declared", kind, id)
+                } else {
+                    write!(
+                        f,
+                        "no {} `{}` is declared, ids are namespaced like `mod{}{}`",
+                        kind, id, NAMESPACE_SEPARATOR, id
+                    )
+                }
+            }
+            Self::InvalidId { kind, id, error } => {
+                write!(f, "{} `{}` is invalid. {}", kind, id, error)
+            }
+            Self::Unresolved {
+                kind,
+                id,
+                referenced_by,
+            } => write!(
+                f,
+                "`{}` refers to {} `{}` which is not declared",
+                referenced_by, kind, id
+            ),
+            Self::Incomplete { kind, id, missing } => {
+                write!(f, "{} `{}` has no {} and is left out", kind, id, missing)
+            }
+        }
+    }
+}
+
+/// Declarations of one kind once every mod is merged
+#[derive(Resource, Debug, Clone)]
+pub struct Registry<T: Declarable> {
+    declared: Vec<T>,
+    index: HashMap<String, usize>,
+    touched: HashMap<String, DeclarationTouch>,
+}
+
+impl<T: Declarable> Default for Registry<T> {
+    fn default() -> Self {
+        Merged::default().into()
+    }
+}
+
+impl<T: Declarable> From<Merged<T>> for Registry<T> {
+    fn from(merged: Merged<T>) -> Self {
+        let mut registry = Self {
+            declared: merged.declared,
+            index: HashMap::default(),
+            touched: merged.touched,
+        };
+        registry.reindex();
+        registry
+    }
+}
+
+impl<T: Declarable> Registry<T> {
+    fn reindex(&mut self) {
+        self.index = self
+            .declared
+            .iter()
+            .enumerate()
+            .map(|(index, declared)| (declared.id().to_string(), index))
+            .collect();
+    }
+
+    pub fn get(&self, id: &str) -> Result<&T, RegistryError> {
+        self.index
+            .get(id)
+            .map(|index| &self.declared[*index])
+            .ok_or_else(|| RegistryError::Missing {
+                kind: T::KIND,
+                id: id.to_string(),
+            })
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.index.contains_key(id)
+    }
+
+    /// In declaration order
+    pub fn iter(&self) -> impl Iterator<Item = &T> {
+        self.declared.iter()
+    }
+
+    /// Declared under `namespace`, whichever mod declared them
+    pub fn in_namespace(&self, namespace: Namespace) -> impl Iterator<Item = &T> {
+        self.declared.iter().filter(move |declared| {
+            declared
+                .id()
+                .parse::<NamespacedId>()
+                .is_ok_and(|id| id.namespace == namespace)
+        })
+    }
+
+    /// Declared ids which don't parse as `ns::id` or whose namespace no loaded mod provides,
+    /// such as overrides of content from a mod missing in the profile
+    pub fn invalid_ids(&self, namespaces: &Namespaces) -> Vec<RegistryError> {
+        self.declared
+            .iter()
+            .filter_map(|declared| {
+                namespaces
+                    .parse(declared.id())
+                    .err()
+                    .map(|error| RegistryError::InvalidId {
+                        kind: T::KIND,
+                        id: declared.id().to_string(),
+                        error,
+                    })
+            })
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.declared.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.declared.is_empty()
+    }
+
+    /// Which mod last declared, overrode, patched or deleted `id`
+    pub fn touched_by(&self, id: &str) -> Option<&DeclarationTouch> {
+        self.touched.get(id)
+    }
+}
+
+impl ItemRegistry {
+    pub fn item(&self, id: &ItemId) -> Result<&ItemDeclared, RegistryError> {
+        self.get(&id.0)
+    }
+}
+
+impl RecipeRegistry {
+    pub fn recipe(&self, id: &RecipeId) -> Result<&RecipeDeclared, RegistryError> {
+        self.get(&id.0)
+    }
+
+    /// Leaves out recipes missing ingredients, output or duration, like those declared by a
+    /// plain [`RecipePack`] and never patched
+    pub fn reject_incomplete(&mut self) -> Vec<RegistryError> {
+        let mut errors = vec![];
+        self.declared
+            .retain(|declared| match declared.recipe.missing() {
+                Some(missing) => {
+                    errors.push(RegistryError::Incomplete {
+                        kind: RecipeDeclared::KIND,
+                        id: declared.id.0.clone(),
+                        missing,
+                    });
+                    false
+                }
+                None => true,
+            });
+        self.reindex();
+        errors
+    }
+}
+
+impl ResearchRegistry {
+    pub fn research(&self, id: &ResearchId) -> Result<&ResearchDeclared, RegistryError> {
+        self.get(&id.0)
+    }
+}
+
+/// Every id a recipe or research refers to which no mod declares
+pub fn unresolved_references(
+    items: &ItemRegistry,
+    recipes: &RecipeRegistry,
+    research: &ResearchRegistry,
+) -> Vec<RegistryError> {
+    let mut errors = vec![];
+
+    for recipe in recipes.iter() {
+        let entries = recipe
+            .recipe
+            .ingredients
+            .iter()
+            .chain(&recipe.recipe.output);
+        for entry in entries {
+            if let Err(RegistryError::Missing { kind, id }) = items.item(&entry.item) {
+                errors.push(RegistryError::Unresolved {
+                    kind,
+                    id,
+                    referenced_by: recipe.id.0.clone(),
+                });
+            }
+        }
+
+        for required in recipe.recipe.research_required.iter() {
+            if let Err(RegistryError::Missing { kind, id }) = research.research(required) {
+                errors.push(RegistryError::Unresolved {
+                    kind,
+                    id,
+                    referenced_by: recipe.id.0.clone(),
+                });
+            }
+        }
+    }
+
+    for declared in research.iter() {
+        for required in declared.required_research.iter() {
+            if let Err(RegistryError::Missing { kind, id }) = research.research(required) {
+                errors.push(RegistryError::Unresolved {
+                    kind,
+                    id,
+                    referenced_by: declared.id.0.clone(),
+                });
+            }
+        }
+    }
+
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(id: &'static str) -> ItemDeclared {
+        ItemDeclared {
+            id: id.into(),
+            category: "food".into(),
+            stack_size: 10,
+        }
+    }
+
+    fn namespace(name: &str) -> Namespace {
+        Namespace::new(name).unwrap()
+    }
+
+    #[test]
+    fn bare_ids_namespaced_by_declaring_mod() {
+        let mut items = Merged::<ItemDeclared>::default();
+
+        items.apply(
+            "base",
+            Declaration::Declare(item("cheese_wheel")).namespaced(namespace("base")),
+        );
+        items.apply(
+            "tyconic",
+            Declaration::Declare(item("cheese_wheel")).namespaced(namespace("tyconic")),
+        );
+        items.apply(
+            "tyconic",
+            Declaration::Patch(ItemPatch {
+                id: "base::cheese_wheel".into(),
+                category: Some("dairy".into()),
+                stack_size: None,
+            })
+            .namespaced(namespace("tyconic")),
+        );
+
+        let items = ItemRegistry::from(items);
+
+        assert_eq!(items.len(), 2);
+        assert_eq!(
+            items.item(&"base::cheese_wheel".into()).unwrap().category,
+            "dairy"
+        );
+        assert_eq!(
+            items.touched_by("base::cheese_wheel").unwrap().mod_name,
+            "tyconic"
+        );
+        assert_eq!(
+            items
+                .in_namespace(namespace("tyconic"))
+                .map(|declared| declared.id.0.as_str())
+                .collect::<Vec<_>>(),
+            vec!["tyconic::cheese_wheel"]
+        );
+    }
+
+    #[test]
+    fn ids_of_unloaded_mods_reported() {
+        let mut namespaces = Namespaces::default();
+        namespaces.register(&"base_0.1.0".parse().unwrap()).unwrap();
+
+        let mut items = Merged::<ItemDeclared>::default();
+        items.apply(
+            "base",
+            Declaration::Declare(item("cheese_wheel")).namespaced(namespace("base")),
+        );
+        items.apply(
+            "base",
+            Declaration::Override(item("dlc::truffle")).namespaced(namespace("base")),
+        );
+
+        assert_eq!(
+            ItemRegistry::from(items).invalid_ids(&namespaces),
+            vec![RegistryError::InvalidId {
+                kind: "item",
+                id: "dlc::truffle".into(),
+                error: NamespaceError::Unregistered(namespace("dlc")),
+            }]
+        );
+    }
+
+    #[test]
+    fn missing_ids_reported() {
+        let items = ItemRegistry::default();
+
+        assert_eq!(
+            items.get("cheese_wheel").unwrap_err().to_string(),
+            "no item `cheese_wheel` is declared, ids are namespaced like `mod::cheese_wheel`"
+        );
+        assert_eq!(
+            items.get("tyconic::cheese_wheel").unwrap_err(),
+            RegistryError::Missing {
+                kind: "item",
+                id: "tyconic::cheese_wheel".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn unresolved_references_reported() {
+        let mut items = Merged::<ItemDeclared>::default();
+        items.apply(
+            "tyconic",
+            Declaration::Declare(item("cheese_wheel")).namespaced(namespace("tyconic")),
+        );
+
+        let mut recipes = Merged::<RecipeDeclared>::default();
+        recipes.apply(
+            "tyconic",
+            Declaration::Declare(RecipeDeclared {
+                id: RecipeId("cheese_pizza".into()),
+                recipe: Recipe {
+                    ingredients: vec![
+                        ItemEntry {
+                            item: "cheese_wheel".into(),
+                            quantity: 1,
+                        },
+                        ItemEntry {
+                            item: "base::dough".into(),
+                            quantity: 1,
+                        },
+                    ],
+                    output: vec![],
+                    research_required: vec![ResearchId("Pizzeria I".into())],
+                    duration: 1000,
+                },
+            })
+            .namespaced(namespace("tyconic")),
+        );
+
+        let errors =
+            unresolved_references(&items.into(), &recipes.into(), &ResearchRegistry::default());
+
+        assert_eq!(
+            errors,
+            vec![
+                RegistryError::Unresolved {
+                    kind: "item",
+                    id: "base::dough".into(),
+                    referenced_by: "tyconic::cheese_pizza".into(),
+                },
+                RegistryError::Unresolved {
+                    kind: "research",
+                    id: "tyconic::Pizzeria I".into(),
+                    referenced_by: "tyconic::cheese_pizza".into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn incomplete_recipes_left_out() {
+        let entry = |item: &'static str| ItemEntry {
+            item: item.into(),
+            quantity: 1,
+        };
+        let mut recipes = Merged::<RecipeDeclared>::default();
+        for id in ["folk_pizza", "rustic_pizza"] {
+            recipes.apply(
+                "tyconic",
+                Declaration::Declare(RecipeId(id.into()).into()).namespaced(namespace("tyconic")),
+            );
+        }
+        recipes.apply(
+            "tyconic",
+            Declaration::Patch(RecipePatch {
+                id: RecipeId("tyconic::folk_pizza".into()),
+                ingredients: Some(vec![entry("tyconic::dough")]),
+                output: Some(vec![entry("tyconic::pizza_slice")]),
+                research_required: None,
+                duration: Some(2000),
+            })
+            .namespaced(namespace("tyconic")),
+        );
+
+        let mut recipes = RecipeRegistry::from(recipes);
+        assert_eq!(
+            recipes.reject_incomplete(),
+            vec![RegistryError::Incomplete {
+                kind: "recipe",
+                id: "tyconic::rustic_pizza".into(),
+                missing: "ingredients",
+            }]
+        );
+        assert!(recipes.contains("tyconic::folk_pizza"));
+        assert!(!recipes.contains("tyconic::rustic_pizza"));
+    }
+}
@@ -1 +1,858 @@
+//! Achievements mods declare in `declarations/achievements.ron`, unlocked once their condition
+//! holds in any level. Unlocks are kept per mod profile in the config directory, so a modded
+//! run doesn't pollute the achievements of a vanilla one.
 
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use bevy::reflect::serde::{ReflectDeserializer, ReflectSerializer};
+use bevy::reflect::TypeRegistry;
+use bevy::utils::HashMap;
+use serde::de::DeserializeSeed;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fmt, fs};
+
+pub struct AchievementsPlugin;
+
+impl Plugin for AchievementsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<AchievementId>()
+            .register_type::<AchievementDeclarations>()
+            .register_type::<AchievementPack>()
+            .register_type::<UnlockedAchievements>()
+            .init_resource::<AchievementRegistry>()
+            .add_event::<AchievementUnlocked>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                load_unlocked_achievements.after(init_mod_profile),
+            )
+            .add_systems(Simulation, run_achievements.after(run_research));
+    }
+}
+
+pub const ACHIEVEMENTS_PATH: &str = "achievements";
+
+pub type AchievementRegistry = Registry<AchievementDeclared>;
+
+#[derive(Component, Debug, Reflect, PartialEq, Eq, Clone, Hash)]
+pub struct AchievementId(pub String);
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct AchievementDeclared {
+    pub id: AchievementId,
+    pub title: String,
+    pub description: String,
+    /// image within the `assets` directory of the declaring mod
+    pub icon: Option<String>,
+    /// title and description stay secret until unlocked
+    pub hidden: bool,
+    pub condition: ConditionFlag,
+}
+
+/// Fields left `None` keep what was declared before
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct AchievementPatch {
+    pub id: AchievementId,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub hidden: Option<bool>,
+    pub condition: Option<ConditionFlag>,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum AchievementDeclaration {
+    Declare(AchievementDeclared),
+    Override(AchievementDeclared),
+    Patch(AchievementPatch),
+    Delete(AchievementId),
+}
+
+/// Content of `declarations/achievements.ron`
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct AchievementDeclarations(pub Vec<AchievementDeclaration>);
+
+/// A plain list of achievements, read as declaring every one of them
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct AchievementPack(pub Vec<AchievementDeclared>);
+
+impl Declarable for AchievementDeclared {
+    type Patch = AchievementPatch;
+
+    const KIND: &'static str = "achievement";
+
+    fn id(&self) -> &str {
+        &self.id.0
+    }
+
+    fn patch_id(patch: &Self::Patch) -> &str {
+        &patch.id.0
+    }
+
+    fn patch(&mut self, patch: &Self::Patch) {
+        if let Some(title) = &patch.title {
+            self.title = title.clone();
+        }
+        if let Some(description) = &patch.description {
+            self.description = description.clone();
+        }
+        if let Some(icon) = &patch.icon {
+            self.icon = Some(icon.clone());
+        }
+        if let Some(hidden) = patch.hidden {
+            self.hidden = hidden;
+        }
+        if let Some(condition) = &patch.condition {
+            self.condition = condition.clone();
+        }
+    }
+
+    fn namespace(&mut self, namespace: Namespace) {
+        self.id.0 = namespaced(namespace, &self.id.0);
+    }
+
+    fn namespace_patch(patch: &mut Self::Patch, namespace: Namespace) {
+        patch.id.0 = namespaced(namespace, &patch.id.0);
+    }
+}
+
+impl From<AchievementDeclaration> for Declaration<AchievementDeclared> {
+    fn from(declaration: AchievementDeclaration) -> Self {
+        match declaration {
+            AchievementDeclaration::Declare(declared) => Self::Declare(declared),
+            AchievementDeclaration::Override(declared) => Self::Override(declared),
+            AchievementDeclaration::Patch(patch) => Self::Patch(patch),
+            AchievementDeclaration::Delete(id) => Self::Delete(id.0),
+        }
+    }
+}
+
+impl AchievementRegistry {
+    pub fn achievement(&self, id: &AchievementId) -> Result<&AchievementDeclared, RegistryError> {
+        self.get(&id.0)
+    }
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct UnlockedAchievement {
+    pub id: AchievementId,
+    /// seconds since the unix epoch
+    pub unlocked_at: u64,
+}
+
+/// Content of `achievements/<profile>.ron` in the config directory
+#[derive(Reflect, Debug, Default, Clone, PartialEq)]
+pub struct UnlockedAchievements(pub Vec<UnlockedAchievement>);
+
+impl UnlockedAchievements {
+    pub fn get(&self, id: &AchievementId) -> Option<&UnlockedAchievement> {
+        self.0.iter().find(|unlocked| unlocked.id == *id)
+    }
+
+    pub fn is_unlocked(&self, id: &AchievementId) -> bool {
+        self.get(id).is_some()
+    }
+
+    /// Returns whether it wasn't unlocked before
+    pub fn unlock(&mut self, id: AchievementId, unlocked_at: u64) -> bool {
+        if self.is_unlocked(&id) {
+            return false;
+        }
+        self.0.push(UnlockedAchievement { id, unlocked_at });
+        true
+    }
+}
+
+/// Achievements of the mod profile of the current level
+#[derive(Resource, Debug, Default)]
+pub struct Achievements {
+    pub unlocked: UnlockedAchievements,
+    /// none when the unlocks couldn't be read, so they aren't overwritten
+    pub path: Option<PathBuf>,
+    trackers: HashMap<AchievementId, ConditionTracker>,
+}
+
+impl Achievements {
+    pub fn new(unlocked: UnlockedAchievements, path: Option<PathBuf>) -> Self {
+        Self {
+            unlocked,
+            path,
+            trackers: default(),
+        }
+    }
+}
+
+/// Mods the [`AchievementRegistry`] was declared by, kept once the level is left so the
+/// achievement browser doesn't have to read them again
+#[derive(Resource, Debug, Clone)]
+pub struct DeclaredAchievementProfile {
+    /// [`profile_key`] of the mods
+    pub key: String,
+    pub mod_profile: ModProfile,
+}
+
+/// Sent once an achievement unlocks, along with a notification
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct AchievementUnlocked(pub AchievementId);
+
+#[derive(Debug)]
+pub enum AchievementError {
+    Io(std::io::Error),
+    Malformed(String),
+}
+
+impl fmt::Display for AchievementError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Malformed(reason) => write!(f, "unlocked achievements are malformed. {}", reason),
+        }
+    }
+}
+
+impl From<std::io::Error> for AchievementError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Mod names of the profile, sorted so versions and load order don't matter, such as
+/// `base+tyconic`
+pub fn profile_key(profile_config: &ModProfileConfig) -> String {
+    key_of(profile_config.0.iter().map(|shorthand| {
+        shorthand
+            .0
+            .parse::<Meta>()
+            .map_or_else(|_| shorthand.0.clone(), |meta| meta.mod_name)
+    }))
+}
+
+/// [`profile_key`] of mods already resolved
+pub fn mod_profile_key(mod_profile: &ModProfile) -> String {
+    key_of(
+        mod_profile
+            .0
+            .iter()
+            .map(|(mod_pack, _)| mod_pack.mod_id.mod_name.clone()),
+    )
+}
+
+fn key_of(mod_names: impl Iterator<Item = String>) -> String {
+    let mut mod_names = mod_names.collect::<Vec<_>>();
+    mod_names.sort();
+    mod_names.dedup();
+    mod_names.join("+")
+}
+
+/// Profiles with unlocked achievements in [`achievements_dir`], sorted
+pub fn achievement_profiles() -> Vec<String> {
+    let Ok(entries) = fs::read_dir(achievements_dir()) else {
+        return vec![];
+    };
+
+    let mut profiles = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+        .filter_map(|path| Some(path.file_stem()?.to_string_lossy().into_owned()))
+        .collect::<Vec<_>>();
+    profiles.sort();
+    profiles
+}
+
+/// platform specific directory holding the unlocked achievements of every profile
+pub fn achievements_dir() -> PathBuf {
+    let project_dir = directories::ProjectDirs::from(
+        env!("PROJECT_QUALIFIER"),
+        env!("PROJECT_ORGANIZATION"),
+        env!("PROJECT_APPLICATION"),
+    )
+    .expect("no valid home directory path could be retrieved from the operating system");
+
+    project_dir.config_dir().join(ACHIEVEMENTS_PATH)
+}
+
+pub fn achievements_path(profile_key: &str) -> PathBuf {
+    achievements_dir().join(profile_key).with_extension("ron")
+}
+
+/// Nothing is unlocked for a profile never played
+pub fn read_unlocked_achievements(
+    path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<UnlockedAchievements, AchievementError> {
+    let ron = match fs::read_to_string(path) {
+        Ok(ron) => ron,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(default()),
+        Err(err) => return Err(err.into()),
+    };
+
+    let mut deserializer = ron::de::Deserializer::from_str(&ron)
+        .map_err(|err| AchievementError::Malformed(err.to_string()))?;
+    let reflected = ReflectDeserializer::new(type_registry)
+        .deserialize(&mut deserializer)
+        .map_err(|err| AchievementError::Malformed(err.to_string()))?;
+
+    UnlockedAchievements::from_reflect(&*reflected)
+        .ok_or_else(|| AchievementError::Malformed("not a list of achievements".into()))
+}
+
+/// Written next to the real file first so a crash can't leave it half written
+pub fn write_unlocked_achievements(
+    path: &Path,
+    unlocked: &UnlockedAchievements,
+    type_registry: &TypeRegistry,
+) -> Result<(), AchievementError> {
+    let serialized = ron::ser::to_string_pretty(
+        &ReflectSerializer::new(unlocked, type_registry),
+        ron::ser::PrettyConfig::new()
+            .depth_limit(4)
+            .indentor("  ".into()),
+    )
+    .map_err(|err| AchievementError::Malformed(err.to_string()))?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp = temp_path(path);
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(serialized.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+    fs::rename(&temp, path)?;
+
+    Ok(())
+}
+
+/// How an achievement shows up in the achievement browser
+#[derive(Debug, Clone, PartialEq)]
+pub struct AchievementListing {
+    pub id: AchievementId,
+    pub title: String,
+    pub description: String,
+    pub icon: Option<String>,
+    pub unlocked_at: Option<u64>,
+}
+
+/// Every declared achievement in declaration order, hidden ones keep their secret until unlocked
+pub fn achievement_listings(
+    achievements: &AchievementRegistry,
+    unlocked: &UnlockedAchievements,
+) -> Vec<AchievementListing> {
+    achievements
+        .iter()
+        .map(|declared| {
+            let unlocked_at = unlocked
+                .get(&declared.id)
+                .map(|unlocked| unlocked.unlocked_at);
+            if declared.hidden && unlocked_at.is_none() {
+                AchievementListing {
+                    id: declared.id.clone(),
+                    title: "Hidden achievement".into(),
+                    description: "Keep playing to find out".into(),
+                    icon: None,
+                    unlocked_at,
+                }
+            } else {
+                AchievementListing {
+                    id: declared.id.clone(),
+                    title: declared.title.clone(),
+                    description: declared.description.clone(),
+                    icon: declared.icon.clone(),
+                    unlocked_at,
+                }
+            }
+        })
+        .collect()
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_secs())
+}
+
+pub fn load_unlocked_achievements(
+    mut cmd: Commands,
+    level: Query<&ModProfileConfig, With<Level>>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok(profile_config) = level.get_single() else {
+        return;
+    };
+    let path = achievements_path(&profile_key(profile_config));
+
+    let achievements = match read_unlocked_achievements(&path, &type_registry.read()) {
+        Ok(unlocked) => Achievements::new(unlocked, Some(path)),
+        Err(err) => {
+            error!(
+                "unable to read achievements at {}. {}",
+                path.to_string_lossy(),
+                err
+            );
+            Notification {
+                title: "Unable to read achievements".into(),
+                level: NotificationLevel::Warning,
+                description: "Achievements unlocked in this session won't be kept".into(),
+            }
+            .queue(Some(Duration::from_secs(10)), &mut notifications_channel);
+            Achievements::new(default(), None)
+        }
+    };
+
+    cmd.insert_resource(achievements);
+}
+
+/// Resources or metrics conditions read were written
+type LevelChanged = Or<(Changed<Level>, Changed<LevelMetrics>)>;
+
+#[allow(clippy::too_many_arguments)]
+pub fn run_achievements(
+    settings: Res<SimulationSettings>,
+    declared: Res<AchievementRegistry>,
+    achievements: Option<ResMut<Achievements>>,
+    type_registry: Res<AppTypeRegistry>,
+    level: Query<(&Level, &LevelMetrics, &ResearchProgress)>,
+    player: Query<&Inventory, With<crate::player::Player>>,
+    held_changed: Query<(), (Changed<Inventory>, With<crate::player::Player>)>,
+    level_changed: Query<(), LevelChanged>,
+    mut flows: EventReader<ItemFlow>,
+    mut research_unlocked: EventReader<ResearchUnlocked>,
+    mut unlocked_channel: EventWriter<AchievementUnlocked>,
+    mut notifications_channel: NotificationChannel,
+) {
+    // nothing a condition reads changes between these, unless it's over time
+    let changed = !flows.is_empty()
+        || !research_unlocked.is_empty()
+        || !held_changed.is_empty()
+        || !level_changed.is_empty();
+    flows.clear();
+    research_unlocked.clear();
+
+    let (Some(mut achievements), Ok((level, metrics, research))) =
+        (achievements, level.get_single())
+    else {
+        return;
+    };
+    let changed = changed || achievements.is_added();
+
+    let pending = declared
+        .iter()
+        .filter(|declared| !achievements.unlocked.is_unlocked(&declared.id))
+        .filter(|declared| changed || declared.condition.over_time())
+        .collect::<Vec<_>>();
+    if pending.is_empty() {
+        return;
+    }
+
+    let context = level_conditions(level, metrics, research, player.iter(), &settings);
+    let mut newly_unlocked = vec![];
+    for declared in pending {
+        let tracker = achievements
+            .trackers
+            .entry(declared.id.clone())
+            .or_default();
+        if tracker.evaluate(&declared.condition, &context) {
+            newly_unlocked.push(declared);
+        }
+    }
+    if newly_unlocked.is_empty() {
+        return;
+    }
+
+    let unlocked_at = unix_time();
+    for declared in newly_unlocked {
+        achievements.trackers.remove(&declared.id);
+        achievements
+            .unlocked
+            .unlock(declared.id.clone(), unlocked_at);
+
+        info!("achievement `{}` unlocked", declared.id.0);
+        Notification {
+            title: "Achievement unlocked".into(),
+            level: NotificationLevel::Info,
+            description: declared.title.clone(),
+        }
+        .queue(Some(Duration::from_secs(5)), &mut notifications_channel);
+        unlocked_channel.send(AchievementUnlocked(declared.id.clone()));
+    }
+
+    let Some(path) = &achievements.path else {
+        return;
+    };
+    if let Err(err) =
+        write_unlocked_achievements(path, &achievements.unlocked, &type_registry.read())
+    {
+        error!(
+            "unable to write achievements at {}. {}",
+            path.to_string_lossy(),
+            err
+        );
+        Notification {
+            title: "Unable to save achievements".into(),
+            level: NotificationLevel::Error,
+            description: err.to_string(),
+        }
+        .queue(None, &mut notifications_channel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InGameState;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    fn achievement(id: &str, hidden: bool, condition: ConditionFlag) -> AchievementDeclared {
+        AchievementDeclared {
+            id: AchievementId(id.into()),
+            title: format!("{} title", id),
+            description: format!("{} description", id),
+            icon: None,
+            hidden,
+            condition,
+        }
+    }
+
+    fn registry(declared: Vec<AchievementDeclared>) -> AchievementRegistry {
+        let mut achievements = Merged::<AchievementDeclared>::default();
+        for declared in declared {
+            achievements.apply("tyconic", Declaration::Declare(declared));
+        }
+        achievements.into()
+    }
+
+    fn money(value: f32) -> ConditionFlag {
+        ConditionFlag::ReachedMetric {
+            metric: "money".into(),
+            value,
+        }
+    }
+
+    #[test]
+    fn profiles_keyed_by_mod_names() {
+        let vanilla = ModProfileConfig(vec!["tyconic_0.0.0-dev".into(), "base_0.0.0-dev".into()]);
+        assert_eq!(profile_key(&vanilla), "base+tyconic");
+
+        let updated = ModProfileConfig(vec!["base_0.1.0".into(), "tyconic_0.2.0".into()]);
+        assert_eq!(profile_key(&updated), profile_key(&vanilla));
+
+        let modded = ModProfileConfig(vec![
+            "tyconic_0.0.0-dev".into(),
+            "base_0.0.0-dev".into(),
+            "overhaul_1.0.0".into(),
+        ]);
+        assert_eq!(profile_key(&modded), "base+overhaul+tyconic");
+    }
+
+    #[test]
+    fn unlocked_achievements_round_trip() {
+        let mut type_registry = TypeRegistry::default();
+        type_registry.register::<UnlockedAchievements>();
+
+        let path = std::env::temp_dir()
+            .join(format!("tyconia_achievements_{}", std::process::id()))
+            .join("base+tyconic.ron");
+        assert_eq!(
+            read_unlocked_achievements(&path, &type_registry).unwrap(),
+            UnlockedAchievements::default()
+        );
+
+        let mut unlocked = UnlockedAchievements::default();
+        assert!(unlocked.unlock(AchievementId("tyconic::first_slice".into()), 1_700_000_000));
+        assert!(!unlocked.unlock(AchievementId("tyconic::first_slice".into()), 1_800_000_000));
+        write_unlocked_achievements(&path, &unlocked, &type_registry).unwrap();
+
+        assert_eq!(
+            read_unlocked_achievements(&path, &type_registry).unwrap(),
+            unlocked
+        );
+        fs::remove_dir_all(path.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn hidden_achievements_listed_once_unlocked() {
+        let achievements = registry(vec![
+            achievement("tyconic::first_slice", false, money(0.)),
+            achievement("tyconic::secret_sauce", true, money(0.)),
+        ]);
+        let mut unlocked = UnlockedAchievements::default();
+
+        let listings = achievement_listings(&achievements, &unlocked);
+        assert_eq!(listings[0].title, "tyconic::first_slice title");
+        assert_eq!(listings[1].title, "Hidden achievement");
+        assert!(listings.iter().all(|listing| listing.unlocked_at.is_none()));
+
+        unlocked.unlock(AchievementId("tyconic::secret_sauce".into()), 10);
+        let listings = achievement_listings(&achievements, &unlocked);
+        assert_eq!(listings[1].title, "tyconic::secret_sauce title");
+        assert_eq!(listings[1].unlocked_at, Some(10));
+    }
+
+    #[test]
+    fn achievements_unlock_in_level() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .add_event::<NotificationEvent>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeRegistry>()
+            .init_resource::<ResearchRegistry>()
+            .add_plugins((
+                SimulationPlugin,
+                MetricsPlugin,
+                ResearchPlugin,
+                AchievementsPlugin,
+            ))
+            .insert_resource(registry(vec![
+                achievement("tyconic::pocket_money", false, money(100.)),
+                achievement(
+                    "tyconic::steady_income",
+                    false,
+                    ConditionFlag::SustainedFor {
+                        conditions: vec![money(100.)],
+                        seconds: 1.,
+                    },
+                ),
+                achievement("tyconic::tycoon", false, money(1_000_000.)),
+            ]))
+            // nothing is written without a path
+            .insert_resource(Achievements::new(default(), None));
+
+        let mut level = Level::default();
+        level.resources.insert("money".into(), 150.);
+        app.world_mut().spawn(level);
+
+        let mut cursor = app
+            .world()
+            .resource::<Events<AchievementUnlocked>>()
+            .get_cursor();
+        let mut unlocked = vec![];
+        for _ in 0..20 {
+            app.update();
+            let events = app.world().resource::<Events<AchievementUnlocked>>();
+            unlocked.extend(
+                cursor
+                    .read(events)
+                    .map(|AchievementUnlocked(id)| id.0.clone()),
+            );
+        }
+
+        assert_eq!(
+            unlocked,
+            vec!["tyconic::pocket_money", "tyconic::steady_income"]
+        );
+        let achievements = app.world().resource::<Achievements>();
+        assert!(!achievements
+            .unlocked
+            .is_unlocked(&AchievementId("tyconic::tycoon".into())));
+    }
+
+    #[test]
+    fn achievements_wait_for_flows_or_research() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .add_event::<NotificationEvent>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeRegistry>()
+            .init_resource::<ResearchRegistry>()
+            .add_plugins((
+                SimulationPlugin,
+                MetricsPlugin,
+                ResearchPlugin,
+                AchievementsPlugin,
+            ))
+            .insert_resource(registry(vec![achievement(
+                "tyconic::pocket_money",
+                false,
+                money(100.),
+            )]))
+            .insert_resource(Achievements::new(default(), None));
+        app.world_mut().spawn(Level::default());
+
+        let pocket_money = AchievementId("tyconic::pocket_money".into());
+        for _ in 0..5 {
+            app.update();
+        }
+
+        // earned without anything telling conditions about it
+        let mut level = app.world_mut().query::<&mut Level>();
+        level
+            .single_mut(app.world_mut())
+            .bypass_change_detection()
+            .resources
+            .insert("money".into(), 150.);
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(!app
+            .world()
+            .resource::<Achievements>()
+            .unlocked
+            .is_unlocked(&pocket_money));
+
+        app.world_mut().send_event(ItemFlow {
+            item: ItemId("tyconic::pizza".into()),
+            quantity: 1,
+            kind: ItemFlowKind::Produced,
+        });
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(app
+            .world()
+            .resource::<Achievements>()
+            .unlocked
+            .is_unlocked(&pocket_money));
+    }
+
+    #[test]
+    fn money_achievements_unlock_once_earned() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .add_event::<NotificationEvent>()
+            .init_resource::<ItemRegistry>()
+            .init_resource::<RecipeRegistry>()
+            .init_resource::<ResearchRegistry>()
+            .add_plugins((
+                SimulationPlugin,
+                MetricsPlugin,
+                ResearchPlugin,
+                AchievementsPlugin,
+            ))
+            .insert_resource(registry(vec![achievement(
+                "tyconic::pocket_money",
+                false,
+                money(100.),
+            )]))
+            .insert_resource(Achievements::new(default(), None));
+        app.world_mut().spawn(Level::default());
+
+        let pocket_money = AchievementId("tyconic::pocket_money".into());
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(!app
+            .world()
+            .resource::<Achievements>()
+            .unlocked
+            .is_unlocked(&pocket_money));
+
+        let mut level = app.world_mut().query::<&mut Level>();
+        level
+            .single_mut(app.world_mut())
+            .resources
+            .insert("money".into(), 150.);
+        for _ in 0..5 {
+            app.update();
+        }
+        assert!(app
+            .world()
+            .resource::<Achievements>()
+            .unlocked
+            .is_unlocked(&pocket_money));
+    }
+
+    #[test]
+    fn mod_pack_tyconic_achievements() {
+        fn serialize_achievements(type_registry: Res<AppTypeRegistry>) {
+            let achievements = AchievementDeclarations(vec![
+                AchievementDeclaration::Declare(AchievementDeclared {
+                    id: AchievementId("first_slice".into()),
+                    title: "First slice".into(),
+                    description: "Bake a pizza".into(),
+                    icon: None,
+                    hidden: false,
+                    condition: ConditionFlag::ReachedMetric {
+                        metric: "tyconic::pizza_slice__total_produced".into(),
+                        value: 1.,
+                    },
+                }),
+                AchievementDeclaration::Declare(AchievementDeclared {
+                    id: AchievementId("zero_waste".into()),
+                    title: "Zero waste".into(),
+                    description: "Keep a kitchen running for ten minutes without wasting food"
+                        .into(),
+                    icon: None,
+                    hidden: true,
+                    condition: ConditionFlag::SustainedFor {
+                        conditions: vec![
+                            ConditionFlag::ReachedMetric {
+                                metric: "tyconic::pizza_slice__produced_per_minute".into(),
+                                value: 10.,
+                            },
+                            ConditionFlag::UnderMetric {
+                                metric: "tyconic::pizza_slice__wasted_per_minute".into(),
+                                value: 1.,
+                            },
+                        ],
+                        seconds: 600.,
+                    },
+                }),
+            ]);
+
+            let type_registry = type_registry.read();
+            let serialized = ron::ser::to_string_pretty(
+                &ReflectSerializer::new(&achievements, &type_registry),
+                ron::ser::PrettyConfig::new().depth_limit(6),
+            )
+            .unwrap();
+
+            let file_path =
+                std::path::Path::new("assets/mods/tyconic/declarations/achievements.ron");
+            std::fs::write(file_path, serialized).unwrap();
+
+            // read back the way mods are declared
+            let mut namespaces = Namespaces::default();
+            let namespace = namespaces
+                .register(&Meta {
+                    mod_name: "tyconic".into(),
+                    version: "0.0.0-dev".parse().unwrap(),
+                })
+                .unwrap();
+            let mut declarations = Declarations::default();
+            declare_mod(
+                &mut declarations,
+                namespace,
+                Path::new("assets/mods/tyconic"),
+                &type_registry,
+            )
+            .unwrap();
+
+            let achievements = AchievementRegistry::from(declarations.achievements);
+            assert!(achievements
+                .achievement(&AchievementId("tyconic::zero_waste".into()))
+                .is_ok_and(|declared| declared.hidden));
+        }
+
+        let mut app = App::new();
+        app.add_plugins(AchievementsPlugin)
+            .register_type::<ItemPack>()
+            .register_type::<RecipePack>()
+            .register_type::<ResearchPack>()
+            .register_type::<ItemDeclarations>()
+            .register_type::<RecipeDeclarations>()
+            .register_type::<ResearchDeclarations>()
+            .add_systems(Startup, serialize_achievements);
+        app.run();
+    }
+}
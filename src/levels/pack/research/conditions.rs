@@ -1,36 +1,506 @@
+use crate::{ItemId, ResearchId};
 use bevy::prelude::*;
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
 
-#[derive(Debug, Reflect)]
+#[derive(Debug, Clone, PartialEq, Reflect)]
 #[reflect(no_field_bounds)]
 pub enum ConditionFlag {
     // Logical combinations
     SatisfyAll(Vec<ConditionFlag>),
     SatisfyAny(Vec<ConditionFlag>),
+    /// None of the conditions hold. Nested conditions are listed rather than boxed since
+    /// boxes can't be reflected.
+    Not(Vec<ConditionFlag>),
     // Basic comparisons
-    ReachedMetric { metric: String, value: f32 },
-    UnderMetric { metric: String, value: f32 },
+    ReachedMetric {
+        metric: String,
+        value: f32,
+    },
+    UnderMetric {
+        metric: String,
+        value: f32,
+    },
+    /// At least `min` and under `max`
+    Between {
+        metric: String,
+        min: f32,
+        max: f32,
+    },
+    // Ratios between metrics, never satisfied while the denominator is 0
+    RatioAtLeast {
+        numerator: String,
+        denominator: String,
+        value: f32,
+    },
+    RatioUnder {
+        numerator: String,
+        denominator: String,
+        value: f32,
+    },
+    // Level state
+    ResearchCompleted(ResearchId),
+    /// Held by the player
+    HasItem {
+        item: ItemId,
+        quantity: usize,
+    },
+    // Over time, only satisfied when evaluated by the same ConditionTracker every tick
+    /// The metric grew by at least `value` within the last `per_seconds`
+    RateAtLeast {
+        metric: String,
+        value: f32,
+        per_seconds: f32,
+    },
+    /// All of the conditions held for the last `seconds` without interruption
+    SustainedFor {
+        conditions: Vec<ConditionFlag>,
+        seconds: f32,
+    },
 }
 
-/// Evaluates a condition against the tracked metrics.
-pub fn evaluate_condition(condition: &ConditionFlag, tracked: &HashMap<String, f32>) -> bool {
-    match condition {
-        ConditionFlag::SatisfyAll(conditions) => {
-            conditions.iter().all(|c| evaluate_condition(c, tracked))
+impl ConditionFlag {
+    /// Whether it or a nested condition has to be evaluated every tick, including comparisons of
+    /// per minute metrics which fall off while nothing moves
+    pub fn over_time(&self) -> bool {
+        fn per_minute(metric: &str) -> bool {
+            matches!(
+                metric.parse(),
+                Ok(crate::Metric::Item {
+                    stat: crate::ItemStat::PerMinute(_),
+                    ..
+                })
+            )
         }
-        ConditionFlag::SatisfyAny(conditions) => {
-            conditions.iter().any(|c| evaluate_condition(c, tracked))
+
+        match self {
+            Self::SatisfyAll(conditions) | Self::SatisfyAny(conditions) | Self::Not(conditions) => {
+                conditions.iter().any(Self::over_time)
+            }
+            Self::ReachedMetric { metric, .. }
+            | Self::UnderMetric { metric, .. }
+            | Self::Between { metric, .. } => per_minute(metric),
+            Self::RatioAtLeast {
+                numerator,
+                denominator,
+                ..
+            }
+            | Self::RatioUnder {
+                numerator,
+                denominator,
+                ..
+            } => per_minute(numerator) || per_minute(denominator),
+            Self::RateAtLeast { .. } | Self::SustainedFor { .. } => true,
+            Self::ResearchCompleted(_) | Self::HasItem { .. } => false,
         }
-        ConditionFlag::ReachedMetric { metric, value } => {
-            let current = tracked.get(metric).unwrap_or(&0.0);
-            *current >= *value
+    }
+}
+
+/// What conditions are checked against
+pub trait ConditionContext {
+    /// 0 for metrics never tracked
+    fn metric(&self, metric: &str) -> f32;
+
+    fn research_completed(&self, _id: &ResearchId) -> bool {
+        false
+    }
+
+    fn item_count(&self, _item: &ItemId) -> usize {
+        0
+    }
+
+    /// Simulated time, conditions over time compare it between evaluations
+    fn seconds(&self) -> f32 {
+        0.
+    }
+}
+
+/// Metrics alone, nothing is researched or held
+impl ConditionContext for HashMap<String, f32> {
+    fn metric(&self, metric: &str) -> f32 {
+        self.get(metric).copied().unwrap_or(0.)
+    }
+}
+
+/// Snapshot of a level for a tick
+#[derive(Debug, Default, Clone)]
+pub struct LevelConditions {
+    pub metrics: HashMap<String, f32>,
+    pub completed_research: HashSet<ResearchId>,
+    /// held by the player
+    pub items: HashMap<ItemId, usize>,
+    pub seconds: f32,
+}
+
+impl ConditionContext for LevelConditions {
+    fn metric(&self, metric: &str) -> f32 {
+        self.metrics.metric(metric)
+    }
+
+    fn research_completed(&self, id: &ResearchId) -> bool {
+        self.completed_research.contains(id)
+    }
+
+    fn item_count(&self, item: &ItemId) -> usize {
+        self.items.get(item).copied().unwrap_or(0)
+    }
+
+    fn seconds(&self) -> f32 {
+        self.seconds
+    }
+}
+
+/// How far a condition is from holding, mirroring the tree of the [`ConditionFlag`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionProgress {
+    pub kind: ProgressKind,
+    pub satisfied: bool,
+    /// between 0 and 1, exactly 1 once satisfied
+    pub fraction: f32,
+    /// what a comparison or a sustained condition measures, none for logical combinations
+    pub measure: Option<Measure>,
+    /// progress of the nested conditions, in order
+    pub children: Vec<ConditionProgress>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProgressKind {
+    /// satisfied along with all its children
+    All,
+    /// as close as its closest child
+    Any,
+    /// as far as its closest child
+    Not,
+    /// time its children held
+    Sustained,
+    /// a measured leaf
+    Comparison,
+}
+
+/// `12000 / 20000 money`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Measure {
+    /// metric, ratio of metrics, item, research or `seconds`
+    pub subject: String,
+    pub current: f32,
+    pub target: f32,
+}
+
+impl fmt::Display for Measure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} / {} {}", self.current, self.target, self.subject)
+    }
+}
+
+impl ConditionProgress {
+    fn new(kind: ProgressKind, satisfied: bool, fraction: f32) -> Self {
+        Self {
+            kind,
+            satisfied,
+            fraction: if satisfied {
+                1.
+            } else {
+                fraction.clamp(0., 1.)
+            },
+            measure: None,
+            children: vec![],
         }
-        ConditionFlag::UnderMetric { metric, value } => {
-            let current = tracked.get(metric).unwrap_or(&0.0);
-            *current < *value
+    }
+
+    fn compared(satisfied: bool, fraction: f32) -> Self {
+        Self::new(ProgressKind::Comparison, satisfied, fraction)
+    }
+
+    fn measured(mut self, subject: impl Into<String>, current: f32, target: f32) -> Self {
+        self.measure = Some(Measure {
+            subject: subject.into(),
+            current,
+            target,
+        });
+        self
+    }
+
+    fn nested(mut self, children: Vec<ConditionProgress>) -> Self {
+        self.children = children;
+        self
+    }
+
+    /// The conditions keeping this one from holding, none once satisfied
+    pub fn blocking(&self) -> Vec<&ConditionProgress> {
+        let mut blocking = vec![];
+        self.collect_blocking(&mut blocking);
+        blocking
+    }
+
+    fn collect_blocking<'a>(&'a self, blocking: &mut Vec<&'a ConditionProgress>) {
+        if self.satisfied {
+            return;
+        }
+        match self.kind {
+            ProgressKind::All | ProgressKind::Any => self
+                .children
+                .iter()
+                .for_each(|child| child.collect_blocking(blocking)),
+            // negations are blocked by what holds
+            ProgressKind::Not => {
+                blocking.extend(self.children.iter().filter(|child| child.satisfied))
+            }
+            // a sustained condition whose conditions hold is only waiting for time to pass
+            ProgressKind::Sustained if self.children.iter().any(|child| !child.satisfied) => self
+                .children
+                .iter()
+                .for_each(|child| child.collect_blocking(blocking)),
+            ProgressKind::Sustained | ProgressKind::Comparison => blocking.push(self),
+        }
+    }
+}
+
+/// How close `current` is to reaching `target`
+fn reaching(current: f32, target: f32) -> f32 {
+    if target <= 0. {
+        1.
+    } else {
+        current / target
+    }
+}
+
+/// How close `current` is to dropping under `target`
+fn dropping(current: f32, target: f32) -> f32 {
+    if current <= 0. {
+        1.
+    } else {
+        target / current
+    }
+}
+
+/// History of the conditions over time within a condition, by their position in the tree.
+/// Belongs to a single condition.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ConditionTracker {
+    /// when a sustained condition started holding
+    since: HashMap<usize, f32>,
+    /// metric values of a rate, oldest first, starting with the last one out of its window
+    samples: HashMap<usize, VecDeque<(f32, f32)>>,
+}
+
+impl ConditionTracker {
+    pub fn evaluate(&mut self, condition: &ConditionFlag, context: &impl ConditionContext) -> bool {
+        self.progress(condition, context).satisfied
+    }
+
+    /// Visits every node, even ones not deciding the outcome, so their history stays complete
+    pub fn progress(
+        &mut self,
+        condition: &ConditionFlag,
+        context: &impl ConditionContext,
+    ) -> ConditionProgress {
+        self.visit(condition, context, &mut 0)
+    }
+
+    fn visit(
+        &mut self,
+        condition: &ConditionFlag,
+        context: &impl ConditionContext,
+        next: &mut usize,
+    ) -> ConditionProgress {
+        let node = *next;
+        *next += 1;
+
+        match condition {
+            ConditionFlag::SatisfyAll(conditions) => {
+                let children = self.visit_all(conditions, context, next);
+                let satisfied = children.iter().all(|child| child.satisfied);
+                let fraction = if children.is_empty() {
+                    1.
+                } else {
+                    children.iter().map(|child| child.fraction).sum::<f32>() / children.len() as f32
+                };
+                ConditionProgress::new(ProgressKind::All, satisfied, fraction).nested(children)
+            }
+            ConditionFlag::SatisfyAny(conditions) => {
+                let children = self.visit_all(conditions, context, next);
+                let satisfied = children.iter().any(|child| child.satisfied);
+                let fraction = children
+                    .iter()
+                    .map(|child| child.fraction)
+                    .fold(0., f32::max);
+                ConditionProgress::new(ProgressKind::Any, satisfied, fraction).nested(children)
+            }
+            ConditionFlag::Not(conditions) => {
+                let children = self.visit_all(conditions, context, next);
+                let satisfied = !children.iter().any(|child| child.satisfied);
+                let closest = children
+                    .iter()
+                    .map(|child| child.fraction)
+                    .fold(0., f32::max);
+                ConditionProgress::new(ProgressKind::Not, satisfied, 1. - closest).nested(children)
+            }
+            ConditionFlag::ReachedMetric { metric, value } => {
+                let current = context.metric(metric);
+                ConditionProgress::new(
+                    ProgressKind::Comparison,
+                    current >= *value,
+                    reaching(current, *value),
+                )
+                .measured(metric, current, *value)
+            }
+            ConditionFlag::UnderMetric { metric, value } => {
+                let current = context.metric(metric);
+                ConditionProgress::new(
+                    ProgressKind::Comparison,
+                    current < *value,
+                    dropping(current, *value),
+                )
+                .measured(metric, current, *value)
+            }
+            ConditionFlag::Between { metric, min, max } => {
+                let current = context.metric(metric);
+                let (fraction, target) = if current < *min {
+                    (reaching(current, *min), *min)
+                } else {
+                    (dropping(current, *max), *max)
+                };
+                ConditionProgress::new(
+                    ProgressKind::Comparison,
+                    *min <= current && current < *max,
+                    fraction,
+                )
+                .measured(metric, current, target)
+            }
+            ConditionFlag::RatioAtLeast {
+                numerator,
+                denominator,
+                value,
+            } => {
+                let ratio = ratio(context, numerator, denominator);
+                let current = ratio.unwrap_or(0.);
+                ConditionProgress::new(
+                    ProgressKind::Comparison,
+                    ratio.is_some_and(|ratio| ratio >= *value),
+                    reaching(current, *value),
+                )
+                .measured(
+                    format!("{} / {}", numerator, denominator),
+                    current,
+                    *value,
+                )
+            }
+            ConditionFlag::RatioUnder {
+                numerator,
+                denominator,
+                value,
+            } => {
+                let ratio = ratio(context, numerator, denominator);
+                let current = ratio.unwrap_or(0.);
+                // no ratio yet is as far from holding as can be
+                let fraction = ratio.map_or(0., |ratio| dropping(ratio, *value));
+                ConditionProgress::new(
+                    ProgressKind::Comparison,
+                    ratio.is_some_and(|ratio| ratio < *value),
+                    fraction,
+                )
+                .measured(
+                    format!("{} / {}", numerator, denominator),
+                    current,
+                    *value,
+                )
+            }
+            ConditionFlag::ResearchCompleted(id) => {
+                let completed = context.research_completed(id);
+                let current = if completed { 1. } else { 0. };
+                ConditionProgress::compared(completed, current).measured(&id.0, current, 1.)
+            }
+            ConditionFlag::HasItem { item, quantity } => {
+                let current = context.item_count(item) as f32;
+                let target = *quantity as f32;
+                ConditionProgress::new(
+                    ProgressKind::Comparison,
+                    current >= target,
+                    reaching(current, target),
+                )
+                .measured(&item.0, current, target)
+            }
+            ConditionFlag::RateAtLeast {
+                metric,
+                value,
+                per_seconds,
+            } => {
+                let now = context.seconds();
+                let current = context.metric(metric);
+                let start = now - per_seconds;
+
+                let samples = self.samples.entry(node).or_default();
+                samples.push_back((now, current));
+                while samples.get(1).is_some_and(|(seconds, _)| *seconds <= start) {
+                    samples.pop_front();
+                }
+
+                let (oldest, sampled) = samples.front().copied().unwrap_or((now, current));
+                let growth = current - sampled;
+                // only once a whole window was observed
+                ConditionProgress::new(
+                    ProgressKind::Comparison,
+                    oldest <= start && growth >= *value,
+                    reaching(growth, *value),
+                )
+                .measured(metric, growth, *value)
+            }
+            ConditionFlag::SustainedFor {
+                conditions,
+                seconds,
+            } => {
+                let now = context.seconds();
+                let children = self.visit_all(conditions, context, next);
+                let elapsed = if children.iter().all(|child| child.satisfied) {
+                    now - *self.since.entry(node).or_insert(now)
+                } else {
+                    self.since.remove(&node);
+                    0.
+                };
+
+                ConditionProgress::new(
+                    ProgressKind::Sustained,
+                    self.since.contains_key(&node) && elapsed >= *seconds,
+                    reaching(elapsed, *seconds),
+                )
+                .measured("seconds", elapsed, *seconds)
+                .nested(children)
+            }
         }
     }
+
+    /// Every condition is visited, whether or not it decides the outcome
+    fn visit_all(
+        &mut self,
+        conditions: &[ConditionFlag],
+        context: &impl ConditionContext,
+        next: &mut usize,
+    ) -> Vec<ConditionProgress> {
+        conditions
+            .iter()
+            .map(|condition| self.visit(condition, context, next))
+            .collect()
+    }
+}
+
+fn ratio(context: &impl ConditionContext, numerator: &str, denominator: &str) -> Option<f32> {
+    let denominator = context.metric(denominator);
+    (denominator != 0.).then(|| context.metric(numerator) / denominator)
+}
+
+/// Evaluates a condition against the tracked metrics. Without a [`ConditionTracker`] kept
+/// across ticks, rates are never satisfied and sustained conditions only without duration.
+pub fn evaluate_condition(condition: &ConditionFlag, context: &impl ConditionContext) -> bool {
+    ConditionTracker::default().evaluate(condition, context)
+}
+
+/// Progress of a condition evaluated once, see [`evaluate_condition`]
+pub fn condition_progress(
+    condition: &ConditionFlag,
+    context: &impl ConditionContext,
+) -> ConditionProgress {
+    ConditionTracker::default().progress(condition, context)
 }
 
 #[cfg(test)]
@@ -124,4 +594,309 @@ mod tests {
         ]);
         assert!(evaluate_condition(&condition, &tracked));
     }
+
+    #[test]
+    fn test_not_and_between() {
+        let condition = ConditionFlag::Not(vec![ConditionFlag::Between {
+            metric: "money".to_string(),
+            min: 100.0,
+            max: 200.0,
+        }]);
+        assert!(evaluate_condition(
+            &condition,
+            &get_tracked(vec![("money", 99.0)])
+        ));
+        assert!(!evaluate_condition(
+            &condition,
+            &get_tracked(vec![("money", 100.0)])
+        ));
+        assert!(!evaluate_condition(
+            &condition,
+            &get_tracked(vec![("money", 199.0)])
+        ));
+        assert!(evaluate_condition(
+            &condition,
+            &get_tracked(vec![("money", 200.0)])
+        ));
+    }
+
+    #[test]
+    fn test_ratio_conditions() {
+        let waste_ratio = |value| ConditionFlag::RatioUnder {
+            numerator: "tyconic::pizza_slice__total_wasted".to_string(),
+            denominator: "tyconic::pizza_slice__total_produced".to_string(),
+            value,
+        };
+        let tracked = get_tracked(vec![
+            ("tyconic::pizza_slice__total_wasted", 5.0),
+            ("tyconic::pizza_slice__total_produced", 100.0),
+        ]);
+        assert!(evaluate_condition(&waste_ratio(0.1), &tracked));
+        assert!(!evaluate_condition(&waste_ratio(0.05), &tracked));
+
+        let condition = ConditionFlag::RatioAtLeast {
+            numerator: "tyconic::pizza_slice__total_produced".to_string(),
+            denominator: "tyconic::pizza_slice__total_wasted".to_string(),
+            value: 20.0,
+        };
+        assert!(evaluate_condition(&condition, &tracked));
+
+        // nothing produced yet makes no ratio at all
+        let tracked = get_tracked(vec![]);
+        assert!(!evaluate_condition(&waste_ratio(0.1), &tracked));
+        assert!(!evaluate_condition(&condition, &tracked));
+    }
+
+    #[test]
+    fn test_research_and_items() {
+        let condition = ConditionFlag::SatisfyAll(vec![
+            ConditionFlag::ResearchCompleted(ResearchId("tyconic::pizzeria_i".into())),
+            ConditionFlag::HasItem {
+                item: "tyconic::pizza_slice".into(),
+                quantity: 4,
+            },
+        ]);
+
+        let mut level = LevelConditions::default();
+        level.items.insert("tyconic::pizza_slice".into(), 4);
+        assert!(!evaluate_condition(&condition, &level));
+
+        level
+            .completed_research
+            .insert(ResearchId("tyconic::pizzeria_i".into()));
+        assert!(evaluate_condition(&condition, &level));
+
+        level.items.insert("tyconic::pizza_slice".into(), 3);
+        assert!(!evaluate_condition(&condition, &level));
+    }
+
+    #[test]
+    fn test_rate_over_window() {
+        let condition = ConditionFlag::RateAtLeast {
+            metric: "money".to_string(),
+            value: 100.0,
+            per_seconds: 10.0,
+        };
+        let mut tracker = ConditionTracker::default();
+        let mut level = LevelConditions::default();
+
+        // 15 money a second, but a whole window has to pass first
+        for second in 0..10 {
+            level.seconds = second as f32;
+            level.metrics.insert("money".into(), 15.0 * second as f32);
+            assert!(!tracker.evaluate(&condition, &level), "{}", second);
+        }
+        level.seconds = 10.0;
+        level.metrics.insert("money".into(), 150.0);
+        assert!(tracker.evaluate(&condition, &level));
+
+        // then 5 a second
+        for second in 11..=20 {
+            level.seconds = second as f32;
+            level
+                .metrics
+                .insert("money".into(), 150.0 + 5.0 * (second - 10) as f32);
+            tracker.evaluate(&condition, &level);
+        }
+        assert!(!tracker.evaluate(&condition, &level));
+        assert!(!evaluate_condition(&condition, &level));
+    }
+
+    #[test]
+    fn test_sustained_condition() {
+        let condition = ConditionFlag::SatisfyAny(vec![
+            ConditionFlag::ReachedMetric {
+                metric: "never".to_string(),
+                value: 1.0,
+            },
+            ConditionFlag::SustainedFor {
+                conditions: vec![ConditionFlag::ReachedMetric {
+                    metric: "money".to_string(),
+                    value: 100.0,
+                }],
+                seconds: 5.0,
+            },
+        ]);
+        let mut tracker = ConditionTracker::default();
+        let mut level = LevelConditions::default();
+        let mut at = |tracker: &mut ConditionTracker, seconds: f32, money: f32| {
+            level.seconds = seconds;
+            level.metrics.insert("money".into(), money);
+            tracker.evaluate(&condition, &level)
+        };
+
+        assert!(!at(&mut tracker, 0.0, 150.0));
+        assert!(!at(&mut tracker, 4.0, 150.0));
+        // interrupted, counting starts over
+        assert!(!at(&mut tracker, 4.5, 50.0));
+        assert!(!at(&mut tracker, 5.0, 150.0));
+        assert!(!at(&mut tracker, 9.0, 150.0));
+        assert!(at(&mut tracker, 10.0, 150.0));
+    }
+
+    #[test]
+    fn test_per_minute_metrics_over_time() {
+        let reached = |metric: &str| ConditionFlag::ReachedMetric {
+            metric: metric.to_string(),
+            value: 10.0,
+        };
+        assert!(!reached("money").over_time());
+        assert!(!reached("tyconic::pizza_slice__total_produced").over_time());
+        assert!(reached("tyconic::pizza_slice__produced_per_minute").over_time());
+        assert!(ConditionFlag::Not(vec![ConditionFlag::RatioUnder {
+            numerator: "tyconic::pizza_slice__wasted_per_minute".to_string(),
+            denominator: "tyconic::pizza_slice__total_produced".to_string(),
+            value: 0.1,
+        }])
+        .over_time());
+    }
+
+    #[test]
+    fn test_reflect_ron_round_trip() {
+        use bevy::reflect::serde::{ReflectDeserializer, ReflectSerializer};
+        use bevy::reflect::{FromReflect, TypeRegistry};
+        use serde::de::DeserializeSeed;
+
+        let mut registry = TypeRegistry::default();
+        registry.register::<ConditionFlag>();
+
+        let condition = ConditionFlag::SatisfyAll(vec![
+            ConditionFlag::Not(vec![ConditionFlag::ResearchCompleted(ResearchId(
+                "tyconic::pizzeria_i".into(),
+            ))]),
+            ConditionFlag::SustainedFor {
+                conditions: vec![ConditionFlag::RateAtLeast {
+                    metric: "money".to_string(),
+                    value: 100.0,
+                    per_seconds: 60.0,
+                }],
+                seconds: 30.0,
+            },
+            ConditionFlag::HasItem {
+                item: "tyconic::pizza_slice".into(),
+                quantity: 4,
+            },
+        ]);
+
+        let serialized = ron::ser::to_string_pretty(
+            &ReflectSerializer::new(&condition, &registry),
+            ron::ser::PrettyConfig::new()
+                .depth_limit(4)
+                .indentor("  ".into()),
+        )
+        .unwrap();
+        let mut deserializer = ron::de::Deserializer::from_str(&serialized).unwrap();
+        let reflected = ReflectDeserializer::new(&registry)
+            .deserialize(&mut deserializer)
+            .unwrap();
+
+        assert_eq!(
+            ConditionFlag::from_reflect(reflected.as_partial_reflect()),
+            Some(condition)
+        );
+    }
+
+    #[test]
+    fn test_progress_mirrors_condition() {
+        let condition = ConditionFlag::SatisfyAll(vec![
+            ConditionFlag::ReachedMetric {
+                metric: "money".to_string(),
+                value: 20000.0,
+            },
+            ConditionFlag::SatisfyAny(vec![
+                ConditionFlag::ReachedMetric {
+                    metric: "tyconic::cheese_wheel__total_produced".to_string(),
+                    value: 3000.0,
+                },
+                ConditionFlag::HasItem {
+                    item: "tyconic::beef_slab".into(),
+                    quantity: 10,
+                },
+            ]),
+            ConditionFlag::UnderMetric {
+                metric: "tyconic::kitchen_waste__total_produced".to_string(),
+                value: 20.0,
+            },
+        ]);
+        let mut level = LevelConditions::default();
+        level.metrics.insert("money".into(), 12000.0);
+        level
+            .metrics
+            .insert("tyconic::cheese_wheel__total_produced".into(), 750.0);
+        level.items.insert("tyconic::beef_slab".into(), 5);
+
+        let progress = condition_progress(&condition, &level);
+        assert!(!progress.satisfied);
+        assert_eq!(progress.children.len(), 3);
+
+        let money = &progress.children[0];
+        assert_eq!(
+            money.measure.as_ref().unwrap().to_string(),
+            "12000 / 20000 money"
+        );
+        assert_eq!(money.fraction, 0.6);
+
+        // the closest alternative counts
+        let any = &progress.children[1];
+        assert!(any.measure.is_none());
+        assert_eq!(any.fraction, 0.5);
+        assert_eq!(any.children[0].fraction, 0.25);
+
+        let waste = &progress.children[2];
+        assert!(waste.satisfied);
+        assert_eq!(waste.fraction, 1.0);
+
+        assert_eq!(progress.fraction, (0.6 + 0.5 + 1.0) / 3.0);
+        assert_eq!(
+            progress
+                .blocking()
+                .iter()
+                .map(|blocking| blocking.measure.as_ref().unwrap().subject.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "money",
+                "tyconic::cheese_wheel__total_produced",
+                "tyconic::beef_slab"
+            ]
+        );
+
+        level.metrics.insert("money".into(), 25000.0);
+        level.items.insert("tyconic::beef_slab".into(), 10);
+        let progress = condition_progress(&condition, &level);
+        assert!(progress.satisfied);
+        assert_eq!(progress.fraction, 1.0);
+        assert!(progress.blocking().is_empty());
+    }
+
+    #[test]
+    fn test_progress_over_time() {
+        let condition = ConditionFlag::Not(vec![ConditionFlag::SustainedFor {
+            conditions: vec![ConditionFlag::ReachedMetric {
+                metric: "money".to_string(),
+                value: 100.0,
+            }],
+            seconds: 10.0,
+        }]);
+        let mut tracker = ConditionTracker::default();
+        let mut level = LevelConditions::default();
+        level.metrics.insert("money".into(), 150.0);
+
+        level.seconds = 0.0;
+        assert!(tracker.progress(&condition, &level).satisfied);
+        level.seconds = 4.0;
+        let progress = tracker.progress(&condition, &level);
+        let sustained = &progress.children[0];
+        assert_eq!(
+            sustained.measure.as_ref().unwrap().to_string(),
+            "4 / 10 seconds"
+        );
+        assert_eq!(progress.fraction, 1.0);
+
+        // a sustained condition whose conditions hold is blocked by time alone
+        level.seconds = 10.0;
+        let progress = tracker.progress(&condition, &level);
+        assert!(!progress.satisfied);
+        assert_eq!(progress.fraction, 0.0);
+        assert_eq!(progress.blocking(), vec![&progress.children[0]]);
+    }
 }
@@ -4,10 +4,11 @@ mod progression;
 
 use crate::*;
 
-pub use conditions::ConditionFlag;
+pub use achievements::*;
+pub use conditions::*;
 pub use progression::*;
 
-#[derive(Reflect)]
+#[derive(Reflect, Debug, Clone, PartialEq)]
 pub struct ResearchDeclared {
     pub id: ResearchId,
     pub display_name: String,
@@ -1,4 +1,443 @@
+//! Research of a level moving from locked to completed. Research becomes available once every
+//! research it requires is completed, and once started completes as soon as its unlock
+//! condition holds for the level's metrics.
+
+use crate::ui::*;
+use crate::*;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use std::{fmt, time::Duration};
+
+pub struct ResearchPlugin;
+
+impl Plugin for ResearchPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ResearchId>()
+            .register_type::<ResearchProgress>()
+            .add_event::<StartResearch>()
+            .add_event::<ResearchUnlocked>()
+            .add_systems(Simulation, run_research.after(track_item_flows));
+    }
+}
 
 #[derive(Component, Debug, Reflect, PartialEq, Eq, Clone, Hash)]
 pub struct ResearchId(pub String);
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, Reflect)]
+pub enum ResearchState {
+    /// some required research isn't completed
+    #[default]
+    Locked,
+    /// waiting to be started
+    Available,
+    /// its unlock condition is checked every tick
+    InProgress,
+    Completed,
+}
+
+/// Asks the level to start checking the unlock condition of an available research
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct StartResearch(pub ResearchId);
+
+/// Sent once a research completes, along with a notification
+#[derive(Event, Debug, Clone, PartialEq)]
+pub struct ResearchUnlocked(pub ResearchId);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResearchError {
+    NotAvailable {
+        id: ResearchId,
+        state: ResearchState,
+    },
+}
+
+impl fmt::Display for ResearchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotAvailable { id, state } => {
+                write!(f, "research `{}` can't be started while {:?}", id.0, state)
+            }
+        }
+    }
+}
+
+/// State of every declared research on the [`Level`], missing ones are locked
+#[derive(Component, Debug, Default, Clone, PartialEq, Reflect)]
+pub struct ResearchProgress {
+    states: HashMap<ResearchId, ResearchState>,
+    /// history of the unlock conditions of research in progress
+    #[reflect(ignore)]
+    trackers: HashMap<ResearchId, ConditionTracker>,
+    /// how far research in progress is from completing, as of the last tick
+    #[reflect(ignore)]
+    reports: HashMap<ResearchId, ConditionProgress>,
+}
+
+/// Progress restored from the states of research, conditions over time start over
+impl FromIterator<(ResearchId, ResearchState)> for ResearchProgress {
+    fn from_iter<I: IntoIterator<Item = (ResearchId, ResearchState)>>(states: I) -> Self {
+        Self {
+            states: states.into_iter().collect(),
+            ..default()
+        }
+    }
+}
+
+impl ResearchProgress {
+    pub fn state(&self, id: &ResearchId) -> ResearchState {
+        self.states.get(id).copied().unwrap_or_default()
+    }
+
+    pub fn is_completed(&self, id: &ResearchId) -> bool {
+        self.state(id) == ResearchState::Completed
+    }
+
+    /// Whether every research the recipe requires is completed
+    pub fn allows(&self, recipe: &Recipe) -> bool {
+        recipe
+            .research_required
+            .iter()
+            .all(|id| self.is_completed(id))
+    }
+
+    /// Why research in progress hasn't completed yet
+    pub fn unlock_progress(&self, id: &ResearchId) -> Option<&ConditionProgress> {
+        self.reports.get(id)
+    }
+
+    pub fn in_progress(&self) -> impl Iterator<Item = &ResearchId> {
+        self.with_state(ResearchState::InProgress)
+    }
+
+    pub fn completed(&self) -> impl Iterator<Item = &ResearchId> {
+        self.with_state(ResearchState::Completed)
+    }
+
+    /// Every research that isn't locked, with its state
+    pub fn states(&self) -> impl Iterator<Item = (&ResearchId, ResearchState)> {
+        self.states.iter().map(|(id, state)| (id, *state))
+    }
+
+    fn with_state(&self, state: ResearchState) -> impl Iterator<Item = &ResearchId> {
+        self.states
+            .iter()
+            .filter(move |(_, current)| **current == state)
+            .map(|(id, _)| id)
+    }
+
+    pub fn start(&mut self, id: &ResearchId) -> Result<(), ResearchError> {
+        match self.state(id) {
+            ResearchState::Available => {
+                self.states.insert(id.clone(), ResearchState::InProgress);
+                Ok(())
+            }
+            state => Err(ResearchError::NotAvailable {
+                id: id.clone(),
+                state,
+            }),
+        }
+    }
+
+    /// Completes research in progress whose condition holds and makes research whose
+    /// requirements are now completed available. Returns the completed research in
+    /// declaration order. Conditions over time count from when the research started.
+    pub fn step(
+        &mut self,
+        research: &ResearchRegistry,
+        context: &impl ConditionContext,
+    ) -> Vec<ResearchId> {
+        self.refresh(research);
+
+        let mut completed = vec![];
+        for declared in research.iter() {
+            if self.state(&declared.id) != ResearchState::InProgress {
+                continue;
+            }
+            let tracker = self.trackers.entry(declared.id.clone()).or_default();
+            let progress = tracker.progress(&declared.unlock_condition, context);
+            if progress.satisfied {
+                completed.push(declared.id.clone());
+            } else {
+                self.reports.insert(declared.id.clone(), progress);
+            }
+        }
+
+        if !completed.is_empty() {
+            for id in completed.iter() {
+                self.states.insert(id.clone(), ResearchState::Completed);
+                self.trackers.remove(id);
+                self.reports.remove(id);
+            }
+            self.refresh(research);
+        }
+
+        completed
+    }
+
+    /// Locked research whose requirements are completed becomes available. Research
+    /// requiring undeclared research stays locked.
+    fn refresh(&mut self, research: &ResearchRegistry) {
+        let available = research
+            .iter()
+            .filter(|declared| {
+                self.state(&declared.id) == ResearchState::Locked
+                    && declared
+                        .required_research
+                        .iter()
+                        .all(|required| self.is_completed(required))
+            })
+            .map(|declared| declared.id.clone())
+            .collect::<Vec<_>>();
+
+        for id in available {
+            self.states.insert(id, ResearchState::Available);
+        }
+    }
+}
+
+/// Everything conditions of the level are checked against at the current tick
+pub fn level_conditions<'a>(
+    level: &Level,
+    metrics: &LevelMetrics,
+    research: &ResearchProgress,
+    held: impl Iterator<Item = &'a Inventory>,
+    settings: &SimulationSettings,
+) -> LevelConditions {
+    let mut items = std::collections::HashMap::new();
+    for entry in held.flat_map(|inventory| inventory.0.iter().flatten()) {
+        *items.entry(entry.item.clone()).or_default() += entry.quantity;
+    }
+
+    LevelConditions {
+        metrics: metrics.tracked(level, settings),
+        completed_research: research.completed().cloned().collect(),
+        items,
+        seconds: level.tick as f32 / settings.tick_rate as f32,
+    }
+}
+
+pub fn run_research(
+    settings: Res<SimulationSettings>,
+    research: Res<ResearchRegistry>,
+    mut start: EventReader<StartResearch>,
+    mut unlocked: EventWriter<ResearchUnlocked>,
+    mut notifications_channel: NotificationChannel,
+    mut level: Query<(&Level, &LevelMetrics, &mut ResearchProgress)>,
+    player: Query<&Inventory, With<crate::player::Player>>,
+) {
+    let Ok((level, metrics, mut progress)) = level.get_single_mut() else {
+        start.clear();
+        return;
+    };
+
+    for StartResearch(id) in start.read() {
+        if let Err(err) = progress.start(id) {
+            warn!("{}", err);
+        }
+    }
+
+    // collecting every metric is only worth it while something can complete
+    let context = if progress.in_progress().next().is_some() {
+        level_conditions(level, metrics, &progress, player.iter(), &settings)
+    } else {
+        default()
+    };
+
+    for id in progress.step(&research, &context) {
+        let display_name = research
+            .research(&id)
+            .map(|declared| declared.display_name.clone())
+            .unwrap_or_else(|_| id.0.clone());
+        info!("research `{}` completed", id.0);
+        Notification {
+            title: "Research completed".into(),
+            level: NotificationLevel::Info,
+            description: format!("{} is unlocked", display_name),
+        }
+        .queue(Some(Duration::from_secs(5)), &mut notifications_channel);
+        unlocked.send(ResearchUnlocked(id));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InGameState;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    fn research(id: &str, required: &[&str], unlock_condition: ConditionFlag) -> ResearchDeclared {
+        ResearchDeclared {
+            id: ResearchId(id.into()),
+            display_name: id.into(),
+            flavor_text: String::new(),
+            unlock_condition,
+            required_research: required
+                .iter()
+                .map(|id| ResearchId(id.to_string()))
+                .collect(),
+        }
+    }
+
+    fn registry(declared: Vec<ResearchDeclared>) -> ResearchRegistry {
+        let mut research = Merged::<ResearchDeclared>::default();
+        for declared in declared {
+            research.apply("tyconic", Declaration::Declare(declared));
+        }
+        research.into()
+    }
+
+    fn money(value: f32) -> ConditionFlag {
+        ConditionFlag::ReachedMetric {
+            metric: "money".into(),
+            value,
+        }
+    }
+
+    #[test]
+    fn research_unlocks_along_prerequisites() {
+        let research = registry(vec![
+            research("tyconic::lemon_stand_i", &[], money(100.)),
+            research(
+                "tyconic::foodie_i",
+                &["tyconic::lemon_stand_i"],
+                ConditionFlag::SatisfyAll(vec![]),
+            ),
+            research("tyconic::pizzeria_i", &["tyconic::missing"], money(0.)),
+        ]);
+        let lemon_stand = ResearchId("tyconic::lemon_stand_i".into());
+        let foodie = ResearchId("tyconic::foodie_i".into());
+        let pizzeria = ResearchId("tyconic::pizzeria_i".into());
+
+        let mut progress = ResearchProgress::default();
+        let mut tracked = std::collections::HashMap::new();
+        tracked.insert("money".to_string(), 50.);
+
+        assert!(progress.step(&research, &tracked).is_empty());
+        assert_eq!(progress.state(&lemon_stand), ResearchState::Available);
+        assert_eq!(progress.state(&foodie), ResearchState::Locked);
+        assert_eq!(
+            progress.start(&foodie),
+            Err(ResearchError::NotAvailable {
+                id: foodie.clone(),
+                state: ResearchState::Locked
+            })
+        );
+
+        // available research doesn't complete before it's started
+        tracked.insert("money".to_string(), 120.);
+        assert!(progress.step(&research, &tracked).is_empty());
+
+        progress.start(&lemon_stand).unwrap();
+        tracked.insert("money".to_string(), 60.);
+        assert!(progress.step(&research, &tracked).is_empty());
+        let report = progress.unlock_progress(&lemon_stand).unwrap();
+        assert_eq!(report.fraction, 0.6);
+
+        tracked.insert("money".to_string(), 150.);
+        assert_eq!(progress.state(&lemon_stand), ResearchState::InProgress);
+        assert_eq!(
+            progress.step(&research, &tracked),
+            vec![lemon_stand.clone()]
+        );
+        assert!(progress.is_completed(&lemon_stand));
+        assert!(progress.unlock_progress(&lemon_stand).is_none());
+        assert_eq!(progress.state(&foodie), ResearchState::Available);
+
+        progress.start(&foodie).unwrap();
+        assert_eq!(progress.step(&research, &tracked), vec![foodie.clone()]);
+        assert!(progress.start(&foodie).is_err());
+
+        // requirements nobody declares never complete
+        assert_eq!(progress.state(&pizzeria), ResearchState::Locked);
+    }
+
+    #[test]
+    fn locked_recipes_dont_craft() {
+        let mut recipes = Merged::<RecipeDeclared>::default();
+        recipes.apply(
+            "tyconic",
+            Declaration::Declare(RecipeDeclared {
+                id: RecipeId("tyconic::folk_pizza".into()),
+                recipe: Recipe {
+                    ingredients: vec![ItemEntry {
+                        item: "tyconic::cheese_wheel".into(),
+                        quantity: 1,
+                    }],
+                    output: vec![ItemEntry {
+                        item: "tyconic::pizza_slice".into(),
+                        quantity: 4,
+                    }],
+                    research_required: vec![ResearchId("tyconic::pizzeria_i".into())],
+                    duration: 100,
+                },
+            }),
+        );
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .add_event::<NotificationEvent>()
+            .insert_resource(RecipeRegistry::from(recipes))
+            .insert_resource(registry(vec![research(
+                "tyconic::pizzeria_i",
+                &[],
+                money(100.),
+            )]))
+            .init_resource::<ItemRegistry>()
+            .add_plugins((
+                SimulationPlugin,
+                CraftingPlugin,
+                MetricsPlugin,
+                ResearchPlugin,
+            ));
+
+        let mut level = Level::default();
+        level.resources.insert("money".into(), 150.);
+        let level = app.world_mut().spawn(level).id();
+
+        let mut inventory = Inventory::with_capacity(4);
+        inventory.dump(vec![ItemEntry {
+            item: "tyconic::cheese_wheel".into(),
+            quantity: 3,
+        }]);
+        let machine = app
+            .world_mut()
+            .spawn((
+                CraftingMachine::new(RecipeId("tyconic::folk_pizza".into())),
+                inventory,
+            ))
+            .id();
+
+        for _ in 0..5 {
+            app.update();
+        }
+        let inventory = app.world().get::<Inventory>(machine).unwrap();
+        assert_eq!(inventory.count(&"tyconic::cheese_wheel".into()), 3);
+        let progress = app.world().get::<ResearchProgress>(level).unwrap();
+        let pizzeria = ResearchId("tyconic::pizzeria_i".into());
+        assert_eq!(progress.state(&pizzeria), ResearchState::Available);
+
+        app.world_mut().send_event(StartResearch(pizzeria.clone()));
+        let mut cursor = app
+            .world()
+            .resource::<Events<ResearchUnlocked>>()
+            .get_cursor();
+        let mut unlocked = vec![];
+        for _ in 0..5 {
+            app.update();
+            let events = app.world().resource::<Events<ResearchUnlocked>>();
+            unlocked.extend(cursor.read(events).cloned());
+        }
+        assert_eq!(unlocked, vec![ResearchUnlocked(pizzeria.clone())]);
+
+        let progress = app.world().get::<ResearchProgress>(level).unwrap();
+        assert!(progress.is_completed(&pizzeria));
+        let inventory = app.world().get::<Inventory>(machine).unwrap();
+        assert!(inventory.count(&"tyconic::pizza_slice".into()) > 0);
+    }
+}
@@ -0,0 +1,798 @@
+//! SemVer 2.0 versions of mods and the requirements of their dependencies such as
+//! `base >=0.1, <0.3`
+
+use super::*;
+use std::cmp::Ordering;
+
+/// A version as specified by SemVer 2.0, ordered by precedence. Build metadata carries no
+/// precedence and only breaks ties so that ordering agrees with equality
+#[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone)]
+#[reflect(Deserialize)]
+pub struct SemVer {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+    /// dot separated pre-release identifiers, `["rc", "1"]` in `1.0.0-rc.1`
+    pub pre: Vec<String>,
+    /// dot separated build metadata, `["sha", "5114f85"]` in `1.0.0+sha.5114f85`
+    pub build: Vec<String>,
+}
+
+impl SemVer {
+    pub fn new(major: u64, minor: u64, patch: u64) -> Self {
+        Self {
+            major,
+            minor,
+            patch,
+            pre: vec![],
+            build: vec![],
+        }
+    }
+
+    pub fn is_prerelease(&self) -> bool {
+        !self.pre.is_empty()
+    }
+
+    /// Precedence as defined by the spec, ignoring build metadata
+    pub fn cmp_precedence(&self, other: &Self) -> Ordering {
+        (self.major, self.minor, self.patch)
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre.is_empty(), other.pre.is_empty()) {
+                (true, true) => Ordering::Equal,
+                // a pre-release comes before its release
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => cmp_identifiers(&self.pre, &other.pre),
+            })
+    }
+}
+
+/// Stage of versions written before pre-release identifiers, as in `stage: ReleaseCandidate(3)`
+#[derive(serde::Deserialize, Default)]
+enum LegacyStage {
+    Dev,
+    Nightly,
+    ReleaseCandidate(u8),
+    #[default]
+    Stable,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename = "SemVer")]
+struct SemVerFields {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    #[serde(default)]
+    pre: Vec<String>,
+    #[serde(default)]
+    build: Vec<String>,
+    #[serde(default)]
+    stage: LegacyStage,
+}
+
+/// Also reads the `stage` of mods installed before pre-release identifiers, as their
+/// pre-release
+impl<'de> serde::Deserialize<'de> for SemVer {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let fields = SemVerFields::deserialize(deserializer)?;
+        let stage = match fields.stage {
+            LegacyStage::Dev => Some("dev".to_string()),
+            LegacyStage::Nightly => Some("nightly".to_string()),
+            LegacyStage::ReleaseCandidate(rc) => Some(format!("rc{}", rc)),
+            LegacyStage::Stable => None,
+        };
+
+        Ok(Self {
+            major: fields.major,
+            minor: fields.minor,
+            patch: fields.patch,
+            pre: stage.into_iter().chain(fields.pre).collect(),
+            build: fields.build,
+        })
+    }
+}
+
+fn is_numeric(identifier: &str) -> bool {
+    identifier.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// Numeric identifiers compare as numbers of any width and before alphanumeric ones,
+/// a shorter list comes first when all identifiers before it are equal
+fn cmp_identifiers(a: &[String], b: &[String]) -> Ordering {
+    for (a, b) in a.iter().zip(b.iter()) {
+        let ordering = match (is_numeric(a), is_numeric(b)) {
+            (true, true) => a.len().cmp(&b.len()).then_with(|| a.cmp(b)),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => a.cmp(b),
+        };
+
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+
+    a.len().cmp(&b.len())
+}
+
+impl PartialOrd for SemVer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for SemVer {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cmp_precedence(other)
+            .then_with(|| cmp_identifiers(&self.build, &other.build))
+    }
+}
+
+/// `(0, 1, 0)` is the in-development `0.1.0-dev`, as every mod starts out
+impl From<(u64, u64, u64)> for SemVer {
+    fn from((major, minor, patch): (u64, u64, u64)) -> Self {
+        Self {
+            pre: vec!["dev".into()],
+            ..Self::new(major, minor, patch)
+        }
+    }
+}
+
+impl fmt::Display for SemVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)?;
+        if !self.pre.is_empty() {
+            write!(f, "-{}", self.pre.join("."))?;
+        }
+        if !self.build.is_empty() {
+            write!(f, "+{}", self.build.join("."))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseSemVerError {
+    /// not `major.minor.patch`
+    MissingComponent(String),
+    InvalidNumber(String),
+    LeadingZero(String),
+    EmptyIdentifier,
+    InvalidIdentifier(String),
+}
+
+impl fmt::Display for ParseSemVerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingComponent(version) => {
+                write!(f, "`{}` is not major.minor.patch", version)
+            }
+            Self::InvalidNumber(number) => write!(f, "`{}` is not a version number", number),
+            Self::LeadingZero(number) => write!(f, "`{}` has a leading zero", number),
+            Self::EmptyIdentifier => write!(f, "empty pre-release or build identifier"),
+            Self::InvalidIdentifier(identifier) => write!(
+                f,
+                "`{}` may only hold ascii letters, digits and `-`",
+                identifier
+            ),
+        }
+    }
+}
+
+fn parse_number(number: &str) -> Result<u64, ParseSemVerError> {
+    if number.is_empty() || !is_numeric(number) {
+        return Err(ParseSemVerError::InvalidNumber(number.to_string()));
+    }
+    if number.len() > 1 && number.starts_with('0') {
+        return Err(ParseSemVerError::LeadingZero(number.to_string()));
+    }
+
+    number
+        .parse()
+        .map_err(|_| ParseSemVerError::InvalidNumber(number.to_string()))
+}
+
+fn parse_identifiers(identifiers: &str, pre: bool) -> Result<Vec<String>, ParseSemVerError> {
+    identifiers
+        .split('.')
+        .map(|identifier| {
+            if identifier.is_empty() {
+                return Err(ParseSemVerError::EmptyIdentifier);
+            }
+            if !identifier
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'-')
+            {
+                return Err(ParseSemVerError::InvalidIdentifier(identifier.to_string()));
+            }
+            // build metadata may keep leading zeros
+            if pre && identifier.len() > 1 && is_numeric(identifier) && identifier.starts_with('0')
+            {
+                return Err(ParseSemVerError::LeadingZero(identifier.to_string()));
+            }
+
+            Ok(identifier.to_string())
+        })
+        .collect()
+}
+
+/// Splits `1.2.3-pre+build` into its version numbers, pre-release and build metadata
+fn split_version(s: &str) -> Result<(&str, Vec<String>, Vec<String>), ParseSemVerError> {
+    let (s, build) = match s.split_once('+') {
+        Some((s, build)) => (s, parse_identifiers(build, false)?),
+        None => (s, vec![]),
+    };
+    let (numbers, pre) = match s.split_once('-') {
+        Some((numbers, pre)) => (numbers, parse_identifiers(pre, true)?),
+        None => (s, vec![]),
+    };
+
+    Ok((numbers, pre, build))
+}
+
+impl FromStr for SemVer {
+    type Err = ParseSemVerError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (numbers, pre, build) = split_version(s)?;
+
+        let numbers = numbers.split('.').collect::<Vec<_>>();
+        let [major, minor, patch] = numbers[..] else {
+            return Err(ParseSemVerError::MissingComponent(s.to_string()));
+        };
+
+        Ok(Self {
+            major: parse_number(major)?,
+            minor: parse_number(minor)?,
+            patch: parse_number(patch)?,
+            pre,
+            build,
+        })
+    }
+}
+
+#[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone, Copy)]
+pub enum VersionOp {
+    Exact,
+    Greater,
+    GreaterEq,
+    Less,
+    LessEq,
+}
+
+#[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone)]
+pub struct Comparator {
+    pub op: VersionOp,
+    pub version: SemVer,
+}
+
+impl Comparator {
+    /// By precedence, build metadata is ignored
+    pub fn matches(&self, version: &SemVer) -> bool {
+        let ordering = version.cmp_precedence(&self.version);
+        match self.op {
+            VersionOp::Exact => ordering == Ordering::Equal,
+            VersionOp::Greater => ordering == Ordering::Greater,
+            VersionOp::GreaterEq => ordering != Ordering::Less,
+            VersionOp::Less => ordering == Ordering::Less,
+            VersionOp::LessEq => ordering != Ordering::Greater,
+        }
+    }
+}
+
+/// Comma separated comparators which all have to match. Empty or `*` matches any version.
+/// `^1.2`, `~1.2` and `1.2.*` are kept as the range of comparators they stand for
+#[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone, Default)]
+pub struct VersionReq(pub Vec<Comparator>);
+
+impl VersionReq {
+    pub fn matches(&self, version: &SemVer) -> bool {
+        self.0.iter().all(|comparator| comparator.matches(version))
+    }
+}
+
+impl fmt::Display for Comparator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let op = match self.op {
+            VersionOp::Exact => "=",
+            VersionOp::Greater => ">",
+            VersionOp::GreaterEq => ">=",
+            VersionOp::Less => "<",
+            VersionOp::LessEq => "<=",
+        };
+        write!(f, "{}{}", op, self.version)
+    }
+}
+
+impl fmt::Display for VersionReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.0.is_empty() {
+            return write!(f, "*");
+        }
+
+        let comparators = self
+            .0
+            .iter()
+            .map(|comparator| comparator.to_string())
+            .collect::<Vec<_>>();
+        write!(f, "{}", comparators.join(", "))
+    }
+}
+
+#[derive(Debug)]
+pub struct ParseVersionReqError(pub String);
+
+impl fmt::Display for ParseVersionReqError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid version requirement `{}`", self.0)
+    }
+}
+
+/// `1.2` as written in a requirement, missing components are `None`. `*`, `x` and `X`
+/// stand for a missing component too
+struct PartialVersion {
+    major: Option<u64>,
+    minor: Option<u64>,
+    patch: Option<u64>,
+    pre: Vec<String>,
+    /// written with `*` rather than left out
+    wildcard: bool,
+}
+
+impl PartialVersion {
+    fn parse(s: &str) -> Result<Self, ParseSemVerError> {
+        let (numbers, pre, _build) = split_version(s)?;
+
+        let wildcard = numbers
+            .split('.')
+            .any(|number| matches!(number, "*" | "x" | "X"));
+        let mut numbers = numbers
+            .split('.')
+            .map(|number| match number {
+                "*" | "x" | "X" => Ok(None),
+                number => parse_number(number).map(Some),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if numbers.len() > 3 {
+            return Err(ParseSemVerError::MissingComponent(s.to_string()));
+        }
+        numbers.resize(3, None);
+
+        // nothing may follow a wildcard
+        if numbers
+            .windows(2)
+            .any(|pair| pair[0].is_none() && pair[1].is_some())
+        {
+            return Err(ParseSemVerError::InvalidNumber(s.to_string()));
+        }
+
+        Ok(Self {
+            major: numbers[0],
+            minor: numbers[1],
+            patch: numbers[2],
+            pre,
+            wildcard,
+        })
+    }
+
+    /// Missing components filled with zeros
+    fn lower(&self) -> SemVer {
+        SemVer {
+            pre: self.pre.clone(),
+            ..SemVer::new(
+                self.major.unwrap_or(0),
+                self.minor.unwrap_or(0),
+                self.patch.unwrap_or(0),
+            )
+        }
+    }
+}
+
+/// Smallest version of `major.minor.patch`, so that `<2.0.0-0` also excludes `2.0.0-dev`
+fn exclusive_upper(major: u64, minor: u64, patch: u64) -> Comparator {
+    Comparator {
+        op: VersionOp::Less,
+        version: SemVer {
+            pre: vec!["0".into()],
+            ..SemVer::new(major, minor, patch)
+        },
+    }
+}
+
+/// Parses a single comparator of a requirement, `^` `~` and wildcards expand into a range
+fn parse_comparators(s: &str) -> Result<Vec<Comparator>, ParseVersionReqError> {
+    let s = s.trim();
+    let err = || ParseVersionReqError(s.to_string());
+
+    let (op, version) = [
+        (">=", Some(VersionOp::GreaterEq)),
+        ("<=", Some(VersionOp::LessEq)),
+        (">", Some(VersionOp::Greater)),
+        ("<", Some(VersionOp::Less)),
+        ("=", Some(VersionOp::Exact)),
+        ("^", None),
+        ("~", None),
+    ]
+    .into_iter()
+    .find_map(|(prefix, op)| {
+        s.strip_prefix(prefix)
+            .map(|version| ((prefix, op), version))
+    })
+    .unwrap_or((("", Some(VersionOp::Exact)), s));
+
+    let partial = PartialVersion::parse(version.trim()).map_err(|_| err())?;
+    let lower = Comparator {
+        op: VersionOp::GreaterEq,
+        version: partial.lower(),
+    };
+
+    let Some(major) = partial.major else {
+        // `*`, only matches anything without an operator
+        return match op {
+            ("", _) => Ok(vec![]),
+            _ => Err(err()),
+        };
+    };
+
+    let comparators = match (op, partial.minor, partial.patch) {
+        // ^1.2.3 := >=1.2.3, <2.0.0-0   ^0.2.3 := >=0.2.3, <0.3.0-0   ^0.0.3 := >=0.0.3, <0.0.4-0
+        (("^", _), minor, patch) => {
+            let upper = match (major, minor, patch) {
+                (0, Some(0), Some(patch)) => exclusive_upper(0, 0, patch + 1),
+                (0, Some(minor), _) => exclusive_upper(0, minor + 1, 0),
+                (major, _, _) => exclusive_upper(major + 1, 0, 0),
+            };
+            vec![lower, upper]
+        }
+        // ~1.2.3 := >=1.2.3, <1.3.0-0   ~1 := >=1.0.0, <2.0.0-0
+        (("~", _), Some(minor), _) => vec![lower, exclusive_upper(major, minor + 1, 0)],
+        (("~", _), None, _) => vec![lower, exclusive_upper(major + 1, 0, 0)],
+        // 1.2.* := >=1.2.0, <1.3.0-0
+        ((_, Some(VersionOp::Exact)), Some(minor), None) if partial.wildcard => {
+            vec![lower, exclusive_upper(major, minor + 1, 0)]
+        }
+        ((_, Some(VersionOp::Exact)), None, None) if partial.wildcard => {
+            vec![lower, exclusive_upper(major + 1, 0, 0)]
+        }
+        // partial versions are padded with zeros, `<0.3` is `<0.3.0`
+        ((_, Some(op)), _, _) => vec![Comparator {
+            op,
+            version: partial.lower(),
+        }],
+        ((_, None), _, _) => return Err(err()),
+    };
+
+    Ok(comparators)
+}
+
+impl FromStr for VersionReq {
+    type Err = ParseVersionReqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() || s == "*" {
+            return Ok(Self::default());
+        }
+
+        let mut comparators = vec![];
+        for comparator in s.split(',') {
+            comparators.extend(parse_comparators(comparator)?);
+        }
+
+        Ok(Self(comparators))
+    }
+}
+
+/// A mod required by another one, written as `base >=0.1, <0.3`, a bare `base`
+/// or the `base_0.1.0` shorthand pinning an exact version
+#[derive(Debug, Reflect, Hash, PartialEq, Eq, Clone)]
+pub struct Dependency {
+    pub mod_name: String,
+    pub requirement: VersionReq,
+}
+
+impl Dependency {
+    pub fn matches(&self, meta: &Meta) -> bool {
+        self.mod_name == meta.mod_name && self.requirement.matches(&meta.version)
+    }
+}
+
+impl fmt::Display for Dependency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.mod_name, self.requirement)
+    }
+}
+
+impl FromStr for Dependency {
+    type Err = ParseVersionReqError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+
+        if let Some((mod_name, requirement)) = s.split_once(char::is_whitespace) {
+            return Ok(Self {
+                mod_name: mod_name.to_string(),
+                requirement: requirement.parse()?,
+            });
+        }
+
+        if let Ok(meta) = s.parse::<Meta>() {
+            return Ok(Self {
+                mod_name: meta.mod_name,
+                requirement: VersionReq(vec![Comparator {
+                    op: VersionOp::Exact,
+                    version: meta.version,
+                }]),
+            });
+        }
+
+        if s.is_empty() {
+            return Err(ParseVersionReqError(s.to_string()));
+        }
+
+        Ok(Self {
+            mod_name: s.to_string(),
+            requirement: VersionReq::default(),
+        })
+    }
+}
+
+impl MetaSource {
+    pub fn dependency(&self) -> Result<Dependency, ParseVersionReqError> {
+        self.id.0.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stable(major: u64, minor: u64, patch: u64) -> SemVer {
+        SemVer::new(major, minor, patch)
+    }
+
+    fn version(s: &str) -> SemVer {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn semver_spec_examples_round_trip() {
+        for s in [
+            "0.0.4",
+            "1.2.3",
+            "10.20.30",
+            "1.1.2-prerelease+meta",
+            "1.1.2+meta",
+            "1.1.2+meta-valid",
+            "1.0.0-alpha",
+            "1.0.0-beta",
+            "1.0.0-alpha.beta",
+            "1.0.0-alpha.beta.1",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha0.valid",
+            "1.0.0-alpha.0valid",
+            "1.0.0-0.3.7",
+            "1.0.0-x.7.z.92",
+            "1.0.0-x-y-z.--",
+            "1.0.0-alpha+001",
+            "1.0.0+20130313144700",
+            "1.0.0-beta+exp.sha.5114f85",
+            "1.0.0+21AF26D3----117B344092BD",
+            "1.0.0-rc.1+build.1",
+            "2.0.0-rc.1+build.123",
+            "1.2.3-beta",
+            "10.2.3-DEV-SNAPSHOT",
+            "1.2.3-SNAPSHOT-123",
+            "2.0.0+build.1848",
+            "2.0.1-alpha.1227",
+            "1.0.0-alpha+beta",
+            "1.2.3----RC-SNAPSHOT.12.9.1--.12+788",
+            "1.2.3----R-S.12.9.1--.12+meta",
+            "1.2.3----RC-SNAPSHOT.12.9.1--.12",
+            "1.0.0+0.build.1-rc.10000aaa-kk-0.1",
+            "18446744073709551615.0.0",
+            "1.0.0-0A.is.legal",
+        ] {
+            assert_eq!(version(s).to_string(), s);
+        }
+
+        let parsed = version("1.0.0-beta+exp.sha.5114f85");
+        assert_eq!(parsed.pre, vec!["beta"]);
+        assert_eq!(parsed.build, vec!["exp", "sha", "5114f85"]);
+    }
+
+    #[test]
+    fn semver_spec_invalid_examples() {
+        for s in [
+            "1",
+            "1.2",
+            "1.2.3-0123",
+            "1.2.3-0123.0123",
+            "1.1.2+.123",
+            "+invalid",
+            "-invalid",
+            "-invalid+invalid",
+            "-invalid.01",
+            "alpha",
+            "alpha.beta",
+            "alpha.beta.1",
+            "alpha.1",
+            "alpha+beta",
+            "alpha_beta",
+            "alpha.",
+            "alpha..",
+            "beta",
+            "1.0.0-alpha_beta",
+            "-alpha.",
+            "1.0.0-alpha..",
+            "1.0.0-alpha..1",
+            "1.0.0-alpha...1",
+            "01.1.1",
+            "1.01.1",
+            "1.1.01",
+            "1.2.3.DEV",
+            "1.2-SNAPSHOT",
+            "1.2.31.2.3----RC-SNAPSHOT.12.09.1--..12+788",
+            "+justmeta",
+            "9.8.7+meta+meta",
+            "9.8.7-whatever+meta+meta",
+            "1.0.0-",
+            "1.0.0+",
+            "99999999999999999999999.999999999999999999.99999999999999999",
+        ] {
+            assert!(s.parse::<SemVer>().is_err(), "{} should be invalid", s);
+        }
+    }
+
+    #[test]
+    fn semver_spec_precedence() {
+        let ordered = [
+            "1.0.0-alpha",
+            "1.0.0-alpha.1",
+            "1.0.0-alpha.beta",
+            "1.0.0-beta",
+            "1.0.0-beta.2",
+            "1.0.0-beta.11",
+            "1.0.0-rc.1",
+            "1.0.0",
+            "2.0.0",
+            "2.1.0",
+            "2.1.1",
+        ]
+        .map(version);
+
+        for pair in ordered.windows(2) {
+            assert!(pair[0] < pair[1], "{} < {}", pair[0], pair[1]);
+        }
+
+        let mut shuffled = ordered.to_vec();
+        shuffled.reverse();
+        shuffled.sort();
+        assert_eq!(shuffled, ordered);
+
+        // build metadata carries no precedence
+        assert_eq!(
+            version("1.0.0+a").cmp_precedence(&version("1.0.0+b")),
+            Ordering::Equal
+        );
+        assert_ne!(version("1.0.0+a"), version("1.0.0+b"));
+        // numeric identifiers of any width
+        assert!(version("1.0.0-99999999999999999999999") > version("1.0.0-9"));
+    }
+
+    #[test]
+    fn version_req_caret_tilde_wildcard() {
+        let caret = "^1.2.3".parse::<VersionReq>().unwrap();
+        assert!(caret.matches(&stable(1, 2, 3)));
+        assert!(caret.matches(&stable(1, 9, 0)));
+        assert!(!caret.matches(&stable(2, 0, 0)));
+        assert!(!caret.matches(&version("2.0.0-rc.1")));
+        assert!(!caret.matches(&version("1.2.3-rc.1")));
+        assert_eq!(caret.to_string(), ">=1.2.3, <2.0.0-0");
+
+        let caret_zero = "^0.2.3".parse::<VersionReq>().unwrap();
+        assert!(caret_zero.matches(&stable(0, 2, 9)));
+        assert!(!caret_zero.matches(&stable(0, 3, 0)));
+
+        let caret_patch = "^0.0.3".parse::<VersionReq>().unwrap();
+        assert!(caret_patch.matches(&stable(0, 0, 3)));
+        assert!(!caret_patch.matches(&stable(0, 0, 4)));
+
+        let tilde = "~1.2".parse::<VersionReq>().unwrap();
+        assert!(tilde.matches(&stable(1, 2, 7)));
+        assert!(!tilde.matches(&stable(1, 3, 0)));
+
+        let tilde_major = "~1".parse::<VersionReq>().unwrap();
+        assert!(tilde_major.matches(&stable(1, 9, 9)));
+        assert!(!tilde_major.matches(&stable(2, 0, 0)));
+
+        let wildcard = "1.2.*".parse::<VersionReq>().unwrap();
+        assert!(wildcard.matches(&stable(1, 2, 0)));
+        assert!(!wildcard.matches(&stable(1, 3, 0)));
+
+        let any = "*".parse::<VersionReq>().unwrap();
+        assert!(any.matches(&version("0.0.0-dev")));
+        assert!(any.matches(&stable(99, 0, 0)));
+
+        let build = ">=1.0.0, <2".parse::<VersionReq>().unwrap();
+        assert!(build.matches(&version("1.0.0+linux")));
+
+        assert!("^*".parse::<VersionReq>().is_err());
+        assert!("1.*.2".parse::<VersionReq>().is_err());
+    }
+
+    #[test]
+    fn version_req_range() {
+        let requirement = ">=0.1, <0.3".parse::<VersionReq>().unwrap();
+
+        assert!(!requirement.matches(&stable(0, 0, 9)));
+        assert!(requirement.matches(&stable(0, 1, 0)));
+        assert!(requirement.matches(&stable(0, 2, 7)));
+        assert!(!requirement.matches(&stable(0, 3, 0)));
+        assert_eq!(requirement.to_string(), ">=0.1.0, <0.3.0");
+    }
+
+    #[test]
+    fn version_req_stage() {
+        let requirement = ">=0.0.0-dev".parse::<VersionReq>().unwrap();
+
+        assert!(requirement.matches(&(0, 0, 0).into()));
+        assert!(requirement.matches(&stable(0, 0, 0)));
+        assert!(!">0.1.0-nightly"
+            .parse::<VersionReq>()
+            .unwrap()
+            .matches(&(0, 1, 0).into()));
+    }
+
+    #[test]
+    fn dependency_forms() {
+        let ranged = "base >=0.1, <0.3".parse::<Dependency>().unwrap();
+        assert_eq!(ranged.mod_name, "base");
+        assert_eq!(ranged.requirement.0.len(), 2);
+
+        let pinned = "cooking_time_2.0.0".parse::<Dependency>().unwrap();
+        assert_eq!(pinned.mod_name, "cooking_time");
+        assert!(pinned.requirement.matches(&stable(2, 0, 0)));
+        assert!(!pinned.requirement.matches(&stable(2, 0, 1)));
+
+        let any = "cooking_time".parse::<Dependency>().unwrap();
+        assert_eq!(any.requirement, VersionReq::default());
+
+        assert!("base >=zero".parse::<Dependency>().is_err());
+    }
+
+    #[test]
+    fn installed_stages_read_as_pre_releases() {
+        use bevy::reflect::serde::TypedReflectDeserializer;
+        use serde::de::DeserializeSeed;
+
+        let mut type_registry = bevy::reflect::TypeRegistry::default();
+        type_registry.register::<Meta>();
+
+        let read = |ron: &str| {
+            let mut deserializer = ron::de::Deserializer::from_str(ron).unwrap();
+            let value = TypedReflectDeserializer::of::<Meta>(&type_registry)
+                .deserialize(&mut deserializer)
+                .unwrap();
+            Meta::from_reflect(&*value).unwrap().to_string()
+        };
+
+        assert_eq!(
+            read("(mod_name: \"base\", version: (major: 0, minor: 1, patch: 0, stage: Dev))"),
+            "base_0.1.0-dev"
+        );
+        assert_eq!(
+            read("(mod_name: \"base\", version: (major: 2, minor: 0, patch: 0, stage: ReleaseCandidate(3)))"),
+            "base_2.0.0-rc3"
+        );
+        assert_eq!(
+            read("(mod_name: \"base\", version: (major: 1, minor: 0, patch: 0, stage: Stable))"),
+            "base_1.0.0"
+        );
+        assert_eq!(
+            read("(mod_name: \"base\", version: (major: 1, minor: 0, patch: 0, pre: [\"x\", \"7\"], build: []))"),
+            "base_1.0.0-x.7"
+        );
+    }
+}
@@ -0,0 +1,1393 @@
+//! Persistence of a running [`Level`] into versioned RON saves inside the config directory
+
+use crate::player::Player;
+use crate::ui::*;
+use crate::*;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::reflect::serde::{ReflectDeserializer, ReflectSerializer};
+use bevy::reflect::TypeRegistry;
+use bevy::utils::{HashMap, HashSet};
+use bevy_ecs_tilemap::prelude::*;
+use serde::de::DeserializeSeed;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fmt, fs};
+
+pub struct SavePlugin;
+
+impl Plugin for SavePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<LevelSave>()
+            .add_event::<SaveLevel>()
+            .add_systems(
+                Update,
+                (
+                    save_level.run_if(on_event::<SaveLevel>),
+                    restore_level.run_if(
+                        resource_exists::<PendingSave>.and(any_with_component::<ModProfile>),
+                    ),
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+pub const SAVES_PATH: &str = "saves";
+pub const SAVE_EXTENSION: &str = "ron";
+pub const TEMP_EXTENSION: &str = "tmp";
+pub const BACKUP_EXTENSION: &str = "bak";
+
+/// Request to snapshot the current level under a save name
+#[derive(Event, Debug, Clone)]
+pub struct SaveLevel(pub String);
+
+impl SaveLevel {
+    pub const QUICKSAVE: &'static str = "quicksave";
+}
+
+/// A save waiting to be restored once the level it belongs to has been spawned
+#[derive(Resource, Debug)]
+pub struct PendingSave(pub LevelSave);
+
+/// Snapshot of a level; bump [`LevelSave::VERSION`] whenever its layout changes
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct LevelSave {
+    pub version: u32,
+    pub total_play_time: Duration,
+    /// simulation ticks run, since version 4
+    #[reflect(default)]
+    pub tick: u64,
+    pub resources: HashMap<String, f32>,
+    pub mod_profile: Vec<MetaShorthand>,
+    pub floor: Vec<TileSave>,
+    /// tiles of the building tilemap that aren't placed buildings, such as countertops
+    pub buildings: Vec<TileSave>,
+    /// since version 3, older saves hold their buildings as tiles of `buildings`
+    #[reflect(default)]
+    pub placed: Vec<BuildingSave>,
+    pub inventories: Vec<InventorySave>,
+    /// research that isn't locked, since version 2
+    #[reflect(default)]
+    pub research: Vec<ResearchSave>,
+    /// since version 2
+    #[reflect(default)]
+    pub item_metrics: Vec<ItemMetricsSave>,
+    /// since version 2
+    #[reflect(default)]
+    pub custom_metrics: HashMap<String, f32>,
+}
+
+impl LevelSave {
+    pub const VERSION: u32 = 4;
+
+    /// Brings a save of an older layout up to [`LevelSave::VERSION`]
+    pub fn upgrade(&mut self) {
+        if self.version < 2 {
+            // research and metrics weren't saved, the level starts over with none
+            self.research.clear();
+            self.item_metrics.clear();
+            self.custom_metrics.clear();
+        }
+        if self.version < 3 {
+            // buildings were saved as the tile holding their item
+            let (buildings, tiles) = std::mem::take(&mut self.buildings)
+                .into_iter()
+                .partition::<Vec<_>, _>(|tile| tile.item.is_some());
+            self.buildings = tiles;
+            self.placed
+                .extend(buildings.into_iter().filter_map(BuildingSave::from_tile));
+        }
+        if self.version < 4 {
+            // the tick wasn't saved, windows recorded against it would be off once it restarts
+            for item_metrics in self.item_metrics.iter_mut() {
+                item_metrics.metrics.restart();
+            }
+            for io in self
+                .placed
+                .iter_mut()
+                .filter_map(|building| building.infinite_io.as_mut())
+            {
+                io.throughput.restart();
+            }
+        }
+        self.version = Self::VERSION;
+    }
+
+    pub fn research_progress(&self) -> ResearchProgress {
+        self.research
+            .iter()
+            .map(|research| (research.id.clone(), research.state))
+            .collect()
+    }
+
+    pub fn level_metrics(&self) -> LevelMetrics {
+        LevelMetrics::restore(
+            self.item_metrics
+                .iter()
+                .map(|saved| (saved.item.clone(), saved.metrics.clone()))
+                .collect(),
+            self.custom_metrics.clone(),
+        )
+    }
+
+    /// Every item id referenced by tiles, buildings, inventories and metrics
+    pub fn items_mut(&mut self) -> impl Iterator<Item = &mut ItemId> {
+        let tiles = self
+            .floor
+            .iter_mut()
+            .chain(self.buildings.iter_mut())
+            .filter_map(|tile| tile.item.as_mut());
+
+        let buildings = self.placed.iter_mut().flat_map(|building| {
+            let arm = building.arm.iter_mut().flat_map(|arm| {
+                arm.config
+                    .filter
+                    .iter_mut()
+                    .chain(arm.hand.iter_mut().map(|hand| &mut hand.item))
+            });
+            let belt = building
+                .belt
+                .iter_mut()
+                .flat_map(|belt| belt.lanes.iter_mut())
+                .flat_map(|lane| lane.0.iter_mut().map(|held| &mut held.item));
+            let source = building
+                .infinite_io
+                .iter_mut()
+                .filter_map(|io| match &mut io.mode {
+                    InfiniteIoMode::Source(item) => Some(item),
+                    InfiniteIoMode::Sink => None,
+                });
+
+            std::iter::once(&mut building.item)
+                .chain(arm)
+                .chain(belt)
+                .chain(source)
+        });
+
+        let slots = self
+            .inventories
+            .iter_mut()
+            .flat_map(|inventory| inventory.slots.iter_mut())
+            .filter_map(|slot| slot.as_mut().map(|entry| &mut entry.item));
+
+        let metrics = self.item_metrics.iter_mut().map(|saved| &mut saved.item);
+
+        tiles.chain(buildings).chain(slots).chain(metrics)
+    }
+
+    /// Recipes machines are set to
+    pub fn recipes_mut(&mut self) -> impl Iterator<Item = &mut RecipeId> {
+        self.placed
+            .iter_mut()
+            .filter_map(|building| building.crafter.as_mut()?.recipe.as_mut())
+    }
+
+    pub fn research_mut(&mut self) -> impl Iterator<Item = &mut ResearchId> {
+        self.research.iter_mut().map(|research| &mut research.id)
+    }
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct TileSave {
+    pub position: TilePos,
+    pub texture_index: u32,
+    pub item: Option<ItemId>,
+}
+
+/// A placed building, spawned again from the definition of its item. Components it was
+/// spawned with keep their state, the inventory is saved with the other inventories
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct BuildingSave {
+    pub item: ItemId,
+    pub origin: TilePos,
+    pub facing: BeltDirection,
+    pub state: Option<String>,
+    /// stands on a countertop
+    pub countertop: bool,
+    pub crafter: Option<CraftingMachine>,
+    pub arm: Option<AutoArm>,
+    pub belt: Option<MoverBelt>,
+    pub infinite_io: Option<InfiniteIo>,
+}
+
+impl BuildingSave {
+    /// Building saved before version 3 as the tile holding its item, which kept no facing nor
+    /// state
+    fn from_tile(tile: TileSave) -> Option<Self> {
+        Some(Self {
+            item: tile.item?,
+            origin: tile.position,
+            facing: default(),
+            state: None,
+            countertop: false,
+            crafter: None,
+            arm: None,
+            belt: None,
+            infinite_io: None,
+        })
+    }
+}
+
+/// Research and item metrics are saved as lists, maps keyed by ids can't be read back
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct ResearchSave {
+    pub id: ResearchId,
+    pub state: ResearchState,
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct ItemMetricsSave {
+    pub item: ItemId,
+    pub metrics: ItemMetrics,
+}
+
+/// Entity an inventory is restored onto
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub enum InventoryOwner {
+    Player,
+    Building(TilePos),
+}
+
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct InventorySave {
+    pub owner: InventoryOwner,
+    pub slots: Vec<Option<ItemEntry>>,
+    pub active: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Serialize(String),
+    Deserialize(String),
+    UnsupportedVersion(u32),
+    /// name that isn't a plain file name within [`saves_dir`]
+    InvalidName(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Serialize(err) => write!(f, "unable to serialize save. {}", err),
+            Self::Deserialize(err) => write!(f, "unable to read save. {}", err),
+            Self::UnsupportedVersion(version) => write!(
+                f,
+                "save version {} is newer than supported version {}",
+                version,
+                LevelSave::VERSION
+            ),
+            Self::InvalidName(name) => write!(f, "`{}` is not a valid save name", name),
+        }
+    }
+}
+
+impl From<std::io::Error> for SaveError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// platform specific directory holding every save
+pub fn saves_dir() -> PathBuf {
+    let project_dir = directories::ProjectDirs::from(
+        env!("PROJECT_QUALIFIER"),
+        env!("PROJECT_ORGANIZATION"),
+        env!("PROJECT_APPLICATION"),
+    )
+    .expect("no valid home directory path could be retrieved from the operating system");
+
+    project_dir.config_dir().join(SAVES_PATH)
+}
+
+/// Characters a save name may not hold, reserved for paths on some platform
+pub const RESERVED_SAVE_CHARS: [char; 9] = ['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Whether `name` stays a single file inside [`saves_dir`] once used as a save name
+pub fn valid_save_name(name: &str) -> bool {
+    !name.trim().is_empty()
+        && !name.starts_with('.')
+        && !name
+            .chars()
+            .any(|c| c.is_control() || RESERVED_SAVE_CHARS.contains(&c))
+}
+
+pub fn save_path(name: &str) -> Result<PathBuf, SaveError> {
+    if !valid_save_name(name) {
+        return Err(SaveError::InvalidName(name.to_string()));
+    }
+
+    Ok(saves_dir().join(name).with_extension(SAVE_EXTENSION))
+}
+
+/// Scratch file a save is written to before it replaces the real one
+pub fn temp_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}", TEMP_EXTENSION));
+    path.with_file_name(file_name)
+}
+
+/// Rotating backup of a save as `<name>.ron.<generation>.bak`, generation 1 being the newest
+pub fn backup_path(path: &Path, generation: usize) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(format!(".{}.{}", generation, BACKUP_EXTENSION));
+    path.with_file_name(file_name)
+}
+
+/// Every save in [`saves_dir`] as `(name, path)`, most recently modified first
+pub fn list_saves() -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(saves_dir()) else {
+        return vec![];
+    };
+
+    let mut saves = entries
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == SAVE_EXTENSION))
+        .filter_map(|path| {
+            let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+            let name = path.file_stem()?.to_string_lossy().into_owned();
+            Some((modified, name, path))
+        })
+        .collect::<Vec<_>>();
+
+    saves.sort_by(|(a, ..), (b, ..)| b.cmp(a));
+    saves
+        .into_iter()
+        .map(|(_, name, path)| (name, path))
+        .collect()
+}
+
+pub fn serialize_save(save: &LevelSave, type_registry: &TypeRegistry) -> Result<String, SaveError> {
+    let reflect_serializer = ReflectSerializer::new(save, type_registry);
+
+    ron::ser::to_string_pretty(
+        &reflect_serializer,
+        ron::ser::PrettyConfig::new()
+            .depth_limit(4)
+            .indentor("  ".into()),
+    )
+    .map_err(|err| SaveError::Serialize(err.to_string()))
+}
+
+pub fn deserialize_save(ron: &str, type_registry: &TypeRegistry) -> Result<LevelSave, SaveError> {
+    let mut deserializer = ron::de::Deserializer::from_str(ron)
+        .map_err(|err| SaveError::Deserialize(err.to_string()))?;
+    let reflect_deserializer = ReflectDeserializer::new(type_registry);
+
+    let partial_reflect_value = reflect_deserializer
+        .deserialize(&mut deserializer)
+        .map_err(|err| SaveError::Deserialize(err.to_string()))?;
+
+    let mut save = LevelSave::from_reflect(&*partial_reflect_value)
+        .ok_or_else(|| SaveError::Deserialize("save does not match level layout".into()))?;
+
+    if save.version > LevelSave::VERSION {
+        return Err(SaveError::UnsupportedVersion(save.version));
+    }
+
+    save.upgrade();
+    Ok(save)
+}
+
+/// Writes the save to a temp file, fsyncs it and atomically renames it into place
+/// so a crash never leaves a half-written save behind. The previous save is kept
+/// as backup generation 1, older ones shift up until `backups` generations remain.
+pub fn write_save(
+    path: &Path,
+    save: &LevelSave,
+    type_registry: &TypeRegistry,
+    backups: usize,
+) -> Result<(), SaveError> {
+    let serialized = serialize_save(save, type_registry)?;
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp = temp_path(path);
+    let mut file = fs::File::create(&temp)?;
+    file.write_all(serialized.as_bytes())?;
+    file.sync_all()?;
+    drop(file);
+
+    rotate_backups(path, backups)?;
+    fs::rename(&temp, path)?;
+
+    // persist the rename itself
+    #[cfg(unix)]
+    if let Some(parent) = path.parent() {
+        fs::File::open(parent)?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+fn rotate_backups(path: &Path, backups: usize) -> Result<(), SaveError> {
+    if backups == 0 || !path.exists() {
+        return Ok(());
+    }
+
+    for generation in (1..backups).rev() {
+        let backup = backup_path(path, generation);
+        if backup.exists() {
+            fs::rename(&backup, backup_path(path, generation + 1))?;
+        }
+    }
+
+    fs::copy(path, backup_path(path, 1))?;
+    Ok(())
+}
+
+pub fn read_save(path: &Path, type_registry: &TypeRegistry) -> Result<LevelSave, SaveError> {
+    let ron = fs::read_to_string(path)?;
+    deserialize_save(&ron, type_registry)
+}
+
+/// Why a save was loaded from one of its backups instead
+#[derive(Debug)]
+pub struct SaveRecovery {
+    pub generation: usize,
+    pub cause: SaveError,
+}
+
+/// Reads the save, falling back to the newest backup that is still valid when
+/// the save itself is corrupt or missing
+pub fn read_save_or_backup(
+    path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<(LevelSave, Option<SaveRecovery>), SaveError> {
+    let cause = match read_save(path, type_registry) {
+        Ok(save) => return Ok((save, None)),
+        // a save from a newer game isn't corrupt, older backups won't help
+        Err(err @ SaveError::UnsupportedVersion(_)) => return Err(err),
+        Err(err) => err,
+    };
+
+    warn!(
+        "save at {} is unreadable, looking for backups. {}",
+        path.to_string_lossy(),
+        cause
+    );
+
+    let mut generation = 1;
+    while backup_path(path, generation).exists() {
+        match read_save(&backup_path(path, generation), type_registry) {
+            Ok(save) => return Ok((save, Some(SaveRecovery { generation, cause }))),
+            Err(err) => warn!("backup {} is unreadable as well. {}", generation, err),
+        }
+        generation += 1;
+    }
+
+    Err(cause)
+}
+
+pub type SaveTilemapQuery<'a, 'b, 'c, TilemapFilter> =
+    Query<'a, 'b, (Entity, &'c mut TileStorage), TilemapFilter>;
+
+type SnapshotTile = (
+    &'static TilePos,
+    &'static TileTextureIndex,
+    Option<&'static ItemId>,
+    Has<Building>,
+);
+
+/// Every tile but buildings, which are saved by [`snapshot_buildings`]
+fn snapshot_tiles(storage: Option<&TileStorage>, tiles: &Query<SnapshotTile>) -> Vec<TileSave> {
+    storage.map_or_else(Vec::new, |storage| {
+        storage
+            .iter()
+            .flatten()
+            .filter_map(|tile| tiles.get(*tile).ok())
+            .filter(|(.., is_building)| !is_building)
+            .map(|(position, texture_index, item, _)| TileSave {
+                position: *position,
+                texture_index: texture_index.0,
+                item: item.cloned(),
+            })
+            .collect()
+    })
+}
+
+type SnapshotBuilding = (
+    &'static TilePos,
+    &'static ItemId,
+    &'static Building,
+    Has<Countertop>,
+    Option<&'static CraftingMachine>,
+    Option<&'static AutoArm>,
+    Option<&'static MoverBelt>,
+    Option<&'static InfiniteIo>,
+);
+
+fn snapshot_buildings(
+    storage: Option<&TileStorage>,
+    buildings: &Query<SnapshotBuilding>,
+) -> Vec<BuildingSave> {
+    storage.map_or_else(Vec::new, |storage| {
+        // tiles of a building footprint all hold the building, which is saved once
+        let mut seen = HashSet::new();
+        storage
+            .iter()
+            .flatten()
+            .filter(|tile| seen.insert(**tile))
+            .filter_map(|tile| buildings.get(*tile).ok())
+            .map(
+                |(origin, item, building, countertop, crafter, arm, belt, infinite_io)| {
+                    BuildingSave {
+                        item: item.clone(),
+                        origin: *origin,
+                        facing: building.facing,
+                        state: building.state.clone(),
+                        countertop,
+                        crafter: crafter.cloned(),
+                        arm: arm.cloned(),
+                        belt: belt.cloned(),
+                        infinite_io: infinite_io.cloned(),
+                    }
+                },
+            )
+            .collect()
+    })
+}
+
+/// Research that isn't locked, by id
+pub fn snapshot_research(research: &ResearchProgress) -> Vec<ResearchSave> {
+    let mut saved = research
+        .states()
+        .map(|(id, state)| ResearchSave {
+            id: id.clone(),
+            state,
+        })
+        .collect::<Vec<_>>();
+    saved.sort_by(|a, b| a.id.0.cmp(&b.id.0));
+    saved
+}
+
+/// Metrics of every item that moved, by item
+pub fn snapshot_item_metrics(metrics: &LevelMetrics) -> Vec<ItemMetricsSave> {
+    let mut saved = metrics
+        .items
+        .iter()
+        .map(|(item, metrics)| ItemMetricsSave {
+            item: item.clone(),
+            metrics: metrics.clone(),
+        })
+        .collect::<Vec<_>>();
+    saved.sort_by(|a, b| a.item.0.cmp(&b.item.0));
+    saved
+}
+
+/// Everything of a running level that ends up in a [`LevelSave`]
+#[derive(SystemParam)]
+pub struct LevelSnapshot<'w, 's> {
+    level: Query<
+        'w,
+        's,
+        (
+            &'static Level,
+            &'static ResearchProgress,
+            &'static LevelMetrics,
+            &'static ModProfileConfig,
+        ),
+    >,
+    floor: Query<'w, 's, &'static TileStorage, (With<FloorTilemap>, Without<BuildingTilemap>)>,
+    buildings: Query<'w, 's, &'static TileStorage, (With<BuildingTilemap>, Without<FloorTilemap>)>,
+    tiles: Query<'w, 's, SnapshotTile>,
+    placed: Query<'w, 's, SnapshotBuilding>,
+    inventories: Query<'w, 's, SnapshotInventory>,
+}
+
+type SnapshotInventory = (
+    &'static Inventory,
+    Option<&'static InventoryActive>,
+    Option<&'static TilePos>,
+    Has<Player>,
+);
+
+impl LevelSnapshot<'_, '_> {
+    pub fn snapshot(&self) -> Option<LevelSave> {
+        let (level, research, metrics, profile_config) = self.level.get_single().ok()?;
+
+        Some(LevelSave {
+            version: LevelSave::VERSION,
+            total_play_time: level.total_play_time,
+            tick: level.tick,
+            resources: level.resources.clone(),
+            mod_profile: profile_config.0.clone(),
+            floor: snapshot_tiles(self.floor.get_single().ok(), &self.tiles),
+            buildings: snapshot_tiles(self.buildings.get_single().ok(), &self.tiles),
+            placed: snapshot_buildings(self.buildings.get_single().ok(), &self.placed),
+            inventories: self
+                .inventories
+                .iter()
+                .filter_map(|(inventory, active, tile_pos, is_player)| {
+                    let owner = match (is_player, tile_pos) {
+                        (true, _) => InventoryOwner::Player,
+                        (false, Some(tile_pos)) => InventoryOwner::Building(*tile_pos),
+                        // inventories without a place in the world can't be restored
+                        (false, None) => return None,
+                    };
+
+                    Some(InventorySave {
+                        owner,
+                        slots: inventory.0.clone(),
+                        active: active.and_then(|active| active.0),
+                    })
+                })
+                .collect(),
+            research: snapshot_research(research),
+            item_metrics: snapshot_item_metrics(metrics),
+            custom_metrics: metrics.custom().clone(),
+        })
+    }
+}
+
+pub fn save_level(
+    mut save_events: EventReader<SaveLevel>,
+    snapshot: LevelSnapshot,
+    settings: Res<AutosaveSettings>,
+    type_registry: Res<AppTypeRegistry>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Some(save) = snapshot.snapshot() else {
+        save_events.clear();
+        warn!("no level to save");
+        return;
+    };
+
+    let type_registry = type_registry.read();
+
+    for SaveLevel(name) in save_events.read() {
+        let written = save_path(name).and_then(|path| {
+            write_save(&path, &save, &type_registry, settings.backups).map(|()| path)
+        });
+        match written {
+            Ok(path) => {
+                info!("saved level at {}", path.to_string_lossy());
+                Notification {
+                    title: "Game saved".into(),
+                    level: NotificationLevel::Info,
+                    description: format!("Saved as `{}`", name),
+                }
+                .queue(Some(Duration::from_secs(3)), &mut notifications_channel);
+            }
+            Err(err) => {
+                error!("failed to save level as `{}`. {}", name, err);
+                Notification {
+                    title: "Failed to save game".into(),
+                    level: NotificationLevel::Error,
+                    description: err.to_string(),
+                }
+                .queue(None, &mut notifications_channel);
+            }
+        }
+    }
+}
+
+fn restore_tiles(
+    cmd: &mut Commands,
+    tilemap: Option<(Entity, Mut<TileStorage>)>,
+    tiles: &[TileSave],
+) -> HashMap<TilePos, Entity> {
+    let mut restored = HashMap::default();
+
+    let Some((tilemap_entity, mut storage)) = tilemap else {
+        if !tiles.is_empty() {
+            warn!("no tilemap to restore {} saved tiles onto", tiles.len());
+        }
+        return restored;
+    };
+
+    for tile in tiles {
+        if let Some(previous) = storage.get(&tile.position) {
+            cmd.entity(previous).despawn_recursive();
+            storage.remove(&tile.position);
+        }
+
+        let mut tile_cmd = cmd.spawn(TileBundle {
+            position: tile.position,
+            texture_index: TileTextureIndex(tile.texture_index),
+            tilemap_id: TilemapId(tilemap_entity),
+            ..default()
+        });
+
+        if let Some(item) = &tile.item {
+            tile_cmd.insert(item.clone());
+        }
+
+        let tile_entity = tile_cmd.id();
+        cmd.entity(tilemap_entity).add_child(tile_entity);
+        storage.set(&tile.position, tile_entity);
+        restored.insert(tile.position, tile_entity);
+    }
+
+    restored
+}
+
+/// Spawns the buildings from their definition over whatever lies on their footprint, adding
+/// them to the tiles `restored` by their origin
+fn restore_buildings(
+    cmd: &mut Commands,
+    tilemap: Option<(Entity, Mut<TileStorage>)>,
+    placed: &[BuildingSave],
+    registry: &BuildingRegistry,
+    restored: &mut HashMap<TilePos, Entity>,
+) {
+    let Some((tilemap_entity, mut storage)) = tilemap else {
+        if !placed.is_empty() {
+            warn!(
+                "no tilemap to restore {} saved buildings onto",
+                placed.len()
+            );
+        }
+        return;
+    };
+
+    for saved in placed {
+        let Some(definition) = registry.building(&saved.item) else {
+            warn!(
+                "no building defined for {}, left out of the level",
+                saved.item.0
+            );
+            continue;
+        };
+        let facing = definition.facing(Some(saved.facing));
+        let Some(cells) = definition.cells(saved.origin, facing) else {
+            warn!(
+                "building {} doesn't fit at {:?}",
+                saved.item.0, saved.origin
+            );
+            continue;
+        };
+
+        for cell in cells.iter() {
+            if let Some(previous) = storage.get(cell) {
+                cmd.entity(previous).despawn_recursive();
+                storage.remove(cell);
+            }
+        }
+
+        let building = definition.spawn(cmd, tilemap_entity, saved.origin, facing);
+        let mut building_cmd = cmd.entity(building);
+        building_cmd.insert(Building {
+            facing,
+            state: saved.state.clone(),
+        });
+        if saved.countertop {
+            building_cmd.insert(Countertop);
+        }
+        if let Some(crafter) = saved
+            .crafter
+            .clone()
+            .filter(|_| definition.has(&BuildingComponent::Crafter(None)))
+        {
+            building_cmd.insert(crafter);
+        }
+        if let Some(arm) = saved
+            .arm
+            .clone()
+            .filter(|_| definition.has(&BuildingComponent::AutoArm))
+        {
+            building_cmd.insert(AutoArm {
+                direction: facing,
+                ..arm
+            });
+        }
+        if let Some(belt) = saved
+            .belt
+            .clone()
+            .filter(|_| definition.has(&BuildingComponent::MoverBelt))
+        {
+            building_cmd.insert(MoverBelt {
+                direction: facing,
+                ..belt
+            });
+        }
+        if let Some(infinite_io) = saved
+            .infinite_io
+            .clone()
+            .filter(|_| definition.has(&BuildingComponent::InfiniteIo))
+        {
+            building_cmd.insert(infinite_io);
+        }
+
+        cmd.entity(tilemap_entity).add_child(building);
+        for cell in cells.iter() {
+            storage.set(cell, building);
+        }
+        restored.insert(saved.origin, building);
+    }
+}
+
+/// Applies [`PendingSave`] onto the freshly spawned level
+#[allow(clippy::too_many_arguments)]
+pub fn restore_level(
+    mut cmd: Commands,
+    pending: Res<PendingSave>,
+    mut level: Query<(&mut Level, &mut ResearchProgress, &mut LevelMetrics)>,
+    mut floor: SaveTilemapQuery<(With<FloorTilemap>, Without<BuildingTilemap>)>,
+    mut buildings: SaveTilemapQuery<(With<BuildingTilemap>, Without<FloorTilemap>)>,
+    mut player_inventory: Query<(&mut Inventory, &mut InventoryActive), With<Player>>,
+    registry: Res<BuildingRegistry>,
+    mut time: ResMut<SimulationTime>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let save = &pending.0;
+
+    time.tick = save.tick;
+    if let Ok((mut level, mut research, mut metrics)) = level.get_single_mut() {
+        level.total_play_time = save.total_play_time;
+        level.tick = save.tick;
+        level.resources = save.resources.clone();
+        *research = save.research_progress();
+        *metrics = save.level_metrics();
+    }
+
+    restore_tiles(&mut cmd, floor.get_single_mut().ok(), &save.floor);
+    let mut restored = restore_tiles(&mut cmd, buildings.get_single_mut().ok(), &save.buildings);
+    restore_buildings(
+        &mut cmd,
+        buildings.get_single_mut().ok(),
+        &save.placed,
+        &registry,
+        &mut restored,
+    );
+
+    for inventory_save in save.inventories.iter() {
+        let inventory = Inventory(inventory_save.slots.clone());
+        let active = InventoryActive(inventory_save.active);
+
+        match &inventory_save.owner {
+            InventoryOwner::Player => {
+                if let Ok((mut player_inventory, mut player_active)) =
+                    player_inventory.get_single_mut()
+                {
+                    *player_inventory = inventory;
+                    *player_active = active;
+                }
+            }
+            InventoryOwner::Building(tile_pos) => match restored.get(tile_pos) {
+                Some(tile_entity) => {
+                    cmd.entity(*tile_entity).insert((inventory, active));
+                }
+                None => warn!("no building at {:?} to restore inventory onto", tile_pos),
+            },
+        }
+    }
+
+    info!("restored level from save");
+    Notification {
+        title: "Game loaded".into(),
+        level: NotificationLevel::Info,
+        description: format!("Played for {} minutes", save.total_play_time.as_secs() / 60),
+    }
+    .queue(Some(Duration::from_secs(3)), &mut notifications_channel);
+
+    cmd.remove_resource::<PendingSave>();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_round_trip() {
+        let mut app = App::new();
+        app.register_type::<LevelSave>();
+        app.add_systems(Startup, |type_registry: Res<AppTypeRegistry>| {
+            let type_registry = type_registry.read();
+
+            let mut resources = HashMap::default();
+            resources.insert("money".into(), 200.);
+
+            let save = LevelSave {
+                version: LevelSave::VERSION,
+                total_play_time: Duration::from_secs(90),
+                tick: 0,
+                resources,
+                mod_profile: vec!["base_0.0.0-dev".into()],
+                floor: vec![TileSave {
+                    position: TilePos { x: 0, y: 0 },
+                    texture_index: 0,
+                    item: None,
+                }],
+                buildings: vec![TileSave {
+                    position: TilePos { x: 18, y: 3 },
+                    texture_index: 1,
+                    item: None,
+                }],
+                placed: vec![BuildingSave {
+                    item: "base::infinite_io".into(),
+                    origin: TilePos { x: 2, y: 3 },
+                    facing: BeltDirection::East,
+                    state: Some(BROKEN.into()),
+                    countertop: false,
+                    crafter: None,
+                    arm: None,
+                    belt: None,
+                    infinite_io: Some(InfiniteIo::source("base::burger".into())),
+                }],
+                inventories: vec![
+                    InventorySave {
+                        owner: InventoryOwner::Player,
+                        slots: vec![
+                            Some(ItemEntry {
+                                item: "base::auto_arm".into(),
+                                quantity: 20,
+                            }),
+                            None,
+                        ],
+                        active: Some(0),
+                    },
+                    InventorySave {
+                        owner: InventoryOwner::Building(TilePos { x: 2, y: 3 }),
+                        slots: vec![None],
+                        active: None,
+                    },
+                ],
+                research: vec![],
+                item_metrics: vec![],
+                custom_metrics: HashMap::default(),
+            };
+
+            let serialized = serialize_save(&save, &type_registry).unwrap();
+            let deserialized = deserialize_save(&serialized, &type_registry).unwrap();
+
+            assert_eq!(save, deserialized);
+        });
+
+        app.run();
+    }
+
+    #[test]
+    fn save_keeps_research_and_metrics() {
+        let mut app = App::new();
+        app.register_type::<LevelSave>();
+        app.add_systems(Startup, |type_registry: Res<AppTypeRegistry>| {
+            let type_registry = type_registry.read();
+
+            let mut declared = Merged::<ResearchDeclared>::default();
+            for (id, required) in [
+                ("tyconic::lemon_stand_i", vec![]),
+                (
+                    "tyconic::foodie_i",
+                    vec![ResearchId("tyconic::lemon_stand_i".into())],
+                ),
+            ] {
+                declared.apply(
+                    "tyconic",
+                    Declaration::Declare(ResearchDeclared {
+                        id: ResearchId(id.into()),
+                        display_name: id.into(),
+                        flavor_text: String::new(),
+                        unlock_condition: ConditionFlag::SatisfyAll(vec![]),
+                        required_research: required,
+                    }),
+                );
+            }
+            let declared = ResearchRegistry::from(declared);
+            let mut research = ResearchProgress::default();
+            research.step(&declared, &LevelConditions::default());
+            research
+                .start(&ResearchId("tyconic::lemon_stand_i".into()))
+                .unwrap();
+            research.step(&declared, &LevelConditions::default());
+
+            let settings = SimulationSettings::default();
+            let mut metrics = LevelMetrics::default();
+            metrics.register("tyconic::customers_served").unwrap();
+            metrics.add("tyconic::customers_served", 4.).unwrap();
+            metrics.track(
+                [&ItemFlow {
+                    item: "tyconic::pizza_slice".into(),
+                    quantity: 4,
+                    kind: ItemFlowKind::Produced,
+                }]
+                .into_iter(),
+                12,
+                &settings,
+            );
+
+            let save = LevelSave {
+                version: LevelSave::VERSION,
+                total_play_time: Duration::from_secs(90),
+                tick: 0,
+                resources: HashMap::default(),
+                mod_profile: vec![],
+                floor: vec![],
+                buildings: vec![],
+                placed: vec![],
+                inventories: vec![],
+                research: snapshot_research(&research),
+                item_metrics: snapshot_item_metrics(&metrics),
+                custom_metrics: metrics.custom().clone(),
+            };
+
+            let serialized = serialize_save(&save, &type_registry).unwrap();
+            let deserialized = deserialize_save(&serialized, &type_registry).unwrap();
+
+            assert_eq!(deserialized, save);
+            assert_eq!(deserialized.research_progress(), research);
+            assert_eq!(deserialized.level_metrics(), metrics);
+        });
+
+        app.run();
+    }
+
+    #[test]
+    fn older_saves_upgrade() {
+        let mut app = App::new();
+        app.register_type::<LevelSave>();
+        app.add_systems(Startup, |type_registry: Res<AppTypeRegistry>| {
+            let type_registry = type_registry.read();
+
+            let ron = r#"{
+                "tyconia::levels::save::LevelSave": (
+                    version: 1,
+                    total_play_time: (secs: 90, nanos: 0),
+                    resources: { "money": 200.0 },
+                    mod_profile: [],
+                    floor: [],
+                    buildings: [
+                        (
+                            position: (x: 18, y: 3),
+                            texture_index: 1,
+                            item: None,
+                        ),
+                        (
+                            position: (x: 2, y: 3),
+                            texture_index: 0,
+                            item: Some(("base::auto_arm")),
+                        ),
+                    ],
+                    inventories: [],
+                ),
+            }"#;
+
+            let save = deserialize_save(ron, &type_registry).unwrap();
+            assert_eq!(save.version, LevelSave::VERSION);
+            assert_eq!(save.resources["money"], 200.);
+            // the countertop stays a tile, the arm becomes a building
+            assert_eq!(save.buildings.len(), 1);
+            assert_eq!(save.buildings[0].item, None);
+            assert_eq!(save.placed.len(), 1);
+            assert_eq!(save.placed[0].item, ItemId::from("base::auto_arm"));
+            assert_eq!(save.placed[0].origin, TilePos { x: 2, y: 3 });
+            assert_eq!(save.research_progress(), ResearchProgress::default());
+            assert_eq!(save.level_metrics(), LevelMetrics::default());
+        });
+
+        app.run();
+    }
+
+    #[test]
+    fn saves_without_a_tick_forget_the_last_minute() {
+        let settings = SimulationSettings::default();
+        let mut metrics = LevelMetrics::default();
+        metrics.track(
+            [&ItemFlow {
+                item: "tyconic::pizza_slice".into(),
+                quantity: 3,
+                kind: ItemFlowKind::Produced,
+            }]
+            .into_iter(),
+            1500,
+            &settings,
+        );
+        let mut save = LevelSave {
+            version: 3,
+            total_play_time: Duration::from_secs(80),
+            tick: 0,
+            resources: HashMap::default(),
+            mod_profile: vec![],
+            floor: vec![],
+            buildings: vec![],
+            placed: vec![],
+            inventories: vec![],
+            research: vec![],
+            item_metrics: snapshot_item_metrics(&metrics),
+            custom_metrics: HashMap::default(),
+        };
+
+        save.upgrade();
+
+        let produced = &save.item_metrics[0].metrics.produced;
+        assert_eq!(produced.total, 3);
+        assert_eq!(produced.per_minute(0, &settings), 0.);
+    }
+
+    #[test]
+    fn buildings_respawn_from_their_definition() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mut registry = BuildingRegistry::default();
+        registry.define(BuildingDefinition {
+            footprint: vec![IVec2::Y],
+            rotations: vec![BeltDirection::North, BeltDirection::East],
+            components: vec![
+                BuildingComponent::Inventory(2),
+                BuildingComponent::Crafter(None),
+            ],
+            ..BuildingDefinition::from(ItemId::from("tyconic::oven"))
+        });
+
+        let crafter = CraftingMachine {
+            recipe: Some(RecipeId("tyconic::folk_pizza".into())),
+            state: CraftingState::Working,
+            progress: Duration::from_millis(300),
+        };
+        let save = LevelSave {
+            version: LevelSave::VERSION,
+            total_play_time: Duration::ZERO,
+            tick: 0,
+            resources: HashMap::default(),
+            mod_profile: vec![],
+            floor: vec![],
+            buildings: vec![],
+            placed: vec![BuildingSave {
+                item: "tyconic::oven".into(),
+                origin: TilePos { x: 4, y: 4 },
+                facing: BeltDirection::East,
+                state: None,
+                countertop: false,
+                crafter: Some(crafter.clone()),
+                arm: None,
+                belt: None,
+                infinite_io: None,
+            }],
+            inventories: vec![InventorySave {
+                owner: InventoryOwner::Building(TilePos { x: 4, y: 4 }),
+                slots: vec![
+                    Some(ItemEntry {
+                        item: "tyconic::cheese_wheel".into(),
+                        quantity: 1,
+                    }),
+                    None,
+                ],
+                active: None,
+            }],
+            research: vec![],
+            item_metrics: vec![],
+            custom_metrics: HashMap::default(),
+        };
+
+        let mut app = App::new();
+        app.add_event::<NotificationEvent>()
+            .init_resource::<SimulationTime>()
+            .insert_resource(registry)
+            .insert_resource(PendingSave(save));
+        app.world_mut().spawn(Level::default());
+        // a countertop lies on the second tile of the footprint
+        let countertop = app
+            .world_mut()
+            .spawn((TilePos { x: 5, y: 4 }, Countertop))
+            .id();
+        let mut storage = TileStorage::empty(TilemapSize { x: 8, y: 8 });
+        storage.set(&TilePos { x: 5, y: 4 }, countertop);
+        let tilemap = app.world_mut().spawn((BuildingTilemap, storage)).id();
+
+        app.world_mut().run_system_once(restore_level).unwrap();
+
+        let world = app.world();
+        let storage = world.get::<TileStorage>(tilemap).unwrap();
+        let oven = storage.get(&TilePos { x: 4, y: 4 }).unwrap();
+        assert_eq!(storage.get(&TilePos { x: 5, y: 4 }), Some(oven));
+        assert!(world.get_entity(countertop).is_err());
+
+        assert_eq!(world.get::<ItemId>(oven), Some(&"tyconic::oven".into()));
+        assert_eq!(
+            world.get::<Building>(oven).map(|building| building.facing),
+            Some(BeltDirection::East)
+        );
+        assert_eq!(world.get::<CraftingMachine>(oven), Some(&crafter));
+        assert_eq!(
+            world
+                .get::<Inventory>(oven)
+                .unwrap()
+                .count(&"tyconic::cheese_wheel".into()),
+            1
+        );
+        assert!(!world.contains_resource::<PendingSave>());
+    }
+
+    #[test]
+    fn per_minute_survives_a_reload() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let settings = SimulationSettings::default();
+        let mut metrics = LevelMetrics::default();
+        for tick in [900, 1200, 1500] {
+            metrics.track(
+                [&ItemFlow {
+                    item: "tyconic::pizza_slice".into(),
+                    quantity: 2,
+                    kind: ItemFlowKind::Produced,
+                }]
+                .into_iter(),
+                tick,
+                &settings,
+            );
+        }
+        let level = Level {
+            tick: 1600,
+            ..Level::default()
+        };
+        let metric = Metric::Item {
+            item: "tyconic::pizza_slice".into(),
+            stat: ItemStat::PerMinute(ItemFlowKind::Produced),
+        };
+        let per_minute = metrics.get(&metric, &level, &settings).unwrap();
+        assert!(per_minute > 0.);
+
+        let mut app = App::new();
+        app.register_type::<LevelSave>();
+        let save = LevelSave {
+            version: LevelSave::VERSION,
+            total_play_time: Duration::from_secs(80),
+            tick: level.tick,
+            resources: HashMap::default(),
+            mod_profile: vec![],
+            floor: vec![],
+            buildings: vec![],
+            placed: vec![],
+            inventories: vec![],
+            research: vec![],
+            item_metrics: snapshot_item_metrics(&metrics),
+            custom_metrics: HashMap::default(),
+        };
+        let save = {
+            let type_registry = app.world().resource::<AppTypeRegistry>().read();
+            let serialized = serialize_save(&save, &type_registry).unwrap();
+            deserialize_save(&serialized, &type_registry).unwrap()
+        };
+
+        app.add_event::<NotificationEvent>()
+            .init_resource::<SimulationTime>()
+            .init_resource::<BuildingRegistry>()
+            .insert_resource(PendingSave(save));
+        app.world_mut().spawn(Level::default());
+        app.world_mut().run_system_once(restore_level).unwrap();
+
+        assert_eq!(app.world().resource::<SimulationTime>().tick, 1600);
+        let (restored, restored_metrics) = app
+            .world_mut()
+            .query::<(&Level, &LevelMetrics)>()
+            .single(app.world());
+        assert_eq!(restored.tick, 1600);
+        assert_eq!(
+            restored_metrics.get(&metric, restored, &settings),
+            Some(per_minute)
+        );
+    }
+
+    #[test]
+    fn save_names_stay_in_saves_dir() {
+        assert_eq!(
+            save_path("quicksave").unwrap(),
+            saves_dir().join("quicksave.ron")
+        );
+        assert!(save_path("my factory 2").is_ok());
+
+        for name in [
+            "",
+            "  ",
+            "../escape",
+            "..",
+            ".hidden",
+            "a/b",
+            "a\\b",
+            "c:",
+            "tab\t",
+        ] {
+            assert!(
+                matches!(save_path(name), Err(SaveError::InvalidName(_))),
+                "{:?} accepted",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn save_newer_version_rejected() {
+        let mut app = App::new();
+        app.register_type::<LevelSave>();
+        app.add_systems(Startup, |type_registry: Res<AppTypeRegistry>| {
+            let type_registry = type_registry.read();
+
+            let save = LevelSave {
+                version: LevelSave::VERSION + 1,
+                total_play_time: Duration::ZERO,
+                tick: 0,
+                resources: HashMap::default(),
+                mod_profile: vec![],
+                floor: vec![],
+                buildings: vec![],
+                placed: vec![],
+                inventories: vec![],
+                research: vec![],
+                item_metrics: vec![],
+                custom_metrics: HashMap::default(),
+            };
+
+            let serialized = serialize_save(&save, &type_registry).unwrap();
+
+            assert!(matches!(
+                deserialize_save(&serialized, &type_registry),
+                Err(SaveError::UnsupportedVersion(_))
+            ));
+        });
+
+        app.run();
+    }
+
+    #[test]
+    fn save_falls_back_to_backup() {
+        let mut app = App::new();
+        app.register_type::<LevelSave>();
+        app.add_systems(Startup, |type_registry: Res<AppTypeRegistry>| {
+            let type_registry = type_registry.read();
+
+            let dir = std::env::temp_dir().join("tyconia_save_falls_back_to_backup");
+            let _ = fs::remove_dir_all(&dir);
+            let path = dir.join("rotating").with_extension(SAVE_EXTENSION);
+
+            let save = |seconds| LevelSave {
+                version: LevelSave::VERSION,
+                total_play_time: Duration::from_secs(seconds),
+                tick: 0,
+                resources: HashMap::default(),
+                mod_profile: vec![],
+                floor: vec![],
+                buildings: vec![],
+                placed: vec![],
+                inventories: vec![],
+                research: vec![],
+                item_metrics: vec![],
+                custom_metrics: HashMap::default(),
+            };
+
+            for seconds in 1..=4 {
+                write_save(&path, &save(seconds), &type_registry, 2).unwrap();
+            }
+
+            assert!(!temp_path(&path).exists());
+            assert!(backup_path(&path, 2).exists());
+            assert!(!backup_path(&path, 3).exists());
+
+            // half-written save and a corrupt newest backup
+            fs::write(&path, "{ \"tyconia::levels::save::LevelSave\": (vers").unwrap();
+            fs::write(backup_path(&path, 1), "").unwrap();
+
+            let (recovered, recovery) = read_save_or_backup(&path, &type_registry).unwrap();
+            assert_eq!(recovered, save(2));
+            assert_eq!(recovery.map(|recovery| recovery.generation), Some(2));
+
+            fs::remove_dir_all(&dir).unwrap();
+        });
+
+        app.run();
+    }
+}
@@ -0,0 +1,319 @@
+//! Factory logic runs in the [`Simulation`] schedule on a fixed tick, independent of the
+//! frame rate. Systems read the length of a tick from [`SimulationTime`] so that replaying
+//! the same inputs for the same number of ticks gives the same level.
+
+use crate::*;
+use bevy::ecs::schedule::ScheduleLabel;
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SimulationSettings>()
+            .register_type::<GameSpeed>()
+            .init_resource::<SimulationSettings>()
+            .init_resource::<GameSpeed>()
+            .init_resource::<SimulationTime>()
+            .init_schedule(Simulation)
+            .add_systems(Simulation, advance_level_tick)
+            .add_systems(OnEnter(GameState::Playing), reset_simulation)
+            .add_systems(
+                Update,
+                (
+                    apply_game_speed.run_if(resource_changed::<GameSpeed>),
+                    run_simulation.run_if(in_state(InGameState::Normal)),
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Runs once per simulation tick, as many times per frame as the game speed requires
+#[derive(ScheduleLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Simulation;
+
+#[derive(Resource, Reflect, Debug, Clone, PartialEq)]
+pub struct SimulationSettings {
+    /// ticks per second of simulated time
+    pub tick_rate: u32,
+    /// ticks run within one frame at most, a slower machine drops the rest and the game
+    /// slows down instead of falling further behind
+    pub max_ticks_per_update: u32,
+}
+
+impl SimulationSettings {
+    pub fn tick(&self) -> Duration {
+        Duration::from_secs(1) / self.tick_rate.max(1)
+    }
+}
+
+impl Default for SimulationSettings {
+    fn default() -> Self {
+        Self {
+            tick_rate: 60,
+            max_ticks_per_update: 16,
+        }
+    }
+}
+
+/// Game speed chosen by the player, [`GameSpeed::Paused`] holds the level in
+/// [`InGameState::Paused`]
+#[derive(Resource, Reflect, Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GameSpeed {
+    Paused,
+    #[default]
+    Normal,
+    Double,
+    Quadruple,
+}
+
+impl GameSpeed {
+    pub fn multiplier(&self) -> u32 {
+        match self {
+            Self::Paused => 0,
+            Self::Normal => 1,
+            Self::Double => 2,
+            Self::Quadruple => 4,
+        }
+    }
+}
+
+impl std::fmt::Display for GameSpeed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Paused => write!(f, "Paused"),
+            speed => write!(f, "{}x", speed.multiplier()),
+        }
+    }
+}
+
+/// Tick being simulated. Within [`Simulation`], `delta` is the length of a tick
+#[derive(Resource, Debug, Default, Clone, PartialEq)]
+pub struct SimulationTime {
+    pub tick: u64,
+    pub delta: Duration,
+    /// frame time not yet simulated
+    accumulated: Duration,
+}
+
+impl SimulationTime {
+    /// Adds frame time scaled by the game speed and returns how many ticks are due
+    pub fn accumulate(
+        &mut self,
+        delta: Duration,
+        speed: GameSpeed,
+        settings: &SimulationSettings,
+    ) -> u32 {
+        let tick = settings.tick();
+        self.delta = tick;
+        self.accumulated += delta * speed.multiplier();
+
+        let mut ticks = 0;
+        while self.accumulated >= tick {
+            if ticks == settings.max_ticks_per_update {
+                self.accumulated = Duration::ZERO;
+                break;
+            }
+
+            self.accumulated -= tick;
+            ticks += 1;
+        }
+
+        ticks
+    }
+}
+
+fn reset_simulation(mut time: ResMut<SimulationTime>, mut speed: ResMut<GameSpeed>) {
+    *time = SimulationTime::default();
+    *speed = GameSpeed::default();
+}
+
+fn apply_game_speed(
+    speed: Res<GameSpeed>,
+    in_game_state: Res<State<InGameState>>,
+    mut next_in_game_state: ResMut<NextState<InGameState>>,
+) {
+    match (*speed, in_game_state.get()) {
+        (GameSpeed::Paused, InGameState::Normal) => next_in_game_state.set(InGameState::Paused),
+        (GameSpeed::Paused, _) => {}
+        (_, InGameState::Paused) => next_in_game_state.set(InGameState::Normal),
+        _ => {}
+    }
+}
+
+/// Runs [`Simulation`] for every tick due since the last frame
+pub fn run_simulation(world: &mut World) {
+    let delta = world.resource::<Time>().delta();
+    let speed = *world.resource::<GameSpeed>();
+    let settings = world.resource::<SimulationSettings>().clone();
+
+    let ticks = world
+        .resource_mut::<SimulationTime>()
+        .accumulate(delta, speed, &settings);
+
+    for _ in 0..ticks {
+        world.run_schedule(Simulation);
+    }
+}
+
+pub fn advance_level_tick(mut time: ResMut<SimulationTime>, mut level: Query<&mut Level>) {
+    time.tick += 1;
+    // conditions wait for `Changed<Level>`, which the tick alone shouldn't trigger
+    for mut level in level.iter_mut() {
+        level.bypass_change_detection().tick += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::state::app::StatesPlugin;
+    use bevy::time::TimeUpdateStrategy;
+
+    fn entry(item: &'static str, quantity: usize) -> ItemEntry {
+        ItemEntry {
+            item: item.into(),
+            quantity,
+        }
+    }
+
+    fn recipes() -> RecipeRegistry {
+        let mut recipes = Merged::<RecipeDeclared>::default();
+        recipes.apply(
+            "tyconic",
+            Declaration::Declare(RecipeDeclared {
+                id: RecipeId("tyconic::folk_pizza".into()),
+                recipe: Recipe {
+                    ingredients: vec![entry("tyconic::cheese_wheel", 1)],
+                    output: vec![entry("tyconic::pizza_slice", 2)],
+                    research_required: vec![],
+                    duration: 250,
+                },
+            }),
+        );
+        recipes.into()
+    }
+
+    /// Headless level with a few machines, each frame lasting `frame`
+    fn app(frame: Duration) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(frame))
+            .insert_state(GameState::Playing)
+            .add_sub_state::<InGameState>()
+            .insert_resource(recipes())
+            .init_resource::<ItemRegistry>()
+            .add_plugins((SimulationPlugin, CraftingPlugin));
+
+        app.world_mut().spawn(Level::default());
+        for cheese in [3, 7, 12] {
+            let mut inventory = Inventory::with_capacity(4);
+            inventory.dump(vec![entry("tyconic::cheese_wheel", cheese)]);
+            app.world_mut().spawn((
+                CraftingMachine::new(RecipeId("tyconic::folk_pizza".into())),
+                inventory,
+            ));
+        }
+
+        app
+    }
+
+    fn run_until_tick(app: &mut App, tick: u64) {
+        while app.world().resource::<SimulationTime>().tick < tick {
+            app.update();
+        }
+    }
+
+    /// Sorted by output so machines compare across apps
+    fn inventories(app: &mut App) -> Vec<Inventory> {
+        let mut inventories = app
+            .world_mut()
+            .query_filtered::<&Inventory, With<CraftingMachine>>()
+            .iter(app.world())
+            .cloned()
+            .collect::<Vec<_>>();
+        inventories.sort_by_key(|inventory| inventory.count(&"tyconic::pizza_slice".into()));
+        inventories
+    }
+
+    #[test]
+    fn ticks_accumulate_with_speed() {
+        let settings = SimulationSettings::default();
+        let mut time = SimulationTime::default();
+
+        assert_eq!(
+            time.accumulate(Duration::from_millis(50), GameSpeed::Normal, &settings),
+            3
+        );
+        assert_eq!(
+            time.accumulate(Duration::from_millis(50), GameSpeed::Quadruple, &settings),
+            12
+        );
+        assert_eq!(
+            time.accumulate(Duration::from_millis(50), GameSpeed::Paused, &settings),
+            0
+        );
+        // a long frame is cut short instead of catching up
+        assert_eq!(
+            time.accumulate(Duration::from_secs(5), GameSpeed::Normal, &settings),
+            settings.max_ticks_per_update
+        );
+        assert_eq!(
+            time.accumulate(Duration::ZERO, GameSpeed::Normal, &settings),
+            0
+        );
+    }
+
+    #[test]
+    fn same_ticks_same_inventories_whatever_the_frame_rate() {
+        let mut slow = app(Duration::from_millis(50));
+        let mut fast = app(Duration::from_millis(7));
+
+        // a fast frame runs one tick at most and lands on the same tick as the slow one
+        run_until_tick(&mut slow, 600);
+        let tick = slow.world().resource::<SimulationTime>().tick;
+        run_until_tick(&mut fast, tick);
+        assert_eq!(fast.world().resource::<SimulationTime>().tick, tick);
+
+        assert_eq!(inventories(&mut slow), inventories(&mut fast));
+        assert_eq!(
+            inventories(&mut slow)[0].count(&"tyconic::pizza_slice".into()),
+            6
+        );
+
+        let level_tick = slow.world_mut().query::<&Level>().single(slow.world()).tick;
+        assert_eq!(level_tick, tick);
+    }
+
+    #[test]
+    fn paused_speed_holds_the_simulation() {
+        let mut app = app(Duration::from_millis(50));
+        run_until_tick(&mut app, 10);
+
+        *app.world_mut().resource_mut::<GameSpeed>() = GameSpeed::Paused;
+        app.update();
+        let tick = app.world().resource::<SimulationTime>().tick;
+        for _ in 0..10 {
+            app.update();
+        }
+
+        assert_eq!(
+            *app.world().resource::<State<InGameState>>().get(),
+            InGameState::Paused
+        );
+        assert_eq!(app.world().resource::<SimulationTime>().tick, tick);
+
+        *app.world_mut().resource_mut::<GameSpeed>() = GameSpeed::Double;
+        for _ in 0..2 {
+            app.update();
+        }
+        assert_eq!(
+            *app.world().resource::<State<InGameState>>().get(),
+            InGameState::Normal
+        );
+        assert!(app.world().resource::<SimulationTime>().tick > tick);
+    }
+}
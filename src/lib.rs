@@ -70,8 +70,14 @@ enum InGameState {
 pub fn handle_game_state_events(
     mut next_game_state: ResMut<NextState<GameState>>,
     mut game_state_events: EventReader<GameState>,
+    mut mod_profile_resolver: ModProfileResolver,
 ) {
     for game_state in game_state_events.read() {
+        // a broken mod profile never makes it into a level
+        if *game_state == GameState::Playing && !mod_profile_resolver.resolve() {
+            continue;
+        }
+
         next_game_state.set(*game_state);
     }
 }
@@ -80,7 +86,7 @@ pub struct GamePlugin;
 
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins((ModProfilePlugin,))
+        app.add_plugins((ModProfilePlugin, MigrationPlugin))
             .register_type::<ModPack>();
 
 
@@ -91,8 +97,9 @@ impl Plugin for GamePlugin {
             .register_type::<State<GameState>>()
             .init_state::<DeveloperMode>()
             .add_event::<GameState>()
+            // after commands of Update, e.g. a PendingSave to resolve mods for, are applied
             .add_systems(
-                Update,
+                PostUpdate,
                 handle_game_state_events.run_if(on_event::<GameState>),
             )
             .enable_state_scoped_entities::<GameState>()
@@ -1,7 +1,13 @@
 use bevy::prelude::*;
 //use bevy::utils::HashMap;
-use std::{collections::HashMap, fs, path::PathBuf};
+use bevy_ecs_tilemap::prelude::*;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
 
+use super::{load_item_asset_map, ItemTextureMap, ITEM_ASSETS_PATH};
+use crate::ui::*;
 use crate::*;
 
 /// Allows an item to be built on the map.
@@ -21,175 +27,175 @@ pub enum BuildingAssetSource {
     InferWithStates(Vec<String>),
 }
 
-#[derive(Component, Reflect)]
-pub struct BuildingAssetMapConfig(pub HashMap<String, BuildingAssetSource>);
-
+/// Sprites of the buildings of the level keyed by [`sprite_key`]
 #[derive(Component, Reflect)]
 pub struct BuildingAssetMap(pub HashMap<String, Handle<Image>>);
 
 pub struct BuildingAssetMapPlugin;
 
-impl Plugin for BuildingAssetMapConfig {
+impl Plugin for BuildingAssetMapPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<BuildingAssetMapConfig>()
+        app.register_type::<BuildingDefinitions>()
             .register_type::<BuildingAssetMap>()
-            //.add_systems(
-            //    OnEnter(GameState::Playing),
-            //    (init_item_asset_map, load_item_asset_map,).chain().after(load_mods_from_profile),
-            //)
-            ;
+            .register_type::<Building>()
+            .init_resource::<BuildingRegistry>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                load_building_definitions
+                    .after(load_mods_from_profile)
+                    .after(load_item_asset_map),
+            )
+            .add_systems(
+                Update,
+                show_building_sprites.run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Sprites of the building placed by `item_id` for the mod at `mod_path`, keyed by state.
+/// [`BuildingAssetSource::InferItem`] has none of its own and is drawn as its item.
+pub fn resolve_building_sprites(
+    mod_path: &Path,
+    item_id: &str,
+    source: &BuildingAssetSource,
+) -> Vec<(Option<String>, PathBuf)> {
+    let inferred = |state: Option<&str>| {
+        mod_path
+            .join(ITEM_ASSETS_PATH)
+            .join(format!("{}.png", sprite_key(item_id, state)))
+    };
+
+    match source {
+        BuildingAssetSource::Path(path) => vec![(None, path.clone())],
+        BuildingAssetSource::Infer => vec![(None, inferred(None))],
+        BuildingAssetSource::InferItem => vec![],
+        BuildingAssetSource::InferWithStates(states) => std::iter::once((None, inferred(None)))
+            .chain(
+                states
+                    .iter()
+                    .map(|state| (Some(state.clone()), inferred(Some(state)))),
+            )
+            .collect(),
     }
 }
 
-//use bevy::reflect::serde::ReflectDeserializer;
-//use serde::de::DeserializeSeed;
-//use std::io::Read;
-//
-//pub fn init_item_asset_map(
-//    mut cmd: Commands,
-//    type_registry: Res<AppTypeRegistry>,
-//    level: Query<(Entity, &ModProfile), With<Level>>,
-//) {
-//    let (level_entity, mod_profile) = level.single();
-//    let item_asset_path = PathBuf::from("derivations/item_assets.ron");
-//    let type_registry = type_registry.read();
-//
-//    let item_asset_pairs = mod_profile
-//        .0
-//        .iter()
-//        .map(|(mod_pack, path)| {
-//            let item_asset_path = path.join(item_asset_path.clone());
-//
-//            (mod_pack, item_asset_path, path)
-//        })
-//        .map(|(mod_pack, item_asset_path, mod_directory)| {
-//            fs::File::open(&item_asset_path)
-//                .map_or_else(
-//                    |err| {
-//                        error!(
-//                            "Failed to open file {}, at {}, Loaded default configuration instead",
-//                            err,
-//                            item_asset_path.to_string_lossy()
-//                        );
-//                        None
-//                    },
-//                    |mut file| {
-//                        let mut ron = String::new();
-//                        file.read_to_string(&mut ron).unwrap();
-//
-//                        let mut deserializer = ron::de::Deserializer::from_str(&ron).unwrap();
-//                        let reflect_deserializer = ReflectDeserializer::new(&type_registry);
-//                        //
-//                        let partial_reflect_value =
-//                            reflect_deserializer.deserialize(&mut deserializer).unwrap();
-//
-//                        let map_config =
-//                            ItemAssetMapConfig::from_reflect(&*partial_reflect_value).unwrap();
-//
-//                        let map_config = map_config
-//                            .0
-//                            .iter()
-//                            .map(|(item_id, asset_source)| {
-//                                let new_item_id = format!(
-//                                    "::{}::{}::{}",
-//                                    mod_pack.meta.namespace, mod_pack.meta.mod_name, item_id
-//                                );
-//
-//                                let asset_source = match asset_source {
-//                                    ItemAssetSource::Infer => ItemAssetSource::Path(
-//                                        item_asset_path
-//                                            .join(mod_directory)
-//                                            .join("assets/textures")
-//                                            .join(format!("{}.png", item_id))
-//                                    ),
-//
-//                                    ItemAssetSource::Path(path) => {
-//                                        ItemAssetSource::Path(path.clone())
-//                                    }
-//                                    _ => unimplemented!(),
-//                                };
-//
-//                                info!("loading item {} at {:?}", new_item_id, asset_source);
-//                                (new_item_id.clone(), asset_source)
-//                            })
-//                            .collect::<HashMap<String, ItemAssetSource>>();
-//
-//                        Some(map_config)
-//                    },
-//                )
-//                .unwrap()
-//        })
-//        .fold(
-//            HashMap::new(),
-//            |mut acc, map: HashMap<String, ItemAssetSource>| {
-//                acc.extend(map);
-//                acc
-//            },
-//        );
-//
-//    cmd.entity(level_entity)
-//        .insert(ItemAssetMapConfig(item_asset_pairs));
-//}
-//
-//pub fn load_item_asset_map(
-//    mut cmd: Commands,
-//    level: Query<(Entity, &ItemAssetMapConfig), With<Level>>,
-//    asset_server: Res<AssetServer>,
-//) {
-//    let (level_entity, item_asset_config) = level.single();
-//
-//    let assets = item_asset_config
-//        .0
-//        .clone()
-//        .into_iter()
-//        .map(|(id, config)| {
-//            let path: PathBuf = match config {
-//                ItemAssetSource::Infer => format!("{}.png", id).into(),
-//                ItemAssetSource::Path(path) => path,
-//                _ => unimplemented!(),
-//            };
-//
-//            let handle = asset_server.load(path);
-//
-//            info!("{} is loaded", id);
-//
-//            (id, handle)
-//        })
-//        .collect();
-//
-//    cmd.entity(level_entity).insert(ItemAssetMap(assets));
-//}
-//
-//mod tests {
-//    use super::*;
-//
-//    #[test]
-//    fn write_asset_map() {
-//        let asset_map = [
-//            ("auto_arm".into(), ItemAssetSource::Infer),
-//            ("mover_belt".into(), ItemAssetSource::Infer),
-//            ("infinite_io".into(), ItemAssetSource::Infer),
-//        ];
-//
-//        let map = ItemAssetMapConfig(asset_map.into());
-//
-//        let mut app = App::new();
-//        app.register_type::<ItemAssetMapConfig>();
-//        app.add_systems(Startup, move |type_registry: Res<AppTypeRegistry>| {
-//            let type_registry = type_registry.read();
-//
-//            let reflect_serializer =
-//                bevy::reflect::serde::ReflectSerializer::new(&map, &type_registry);
-//
-//            let serialized = ron::ser::to_string_pretty(
-//                &reflect_serializer,
-//                ron::ser::PrettyConfig::new().depth_limit(6),
-//            )
-//            .unwrap();
-//
-//            let file_path = std::path::Path::new("assets/mods/base/derivations/item_assets.ron");
-//            std::fs::write(&file_path, serialized).unwrap();
-//        });
-//        app.run();
-//    }
-//}
+/// Reads the buildings of every mod of the profile into the [`BuildingRegistry`] and loads
+/// their sprites
+pub fn load_building_definitions(
+    mut cmd: Commands,
+    level: Query<(Entity, &ModProfile, Option<&ItemTextureMap>), With<Level>>,
+    type_registry: Res<AppTypeRegistry>,
+    asset_server: Res<AssetServer>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok((level_entity, profile, item_textures)) = level.get_single() else {
+        return;
+    };
+    let type_registry = type_registry.read();
+
+    let mut registry = BuildingRegistry::default();
+    let mut sprites = HashMap::new();
+
+    for (mod_pack, mod_path) in profile.0.iter() {
+        // mods without a namespace are reported as their declarations are loaded
+        let Ok(namespace) = mod_pack.mod_id.namespace() else {
+            continue;
+        };
+
+        let definitions = match read_building_definitions(mod_path, &type_registry) {
+            Ok(definitions) => definitions,
+            Err(err) => {
+                error!("unable to read buildings of {}. {}", mod_pack.mod_id, err);
+                Notification {
+                    title: "Unable to read buildings".into(),
+                    level: NotificationLevel::Error,
+                    description: format!("{}: {}", mod_pack.mod_id, err),
+                }
+                .queue(None, &mut notifications_channel);
+                continue;
+            }
+        };
+
+        for mut definition in definitions {
+            let written = definition.item.0.clone();
+            definition.namespace(namespace);
+            let id = &definition.item.0;
+
+            for (state, path) in resolve_building_sprites(mod_path, &written, &definition.texture) {
+                debug!("loading building {} at {}", id, path.to_string_lossy());
+                sprites.insert(sprite_key(id, state.as_deref()), asset_server.load(path));
+            }
+            if let Some(texture) = item_textures
+                .filter(|_| definition.texture == BuildingAssetSource::InferItem)
+                .and_then(|textures| textures.0.get(id))
+            {
+                sprites.insert(id.clone(), texture.clone());
+            }
+
+            registry.define(definition);
+        }
+    }
+
+    cmd.entity(level_entity).insert(BuildingAssetMap(sprites));
+    cmd.insert_resource(registry);
+}
+
+/// Index of `image` within the texture of a tilemap, added to the tilemap when missing
+pub fn texture_index_of(
+    texture: &mut TilemapTexture,
+    image: &Handle<Image>,
+) -> Option<TileTextureIndex> {
+    let TilemapTexture::Vector(images) = texture else {
+        return None;
+    };
+
+    let index = images
+        .iter()
+        .position(|held| held == image)
+        .unwrap_or_else(|| {
+            images.push(image.clone());
+            images.len() - 1
+        });
+    Some(TileTextureIndex(index as u32))
+}
+
+/// Shows the sprite of a building for its facing and state
+pub fn show_building_sprites(
+    mut buildings: Query<
+        (
+            &ItemId,
+            &Building,
+            &TilemapId,
+            &mut TileTextureIndex,
+            &mut TileFlip,
+        ),
+        Changed<Building>,
+    >,
+    mut tilemaps: Query<&mut TilemapTexture>,
+    sprites: Query<&BuildingAssetMap, With<Level>>,
+    registry: Res<BuildingRegistry>,
+) {
+    let Ok(sprites) = sprites.get_single() else {
+        return;
+    };
+
+    for (item, building, tilemap, mut texture_index, mut flip) in buildings.iter_mut() {
+        let Some(definition) = registry.building(item) else {
+            continue;
+        };
+        let (key, flip_x) = definition.sprite(building.facing, building.state.as_deref());
+        let Some(image) = sprites.0.get(&key) else {
+            warn!("building {} has no sprite {}", item.0, key);
+            continue;
+        };
+
+        if let Some(index) = tilemaps
+            .get_mut(tilemap.0)
+            .ok()
+            .and_then(|mut texture| texture_index_of(&mut texture, image))
+        {
+            *texture_index = index;
+        }
+        flip.x = flip_x;
+    }
+}
@@ -1,6 +1,11 @@
 use bevy::prelude::*;
 //use bevy::utils::HashMap;
-use std::{collections::HashMap, fs, path::PathBuf};
+use bevy::reflect::TypeRegistry;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    path::{Path, PathBuf},
+};
 
 use crate::*;
 
@@ -21,8 +26,9 @@ pub enum ItemTextureSource {
 #[derive(Debug, PartialEq, Clone, Reflect, Eq, Hash)]
 pub struct ItemTexturePath(pub PathBuf);
 
+/// Sorted by item id, so files written back only change where the textures did
 #[derive(Component, Reflect)]
-pub struct ItemTextureMapSource(pub HashMap<String, ItemTextureSource>);
+pub struct ItemTextureMapSource(pub BTreeMap<String, ItemTextureSource>);
 
 #[derive(Component, Reflect)]
 pub struct ItemTextureMapConfig(pub HashMap<String, ItemTexturePath>);
@@ -34,22 +40,57 @@ pub struct ItemTextureMapPlugin;
 
 impl Plugin for ItemTextureMapPlugin {
     fn build(&self, app: &mut App) {
-        app.register_type::<ItemTextureMapSource>().add_systems(
-            OnEnter(GameState::Playing),
-            (
-                init_item_asset_map,
-                load_item_asset_map,
-                loading_item_asset_map,
-            )
-                .chain()
-                .after(load_mods_from_profile),
-        );
+        app.register_type::<ItemTextureMapSource>()
+            .register_type::<ItemTextureSource>()
+            .add_systems(
+                OnEnter(GameState::Playing),
+                (
+                    init_item_asset_map,
+                    load_item_asset_map,
+                    loading_item_asset_map,
+                )
+                    .chain()
+                    .after(load_mods_from_profile),
+            );
     }
 }
 
 pub const ITEM_ASSETS_RON: &str = "derivations/item_assets.ron";
 pub const ITEM_ASSETS_PATH: &str = "assets/textures";
 
+/// Texture of `item_id` for the mod at `mod_path`, variants are drawn from the same texture
+pub fn resolve_item_texture(mod_path: &Path, item_id: &str, source: &ItemTextureSource) -> PathBuf {
+    match source {
+        ItemTextureSource::Path(path) => path.clone(),
+        ItemTextureSource::Auto | ItemTextureSource::AutoWithVariants(_) => mod_path
+            .join(ITEM_ASSETS_PATH)
+            .join(format!("{}.png", item_id)),
+    }
+}
+
+/// Texture sources of one mod as written in its `derivations/item_assets.ron`
+pub fn read_item_texture_sources(
+    mod_path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<BTreeMap<String, ItemTextureSource>, String> {
+    let path = mod_path.join(ITEM_ASSETS_RON);
+    let Some(value) = read_ron_file(&path, type_registry)? else {
+        return Ok(BTreeMap::new());
+    };
+
+    ItemTextureMapSource::from_reflect(&*value)
+        .map(|ItemTextureMapSource(sources)| sources)
+        .ok_or_else(|| format!("{} holds no item textures", path.to_string_lossy()))
+}
+
+pub fn write_item_texture_sources(
+    mod_path: &Path,
+    sources: &ItemTextureMapSource,
+    type_registry: &TypeRegistry,
+) -> Result<(), String> {
+    write_ron_file(&mod_path.join(ITEM_ASSETS_RON), sources, type_registry)
+}
+
 use bevy::reflect::serde::ReflectDeserializer;
 use serde::de::DeserializeSeed;
 use std::io::Read;
@@ -107,18 +148,11 @@ pub fn init_item_asset_map(
                                 let new_item_id =
                                     format!("{}::{}", mod_pack.mod_id.mod_name, item_id);
 
-                                let asset_source = match asset_source {
-                                    ItemTextureSource::Auto => ItemTexturePath(
-                                        item_texture_path
-                                            .join(mod_directory)
-                                            .join(ITEM_ASSETS_PATH)
-                                            .join(format!("{}.png", item_id)),
-                                    ),
-
-                                    ItemTextureSource::Path(path) => ItemTexturePath(path.clone()),
-
-                                    _ => unimplemented!(),
-                                };
+                                let asset_source = ItemTexturePath(resolve_item_texture(
+                                    mod_directory,
+                                    item_id,
+                                    asset_source,
+                                ));
 
                                 debug!(
                                     "loading item {} at {}",
@@ -243,7 +277,8 @@ mod tests {
         let tyconic_asset_map_source = ItemTextureMapSource(tyconic_asset_map_source.into());
 
         let mut app = App::new();
-        app.register_type::<ItemTextureMapSource>();
+        app.register_type::<ItemTextureMapSource>()
+            .register_type::<ItemTextureSource>();
         app.add_systems(Startup, move |type_registry: Res<AppTypeRegistry>| {
             let type_registry = type_registry.read();
 
@@ -0,0 +1,266 @@
+//! Lists the achievements of a mod profile and which of them were unlocked. Details come from
+//! the declarations of the last level played, other profiles only list what they unlocked.
+
+use crate::menu::*;
+use crate::ui::WindowMeta;
+use crate::*;
+use bevy::reflect::TypeRegistry;
+use std::path::PathBuf;
+
+pub struct AchievementsMenuPlugin;
+
+impl Plugin for AchievementsMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(MenuNavState::Achievements), (setup,))
+            .add_systems(
+                Update,
+                (
+                    select_profile.run_if(any_with_component::<AchievementProfileButton>),
+                    list_achievements
+                        .run_if(resource_exists_and_changed::<SelectedAchievementProfile>),
+                )
+                    .chain()
+                    .run_if(in_state(MenuNavState::Achievements)),
+            );
+    }
+}
+
+/// Button listing the achievements of the profile with the given [`profile_key`]
+#[derive(Debug, Component)]
+pub struct AchievementProfileButton(pub String);
+
+/// Holds the listings of the selected profile
+#[derive(Component)]
+pub struct AchievementList;
+
+/// [`profile_key`] of the profile listed
+#[derive(Resource, Debug, Clone, PartialEq)]
+pub struct SelectedAchievementProfile(pub String);
+
+pub(crate) fn setup(
+    mut cmd: Commands,
+    backdrop: super::MenuBackdropQuery,
+    fonts: Res<loading::FontAssets>,
+    ui: Res<loading::UiAssets>,
+    declared: Option<Res<DeclaredAchievementProfile>>,
+) {
+    let selected = declared.map_or_else(
+        || profile_key(&ModProfileConfig::default()),
+        |declared| declared.key.clone(),
+    );
+    let mut profiles = achievement_profiles();
+    profiles.push(selected.clone());
+    profiles.sort();
+    profiles.dedup();
+
+    cmd.insert_resource(SelectedAchievementProfile(selected));
+
+    cmd.entity(backdrop.single()).with_children(|parent| {
+        spawn_window(
+            parent,
+            StateScoped(MenuNavState::Achievements),
+            ChangeStates(MenuNavState::Root),
+            &ui,
+            &fonts,
+            WindowMeta::new("Achievements".into(), 400., 4. / 3.),
+            |parent| {
+                parent
+                    .spawn(Node {
+                        width: Val::Percent(100.),
+                        padding: UiRect::all(Val::Px(UI_SCALE)),
+                        flex_wrap: FlexWrap::Wrap,
+                        column_gap: Val::Px(UI_SCALE),
+                        row_gap: Val::Px(UI_SCALE),
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        for profile in profiles.iter() {
+                            parent
+                                .spawn(Node {
+                                    height: Val::Px(UI_SCALE * 8.),
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    spawn_button(
+                                        profile.as_str().into(),
+                                        AchievementProfileButton(profile.clone()),
+                                        parent,
+                                        &fonts,
+                                        &ui,
+                                    );
+                                });
+                        }
+                    });
+
+                parent.spawn((
+                    AchievementList,
+                    Node {
+                        width: Val::Percent(100.),
+                        padding: UiRect::all(Val::Px(UI_SCALE * 2.)),
+                        flex_direction: FlexDirection::Column,
+                        row_gap: Val::Px(UI_SCALE),
+                        overflow: Overflow::scroll_y(),
+                        ..default()
+                    },
+                    Scrollable,
+                ));
+            },
+        );
+    });
+}
+
+fn select_profile(
+    buttons: Query<(&DepressButton, &AchievementProfileButton), Changed<DepressButton>>,
+    mut selected: ResMut<SelectedAchievementProfile>,
+) {
+    for (depress, AchievementProfileButton(profile)) in buttons.iter() {
+        if depress.invoked() {
+            selected.set_if_neq(SelectedAchievementProfile(profile.clone()));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn list_achievements(
+    mut cmd: Commands,
+    list: Query<Entity, With<AchievementList>>,
+    selected: Res<SelectedAchievementProfile>,
+    fonts: Res<loading::FontAssets>,
+    asset_server: Res<AssetServer>,
+    registry: Res<AchievementRegistry>,
+    declared: Option<Res<DeclaredAchievementProfile>>,
+    type_registry: Res<AppTypeRegistry>,
+) {
+    let Ok(list) = list.get_single() else {
+        return;
+    };
+
+    let declared = declared
+        .as_deref()
+        .filter(|declared| declared.key == selected.0)
+        .map(|declared| (&*registry, declared));
+    let listings = profile_achievements(&selected.0, declared, &type_registry.read());
+    let unlocked = listings
+        .iter()
+        .filter(|(listing, _)| listing.unlocked_at.is_some())
+        .count();
+
+    cmd.entity(list)
+        .despawn_descendants()
+        .with_children(|parent| {
+            if declared.is_none() {
+                body_text(
+                    "Play a level with these mods to see every achievement",
+                    parent,
+                    &fonts,
+                );
+            }
+
+            if listings.is_empty() {
+                let empty = match declared {
+                    Some(_) => "No achievements declared",
+                    None => "No achievements unlocked",
+                };
+                section_text(empty, parent, &fonts);
+                return;
+            }
+
+            let count = match declared {
+                Some(_) => format!("{} of {} unlocked", unlocked, listings.len()),
+                None => format!("{} unlocked", unlocked),
+            };
+            body_text(&count, parent, &fonts);
+
+            for (listing, icon) in listings.iter() {
+                parent
+                    .spawn(Node {
+                        column_gap: Val::Px(UI_SCALE * 2.),
+                        align_items: AlignItems::Center,
+                        ..default()
+                    })
+                    .with_children(|parent| {
+                        if let Some(icon) = icon {
+                            parent.spawn((
+                                ImageNode::new(asset_server.load(icon.clone())),
+                                Node {
+                                    width: Val::Px(UI_SCALE * 8.),
+                                    height: Val::Px(UI_SCALE * 8.),
+                                    ..default()
+                                },
+                            ));
+                        }
+
+                        parent
+                            .spawn(Node {
+                                flex_direction: FlexDirection::Column,
+                                ..default()
+                            })
+                            .with_children(|parent| {
+                                section_text(&listing.title, parent, &fonts);
+                                body_text(&listing.description, parent, &fonts);
+                                body_text(
+                                    match listing.unlocked_at {
+                                        Some(_) => "Unlocked",
+                                        None => "Locked",
+                                    },
+                                    parent,
+                                    &fonts,
+                                );
+                            });
+                    });
+            }
+        });
+}
+
+/// Listings of every achievement the profile declares, paired with the absolute path of its
+/// icon. Without its declarations only the unlocked ones are known, by their id.
+fn profile_achievements(
+    key: &str,
+    declared: Option<(&AchievementRegistry, &DeclaredAchievementProfile)>,
+    type_registry: &TypeRegistry,
+) -> Vec<(AchievementListing, Option<PathBuf>)> {
+    let path = achievements_path(key);
+    let unlocked = read_unlocked_achievements(&path, type_registry).unwrap_or_else(|err| {
+        error!(
+            "unable to read achievements at {}. {}",
+            path.to_string_lossy(),
+            err
+        );
+        default()
+    });
+
+    let Some((achievements, declared)) = declared else {
+        return unlocked
+            .0
+            .into_iter()
+            .map(|unlocked| {
+                let listing = AchievementListing {
+                    title: unlocked.id.0.clone(),
+                    id: unlocked.id,
+                    description: String::new(),
+                    icon: None,
+                    unlocked_at: Some(unlocked.unlocked_at),
+                };
+                (listing, None)
+            })
+            .collect();
+    };
+
+    achievement_listings(achievements, &unlocked)
+        .into_iter()
+        .map(|listing| {
+            let icon = listing.icon.as_ref().and_then(|icon| {
+                let touch = achievements.touched_by(&listing.id.0)?;
+                let (_, mod_path) = declared
+                    .mod_profile
+                    .0
+                    .iter()
+                    .find(|(mod_pack, _)| mod_pack.mod_id.mod_name == touch.mod_name)?;
+
+                Some(mod_path.join(ModProfile::ASSET_PATH).join(icon))
+            });
+
+            (listing, icon)
+        })
+        .collect()
+}
@@ -1 +1,124 @@
+//! Lists saves from the config directory and restores the selected one into a level
 
+use crate::menu::*;
+use crate::ui::WindowMeta;
+use crate::*;
+use std::path::PathBuf;
+
+pub struct LoadGamePlugin;
+
+impl Plugin for LoadGamePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(MenuNavState::LoadGame), (setup,))
+            .add_systems(
+                Update,
+                load_selected_save.run_if(any_with_component::<LoadSaveButton>),
+            );
+    }
+}
+
+/// Button loading the save at the given path
+#[derive(Debug, Component)]
+pub struct LoadSaveButton(pub PathBuf);
+
+pub(crate) fn setup(
+    mut cmd: Commands,
+    backdrop: super::MenuBackdropQuery,
+    fonts: Res<loading::FontAssets>,
+    ui: Res<loading::UiAssets>,
+) {
+    let saves = list_saves();
+
+    cmd.entity(backdrop.single()).with_children(|parent| {
+        spawn_window(
+            parent,
+            StateScoped(MenuNavState::LoadGame),
+            ChangeStates(MenuNavState::Root),
+            &ui,
+            &fonts,
+            WindowMeta::new("Load Game".into(), 400., 4. / 3.),
+            |parent| {
+                parent
+                    .spawn((
+                        Node {
+                            width: Val::Percent(100.),
+                            padding: UiRect::all(Val::Px(UI_SCALE * 2.)),
+                            flex_direction: FlexDirection::Column,
+                            row_gap: Val::Px(UI_SCALE),
+                            overflow: Overflow::scroll_y(),
+                            ..default()
+                        },
+                        Scrollable,
+                    ))
+                    .with_children(|parent| {
+                        if saves.is_empty() {
+                            section_text("No saves yet", parent, &fonts);
+                        }
+
+                        for (name, path) in saves.iter() {
+                            parent
+                                .spawn(Node {
+                                    height: Val::Px(UI_SCALE * 8.),
+                                    ..default()
+                                })
+                                .with_children(|parent| {
+                                    spawn_button(
+                                        name.as_str().into(),
+                                        LoadSaveButton(path.clone()),
+                                        parent,
+                                        &fonts,
+                                        &ui,
+                                    );
+                                });
+                        }
+                    });
+            },
+        );
+    });
+}
+
+fn load_selected_save(
+    mut cmd: Commands,
+    buttons: Query<(&DepressButton, &LoadSaveButton), Changed<DepressButton>>,
+    type_registry: Res<AppTypeRegistry>,
+    mut game_state_channel: EventWriter<GameState>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let type_registry = type_registry.read();
+
+    for (depress, LoadSaveButton(path)) in buttons.iter() {
+        if !depress.invoked() {
+            continue;
+        }
+
+        match read_save_or_backup(path, &type_registry) {
+            Ok((save, recovery)) => {
+                info!("loading save at {}", path.to_string_lossy());
+
+                if let Some(SaveRecovery { generation, cause }) = recovery {
+                    Notification {
+                        title: "Save restored from backup".into(),
+                        level: NotificationLevel::Warning,
+                        description: format!(
+                            "The save could not be read ({}), backup #{} was loaded instead",
+                            cause, generation
+                        ),
+                    }
+                    .queue(None, &mut notifications_channel);
+                }
+
+                cmd.insert_resource(PendingSave(save));
+                game_state_channel.send(GameState::Playing);
+            }
+            Err(err) => {
+                error!("failed to load save {}. {}", path.to_string_lossy(), err);
+                Notification {
+                    title: "Failed to load game".into(),
+                    level: NotificationLevel::Error,
+                    description: err.to_string(),
+                }
+                .queue(None, &mut notifications_channel);
+            }
+        }
+    }
+}
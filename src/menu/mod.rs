@@ -3,6 +3,7 @@ use crate::ui::*;
 use crate::{ChangeStates, GameState};
 use bevy::prelude::*;
 
+pub mod achievements;
 pub mod load_game;
 pub mod new_game;
 pub mod settings;
@@ -41,7 +42,12 @@ impl Plugin for MenuPlugin {
                 move_menu_background.run_if(any_with_component::<MenuBackground>),
             )
             // settings
-            .add_plugins((settings::SettingsPlugin, new_game::NewGamePlugin));
+            .add_plugins((
+                settings::SettingsPlugin,
+                new_game::NewGamePlugin,
+                load_game::LoadGamePlugin,
+                achievements::AchievementsMenuPlugin,
+            ));
     }
 }
 
@@ -160,7 +166,8 @@ fn setup(
                         for (name, game_state, menu_nav) in &[
                             //("Continue", Some(GameState::Playing), None),
                             ("New Game", None, Some(MenuNavState::NewGame)),
-                            //("Load Game", None, Some(MenuNavState::LoadGame)),
+                            ("Load Game", None, Some(MenuNavState::LoadGame)),
+                            ("Achievements", None, Some(MenuNavState::Achievements)),
                             ("Editor", Some(GameState::Playing), None),
                             ("Settings", None, Some(MenuNavState::Settings)),
                             #[cfg(not(target_arch = "wasm32"))]
@@ -243,7 +250,8 @@ pub enum MenuNavState {
     #[default]
     Root,
     NewGame,
-    //LoadGame,
+    LoadGame,
+    Achievements,
     Settings,
 }
 
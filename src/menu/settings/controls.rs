@@ -2,6 +2,7 @@ use super::SettingsTabsState;
 use crate::actions::*;
 use crate::loading::*;
 use crate::ui::*;
+use crate::GameSpeed;
 
 use bevy::prelude::*;
 
@@ -74,6 +75,28 @@ pub fn setup(
                     &ui,
                     &input_mappings,
                 );
+                input_map_entry(
+                    UiAction::ResearchTreeToggle,
+                    parent,
+                    &fonts,
+                    &ui,
+                    &input_mappings,
+                );
+                input_map_entry(UiAction::QuickSave, parent, &fonts, &ui, &input_mappings);
+                for speed in [
+                    GameSpeed::Paused,
+                    GameSpeed::Normal,
+                    GameSpeed::Double,
+                    GameSpeed::Quadruple,
+                ] {
+                    input_map_entry(
+                        UiAction::GameSpeed(speed),
+                        parent,
+                        &fonts,
+                        &ui,
+                        &input_mappings,
+                    );
+                }
                 input_map_entry(UiAction::Zoom(2), parent, &fonts, &ui, &input_mappings);
                 input_map_entry(
                     UiAction::HotbarSlotNext,
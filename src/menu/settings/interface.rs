@@ -5,6 +5,9 @@ use crate::ui::*;
 
 const MIN_UI_SCALE: f32 = 0.9;
 const MAX_UI_SCALE: f32 = 1.4;
+/// autosave interval in minutes, below one minute autosaving is disabled
+const AUTOSAVE_MINUTES: std::ops::Range<f32> = (0.)..30.;
+const AUTOSAVE_BACKUPS: std::ops::Range<f32> = (0.)..10.;
 
 pub fn setup(
     mut cmd: Commands,
@@ -13,8 +16,16 @@ pub fn setup(
     ui: Res<UiAssets>,
 
     ui_scale: Res<UiScale>,
+    autosave: Res<crate::AutosaveSettings>,
 ) {
     let ui_scale_slider = Slider::new(MIN_UI_SCALE..MAX_UI_SCALE, ui_scale.0, usize::MAX);
+    let autosave_minutes = if autosave.enabled {
+        autosave.interval.as_secs_f32() / 60.
+    } else {
+        0.
+    };
+    let autosave_interval_slider = Slider::new(AUTOSAVE_MINUTES, autosave_minutes, 30);
+    let autosave_backups_slider = Slider::new(AUTOSAVE_BACKUPS, autosave.backups as f32, 10);
 
     cmd.entity(backdrop.single()).with_children(|parent| {
         parent
@@ -29,6 +40,25 @@ pub fn setup(
             .with_children(|parent| {
                 section_text("General", parent, &fonts);
                 labeled_slider(parent, "UI scale", UiScaler, &ui, &fonts, ui_scale_slider);
+
+                separator(parent);
+                section_text("Autosave", parent, &fonts);
+                labeled_slider(
+                    parent,
+                    "Interval",
+                    AutosaveSlider::Interval,
+                    &ui,
+                    &fonts,
+                    autosave_interval_slider,
+                );
+                labeled_slider(
+                    parent,
+                    "Backups",
+                    AutosaveSlider::Backups,
+                    &ui,
+                    &fonts,
+                    autosave_backups_slider,
+                );
             });
     });
 }
@@ -45,3 +75,30 @@ pub fn ui_scaling(
         info!("Scaling is {}", slider.valued());
     });
 }
+
+#[derive(Debug, Component)]
+pub enum AutosaveSlider {
+    Interval,
+    Backups,
+}
+
+pub fn autosave_configure(
+    mut autosave: ResMut<crate::AutosaveSettings>,
+    autosave_slider: Query<(&Slider, &AutosaveSlider), Changed<Slider>>,
+) {
+    for (slider, autosave_slider) in autosave_slider.iter() {
+        let value = slider.valued().round();
+
+        match autosave_slider {
+            AutosaveSlider::Interval => {
+                autosave.enabled = value >= 1.;
+                if autosave.enabled {
+                    autosave.interval = std::time::Duration::from_secs(value as u64 * 60);
+                }
+            }
+            AutosaveSlider::Backups => autosave.backups = value as usize,
+        }
+
+        info!("Autosave is {:?}", *autosave);
+    }
+}
@@ -54,6 +54,8 @@ impl Plugin for SettingsPlugin {
                     controls::configure.run_if(any_with_component::<controls::RemapButton>),
                     // ui
                     interface::ui_scaling.run_if(any_with_component::<interface::UiScaler>),
+                    interface::autosave_configure
+                        .run_if(any_with_component::<interface::AutosaveSlider>),
                     // developer mode
                     (
                         enable_developer_mode.run_if(in_state(crate::DeveloperMode(false))),
@@ -0,0 +1,607 @@
+//! Upgrades saves made with older mod versions using the migrations every mod
+//! ships under [`ModProfile::MIGRATION_PATH`]
+//!
+//! A migration file looks like
+//! ```ron
+//! {
+//!   "tyconia::mods::migrations::Migration": (
+//!     from: (major: 0, minor: 1, patch: 0, pre: [], build: []),
+//!     to: (major: 0, minor: 2, patch: 0, pre: [], build: []),
+//!     items: { "cheese": "cheese_wheel" },
+//!     recipes: {},
+//!     research: {},
+//!     script: Some("migrations/0.2.0.rhai"),
+//!   ),
+//! }
+//! ```
+
+use crate::ui::*;
+use crate::*;
+use bevy::prelude::*;
+use bevy::reflect::serde::ReflectDeserializer;
+use bevy::reflect::TypeRegistry;
+use bevy::utils::HashMap;
+use serde::de::DeserializeSeed;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fmt, fs};
+
+pub struct MigrationPlugin;
+
+impl Plugin for MigrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Migration>().add_systems(
+            Update,
+            migrate_pending_save
+                .before(restore_level)
+                .run_if(resource_exists::<PendingSave>.and(any_with_component::<ModProfile>))
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Renames applied to saves made with a mod version inside `from..to`
+#[derive(Reflect, Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub from: SemVer,
+    /// version the save is on once migrated, the next migration continues from here
+    pub to: SemVer,
+    pub items: HashMap<String, String>,
+    pub recipes: HashMap<String, String>,
+    pub research: HashMap<String, String>,
+    /// rhai script relative to the mod directory, run after the renames. It may define
+    /// `migrate_item(id)` returning the new id and `migrate_resources(resources)`
+    /// returning the new resource map
+    pub script: Option<PathBuf>,
+}
+
+#[derive(Debug)]
+pub enum MigrationError {
+    Io(std::io::Error),
+    Deserialize(String),
+    Script(String),
+}
+
+impl fmt::Display for MigrationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Deserialize(err) => write!(f, "unable to read migration. {}", err),
+            Self::Script(err) => write!(f, "migration script failed. {}", err),
+        }
+    }
+}
+
+impl From<std::io::Error> for MigrationError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+/// Looks up `id` in `renames`, both bare and qualified as `mod_name::id`
+fn rename(renames: &HashMap<String, String>, mod_name: &str, id: &str) -> Option<String> {
+    match id
+        .strip_prefix(mod_name)
+        .and_then(|id| id.strip_prefix("::"))
+    {
+        Some(local) => renames
+            .get(local)
+            .map(|renamed| format!("{}::{}", mod_name, renamed)),
+        None => renames.get(id).cloned(),
+    }
+}
+
+impl Migration {
+    pub fn applies_to(&self, version: &SemVer) -> bool {
+        self.from <= *version && *version < self.to
+    }
+
+    pub fn rename_item(&self, mod_name: &str, id: &ItemId) -> Option<ItemId> {
+        rename(&self.items, mod_name, &id.0).map(ItemId)
+    }
+
+    pub fn rename_recipe(&self, mod_name: &str, id: &RecipeId) -> Option<RecipeId> {
+        rename(&self.recipes, mod_name, &id.0).map(RecipeId)
+    }
+
+    pub fn rename_research(&self, mod_name: &str, id: &ResearchId) -> Option<ResearchId> {
+        rename(&self.research, mod_name, &id.0).map(ResearchId)
+    }
+
+    /// Renames every item, recipe and research referenced by the save, metrics named after an
+    /// item included
+    pub fn apply(&self, mod_name: &str, save: &mut LevelSave) {
+        for item in save.items_mut() {
+            if let Some(renamed) = self.rename_item(mod_name, item) {
+                *item = renamed;
+            }
+        }
+        for recipe in save.recipes_mut() {
+            if let Some(renamed) = self.rename_recipe(mod_name, recipe) {
+                *recipe = renamed;
+            }
+        }
+        for research in save.research_mut() {
+            if let Some(renamed) = self.rename_research(mod_name, research) {
+                *research = renamed;
+            }
+        }
+
+        save.resources = save
+            .resources
+            .drain()
+            .map(|(key, value)| {
+                let key = match key.split_once("__") {
+                    Some((item, metric)) => rename(&self.items, mod_name, item)
+                        .map_or(key.clone(), |item| format!("{}__{}", item, metric)),
+                    None => key,
+                };
+                (key, value)
+            })
+            .collect();
+    }
+}
+
+/// Every migration of the mod at `mod_path`, ordered by the version it starts from
+pub fn load_migrations(
+    mod_path: &Path,
+    type_registry: &TypeRegistry,
+) -> Result<Vec<Migration>, MigrationError> {
+    let migration_dir = mod_path.join(ModProfile::MIGRATION_PATH);
+    if !migration_dir.exists() {
+        return Ok(vec![]);
+    }
+
+    let mut migrations = vec![];
+    for entry in fs::read_dir(migration_dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "ron") {
+            continue;
+        }
+
+        let ron = fs::read_to_string(&path)?;
+        let mut deserializer = ron::de::Deserializer::from_str(&ron)
+            .map_err(|err| MigrationError::Deserialize(err.to_string()))?;
+        let reflect_deserializer = ReflectDeserializer::new(type_registry);
+
+        let partial_reflect_value = reflect_deserializer
+            .deserialize(&mut deserializer)
+            .map_err(|err| {
+                MigrationError::Deserialize(format!("{}. {}", path.to_string_lossy(), err))
+            })?;
+
+        migrations.push(
+            Migration::from_reflect(&*partial_reflect_value).ok_or_else(|| {
+                MigrationError::Deserialize(format!(
+                    "{} is not a migration",
+                    path.to_string_lossy()
+                ))
+            })?,
+        );
+    }
+
+    migrations.sort_by(|a, b| a.from.cmp(&b.from));
+    Ok(migrations)
+}
+
+/// Chain of migrations bringing a save from `saved` up to at most `installed`
+pub fn migration_chain<'a>(
+    saved: &SemVer,
+    installed: &SemVer,
+    migrations: &'a [Migration],
+) -> Vec<&'a Migration> {
+    let mut version = saved.clone();
+    let mut chain = vec![];
+
+    for migration in migrations {
+        if migration.applies_to(&version) && migration.to <= *installed {
+            version = migration.to.clone();
+            chain.push(migration);
+        }
+    }
+
+    chain
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod script {
+    use super::*;
+    use bevy_mod_scripting::rhai::rhai::{Dynamic, Engine, Map, Scope, AST, FLOAT};
+
+    pub struct MigrationScript {
+        engine: Engine,
+        ast: AST,
+    }
+
+    impl MigrationScript {
+        pub fn load(path: &Path) -> Result<Self, MigrationError> {
+            let engine = Engine::new();
+            let ast = engine
+                .compile(fs::read_to_string(path)?)
+                .map_err(|err| MigrationError::Script(err.to_string()))?;
+
+            Ok(Self { engine, ast })
+        }
+
+        fn defines(&self, function: &str) -> bool {
+            self.ast.iter_functions().any(|f| f.name == function)
+        }
+
+        fn rename(&self, function: &str, id: &str) -> Result<Option<String>, MigrationError> {
+            if !self.defines(function) {
+                return Ok(None);
+            }
+
+            self.engine
+                .call_fn::<String>(&mut Scope::new(), &self.ast, function, (id.to_string(),))
+                .map(Some)
+                .map_err(|err| MigrationError::Script(err.to_string()))
+        }
+
+        pub fn apply(&self, save: &mut LevelSave) -> Result<(), MigrationError> {
+            for item in save.items_mut() {
+                if let Some(renamed) = self.rename("migrate_item", &item.0)? {
+                    item.0 = renamed;
+                }
+            }
+
+            if self.defines("migrate_resources") {
+                let resources = save
+                    .resources
+                    .iter()
+                    .map(|(key, value)| (key.as_str().into(), Dynamic::from_float(*value as FLOAT)))
+                    .collect::<Map>();
+
+                let migrated = self
+                    .engine
+                    .call_fn::<Map>(
+                        &mut Scope::new(),
+                        &self.ast,
+                        "migrate_resources",
+                        (resources,),
+                    )
+                    .map_err(|err| MigrationError::Script(err.to_string()))?;
+
+                save.resources = migrated
+                    .into_iter()
+                    .filter_map(|(key, value)| {
+                        Some((key.to_string(), value.as_float().ok()? as f32))
+                    })
+                    .collect();
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_script(mod_path: &Path, script: &Path, save: &mut LevelSave) -> Result<(), MigrationError> {
+    script::MigrationScript::load(&mod_path.join(script))?.apply(save)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn run_script(
+    _mod_path: &Path,
+    script: &Path,
+    _save: &mut LevelSave,
+) -> Result<(), MigrationError> {
+    warn!(
+        "skipping migration script {}, scripting is unavailable on this platform",
+        script.to_string_lossy()
+    );
+    Ok(())
+}
+
+/// Migrates [`PendingSave`] for every mod installed at a newer version than the save was made
+/// with. A migration failing aborts the load, the save is left as it was on disk
+pub fn migrate_pending_save(
+    mut cmd: Commands,
+    mut pending: ResMut<PendingSave>,
+    profile: Query<&ModProfile, With<Level>>,
+    type_registry: Res<AppTypeRegistry>,
+    mut game_state_channel: EventWriter<GameState>,
+    mut notifications_channel: NotificationChannel,
+) {
+    let Ok(profile) = profile.get_single() else {
+        return;
+    };
+    let type_registry = type_registry.read();
+
+    let outdated = pending
+        .0
+        .mod_profile
+        .iter()
+        .enumerate()
+        .filter_map(|(index, shorthand)| {
+            let saved = shorthand.0.parse::<Meta>().ok()?;
+            let (mod_pack, mod_path) = profile
+                .0
+                .iter()
+                .find(|(mod_pack, _)| mod_pack.mod_id.mod_name == saved.mod_name)?;
+
+            (saved.version < mod_pack.mod_id.version)
+                .then(|| (index, saved, mod_pack.mod_id.clone(), mod_path.clone()))
+        })
+        .collect::<Vec<_>>();
+    if outdated.is_empty() {
+        return;
+    }
+
+    let mut save = pending.0.clone();
+    for (index, saved, installed, mod_path) in outdated.iter() {
+        let result = load_migrations(mod_path, &type_registry).and_then(|migrations| {
+            for migration in migration_chain(&saved.version, &installed.version, &migrations) {
+                info!(
+                    "migrating save of {} from {} to {}",
+                    saved.mod_name, migration.from, migration.to
+                );
+                migration.apply(&saved.mod_name, &mut save);
+
+                if let Some(script) = &migration.script {
+                    run_script(mod_path, script, &mut save)?;
+                }
+            }
+            Ok(())
+        });
+
+        if let Err(err) = result {
+            error!("failed to migrate save of {}. {}", saved.mod_name, err);
+            Notification {
+                title: "Save migration failed".into(),
+                level: NotificationLevel::Error,
+                description: format!(
+                    "{}: {}. The save was not loaded and is left as it was",
+                    saved.mod_name, err
+                ),
+            }
+            .queue(None, &mut notifications_channel);
+
+            cmd.remove_resource::<PendingSave>();
+            game_state_channel.send(GameState::Menu);
+            return;
+        }
+
+        save.mod_profile[*index] = MetaShorthand(installed.to_string());
+    }
+
+    for (_, saved, installed, _) in outdated {
+        Notification {
+            title: "Save migrated".into(),
+            level: NotificationLevel::Info,
+            description: format!(
+                "{} was updated from {} to {}",
+                saved.mod_name, saved.version, installed.version
+            ),
+        }
+        .queue(Some(Duration::from_secs(5)), &mut notifications_channel);
+    }
+    pending.0 = save;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs_tilemap::prelude::TilePos;
+
+    fn migration(from: (u64, u64, u64), to: (u64, u64, u64), items: &[(&str, &str)]) -> Migration {
+        Migration {
+            from: from.into(),
+            to: to.into(),
+            items: items
+                .iter()
+                .map(|(old, new)| (old.to_string(), new.to_string()))
+                .collect(),
+            recipes: HashMap::default(),
+            research: HashMap::default(),
+            script: None,
+        }
+    }
+
+    #[test]
+    fn migration_chain_in_order() {
+        let migrations = vec![
+            migration((0, 1, 0), (0, 2, 0), &[("cheese", "cheese_wheel")]),
+            migration((0, 2, 0), (0, 3, 0), &[("cheese_wheel", "aged_cheese")]),
+            migration((0, 3, 0), (0, 4, 0), &[]),
+        ];
+
+        let chain = migration_chain(&(0, 1, 5).into(), &(0, 3, 0).into(), &migrations);
+        assert_eq!(chain, vec![&migrations[0], &migrations[1]]);
+
+        let chain = migration_chain(&(0, 2, 0).into(), &(0, 4, 0).into(), &migrations);
+        assert_eq!(chain, vec![&migrations[1], &migrations[2]]);
+
+        assert!(migration_chain(&(0, 4, 0).into(), &(0, 4, 0).into(), &migrations).is_empty());
+    }
+
+    #[test]
+    fn migration_renames_save() {
+        let mut resources = HashMap::default();
+        resources.insert("base::cheese__total_produced".to_string(), 12.);
+        resources.insert("money".to_string(), 100.);
+
+        let mut save = LevelSave {
+            version: LevelSave::VERSION,
+            total_play_time: Duration::ZERO,
+            tick: 0,
+            resources,
+            mod_profile: vec!["base_0.1.0".into()],
+            floor: vec![],
+            buildings: vec![],
+            placed: vec![BuildingSave {
+                item: "cheese".into(),
+                origin: TilePos { x: 0, y: 0 },
+                facing: BeltDirection::North,
+                state: None,
+                countertop: false,
+                crafter: None,
+                arm: None,
+                belt: None,
+                infinite_io: None,
+            }],
+            inventories: vec![InventorySave {
+                owner: InventoryOwner::Player,
+                slots: vec![
+                    Some(ItemEntry {
+                        item: "base::cheese".into(),
+                        quantity: 3,
+                    }),
+                    Some(ItemEntry {
+                        item: "other::cheese".into(),
+                        quantity: 1,
+                    }),
+                ],
+                active: None,
+            }],
+            research: vec![],
+            item_metrics: vec![],
+            custom_metrics: HashMap::default(),
+        };
+
+        migration((0, 1, 0), (0, 2, 0), &[("cheese", "cheese_wheel")]).apply("base", &mut save);
+
+        assert_eq!(save.placed[0].item, ItemId::from("cheese_wheel"));
+        assert_eq!(
+            save.inventories[0].slots[0].as_ref().unwrap().item,
+            ItemId("base::cheese_wheel".into())
+        );
+        // other mods keep their ids
+        assert_eq!(
+            save.inventories[0].slots[1].as_ref().unwrap().item,
+            ItemId("other::cheese".into())
+        );
+        assert_eq!(
+            save.resources.get("base::cheese_wheel__total_produced"),
+            Some(&12.)
+        );
+        assert_eq!(save.resources.get("money"), Some(&100.));
+    }
+
+    fn save(mod_profile: &str) -> LevelSave {
+        LevelSave {
+            version: LevelSave::VERSION,
+            total_play_time: Duration::ZERO,
+            tick: 0,
+            resources: HashMap::default(),
+            mod_profile: vec![MetaShorthand(mod_profile.into())],
+            floor: vec![],
+            buildings: vec![],
+            placed: vec![],
+            inventories: vec![],
+            research: vec![],
+            item_metrics: vec![],
+            custom_metrics: HashMap::default(),
+        }
+    }
+
+    #[test]
+    fn migration_renames_recipes() {
+        let mut save = save("base_0.1.0");
+        save.placed = ["base::pizza", "other::pizza"]
+            .into_iter()
+            .map(|recipe| BuildingSave {
+                item: "base::oven".into(),
+                origin: TilePos { x: 0, y: 0 },
+                facing: BeltDirection::North,
+                state: None,
+                countertop: false,
+                crafter: Some(CraftingMachine::new(RecipeId(recipe.into()))),
+                arm: None,
+                belt: None,
+                infinite_io: None,
+            })
+            .collect();
+
+        let mut migration = migration((0, 1, 0), (0, 2, 0), &[]);
+        migration
+            .recipes
+            .insert("pizza".into(), "folk_pizza".into());
+        migration.apply("base", &mut save);
+
+        let recipes = save
+            .placed
+            .iter()
+            .map(|building| building.crafter.as_ref().unwrap().recipe.clone().unwrap().0)
+            .collect::<Vec<_>>();
+        assert_eq!(recipes, vec!["base::folk_pizza", "other::pizza"]);
+    }
+
+    #[test]
+    fn migration_renames_research() {
+        let mut save = save("base_0.1.0");
+        save.research = ["base::ovens", "other::ovens"]
+            .into_iter()
+            .map(|id| ResearchSave {
+                id: ResearchId(id.into()),
+                state: ResearchState::Completed,
+            })
+            .collect();
+
+        let mut migration = migration((0, 1, 0), (0, 2, 0), &[]);
+        migration
+            .research
+            .insert("ovens".into(), "stone_ovens".into());
+        migration.apply("base", &mut save);
+
+        assert!(save
+            .research_progress()
+            .is_completed(&ResearchId("base::stone_ovens".into())));
+        assert!(save
+            .research_progress()
+            .is_completed(&ResearchId("other::ovens".into())));
+    }
+
+    #[test]
+    fn failed_migration_aborts_the_load() {
+        use bevy::ecs::system::RunSystemOnce;
+
+        let mod_path = std::env::temp_dir().join("tyconia_failed_migration_aborts_the_load");
+        let _ = fs::remove_dir_all(&mod_path);
+        fs::create_dir_all(mod_path.join(ModProfile::MIGRATION_PATH)).unwrap();
+        fs::write(
+            mod_path.join(ModProfile::MIGRATION_PATH).join("0.2.0.ron"),
+            "{ \"tyconia::mods::migrations::Migration\": (fro",
+        )
+        .unwrap();
+
+        let installed = ModPack {
+            mod_id: "base_0.2.0".parse().unwrap(),
+            descriptor: MetaDescriptor {
+                display_name: "base".into(),
+                thumbnail: None,
+                cover_art: None,
+                descripion: String::new(),
+                dependencies: vec![],
+            },
+            attributions: MetaAttributions {
+                authors: vec![],
+                licenses: vec![],
+                credits: vec![],
+            },
+        };
+
+        let mut app = App::new();
+        app.register_type::<Migration>()
+            .add_event::<NotificationEvent>()
+            .add_event::<GameState>()
+            .insert_resource(PendingSave(save("base_0.1.0")));
+        app.world_mut().spawn((
+            Level::default(),
+            ModProfile(vec![(installed, mod_path.clone())]),
+        ));
+
+        app.world_mut()
+            .run_system_once(migrate_pending_save)
+            .unwrap();
+
+        assert!(!app.world().contains_resource::<PendingSave>());
+        let states = app.world().resource::<Events<GameState>>();
+        assert_eq!(
+            states.iter_current_update_events().collect::<Vec<_>>(),
+            vec![&GameState::Menu]
+        );
+
+        fs::remove_dir_all(&mod_path).unwrap();
+    }
+}
@@ -5,3 +5,9 @@ pub use scripts::*;
 
 mod profiles;
 pub use profiles::*;
+
+mod migrations;
+pub use migrations::*;
+
+mod resolver;
+pub use resolver::*;
@@ -1,7 +1,7 @@
+use crate::ui::*;
 use crate::*;
 use bevy::prelude::*;
-use bevy::utils::HashSet;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 pub struct ModProfilePlugin;
 
@@ -18,23 +18,25 @@ impl Plugin for ModProfilePlugin {
 
 use std::fs;
 
-pub fn init_mod_profile(mut cmd: Commands, level: Query<Entity, With<Level>>) {
+pub fn init_mod_profile(
+    mut cmd: Commands,
+    level: Query<Entity, With<Level>>,
+    pending_save: Option<Res<PendingSave>>,
+) {
     let level = level.single();
 
-    cmd.entity(level).insert(ModProfileConfig::default());
+    cmd.entity(level)
+        .insert(ModProfileConfig::for_save(pending_save.as_deref()));
 }
 
+use bevy::ecs::system::SystemParam;
 use bevy::reflect::serde::ReflectDeserializer;
+use bevy::reflect::TypeRegistry;
 use serde::de::DeserializeSeed;
-use std::io::Read;
+use std::fmt;
 
-pub fn load_mods_from_profile(
-    mut cmd: Commands,
-    profile: Query<(Entity, &ModProfileConfig), With<Level>>,
-    type_registry: Res<AppTypeRegistry>,
-) {
-    let type_registry = type_registry.read();
-    info!("about to load mods!");
+/// platform specific directory every mod is installed into
+pub fn mods_dir() -> PathBuf {
     // load platform specific app directories
     let project_dir = directories::ProjectDirs::from(
         env!("PROJECT_QUALIFIER"),
@@ -43,72 +45,182 @@ pub fn load_mods_from_profile(
     )
     .expect("no valid home directory path could be retrieved from the operating system");
 
-    let mod_dir = project_dir.config_dir().join("mods");
+    project_dir.config_dir().join("mods")
+}
 
-    let mut loaded_mods = HashSet::new();
+#[derive(Debug)]
+pub enum ModProfileError {
+    Unreadable { mod_id: String, reason: String },
+    Unresolved(ResolveError),
+}
 
-    let (level_entity, profile_config) = profile.single();
+impl fmt::Display for ModProfileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unreadable { mod_id, reason } => {
+                write!(f, "unable to read mod {}. {}", mod_id, reason)
+            }
+            Self::Unresolved(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+fn read_mod_pack(mod_path: &Path, type_registry: &TypeRegistry) -> Result<ModPack, String> {
+    let mod_meta_path = mod_path.join("meta.ron");
+
+    let ron = fs::read_to_string(&mod_meta_path)
+        .map_err(|err| format!("{} {}", mod_meta_path.to_string_lossy(), err))?;
+    let mut deserializer = ron::de::Deserializer::from_str(&ron).map_err(|err| err.to_string())?;
+    let reflect_deserializer = ReflectDeserializer::new(type_registry);
+
+    let partial_reflect_value = reflect_deserializer
+        .deserialize(&mut deserializer)
+        .map_err(|err| err.to_string())?;
 
-    for mod_path in profile_config.0.iter().map(|shorthand| {
-        let meta: Meta = shorthand.0.parse().unwrap();
-        mod_dir.join(meta.mod_name)
-    }) {
-        let mod_meta_path = mod_path.join("meta.ron");
-
-        match fs::File::open(&mod_meta_path) {
-            Ok(mut mod_meta) => {
-                let mut ron = String::new();
-                mod_meta.read_to_string(&mut ron).map_or_else(
-                    |err| {
-                        error!(
-                            "Unable to read ron at {}. {}",
-                            mod_meta_path.to_string_lossy(),
-                            err
-                        );
-                    },
-                    |_| {
-                        let mut deserializer = ron::de::Deserializer::from_str(&ron).unwrap();
-                        let reflect_deserializer = ReflectDeserializer::new(&type_registry);
-
-                        let partial_reflect_value =
-                            reflect_deserializer.deserialize(&mut deserializer).unwrap();
-
-                        let mod_pack = ModPack::from_reflect(&*partial_reflect_value).unwrap();
-
-                        info!(
-                            "loaded mod pack {} at {}",
-                            mod_pack.mod_id,
-                            mod_path.to_string_lossy()
-                        );
-
-                        loaded_mods.insert((mod_pack, mod_path));
-                    },
-                );
+    ModPack::from_reflect(&*partial_reflect_value)
+        .ok_or_else(|| format!("{} is not a mod pack", mod_meta_path.to_string_lossy()))
+}
+
+/// Reads every mod of the profile from [`mods_dir`] and orders them by their dependencies
+pub fn read_mod_profile(
+    profile_config: &ModProfileConfig,
+    type_registry: &TypeRegistry,
+) -> Result<ModProfile, Vec<ModProfileError>> {
+    let mod_dir = mods_dir();
+
+    let mut errors = vec![];
+    let mut mods = vec![];
+
+    for shorthand in profile_config.0.iter() {
+        let mod_path = match shorthand.0.parse::<Meta>() {
+            Ok(meta) => mod_dir.join(meta.mod_name),
+            Err(_) => {
+                errors.push(ModProfileError::Unreadable {
+                    mod_id: shorthand.0.clone(),
+                    reason: "invalid mod shorthand".into(),
+                });
+                continue;
             }
+        };
+
+        match read_mod_pack(&mod_path, type_registry) {
+            Ok(mod_pack) => mods.push((mod_pack, mod_path)),
+            Err(reason) => errors.push(ModProfileError::Unreadable {
+                mod_id: shorthand.0.clone(),
+                reason,
+            }),
+        }
+    }
 
-            Err(error) => {
-                error!(
-                    "Failed to open mod meta file at {}. {}",
-                    mod_meta_path.to_string_lossy(),
-                    error
-                );
+    let mod_packs = mods
+        .iter()
+        .map(|(mod_pack, _)| mod_pack.clone())
+        .collect::<Vec<_>>();
+
+    match resolve_load_order(&mod_packs) {
+        Ok(order) if errors.is_empty() => Ok(ModProfile(
+            order.into_iter().map(|index| mods[index].clone()).collect(),
+        )),
+        Ok(_) => Err(errors),
+        Err(resolve_errors) => {
+            errors.extend(resolve_errors.into_iter().map(ModProfileError::Unresolved));
+            Err(errors)
+        }
+    }
+}
+
+/// Profile resolved before entering [`GameState::Playing`], picked up by [`load_mods_from_profile`]
+#[derive(Resource, Debug)]
+pub struct ResolvedModProfile(pub ModProfile);
+
+/// Resolves the mods a level is about to be played with
+#[derive(SystemParam)]
+pub struct ModProfileResolver<'w, 's> {
+    cmd: Commands<'w, 's>,
+    pending_save: Option<Res<'w, PendingSave>>,
+    type_registry: Res<'w, AppTypeRegistry>,
+    notifications_channel: NotificationChannel<'w>,
+}
+
+impl ModProfileResolver<'_, '_> {
+    /// Every problem of the profile is reported as a notification, returns whether the level can be played
+    pub fn resolve(&mut self) -> bool {
+        let profile_config = ModProfileConfig::for_save(self.pending_save.as_deref());
+
+        match read_mod_profile(&profile_config, &self.type_registry.read()) {
+            Ok(mod_profile) => {
+                self.cmd.insert_resource(ResolvedModProfile(mod_profile));
+                true
+            }
+            Err(errors) => {
+                for err in errors.iter() {
+                    error!("{}", err);
+                    Notification {
+                        title: "Unable to load mods".into(),
+                        level: NotificationLevel::Error,
+                        description: err.to_string(),
+                    }
+                    .queue(None, &mut self.notifications_channel);
+                }
+
+                self.cmd.remove_resource::<PendingSave>();
+                false
             }
         }
     }
+}
 
-    cmd.entity(level_entity).insert(ModProfile(loaded_mods));
+pub fn load_mods_from_profile(
+    mut cmd: Commands,
+    profile: Query<(Entity, &ModProfileConfig), With<Level>>,
+    resolved: Option<Res<ResolvedModProfile>>,
+    type_registry: Res<AppTypeRegistry>,
+) {
+    info!("about to load mods!");
+    let (level_entity, profile_config) = profile.single();
+
+    let mod_profile = match resolved {
+        Some(resolved) => {
+            cmd.remove_resource::<ResolvedModProfile>();
+            resolved.0.clone()
+        }
+        None => read_mod_profile(profile_config, &type_registry.read()).unwrap_or_else(|errors| {
+            errors.iter().for_each(|err| error!("{}", err));
+            ModProfile(vec![])
+        }),
+    };
+
+    for (mod_pack, mod_path) in mod_profile.0.iter() {
+        info!(
+            "loaded mod pack {} at {}",
+            mod_pack.mod_id,
+            mod_path.to_string_lossy()
+        );
+    }
+
+    cmd.entity(level_entity).insert(mod_profile);
 }
 
 #[derive(Component, Reflect)]
 pub struct ModProfileConfig(pub Vec<MetaShorthand>);
 
-#[derive(Component, Reflect)]
-pub struct ModProfile(pub HashSet<(ModPack, PathBuf)>);
+impl ModProfileConfig {
+    /// a loaded save brings along the mods it was played with
+    pub fn for_save(pending_save: Option<&PendingSave>) -> Self {
+        pending_save.map_or_else(Self::default, |pending_save| {
+            Self(pending_save.0.mod_profile.clone())
+        })
+    }
+}
+
+/// Mods of the level in load order, dependencies first
+#[derive(Component, Reflect, Debug, Clone)]
+pub struct ModProfile(pub Vec<(ModPack, PathBuf)>);
 
 impl ModProfile {
     const SCENARIO_PATH: &'static str = "scenarios";
-    const MIGRATION_PATH: &'static str = "migrations";
-    const ASSET_PATH: &'static str = "assets";
+    pub const MIGRATION_PATH: &'static str = "migrations";
+    pub const ASSET_PATH: &'static str = "assets";
 
     pub fn scenario(&self) -> Option<PathBuf> {
         self.0.iter().find(|(_, _)| true);
@@ -0,0 +1,332 @@
+//! Orders a mod profile so every mod loads after the mods it depends on
+
+use crate::*;
+use bevy::utils::{HashMap, HashSet};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ResolveError {
+    /// the same mod is listed more than once
+    Duplicate {
+        mod_name: String,
+        versions: Vec<SemVer>,
+    },
+    InvalidDependency {
+        mod_id: Meta,
+        dependency: String,
+        reason: String,
+    },
+    Missing {
+        mod_id: Meta,
+        dependency: Dependency,
+    },
+    Conflict {
+        mod_id: Meta,
+        dependency: Dependency,
+        found: SemVer,
+    },
+    /// mod names along the cycle, the first one repeated at the end
+    Cycle(Vec<String>),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate { mod_name, versions } => {
+                let versions = versions
+                    .iter()
+                    .map(|version| version.to_string())
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "{} is listed multiple times ({})",
+                    mod_name,
+                    versions.join(", ")
+                )
+            }
+            Self::InvalidDependency {
+                mod_id,
+                dependency,
+                reason,
+            } => write!(
+                f,
+                "{} has an invalid dependency `{}`. {}",
+                mod_id, dependency, reason
+            ),
+            Self::Missing { mod_id, dependency } => {
+                write!(f, "{} requires {} which is missing", mod_id, dependency)
+            }
+            Self::Conflict {
+                mod_id,
+                dependency,
+                found,
+            } => write!(
+                f,
+                "{} requires {} but {} is installed",
+                mod_id, dependency, found
+            ),
+            Self::Cycle(mod_names) => {
+                write!(f, "dependency cycle {}", mod_names.join(" -> "))
+            }
+        }
+    }
+}
+
+/// Indices into `mods` in load order, dependencies first and otherwise keeping the
+/// order of the profile. Every problem found is reported, not just the first one.
+pub fn resolve_load_order(mods: &[ModPack]) -> Result<Vec<usize>, Vec<ResolveError>> {
+    let mut errors = vec![];
+
+    let mut by_name = HashMap::<&str, usize>::default();
+    for (index, mod_pack) in mods.iter().enumerate() {
+        let mod_name = mod_pack.mod_id.mod_name.as_str();
+        if by_name.contains_key(mod_name) {
+            continue;
+        }
+        by_name.insert(mod_name, index);
+
+        let versions = mods
+            .iter()
+            .filter(|other| other.mod_id.mod_name == mod_name)
+            .map(|other| other.mod_id.version.clone())
+            .collect::<Vec<_>>();
+        if versions.len() > 1 {
+            errors.push(ResolveError::Duplicate {
+                mod_name: mod_name.to_string(),
+                versions,
+            });
+        }
+    }
+
+    let dependencies = mods
+        .iter()
+        .map(|mod_pack| {
+            let mut edges = vec![];
+
+            for source in mod_pack.descriptor.dependencies.iter() {
+                let dependency = match source.dependency() {
+                    Ok(dependency) => dependency,
+                    Err(err) => {
+                        errors.push(ResolveError::InvalidDependency {
+                            mod_id: mod_pack.mod_id.clone(),
+                            dependency: source.id.0.clone(),
+                            reason: err.to_string(),
+                        });
+                        continue;
+                    }
+                };
+
+                let Some(&index) = by_name.get(dependency.mod_name.as_str()) else {
+                    errors.push(ResolveError::Missing {
+                        mod_id: mod_pack.mod_id.clone(),
+                        dependency,
+                    });
+                    continue;
+                };
+
+                let found = mods[index].mod_id.version.clone();
+                if !dependency.requirement.matches(&found) {
+                    errors.push(ResolveError::Conflict {
+                        mod_id: mod_pack.mod_id.clone(),
+                        dependency,
+                        found,
+                    });
+                }
+
+                if !edges.contains(&index) {
+                    edges.push(index);
+                }
+            }
+
+            edges
+        })
+        .collect::<Vec<_>>();
+
+    let mut order = Vec::with_capacity(mods.len());
+    let mut placed = vec![false; mods.len()];
+
+    // always place the earliest mod of the profile whose dependencies are loaded
+    while let Some(index) = (0..mods.len()).find(|index| {
+        !placed[*index]
+            && dependencies[*index]
+                .iter()
+                .all(|dependency| placed[*dependency])
+    }) {
+        placed[index] = true;
+        order.push(index);
+    }
+
+    if order.len() < mods.len() {
+        errors.extend(
+            find_cycles(&dependencies, &placed)
+                .into_iter()
+                .map(|cycle| {
+                    ResolveError::Cycle(
+                        cycle
+                            .iter()
+                            .chain(cycle.first())
+                            .map(|index| mods[*index].mod_id.mod_name.clone())
+                            .collect(),
+                    )
+                }),
+        );
+    }
+
+    if errors.is_empty() {
+        Ok(order)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Every distinct cycle among the mods that could not be placed
+fn find_cycles(dependencies: &[Vec<usize>], placed: &[bool]) -> Vec<Vec<usize>> {
+    fn visit(
+        node: usize,
+        dependencies: &[Vec<usize>],
+        path: &mut Vec<usize>,
+        done: &mut HashSet<usize>,
+        cycles: &mut Vec<Vec<usize>>,
+    ) {
+        if let Some(start) = path.iter().position(|visited| *visited == node) {
+            let mut cycle = path[start..].to_vec();
+            // rotate so the same cycle found from another mod compares equal
+            let smallest = (0..cycle.len()).min_by_key(|i| cycle[*i]).unwrap_or(0);
+            cycle.rotate_left(smallest);
+
+            if !cycles.contains(&cycle) {
+                cycles.push(cycle);
+            }
+            return;
+        }
+
+        if done.contains(&node) {
+            return;
+        }
+
+        path.push(node);
+        for dependency in dependencies[node].iter() {
+            visit(*dependency, dependencies, path, done, cycles);
+        }
+        path.pop();
+        done.insert(node);
+    }
+
+    let mut cycles = vec![];
+    let mut done = HashSet::default();
+
+    for node in (0..dependencies.len()).filter(|node| !placed[*node]) {
+        visit(node, dependencies, &mut vec![], &mut done, &mut cycles);
+    }
+
+    cycles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mod_pack(mod_id: &str, dependencies: &[&str]) -> ModPack {
+        ModPack {
+            mod_id: mod_id.parse().unwrap(),
+            descriptor: MetaDescriptor {
+                display_name: mod_id.into(),
+                thumbnail: None,
+                cover_art: None,
+                descripion: String::new(),
+                dependencies: dependencies
+                    .iter()
+                    .map(|dependency| MetaSource {
+                        id: MetaShorthand(dependency.to_string()),
+                        sources: vec![],
+                    })
+                    .collect(),
+            },
+            attributions: MetaAttributions {
+                authors: vec![],
+                licenses: vec![],
+                credits: vec![],
+            },
+        }
+    }
+
+    fn names(mods: &[ModPack], order: &[usize]) -> Vec<String> {
+        order
+            .iter()
+            .map(|index| mods[*index].mod_id.mod_name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn dependencies_load_first() {
+        let mods = vec![
+            mod_pack("tyconic_0.2.0", &["base >=0.1, <0.3"]),
+            mod_pack("cooking_time_1.0.0", &["tyconic", "base"]),
+            mod_pack("base_0.2.1", &[]),
+        ];
+
+        let order = resolve_load_order(&mods).unwrap();
+        assert_eq!(
+            names(&mods, &order),
+            vec!["base", "tyconic", "cooking_time"]
+        );
+    }
+
+    #[test]
+    fn independent_mods_keep_profile_order() {
+        let mods = vec![
+            mod_pack("zeta_1.0.0", &[]),
+            mod_pack("alpha_1.0.0", &[]),
+            mod_pack("mid_1.0.0", &["alpha"]),
+        ];
+
+        let order = resolve_load_order(&mods).unwrap();
+        assert_eq!(names(&mods, &order), vec!["zeta", "alpha", "mid"]);
+    }
+
+    #[test]
+    fn all_problems_reported() {
+        let mods = vec![
+            mod_pack(
+                "tyconic_0.3.0",
+                &["base >=0.1, <0.3", "missing_mod", "base >=zero"],
+            ),
+            mod_pack("base_0.3.0", &[]),
+            mod_pack("base_0.1.0", &[]),
+        ];
+
+        let errors = resolve_load_order(&mods).unwrap_err();
+        assert_eq!(errors.len(), 4);
+        assert!(
+            matches!(&errors[0], ResolveError::Duplicate { mod_name, .. } if mod_name == "base")
+        );
+        assert!(
+            matches!(&errors[1], ResolveError::Conflict { dependency, .. } if dependency.mod_name == "base")
+        );
+        assert!(
+            matches!(&errors[2], ResolveError::Missing { dependency, .. } if dependency.mod_name == "missing_mod")
+        );
+        assert!(matches!(&errors[3], ResolveError::InvalidDependency { .. }));
+    }
+
+    #[test]
+    fn cycles_detected() {
+        let mods = vec![
+            mod_pack("a_1.0.0", &["b"]),
+            mod_pack("b_1.0.0", &["c"]),
+            mod_pack("c_1.0.0", &["a"]),
+            mod_pack("d_1.0.0", &["a"]),
+            mod_pack("e_1.0.0", &["e"]),
+            mod_pack("f_1.0.0", &[]),
+        ];
+
+        let errors = resolve_load_order(&mods).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                ResolveError::Cycle(vec!["a".into(), "b".into(), "c".into(), "a".into()]),
+                ResolveError::Cycle(vec!["e".into(), "e".into()]),
+            ]
+        );
+    }
+}
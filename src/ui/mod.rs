@@ -12,6 +12,7 @@ mod range_slider;
 //mod selection;
 mod system_cursor;
 mod tabs;
+mod text_field;
 mod tooltip;
 mod window;
 
@@ -22,6 +23,7 @@ pub use notification::*;
 pub use range_slider::*;
 pub use system_cursor::*;
 pub use tabs::*;
+pub use text_field::*;
 pub use window::*;
 
 use crate::loading::FontAssets;
@@ -52,6 +54,7 @@ impl Plugin for UiPlugin {
             button::ButtonPlugin,
             checkbox::CheckboxPlugin,
             range_slider::RangeSliderPlugin,
+            text_field::TextFieldPlugin,
             window::WindowPlugin,
             notification::NotificationPlugin,
             #[cfg(not(target_arch = "wasm32"))]
@@ -0,0 +1,137 @@
+//! Single line text input, focused by clicking it and typed into from the keyboard
+
+use crate::loading::{FontAssets, UiAssets};
+use crate::ui::*;
+use bevy::input::keyboard::{Key, KeyboardInput};
+
+pub struct TextFieldPlugin;
+
+impl Plugin for TextFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (focus_text_fields, type_into_text_fields, show_text_fields)
+                .chain()
+                .run_if(any_with_component::<TextField>),
+        );
+    }
+}
+
+/// Value typed so far, consumers watch for `Changed<TextField>`
+#[derive(Component, Debug, Default, Clone, PartialEq)]
+#[require(Button)]
+pub struct TextField {
+    pub value: String,
+    pub focused: bool,
+}
+
+/// Text showing the value of the parent [`TextField`]
+#[derive(Component, Debug)]
+pub struct TextFieldText;
+
+/// Keyboard actions shouldn't fire while typing
+pub fn text_field_focused(fields: Query<&TextField>) -> bool {
+    fields.iter().any(|field| field.focused)
+}
+
+/// Clicking a field focuses it, clicking anywhere else leaves it
+fn focus_text_fields(
+    mouse_button: Res<ButtonInput<MouseButton>>,
+    mut fields: Query<(&mut TextField, &Interaction)>,
+) {
+    if !mouse_button.just_pressed(MouseButton::Left) {
+        return;
+    }
+
+    for (mut field, interaction) in fields.iter_mut() {
+        let focused = *interaction == Interaction::Pressed;
+        if field.focused != focused {
+            field.focused = focused;
+        }
+    }
+}
+
+fn type_into_text_fields(
+    mut keyboard: EventReader<KeyboardInput>,
+    mut fields: Query<&mut TextField>,
+) {
+    let keys = keyboard
+        .read()
+        .filter(|input| input.state.is_pressed())
+        .map(|input| input.logical_key.clone())
+        .collect::<Vec<_>>();
+
+    let Some(mut field) = fields.iter_mut().find(|field| field.focused) else {
+        return;
+    };
+
+    for key in keys {
+        match key {
+            Key::Character(text) => field.value.extend(text.chars().filter(|c| !c.is_control())),
+            Key::Space => field.value.push(' '),
+            Key::Backspace => {
+                field.value.pop();
+            }
+            Key::Enter | Key::Escape | Key::Tab => field.focused = false,
+            _ => {}
+        }
+    }
+}
+
+fn show_text_fields(
+    fields: Query<(&TextField, &Children), Changed<TextField>>,
+    mut texts: Query<&mut Text, With<TextFieldText>>,
+) {
+    for (field, children) in fields.iter() {
+        let mut texts = texts.iter_many_mut(children);
+        while let Some(mut text) = texts.fetch_next() {
+            text.0 = if field.focused {
+                format!("{}|", field.value)
+            } else {
+                field.value.clone()
+            };
+        }
+    }
+}
+
+/// Textured text field.
+///
+/// # Arguments
+///
+/// * `value` - initial value
+/// * `components` - components you want to include for reading the value back
+/// * `commands` - commands used to spawn the field
+pub fn spawn_text_field<'a>(
+    value: &str,
+    components: impl Bundle,
+    commands: &'a mut ChildBuilder,
+    fonts: &Res<FontAssets>,
+    ui: &Res<UiAssets>,
+) -> EntityCommands<'a> {
+    let mut entity_cmd = commands.spawn((
+        TextField {
+            value: value.into(),
+            focused: false,
+        },
+        components,
+        Node {
+            flex_grow: 1.,
+            min_width: Val::Px(UI_SCALE * 8.),
+            min_height: Val::Px(UI_SCALE * 4.),
+            padding: UiRect::axes(Val::Px(UI_SCALE), Val::Px(UI_SCALE / 2.)),
+            align_items: AlignItems::Center,
+            ..default()
+        },
+        ImageNode {
+            image: ui.inventory_slot.clone(),
+            image_mode: NODE_IMG_MODE_SLICED,
+            ..default()
+        },
+    ));
+
+    entity_cmd.with_children(|parent| {
+        body_text(value, parent, fonts).insert(TextFieldText);
+    });
+
+    entity_cmd
+}